@@ -0,0 +1,65 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+
+use crate::join_paths::{find_paths_for_conn, JoinPathRequest};
+use crate::local_store::LocalStore;
+
+pub const FIND_JOIN_PATHS: &str = "find_join_paths";
+
+/// OpenAI-compatible function definitions offered to the model when the chat
+/// is bound to a connection.
+pub fn tool_definitions() -> Vec<Value> {
+    vec![json!({
+        "type": "function",
+        "function": {
+            "name": FIND_JOIN_PATHS,
+            "description": "Find the shortest join paths between two tables through foreign keys. Returns the exact ON predicates to use; never invent joins that are not returned here.",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "from": { "type": "string", "description": "Start table, `schema.table` or bare table name" },
+                    "to": { "type": "string", "description": "Target table, `schema.table` or bare table name" },
+                    "max_depth": { "type": "integer", "minimum": 1, "maximum": 8 }
+                },
+                "required": ["from", "to"]
+            }
+        }
+    })]
+}
+
+#[derive(Debug, Deserialize)]
+struct JoinPathArgs {
+    from: String,
+    to: String,
+    #[serde(default)]
+    max_depth: Option<usize>,
+}
+
+/// Executes a tool call requested by the model and returns the JSON payload
+/// that is sent back as the `tool` message.
+pub async fn run_tool(
+    app: &AppHandle,
+    conn_id: &str,
+    name: &str,
+    arguments: &str,
+) -> Result<Value, String> {
+    match name {
+        FIND_JOIN_PATHS => {
+            let args: JoinPathArgs = serde_json::from_str(arguments)
+                .map_err(|err| format!("invalid_tool_arguments: {}", err))?;
+            let store = app.state::<LocalStore>();
+            let request = JoinPathRequest {
+                conn_id: conn_id.to_string(),
+                from: args.from,
+                to: args.to,
+                max_depth: args.max_depth,
+                extra_hops: Some(1),
+                limit: None,
+            };
+            let result = find_paths_for_conn(&store, &request).await?;
+            serde_json::to_value(result).map_err(|err| err.to_string())
+        }
+        other => Err(format!("unknown_tool:{}", other)),
+    }
+}
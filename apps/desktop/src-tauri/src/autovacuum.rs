@@ -0,0 +1,557 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::collections::HashMap;
+use tauri::State;
+
+use crate::local_store::{now_sec, LocalStore};
+use crate::pg::{begin_read_only, fetch_as, fetch_one_as, PgPools, ANALYSIS_STATEMENT_TIMEOUT_MS};
+
+/// `vacuum_failsafe_age` / `vacuum_multixact_failsafe_age` defaults: past
+/// this, VACUUM drops everything else to freeze.
+const FAILSAFE_AGE: i64 = 1_600_000_000;
+/// Ages are reported as a share of the hard limit of 2^31 transactions.
+const WRAPAROUND_LIMIT: f64 = 2_147_483_648.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Ok,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceIssue {
+    /// `wraparound`, `multixact_wraparound`, `vacuum_overdue`,
+    /// `analyze_overdue`, `never_analyzed`, `autovacuum_disabled`,
+    /// `autovacuum_off` or `track_counts_off`.
+    pub kind: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Server-wide autovacuum settings; per-table reloptions override them.
+#[derive(Debug, Clone, Serialize)]
+pub struct AutovacuumSettings {
+    pub autovacuum: bool,
+    pub track_counts: bool,
+    pub vacuum_threshold: f64,
+    pub vacuum_scale_factor: f64,
+    pub analyze_threshold: f64,
+    pub analyze_scale_factor: f64,
+    pub freeze_max_age: i64,
+    pub multixact_freeze_max_age: i64,
+    pub max_workers: i64,
+    pub naptime: Option<String>,
+}
+
+impl AutovacuumSettings {
+    fn from_rows(rows: &[SettingRow]) -> Self {
+        let map: HashMap<&str, &str> = rows
+            .iter()
+            .map(|row| (row.name.as_str(), row.setting.as_str()))
+            .collect();
+        let num = |name: &str, default: f64| {
+            map.get(name)
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(default)
+        };
+        AutovacuumSettings {
+            autovacuum: map.get("autovacuum").is_none_or(|v| *v == "on"),
+            track_counts: map.get("track_counts").is_none_or(|v| *v == "on"),
+            vacuum_threshold: num("autovacuum_vacuum_threshold", 50.0),
+            vacuum_scale_factor: num("autovacuum_vacuum_scale_factor", 0.2),
+            analyze_threshold: num("autovacuum_analyze_threshold", 50.0),
+            analyze_scale_factor: num("autovacuum_analyze_scale_factor", 0.1),
+            freeze_max_age: num("autovacuum_freeze_max_age", 200_000_000.0) as i64,
+            multixact_freeze_max_age: num("autovacuum_multixact_freeze_max_age", 400_000_000.0)
+                as i64,
+            max_workers: num("autovacuum_max_workers", 3.0) as i64,
+            naptime: map.get("autovacuum_naptime").map(|v| format!("{}s", v)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SettingRow {
+    name: String,
+    setting: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TableActivity {
+    pub schema: String,
+    pub table: String,
+    pub n_live_tup: i64,
+    pub n_dead_tup: i64,
+    pub n_mod_since_analyze: i64,
+    pub reltuples: f64,
+    pub last_vacuum: Option<String>,
+    pub last_autovacuum: Option<String>,
+    pub last_analyze: Option<String>,
+    pub last_autoanalyze: Option<String>,
+    pub vacuum_count: i64,
+    pub autovacuum_count: i64,
+    pub analyze_count: i64,
+    pub autoanalyze_count: i64,
+    /// Oldest of the table and its TOAST table.
+    pub xid_age: i64,
+    pub mxid_age: i64,
+    pub reloptions: Vec<String>,
+    pub size_bytes: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VacuumProgress {
+    pub pid: i64,
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    pub phase: String,
+    pub heap_blks_total: i64,
+    pub heap_blks_scanned: i64,
+    pub heap_blks_vacuumed: i64,
+    pub index_vacuum_count: i64,
+    pub is_autovacuum: bool,
+    pub to_prevent_wraparound: bool,
+    pub running_secs: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableMaintenance {
+    pub schema: String,
+    pub table: String,
+    pub severity: Severity,
+    pub issues: Vec<MaintenanceIssue>,
+    pub n_live_tup: i64,
+    pub n_dead_tup: i64,
+    pub dead_ratio: f64,
+    pub n_mod_since_analyze: i64,
+    /// Dead tuples at which autovacuum picks the table up.
+    pub vacuum_trigger: f64,
+    pub analyze_trigger: f64,
+    pub autovacuum_enabled: bool,
+    pub reloptions: Vec<String>,
+    pub last_vacuum: Option<String>,
+    pub last_autovacuum: Option<String>,
+    pub last_analyze: Option<String>,
+    pub last_autoanalyze: Option<String>,
+    pub vacuum_count: i64,
+    pub autovacuum_count: i64,
+    pub analyze_count: i64,
+    pub autoanalyze_count: i64,
+    pub xid_age: i64,
+    pub mxid_age: i64,
+    pub freeze_max_age: i64,
+    /// `xid_age` as a percentage of the 2^31 wraparound limit.
+    pub wraparound_percent: f64,
+    pub size_bytes: i64,
+    pub vacuum_in_progress: Option<VacuumProgress>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DatabaseAge {
+    pub xid_age: i64,
+    pub mxid_age: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutovacuumReport {
+    pub settings: AutovacuumSettings,
+    pub database: Option<DatabaseAge>,
+    /// Server-level problems, e.g. autovacuum switched off.
+    pub issues: Vec<MaintenanceIssue>,
+    pub in_progress: Vec<VacuumProgress>,
+    /// Most severe first.
+    pub tables: Vec<TableMaintenance>,
+    pub generated_at: i64,
+}
+
+const SETTINGS_SQL: &str = r#"
+SELECT name, setting
+FROM pg_catalog.pg_settings
+WHERE name IN (
+  'autovacuum', 'track_counts', 'autovacuum_naptime', 'autovacuum_max_workers',
+  'autovacuum_vacuum_threshold', 'autovacuum_vacuum_scale_factor',
+  'autovacuum_analyze_threshold', 'autovacuum_analyze_scale_factor',
+  'autovacuum_freeze_max_age', 'autovacuum_multixact_freeze_max_age'
+)"#;
+
+const TABLES_SQL: &str = r#"
+SELECT
+  s.schemaname::text AS schema,
+  s.relname::text AS "table",
+  COALESCE(s.n_live_tup, 0)::bigint AS n_live_tup,
+  COALESCE(s.n_dead_tup, 0)::bigint AS n_dead_tup,
+  COALESCE(s.n_mod_since_analyze, 0)::bigint AS n_mod_since_analyze,
+  GREATEST(c.reltuples, 0)::float8 AS reltuples,
+  s.last_vacuum::text AS last_vacuum,
+  s.last_autovacuum::text AS last_autovacuum,
+  s.last_analyze::text AS last_analyze,
+  s.last_autoanalyze::text AS last_autoanalyze,
+  COALESCE(s.vacuum_count, 0)::bigint AS vacuum_count,
+  COALESCE(s.autovacuum_count, 0)::bigint AS autovacuum_count,
+  COALESCE(s.analyze_count, 0)::bigint AS analyze_count,
+  COALESCE(s.autoanalyze_count, 0)::bigint AS autoanalyze_count,
+  GREATEST(pg_catalog.age(c.relfrozenxid), COALESCE(pg_catalog.age(t.relfrozenxid), 0))::bigint AS xid_age,
+  GREATEST(pg_catalog.mxid_age(c.relminmxid), COALESCE(pg_catalog.mxid_age(t.relminmxid), 0))::bigint AS mxid_age,
+  COALESCE(c.reloptions, '{}')::text[] AS reloptions,
+  pg_catalog.pg_total_relation_size(c.oid)::bigint AS size_bytes
+FROM pg_catalog.pg_stat_user_tables s
+JOIN pg_catalog.pg_class c ON c.oid = s.relid
+LEFT JOIN pg_catalog.pg_class t ON t.oid = c.reltoastrelid
+WHERE c.relkind IN ('r', 'm')
+ORDER BY 1, 2"#;
+
+/// `query` is only visible with `pg_read_all_stats`; without it the
+/// autovacuum flags read as false.
+const PROGRESS_SQL: &str = r#"
+SELECT
+  p.pid::bigint AS pid,
+  n.nspname::text AS schema,
+  c.relname::text AS "table",
+  p.phase,
+  p.heap_blks_total::bigint AS heap_blks_total,
+  p.heap_blks_scanned::bigint AS heap_blks_scanned,
+  p.heap_blks_vacuumed::bigint AS heap_blks_vacuumed,
+  p.index_vacuum_count::bigint AS index_vacuum_count,
+  COALESCE(a.query LIKE 'autovacuum:%', false) AS is_autovacuum,
+  COALESCE(a.query LIKE '%(to prevent wraparound)%', false) AS to_prevent_wraparound,
+  EXTRACT(EPOCH FROM (pg_catalog.now() - a.xact_start))::float8 AS running_secs
+FROM pg_catalog.pg_stat_progress_vacuum p
+LEFT JOIN pg_catalog.pg_class c ON c.oid = p.relid
+LEFT JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+LEFT JOIN pg_catalog.pg_stat_activity a ON a.pid = p.pid
+WHERE p.datname = pg_catalog.current_database()
+ORDER BY p.pid"#;
+
+const DATABASE_AGE_SQL: &str = r#"
+SELECT
+  pg_catalog.age(datfrozenxid)::bigint AS xid_age,
+  pg_catalog.mxid_age(datminmxid)::bigint AS mxid_age
+FROM pg_catalog.pg_database
+WHERE datname = pg_catalog.current_database()"#;
+
+fn reloption_map(reloptions: &[String]) -> HashMap<&str, &str> {
+    reloptions
+        .iter()
+        .filter_map(|opt| opt.split_once('='))
+        .collect()
+}
+
+fn issue(kind: &str, severity: Severity, message: String) -> MaintenanceIssue {
+    MaintenanceIssue {
+        kind: kind.to_string(),
+        severity,
+        message,
+    }
+}
+
+fn age_issue(
+    kind: &str,
+    label: &str,
+    age: i64,
+    freeze_max_age: i64,
+    vacuuming: bool,
+) -> Option<MaintenanceIssue> {
+    let severity = if age >= FAILSAFE_AGE {
+        Severity::Critical
+    } else if age >= freeze_max_age && !vacuuming {
+        Severity::High
+    } else if age >= freeze_max_age || age * 4 >= freeze_max_age * 3 {
+        Severity::Medium
+    } else {
+        return None;
+    };
+    Some(issue(
+        kind,
+        severity,
+        format!(
+            "{} age {} ({:.1}% of wraparound), freeze_max_age {}",
+            label,
+            age,
+            age as f64 / WRAPAROUND_LIMIT * 100.0,
+            freeze_max_age
+        ),
+    ))
+}
+
+/// Applies reloption overrides to the server settings and flags what
+/// autovacuum is behind on. A vacuum already running on the table softens
+/// the overdue and wraparound findings.
+pub fn assess(
+    table: &TableActivity,
+    settings: &AutovacuumSettings,
+    progress: Option<&VacuumProgress>,
+) -> TableMaintenance {
+    let options = reloption_map(&table.reloptions);
+    let opt = |name: &str, default: f64| {
+        options
+            .get(name)
+            .and_then(|v| v.parse::<f64>().ok())
+            .unwrap_or(default)
+    };
+    let autovacuum_enabled = options
+        .get("autovacuum_enabled")
+        .is_none_or(|v| !matches!(v.to_ascii_lowercase().as_str(), "false" | "off" | "0"));
+    let freeze_max_age = opt("autovacuum_freeze_max_age", settings.freeze_max_age as f64)
+        .min(settings.freeze_max_age as f64) as i64;
+    let multixact_freeze_max_age = opt(
+        "autovacuum_multixact_freeze_max_age",
+        settings.multixact_freeze_max_age as f64,
+    )
+    .min(settings.multixact_freeze_max_age as f64) as i64;
+    let vacuum_trigger = opt("autovacuum_vacuum_threshold", settings.vacuum_threshold)
+        + opt(
+            "autovacuum_vacuum_scale_factor",
+            settings.vacuum_scale_factor,
+        ) * table.reltuples;
+    let analyze_trigger = opt("autovacuum_analyze_threshold", settings.analyze_threshold)
+        + opt(
+            "autovacuum_analyze_scale_factor",
+            settings.analyze_scale_factor,
+        ) * table.reltuples;
+    let vacuuming = progress.is_some();
+
+    let mut issues = Vec::new();
+    issues.extend(age_issue(
+        "wraparound",
+        "Transaction ID",
+        table.xid_age,
+        freeze_max_age,
+        vacuuming,
+    ));
+    issues.extend(age_issue(
+        "multixact_wraparound",
+        "Multixact ID",
+        table.mxid_age,
+        multixact_freeze_max_age,
+        vacuuming,
+    ));
+    if !autovacuum_enabled {
+        let severity = if table.n_dead_tup as f64 > vacuum_trigger {
+            Severity::High
+        } else {
+            Severity::Medium
+        };
+        issues.push(issue(
+            "autovacuum_disabled",
+            severity,
+            "autovacuum_enabled=false; only manual VACUUM and anti-wraparound runs reach this table"
+                .to_string(),
+        ));
+    }
+    if table.n_dead_tup as f64 > vacuum_trigger {
+        let over = table.n_dead_tup as f64 / vacuum_trigger.max(1.0);
+        let severity = if vacuuming {
+            Severity::Low
+        } else if over >= 4.0 {
+            Severity::High
+        } else if over >= 2.0 {
+            Severity::Medium
+        } else {
+            Severity::Low
+        };
+        issues.push(issue(
+            "vacuum_overdue",
+            severity,
+            format!(
+                "{} dead tuples, {:.1}x the autovacuum trigger of {:.0}",
+                table.n_dead_tup, over, vacuum_trigger
+            ),
+        ));
+    }
+    let never_analyzed = table.last_analyze.is_none() && table.last_autoanalyze.is_none();
+    if never_analyzed && table.n_live_tup > 0 {
+        issues.push(issue(
+            "never_analyzed",
+            Severity::Medium,
+            "No statistics; the planner is guessing row counts".to_string(),
+        ));
+    } else if table.n_mod_since_analyze as f64 > analyze_trigger {
+        let over = table.n_mod_since_analyze as f64 / analyze_trigger.max(1.0);
+        issues.push(issue(
+            "analyze_overdue",
+            if over >= 4.0 {
+                Severity::Medium
+            } else {
+                Severity::Low
+            },
+            format!(
+                "{} rows modified since the last analyze, {:.1}x the trigger of {:.0}",
+                table.n_mod_since_analyze, over, analyze_trigger
+            ),
+        ));
+    }
+
+    let total = table.n_live_tup + table.n_dead_tup;
+    TableMaintenance {
+        schema: table.schema.clone(),
+        table: table.table.clone(),
+        severity: issues
+            .iter()
+            .map(|i| i.severity)
+            .max()
+            .unwrap_or(Severity::Ok),
+        issues,
+        n_live_tup: table.n_live_tup,
+        n_dead_tup: table.n_dead_tup,
+        dead_ratio: if total > 0 {
+            table.n_dead_tup as f64 / total as f64
+        } else {
+            0.0
+        },
+        n_mod_since_analyze: table.n_mod_since_analyze,
+        vacuum_trigger,
+        analyze_trigger,
+        autovacuum_enabled,
+        reloptions: table.reloptions.clone(),
+        last_vacuum: table.last_vacuum.clone(),
+        last_autovacuum: table.last_autovacuum.clone(),
+        last_analyze: table.last_analyze.clone(),
+        last_autoanalyze: table.last_autoanalyze.clone(),
+        vacuum_count: table.vacuum_count,
+        autovacuum_count: table.autovacuum_count,
+        analyze_count: table.analyze_count,
+        autoanalyze_count: table.autoanalyze_count,
+        xid_age: table.xid_age,
+        mxid_age: table.mxid_age,
+        freeze_max_age,
+        wraparound_percent: table.xid_age as f64 / WRAPAROUND_LIMIT * 100.0,
+        size_bytes: table.size_bytes,
+        vacuum_in_progress: progress.cloned(),
+    }
+}
+
+fn server_issues(settings: &AutovacuumSettings) -> Vec<MaintenanceIssue> {
+    let mut issues = Vec::new();
+    if !settings.autovacuum {
+        issues.push(issue(
+            "autovacuum_off",
+            Severity::High,
+            "autovacuum is off; only anti-wraparound vacuums will run".to_string(),
+        ));
+    }
+    if !settings.track_counts {
+        issues.push(issue(
+            "track_counts_off",
+            Severity::High,
+            "track_counts is off; autovacuum has no activity statistics to work from".to_string(),
+        ));
+    }
+    issues
+}
+
+pub async fn collect_report(conn: &mut PgConnection) -> Result<AutovacuumReport, String> {
+    let settings_rows: Vec<SettingRow> = fetch_as(conn, SETTINGS_SQL, &[]).await?;
+    let settings = AutovacuumSettings::from_rows(&settings_rows);
+    let tables: Vec<TableActivity> = fetch_as(conn, TABLES_SQL, &[]).await?;
+    let in_progress: Vec<VacuumProgress> = fetch_as(conn, PROGRESS_SQL, &[]).await?;
+    let database: Option<DatabaseAge> = fetch_one_as(conn, DATABASE_AGE_SQL, &[]).await?;
+
+    let mut tables: Vec<TableMaintenance> = tables
+        .iter()
+        .map(|table| {
+            let progress = in_progress.iter().find(|p| {
+                p.schema.as_deref() == Some(table.schema.as_str())
+                    && p.table.as_deref() == Some(table.table.as_str())
+            });
+            assess(table, &settings, progress)
+        })
+        .collect();
+    tables.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| b.xid_age.cmp(&a.xid_age))
+            .then_with(|| b.n_dead_tup.cmp(&a.n_dead_tup))
+    });
+    Ok(AutovacuumReport {
+        issues: server_issues(&settings),
+        settings,
+        database,
+        in_progress,
+        tables,
+        generated_at: now_sec(),
+    })
+}
+
+#[tauri::command]
+pub async fn autovacuum_report(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    conn_id: String,
+) -> Result<AutovacuumReport, String> {
+    let pool = pools.pool_for(&store, &conn_id).await?;
+    let mut tx = begin_read_only(&pool, ANALYSIS_STATEMENT_TIMEOUT_MS).await?;
+    collect_report(&mut tx).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> AutovacuumSettings {
+        AutovacuumSettings::from_rows(&[SettingRow {
+            name: "autovacuum_vacuum_scale_factor".into(),
+            setting: "0.2".into(),
+        }])
+    }
+
+    fn table() -> TableActivity {
+        TableActivity {
+            schema: "public".into(),
+            table: "orders".into(),
+            n_live_tup: 10_000,
+            n_dead_tup: 0,
+            n_mod_since_analyze: 0,
+            reltuples: 10_000.0,
+            last_vacuum: None,
+            last_autovacuum: Some("2026-01-01 00:00:00+00".into()),
+            last_analyze: None,
+            last_autoanalyze: Some("2026-01-01 00:00:00+00".into()),
+            vacuum_count: 0,
+            autovacuum_count: 1,
+            analyze_count: 0,
+            autoanalyze_count: 1,
+            xid_age: 1_000,
+            mxid_age: 0,
+            reloptions: Vec::new(),
+            size_bytes: 1 << 20,
+        }
+    }
+
+    #[test]
+    fn flags_dead_tuples_against_effective_trigger() {
+        let mut t = table();
+        assert_eq!(assess(&t, &settings(), None).severity, Severity::Ok);
+
+        // Default trigger: 50 + 0.2 * 10k = 2050.
+        t.n_dead_tup = 9_000;
+        let report = assess(&t, &settings(), None);
+        assert_eq!(report.vacuum_trigger, 2050.0);
+        assert_eq!(report.severity, Severity::High);
+
+        // A per-table scale factor raises the trigger.
+        t.reloptions = vec!["autovacuum_vacuum_scale_factor=0.5".into()];
+        let report = assess(&t, &settings(), None);
+        assert_eq!(report.vacuum_trigger, 5050.0);
+        assert_eq!(report.severity, Severity::Low);
+    }
+
+    #[test]
+    fn grades_wraparound_age() {
+        let mut t = table();
+        t.xid_age = 210_000_000;
+        assert_eq!(assess(&t, &settings(), None).severity, Severity::High);
+        t.xid_age = 1_700_000_000;
+        t.reloptions = vec!["autovacuum_enabled=false".into()];
+        let report = assess(&t, &settings(), None);
+        assert_eq!(report.severity, Severity::Critical);
+        assert!(!report.autovacuum_enabled);
+        assert!(report
+            .issues
+            .iter()
+            .any(|i| i.kind == "autovacuum_disabled"));
+    }
+}
@@ -0,0 +1,496 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, Row};
+use std::collections::HashMap;
+use tauri::State;
+
+use crate::local_store::{db_error, now_sec, row_text, LocalStore};
+use crate::pg::{begin_read_only, fetch_as, PgPools, ANALYSIS_STATEMENT_TIMEOUT_MS};
+
+/// Objects smaller than this are left out of the report and the history.
+const DEFAULT_MIN_BYTES: i64 = 1024 * 1024;
+const HISTORY_RETENTION_SECS: i64 = 180 * 24 * 3600;
+const DEFAULT_HISTORY_LIMIT: i64 = 200;
+const PAGE_HEADER: f64 = 24.0;
+/// `BTPageOpaqueData` at the end of every B-tree page.
+const BTREE_PAGE_OPAQUE: f64 = 16.0;
+
+/// Per-table inputs for the estimate, all read from the catalogs and
+/// `pg_stats`; nothing touches the heap.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TableStats {
+    pub schema: String,
+    pub table: String,
+    pub reltuples: f64,
+    pub heap_pages: i64,
+    pub toast_pages: i64,
+    pub toast_tuples: f64,
+    pub fillfactor: i64,
+    pub block_size: i64,
+    pub max_align: i64,
+    pub tuple_header: i64,
+    pub tuple_data_width: f64,
+    /// Columns without statistics (or of type `name`) make the width unreliable.
+    pub is_na: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexStats {
+    pub schema: String,
+    pub table: String,
+    pub index: String,
+    pub reltuples: f64,
+    pub relpages: i64,
+    pub fillfactor: i64,
+    pub block_size: i64,
+    pub max_align: i64,
+    pub has_nulls: bool,
+    pub null_data_width: f64,
+    pub is_na: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BloatKind {
+    Table,
+    Index,
+}
+
+impl BloatKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            BloatKind::Table => "table",
+            BloatKind::Index => "index",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BloatPoint {
+    pub taken_at: i64,
+    pub real_bytes: i64,
+    pub bloat_bytes: i64,
+    pub bloat_ratio: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BloatEstimate {
+    pub kind: BloatKind,
+    pub schema: String,
+    pub table: String,
+    /// Index name; `None` for tables.
+    pub index: Option<String>,
+    pub real_bytes: i64,
+    /// Space beyond a perfectly packed relation.
+    pub extra_bytes: i64,
+    pub extra_ratio: f64,
+    /// Space beyond what the fill factor reserves; this is the reclaimable part.
+    pub bloat_bytes: i64,
+    pub bloat_ratio: f64,
+    pub fillfactor: i64,
+    pub is_na: bool,
+    /// The same object in the previous recorded run.
+    pub previous: Option<BloatPoint>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BloatReport {
+    pub tables: Vec<BloatEstimate>,
+    pub indexes: Vec<BloatEstimate>,
+    pub total_bloat_bytes: i64,
+    pub generated_at: i64,
+}
+
+const TABLE_STATS_SQL: &str = r#"
+SELECT
+  ns.nspname::text AS schema,
+  tbl.relname::text AS "table",
+  GREATEST(tbl.reltuples, 0)::float8 AS reltuples,
+  tbl.relpages::bigint AS heap_pages,
+  COALESCE(toast.relpages, 0)::bigint AS toast_pages,
+  GREATEST(COALESCE(toast.reltuples, 0), 0)::float8 AS toast_tuples,
+  COALESCE(substring(array_to_string(tbl.reloptions, ' ') FROM 'fillfactor=([0-9]+)')::int, 100)::bigint AS fillfactor,
+  current_setting('block_size')::bigint AS block_size,
+  CASE WHEN version() ~ 'mingw32|64-bit|x86_64|ppc64|ia64|amd64|aarch64|arm64' THEN 8 ELSE 4 END::bigint AS max_align,
+  (23 + CASE WHEN MAX(COALESCE(s.null_frac, 0)) > 0 THEN (7 + count(s.attname)) / 8 ELSE 0 END)::bigint AS tuple_header,
+  COALESCE(sum((1 - COALESCE(s.null_frac, 0)) * COALESCE(s.avg_width, 0)), 0)::float8 AS tuple_data_width,
+  (bool_or(att.atttypid = 'pg_catalog.name'::regtype)
+    OR count(*) <> count(s.attname)
+    OR tbl.reltuples < 0) AS is_na
+FROM pg_catalog.pg_attribute att
+JOIN pg_catalog.pg_class tbl ON tbl.oid = att.attrelid
+JOIN pg_catalog.pg_namespace ns ON ns.oid = tbl.relnamespace
+LEFT JOIN pg_catalog.pg_stats s
+  ON s.schemaname = ns.nspname AND s.tablename = tbl.relname
+ AND s.inherited = false AND s.attname = att.attname
+LEFT JOIN pg_catalog.pg_class toast ON toast.oid = tbl.reltoastrelid
+WHERE att.attnum > 0
+  AND NOT att.attisdropped
+  AND tbl.relkind IN ('r', 'm')
+  AND ns.nspname NOT IN ('pg_catalog', 'information_schema')
+  AND ns.nspname !~ '^pg_toast'
+GROUP BY ns.nspname, tbl.relname, tbl.reltuples, tbl.relpages, toast.relpages, toast.reltuples, tbl.reloptions
+ORDER BY 1, 2"#;
+
+/// Columns of every B-tree index (INCLUDE columns live in leaf tuples too),
+/// resolved to the table column or the index's own expression column so
+/// `pg_stats` widths apply.
+const INDEX_STATS_SQL: &str = r#"
+WITH idx AS (
+  SELECT
+    ci.oid AS idxoid, i.indrelid AS tbloid, ci.relname AS idxname,
+    ci.reltuples, ci.relpages,
+    COALESCE(substring(array_to_string(ci.reloptions, ' ') FROM 'fillfactor=([0-9]+)')::int, 90) AS fillfactor,
+    i.indkey, generate_series(0, i.indnatts - 1) AS attpos
+  FROM pg_catalog.pg_index i
+  JOIN pg_catalog.pg_class ci ON ci.oid = i.indexrelid
+  JOIN pg_catalog.pg_am am ON am.oid = ci.relam
+  WHERE am.amname = 'btree' AND ci.relpages > 0
+),
+cols AS (
+  SELECT
+    idx.*,
+    ct.relname AS tblname, ct.relnamespace,
+    COALESCE(a1.attname, a2.attname) AS attname,
+    COALESCE(a1.atttypid, a2.atttypid) AS atttypid,
+    CASE WHEN a1.attnum IS NULL THEN idx.idxname ELSE ct.relname END AS attrelname
+  FROM idx
+  JOIN pg_catalog.pg_class ct ON ct.oid = idx.tbloid
+  LEFT JOIN pg_catalog.pg_attribute a1
+    ON idx.indkey[idx.attpos] <> 0 AND a1.attrelid = idx.tbloid AND a1.attnum = idx.indkey[idx.attpos]
+  LEFT JOIN pg_catalog.pg_attribute a2
+    ON idx.indkey[idx.attpos] = 0 AND a2.attrelid = idx.idxoid AND a2.attnum = idx.attpos + 1
+)
+SELECT
+  n.nspname::text AS schema,
+  c.tblname::text AS "table",
+  c.idxname::text AS "index",
+  GREATEST(c.reltuples, 0)::float8 AS reltuples,
+  c.relpages::bigint AS relpages,
+  c.fillfactor::bigint AS fillfactor,
+  current_setting('block_size')::bigint AS block_size,
+  CASE WHEN version() ~ 'mingw32|64-bit|x86_64|ppc64|ia64|amd64|aarch64|arm64' THEN 8 ELSE 4 END::bigint AS max_align,
+  MAX(COALESCE(s.null_frac, 0)) > 0 AS has_nulls,
+  COALESCE(sum((1 - COALESCE(s.null_frac, 0)) * COALESCE(s.avg_width, 1024)), 0)::float8 AS null_data_width,
+  (bool_or(c.atttypid = 'pg_catalog.name'::regtype)
+    OR count(*) <> count(s.attname)
+    OR c.reltuples < 0) AS is_na
+FROM cols c
+JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+LEFT JOIN pg_catalog.pg_stats s
+  ON s.schemaname = n.nspname AND s.tablename = c.attrelname AND s.attname = c.attname
+WHERE n.nspname NOT IN ('pg_catalog', 'information_schema')
+  AND n.nspname !~ '^pg_toast'
+GROUP BY n.nspname, c.tblname, c.idxname, c.reltuples, c.relpages, c.fillfactor
+ORDER BY 1, 2, 3"#;
+
+/// `x` rounded up to the next multiple of `align`, the way tuple headers and
+/// data are padded on disk.
+fn align_up(x: f64, align: f64) -> f64 {
+    (x / align).ceil() * align
+}
+
+fn ratio(part: i64, whole: i64) -> f64 {
+    if whole > 0 {
+        part as f64 / whole as f64
+    } else {
+        0.0
+    }
+}
+
+/// Heap estimate: average tuple size from `pg_stats` widths plus aligned
+/// header and line pointer, packed into pages with and without the fill factor.
+/// TOAST is assumed to hold four chunks per page.
+pub fn estimate_table(stats: &TableStats) -> BloatEstimate {
+    let bs = stats.block_size as f64;
+    let ma = stats.max_align as f64;
+    let tuple =
+        4.0 + align_up(stats.tuple_header as f64, ma) + align_up(stats.tuple_data_width.ceil(), ma);
+    let usable = bs - PAGE_HEADER;
+    let fillfactor = stats.fillfactor.clamp(10, 100) as f64;
+    let toast = (stats.toast_tuples / 4.0).ceil();
+    let packed_pages = (stats.reltuples / (usable / tuple)).ceil() + toast;
+    let ff_pages = (stats.reltuples / (usable * fillfactor / (tuple * 100.0))).ceil() + toast;
+    let pages = stats.heap_pages + stats.toast_pages;
+    let extra_pages = (pages - packed_pages as i64).max(0);
+    let bloat_pages = (pages - ff_pages as i64).max(0);
+    BloatEstimate {
+        kind: BloatKind::Table,
+        schema: stats.schema.clone(),
+        table: stats.table.clone(),
+        index: None,
+        real_bytes: pages * stats.block_size,
+        extra_bytes: extra_pages * stats.block_size,
+        extra_ratio: ratio(extra_pages, pages),
+        bloat_bytes: bloat_pages * stats.block_size,
+        bloat_ratio: ratio(bloat_pages, pages),
+        fillfactor: stats.fillfactor,
+        is_na: stats.is_na,
+        previous: None,
+    }
+}
+
+/// B-tree leaf estimate: one index tuple per heap row, each an aligned
+/// `IndexTupleData` header (plus null bitmap) and key data with a 4-byte line
+/// pointer. One page is added for the metapage.
+pub fn estimate_index(stats: &IndexStats) -> BloatEstimate {
+    let bs = stats.block_size as f64;
+    let ma = stats.max_align as f64;
+    let header = if stats.has_nulls { 12.0 } else { 8.0 };
+    let data = stats.null_data_width.round();
+    let tuple = align_up(header, ma) + if data > 0.0 { align_up(data, ma) } else { 0.0 };
+    let usable = bs - BTREE_PAGE_OPAQUE - PAGE_HEADER;
+    let fillfactor = stats.fillfactor.clamp(10, 100) as f64;
+    let per_page = (usable / (4.0 + tuple)).floor().max(1.0);
+    let per_page_ff = (usable * fillfactor / (100.0 * (4.0 + tuple)))
+        .floor()
+        .max(1.0);
+    let packed_pages = 1 + (stats.reltuples / per_page).ceil() as i64;
+    let ff_pages = 1 + (stats.reltuples / per_page_ff).ceil() as i64;
+    let pages = stats.relpages;
+    let extra_pages = (pages - packed_pages).max(0);
+    let bloat_pages = (pages - ff_pages).max(0);
+    BloatEstimate {
+        kind: BloatKind::Index,
+        schema: stats.schema.clone(),
+        table: stats.table.clone(),
+        index: Some(stats.index.clone()),
+        real_bytes: pages * stats.block_size,
+        extra_bytes: extra_pages * stats.block_size,
+        extra_ratio: ratio(extra_pages, pages),
+        bloat_bytes: bloat_pages * stats.block_size,
+        bloat_ratio: ratio(bloat_pages, pages),
+        fillfactor: stats.fillfactor,
+        is_na: stats.is_na,
+        previous: None,
+    }
+}
+
+impl BloatEstimate {
+    fn object_name(&self) -> &str {
+        self.index.as_deref().unwrap_or(&self.table)
+    }
+}
+
+fn rank(estimates: &mut [BloatEstimate]) {
+    estimates.sort_by(|a, b| {
+        b.bloat_bytes
+            .cmp(&a.bloat_bytes)
+            .then_with(|| b.real_bytes.cmp(&a.real_bytes))
+    });
+}
+
+pub async fn collect_estimates(
+    conn: &mut PgConnection,
+    min_bytes: i64,
+) -> Result<(Vec<BloatEstimate>, Vec<BloatEstimate>), String> {
+    let tables: Vec<TableStats> = fetch_as(conn, TABLE_STATS_SQL, &[]).await?;
+    let indexes: Vec<IndexStats> = fetch_as(conn, INDEX_STATS_SQL, &[]).await?;
+    let mut tables: Vec<BloatEstimate> = tables
+        .iter()
+        .map(estimate_table)
+        .filter(|e| e.real_bytes >= min_bytes)
+        .collect();
+    let mut indexes: Vec<BloatEstimate> = indexes
+        .iter()
+        .map(estimate_index)
+        .filter(|e| e.real_bytes >= min_bytes)
+        .collect();
+    rank(&mut tables);
+    rank(&mut indexes);
+    Ok((tables, indexes))
+}
+
+type HistoryKey = (String, String, String);
+
+async fn previous_run(
+    store: &LocalStore,
+    conn_id: &str,
+) -> Result<HashMap<HistoryKey, BloatPoint>, String> {
+    let rows = sqlx::query(
+        "SELECT kind, schema_name, object_name, taken_at, real_bytes, bloat_bytes, bloat_ratio
+         FROM bloat_history
+         WHERE conn_id = ?1 AND taken_at = (SELECT MAX(taken_at) FROM bloat_history WHERE conn_id = ?1)",
+    )
+    .bind(conn_id)
+    .fetch_all(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    let mut previous = HashMap::new();
+    for row in rows {
+        let key = (
+            row_text(&row, "kind").unwrap_or_default(),
+            row_text(&row, "schema_name").unwrap_or_default(),
+            row_text(&row, "object_name").unwrap_or_default(),
+        );
+        previous.insert(key, point_from_row(&row)?);
+    }
+    Ok(previous)
+}
+
+fn point_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<BloatPoint, String> {
+    Ok(BloatPoint {
+        taken_at: row.try_get("taken_at").map_err(db_error)?,
+        real_bytes: row.try_get("real_bytes").map_err(db_error)?,
+        bloat_bytes: row.try_get("bloat_bytes").map_err(db_error)?,
+        bloat_ratio: row.try_get("bloat_ratio").map_err(db_error)?,
+    })
+}
+
+async fn record_history(
+    store: &LocalStore,
+    conn_id: &str,
+    taken_at: i64,
+    estimates: &[&BloatEstimate],
+) -> Result<(), String> {
+    let mut tx = store.pool()?.begin().await.map_err(db_error)?;
+    for estimate in estimates {
+        sqlx::query(
+            "INSERT OR REPLACE INTO bloat_history
+               (conn_id, taken_at, kind, schema_name, object_name, table_name, real_bytes, bloat_bytes, bloat_ratio, is_na)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        )
+        .bind(conn_id)
+        .bind(taken_at)
+        .bind(estimate.kind.as_str())
+        .bind(&estimate.schema)
+        .bind(estimate.object_name())
+        .bind(&estimate.table)
+        .bind(estimate.real_bytes)
+        .bind(estimate.bloat_bytes)
+        .bind(estimate.bloat_ratio)
+        .bind(estimate.is_na)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    }
+    sqlx::query("DELETE FROM bloat_history WHERE conn_id = ?1 AND taken_at < ?2")
+        .bind(conn_id)
+        .bind(taken_at - HISTORY_RETENTION_SECS)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)
+}
+
+/// Estimates table and B-tree index bloat from catalog statistics (the same
+/// approach as the widely used `pgsql-bloat-estimation` queries) and appends
+/// the run to `bloat_history`. Accuracy depends on a recent `ANALYZE`.
+#[tauri::command]
+pub async fn estimate_bloat(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    conn_id: String,
+    min_bytes: Option<i64>,
+) -> Result<BloatReport, String> {
+    let pool = pools.pool_for(&store, &conn_id).await?;
+    let mut tx = begin_read_only(&pool, ANALYSIS_STATEMENT_TIMEOUT_MS).await?;
+    let (mut tables, mut indexes) =
+        collect_estimates(&mut tx, min_bytes.unwrap_or(DEFAULT_MIN_BYTES).max(0)).await?;
+    drop(tx);
+
+    let previous = previous_run(&store, &conn_id).await?;
+    for estimate in tables.iter_mut().chain(indexes.iter_mut()) {
+        let key = (
+            estimate.kind.as_str().to_string(),
+            estimate.schema.clone(),
+            estimate.object_name().to_string(),
+        );
+        estimate.previous = previous.get(&key).cloned();
+    }
+    let generated_at = now_sec();
+    let all: Vec<&BloatEstimate> = tables.iter().chain(indexes.iter()).collect();
+    record_history(&store, &conn_id, generated_at, &all).await?;
+    Ok(BloatReport {
+        total_bloat_bytes: all.iter().map(|e| e.bloat_bytes).sum(),
+        tables,
+        indexes,
+        generated_at,
+    })
+}
+
+/// Recorded estimates for one table or index, oldest first.
+#[tauri::command]
+pub async fn bloat_history(
+    store: State<'_, LocalStore>,
+    conn_id: String,
+    kind: BloatKind,
+    schema: String,
+    name: String,
+    limit: Option<i64>,
+) -> Result<Vec<BloatPoint>, String> {
+    let rows = sqlx::query(
+        "SELECT taken_at, real_bytes, bloat_bytes, bloat_ratio FROM (
+           SELECT taken_at, real_bytes, bloat_bytes, bloat_ratio FROM bloat_history
+           WHERE conn_id = ?1 AND kind = ?2 AND schema_name = ?3 AND object_name = ?4
+           ORDER BY taken_at DESC LIMIT ?5
+         ) ORDER BY taken_at",
+    )
+    .bind(&conn_id)
+    .bind(kind.as_str())
+    .bind(&schema)
+    .bind(&name)
+    .bind(limit.unwrap_or(DEFAULT_HISTORY_LIMIT).max(1))
+    .fetch_all(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    rows.iter().map(point_from_row).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_estimate_accounts_for_fillfactor() {
+        // 100k rows of ~100 bytes: 132 bytes per tuple on disk, ~62 per page.
+        let mut stats = TableStats {
+            schema: "public".into(),
+            table: "orders".into(),
+            reltuples: 100_000.0,
+            heap_pages: 1617,
+            toast_pages: 0,
+            toast_tuples: 0.0,
+            fillfactor: 100,
+            block_size: 8192,
+            max_align: 8,
+            tuple_header: 23,
+            tuple_data_width: 100.0,
+            is_na: false,
+        };
+        let compact = estimate_table(&stats);
+        assert_eq!(compact.real_bytes, 1617 * 8192);
+        assert_eq!(compact.bloat_bytes, 0);
+
+        stats.heap_pages = 3234;
+        let bloated = estimate_table(&stats);
+        assert_eq!(bloated.bloat_bytes, 1617 * 8192);
+        assert!((bloated.bloat_ratio - 0.5).abs() < 1e-9);
+
+        stats.fillfactor = 50;
+        let reserved = estimate_table(&stats);
+        assert!(reserved.bloat_bytes <= 8192);
+        assert_eq!(reserved.extra_bytes, bloated.extra_bytes);
+    }
+
+    #[test]
+    fn index_estimate_counts_leaf_pages() {
+        // int8 keys: 16-byte tuples + 4-byte line pointers, 407 per page.
+        let stats = IndexStats {
+            schema: "public".into(),
+            table: "orders".into(),
+            index: "orders_pkey".into(),
+            reltuples: 100_000.0,
+            relpages: 1000,
+            fillfactor: 100,
+            block_size: 8192,
+            max_align: 8,
+            has_nulls: false,
+            null_data_width: 8.0,
+            is_na: false,
+        };
+        let estimate = estimate_index(&stats);
+        let expected_pages = 1 + (100_000.0_f64 / 407.0).ceil() as i64;
+        assert_eq!(estimate.extra_bytes, (1000 - expected_pages) * 8192);
+        assert_eq!(estimate.index.as_deref(), Some("orders_pkey"));
+    }
+}
@@ -0,0 +1,549 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use tauri::State;
+
+use crate::jsonb_shapes::{load_shapes, shape_outline, shapes_for_table, JsonbShape};
+use crate::local_store::{db_error, row_text, LocalStore};
+use crate::schema_cache::{load_schema_cache, SchemaCachePayload};
+
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+const DEFAULT_LIMIT: usize = 6;
+/// Candidates taken from each of the BM25 and embedding rankings before the
+/// hybrid score is applied to their union.
+const RERANK_POOL: usize = 24;
+const EMBEDDING_WEIGHT: f64 = 0.6;
+const EMBEDDING_BATCH: usize = 64;
+/// Document vectors kept in memory; the cache is dropped when it grows past this.
+const MAX_CACHED_VECTORS: usize = 4096;
+
+/// A retrievable context chunk. `id`, `kind`, `title`, `summary` and `content`
+/// follow `AssistantContextChunk` so results can be fed back to the model or
+/// rendered by the context sidebar unchanged.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextDocument {
+    pub id: String,
+    pub kind: String,
+    pub title: String,
+    pub summary: String,
+    pub content: Value,
+    #[serde(skip)]
+    text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoredChunk {
+    #[serde(flatten)]
+    pub chunk: ContextDocument,
+    pub score: f64,
+}
+
+/// Embedding endpoint settings resolved by the assistant from the provider.
+#[derive(Debug, Clone)]
+pub struct EmbeddingConfig {
+    pub base_url: String,
+    pub bearer: Option<String>,
+    pub model: String,
+}
+
+/// Lowercased word tokens. Identifiers are kept whole and split on `_`, and
+/// CJK characters become single-character tokens so Chinese prompts match.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let flush = |word: &mut String, tokens: &mut Vec<String>| {
+        if word.is_empty() {
+            return;
+        }
+        let lowered = word.to_lowercase();
+        let parts: Vec<&str> = lowered.split('_').filter(|p| !p.is_empty()).collect();
+        if parts.len() > 1 {
+            tokens.extend(parts.iter().map(|p| p.to_string()));
+        }
+        tokens.push(lowered.trim_matches('_').to_string());
+        word.clear();
+    };
+    for ch in text.chars() {
+        if is_cjk(ch) {
+            flush(&mut word, &mut tokens);
+            tokens.push(ch.to_string());
+        } else if ch.is_alphanumeric() || ch == '_' {
+            word.push(ch);
+        } else {
+            flush(&mut word, &mut tokens);
+        }
+    }
+    flush(&mut word, &mut tokens);
+    tokens.retain(|t| !t.is_empty());
+    tokens
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(ch as u32, 0x4E00..=0x9FFF | 0x3400..=0x4DBF | 0x3040..=0x30FF | 0xAC00..=0xD7AF)
+}
+
+pub struct Bm25Index {
+    term_freqs: Vec<HashMap<String, usize>>,
+    doc_lens: Vec<usize>,
+    avg_len: f64,
+    doc_freq: HashMap<String, usize>,
+}
+
+impl Bm25Index {
+    pub fn build<'a>(texts: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut term_freqs = Vec::new();
+        let mut doc_lens = Vec::new();
+        let mut doc_freq: HashMap<String, usize> = HashMap::new();
+        for text in texts {
+            let tokens = tokenize(text);
+            let mut tf: HashMap<String, usize> = HashMap::new();
+            for token in &tokens {
+                *tf.entry(token.clone()).or_default() += 1;
+            }
+            for term in tf.keys() {
+                *doc_freq.entry(term.clone()).or_default() += 1;
+            }
+            doc_lens.push(tokens.len());
+            term_freqs.push(tf);
+        }
+        let total: usize = doc_lens.iter().sum();
+        let avg_len = if doc_lens.is_empty() {
+            0.0
+        } else {
+            total as f64 / doc_lens.len() as f64
+        };
+        Self {
+            term_freqs,
+            doc_lens,
+            avg_len,
+            doc_freq,
+        }
+    }
+
+    /// Scores every document against `query`, highest first, dropping zeros.
+    pub fn search(&self, query: &str) -> Vec<(usize, f64)> {
+        let n = self.term_freqs.len() as f64;
+        let terms: HashSet<String> = tokenize(query).into_iter().collect();
+        let mut scored: Vec<(usize, f64)> = self
+            .term_freqs
+            .iter()
+            .enumerate()
+            .map(|(idx, tf)| {
+                let len_norm = if self.avg_len > 0.0 {
+                    self.doc_lens[idx] as f64 / self.avg_len
+                } else {
+                    1.0
+                };
+                let score = terms
+                    .iter()
+                    .filter_map(|term| {
+                        let freq = *tf.get(term)? as f64;
+                        let df = *self.doc_freq.get(term)? as f64;
+                        let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+                        Some(
+                            idf * freq * (BM25_K1 + 1.0)
+                                / (freq + BM25_K1 * (1.0 - BM25_B + BM25_B * len_norm)),
+                        )
+                    })
+                    .sum::<f64>();
+                (idx, score)
+            })
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored
+    }
+}
+
+fn schema_documents(
+    conn_id: &str,
+    payload: &SchemaCachePayload,
+    shapes: &[JsonbShape],
+) -> Vec<ContextDocument> {
+    payload
+        .tables
+        .iter()
+        .map(|table| {
+            let label = format!("{}.{}", table.schema, table.name);
+            let ddl = payload
+                .ddls
+                .iter()
+                .find(|d| d.schema == table.schema && d.name == table.name)
+                .map(|d| d.ddl.clone());
+            let mut text = format!("{} {} {}", table.schema, table.name, label);
+            if let Some(comment) = &table.comment {
+                text.push(' ');
+                text.push_str(comment);
+            }
+            for column in &table.columns {
+                text.push(' ');
+                text.push_str(&column.name);
+                text.push(' ');
+                text.push_str(&column.data_type);
+                if let Some(comment) = &column.comment {
+                    text.push(' ');
+                    text.push_str(comment);
+                }
+                for reference in column.all_references() {
+                    text.push_str(&format!(" {} {}", reference.table, reference.column));
+                }
+            }
+            if let Some(ddl) = &ddl {
+                text.push(' ');
+                text.push_str(ddl);
+            }
+            let json_shapes = shapes_for_table(shapes, &table.schema, &table.name);
+            if let Some(Value::Object(columns)) = &json_shapes {
+                for (column, shape) in columns {
+                    for line in shape_outline(column, shape) {
+                        let path = line.split(':').next().unwrap_or_default();
+                        text.push(' ');
+                        text.push_str(&path.replace(['.', '['], " ").replace(']', ""));
+                    }
+                }
+            }
+            let pk: Vec<&str> = table
+                .columns
+                .iter()
+                .filter(|c| c.is_primary_key.unwrap_or(false))
+                .map(|c| c.name.as_str())
+                .collect();
+            let total = table.columns.len();
+            let summary = format!(
+                "{} column{} • {}",
+                total,
+                if total == 1 { "" } else { "s" },
+                if pk.is_empty() {
+                    "No primary key".to_string()
+                } else {
+                    format!("PK: {}", pk.join(", "))
+                }
+            );
+            ContextDocument {
+                id: format!("schema:{}:{}", conn_id, label),
+                kind: "schema-table".to_string(),
+                title: label,
+                summary,
+                content: json!({
+                    "schema": table.schema,
+                    "table": table.name,
+                    "comment": table.comment,
+                    "columns": table.columns.iter().map(|c| json!({
+                        "name": c.name,
+                        "dataType": c.data_type,
+                        "nullable": c.nullable,
+                        "isPrimaryKey": c.is_primary_key.unwrap_or(false),
+                        "isForeignKey": c.is_foreign_key.unwrap_or(false),
+                        "references": c.references,
+                        "comment": c.comment,
+                    })).collect::<Vec<_>>(),
+                    "ddl": ddl,
+                    "jsonShapes": json_shapes,
+                }),
+                text,
+            }
+        })
+        .collect()
+}
+
+async fn saved_sql_documents(pool: &SqlitePool) -> Result<Vec<ContextDocument>, String> {
+    let rows = sqlx::query(
+        "SELECT id, name, description, sql, variables FROM saved_sql WHERE is_archived = 0",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(db_error)?;
+    let mut docs = Vec::with_capacity(rows.len());
+    for row in rows {
+        let id = row_text(&row, "id").unwrap_or_default();
+        let name = row_text(&row, "name").unwrap_or_default();
+        let description = row_text(&row, "description").filter(|d| !d.trim().is_empty());
+        let variables: Vec<String> = row_text(&row, "variables")
+            .and_then(|raw| serde_json::from_str::<Vec<Value>>(&raw).ok())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|v| v.get("name").and_then(Value::as_str).map(str::to_string))
+            .collect();
+        let summary = [
+            description.clone(),
+            (!variables.is_empty()).then(|| format!("Variables: {}", variables.join(", "))),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" • ");
+        let text = format!(
+            "{} {} {} {}",
+            name,
+            description.clone().unwrap_or_default(),
+            variables.join(" "),
+            row_text(&row, "sql").unwrap_or_default()
+        );
+        docs.push(ContextDocument {
+            id: format!("saved:{}", id),
+            kind: "saved-sql".to_string(),
+            title: name.clone(),
+            summary: if summary.is_empty() {
+                "Saved SQL template".to_string()
+            } else {
+                summary
+            },
+            content: json!({
+                "id": id,
+                "name": name,
+                "description": description,
+                "variables": variables,
+            }),
+            text,
+        });
+    }
+    Ok(docs)
+}
+
+async fn fetch_embeddings(
+    config: &EmbeddingConfig,
+    inputs: &[String],
+) -> Result<Vec<Vec<f64>>, String> {
+    let endpoint = format!("{}/embeddings", config.base_url.trim_end_matches('/'));
+    let mut request = Client::new()
+        .post(endpoint)
+        .json(&json!({ "model": config.model, "input": inputs }));
+    if let Some(token) = config.bearer.as_deref() {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    let body: Value = response.json().await.map_err(|err| err.to_string())?;
+    if !status.is_success() {
+        return Err(body
+            .get("error")
+            .and_then(|err| err.get("message"))
+            .and_then(Value::as_str)
+            .unwrap_or("embedding request failed")
+            .to_string());
+    }
+    let mut data: Vec<(usize, Vec<f64>)> = body
+        .get("data")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "embedding response missing data".to_string())?
+        .iter()
+        .enumerate()
+        .map(|(pos, item)| {
+            let index = item
+                .get("index")
+                .and_then(Value::as_u64)
+                .map(|i| i as usize)
+                .unwrap_or(pos);
+            let vector = item
+                .get("embedding")
+                .and_then(Value::as_array)
+                .map(|values| values.iter().filter_map(Value::as_f64).collect())
+                .unwrap_or_default();
+            (index, vector)
+        })
+        .collect();
+    data.sort_by_key(|(index, _)| *index);
+    if data.len() != inputs.len() {
+        return Err("embedding response size mismatch".to_string());
+    }
+    Ok(data.into_iter().map(|(_, vector)| vector).collect())
+}
+
+/// Embeds the query and every document, reusing document vectors from
+/// earlier calls with the same model and text.
+async fn embed_documents(
+    config: &EmbeddingConfig,
+    query: &str,
+    docs: &[ContextDocument],
+) -> Result<(Vec<f64>, Vec<Vec<f64>>), String> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Vec<f64>>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(Default::default);
+    let keys: Vec<String> = docs
+        .iter()
+        .map(|doc| {
+            let digest = Sha256::digest(doc.text.as_bytes());
+            format!("{}:{}", config.model, hex::encode(digest))
+        })
+        .collect();
+    let mut vectors: Vec<Option<Vec<f64>>> = {
+        let cached = cache.lock().map_err(|_| "embedding_cache_poisoned")?;
+        keys.iter().map(|key| cached.get(key).cloned()).collect()
+    };
+    let missing: Vec<usize> = (0..docs.len()).filter(|&i| vectors[i].is_none()).collect();
+    let query_vector = fetch_embeddings(config, &[query.to_string()])
+        .await?
+        .pop()
+        .unwrap_or_default();
+    for batch in missing.chunks(EMBEDDING_BATCH) {
+        let inputs: Vec<String> = batch.iter().map(|&i| docs[i].text.clone()).collect();
+        let fetched = fetch_embeddings(config, &inputs).await?;
+        let mut cached = cache.lock().map_err(|_| "embedding_cache_poisoned")?;
+        if cached.len() + fetched.len() > MAX_CACHED_VECTORS {
+            cached.clear();
+        }
+        for (&i, vector) in batch.iter().zip(fetched) {
+            cached.insert(keys[i].clone(), vector.clone());
+            vectors[i] = Some(vector);
+        }
+    }
+    Ok((
+        query_vector,
+        vectors.into_iter().map(Option::unwrap_or_default).collect(),
+    ))
+}
+
+/// Merges the BM25 top candidates with the nearest documents by embedding,
+/// scored `(1 - w) * bm25 / best_bm25 + w * cosine`. Documents with no
+/// lexical overlap still compete on similarity alone.
+fn hybrid_rank(
+    ranked: &[(usize, f64)],
+    query_vector: &[f64],
+    doc_vectors: &[Vec<f64>],
+) -> Vec<(usize, f64)> {
+    let similarity: Vec<f64> = doc_vectors
+        .iter()
+        .map(|vector| cosine(query_vector, vector))
+        .collect();
+    let mut nearest: Vec<usize> = (0..doc_vectors.len()).collect();
+    nearest.sort_by(|a, b| similarity[*b].total_cmp(&similarity[*a]));
+    let mut pool: Vec<usize> = ranked.iter().take(RERANK_POOL).map(|(i, _)| *i).collect();
+    for idx in nearest.into_iter().take(RERANK_POOL) {
+        if !pool.contains(&idx) {
+            pool.push(idx);
+        }
+    }
+    let lexical: HashMap<usize, f64> = ranked.iter().copied().collect();
+    let top = ranked
+        .first()
+        .map(|(_, s)| *s)
+        .unwrap_or(1.0)
+        .max(f64::EPSILON);
+    let mut merged: Vec<(usize, f64)> = pool
+        .into_iter()
+        .map(|idx| {
+            let bm25 = lexical.get(&idx).copied().unwrap_or(0.0) / top;
+            (
+                idx,
+                (1.0 - EMBEDDING_WEIGHT) * bm25 + EMBEDDING_WEIGHT * similarity[idx],
+            )
+        })
+        .collect();
+    merged.sort_by(|a, b| b.1.total_cmp(&a.1));
+    merged
+}
+
+fn cosine(a: &[f64], b: &[f64]) -> f64 {
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// Ranks schema tables and saved SQL for `query`. BM25 always runs; with an
+/// embedding config the BM25 and embedding top candidates are merged under a
+/// hybrid score. Embedding failures fall back to the plain BM25 order.
+pub async fn retrieve(
+    store: &LocalStore,
+    conn_id: Option<&str>,
+    query: &str,
+    limit: usize,
+    embeddings: Option<&EmbeddingConfig>,
+) -> Result<Vec<ScoredChunk>, String> {
+    let mut docs = Vec::new();
+    if let Some(conn_id) = conn_id {
+        if let Some(payload) = load_schema_cache(&store.pool()?, conn_id).await? {
+            let shapes = load_shapes(store, conn_id).await?;
+            docs.extend(schema_documents(conn_id, &payload, &shapes));
+        }
+    }
+    docs.extend(saved_sql_documents(&store.pool()?).await?);
+    if docs.is_empty() || query.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let index = Bm25Index::build(docs.iter().map(|d| d.text.as_str()));
+    let mut ranked = index.search(query);
+    if let Some(config) = embeddings {
+        if let Ok((query_vector, doc_vectors)) = embed_documents(config, query, &docs).await {
+            ranked = hybrid_rank(&ranked, &query_vector, &doc_vectors);
+        }
+    }
+    Ok(ranked
+        .into_iter()
+        .take(limit)
+        .map(|(idx, score)| ScoredChunk {
+            chunk: docs[idx].clone(),
+            score,
+        })
+        .collect())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RetrieveContextRequest {
+    #[serde(default)]
+    pub conn_id: Option<String>,
+    pub query: String,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[tauri::command]
+pub async fn retrieve_context(
+    store: State<'_, LocalStore>,
+    payload: RetrieveContextRequest,
+) -> Result<Vec<ScoredChunk>, String> {
+    retrieve(
+        &store,
+        payload.conn_id.as_deref(),
+        &payload.query,
+        payload.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, 50),
+        None,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenizes_identifiers_and_cjk() {
+        let tokens = tokenize("Orders.customer_id 订单");
+        assert_eq!(
+            tokens,
+            vec!["orders", "customer", "id", "customer_id", "订", "单"]
+        );
+    }
+
+    #[test]
+    fn bm25_prefers_matching_documents() {
+        let index = Bm25Index::build([
+            "public orders id customer_id total_amount",
+            "public customers id email name",
+            "daily orphan rows check",
+        ]);
+        let ranked = index.search("customer email");
+        assert_eq!(ranked[0].0, 1);
+        assert!(ranked.iter().all(|(idx, _)| *idx != 2));
+    }
+
+    #[test]
+    fn hybrid_rank_admits_documents_without_lexical_overlap() {
+        // Doc 0 matches lexically; doc 2 shares no terms but sits closest to
+        // the query embedding.
+        let ranked = vec![(0, 3.0)];
+        let vectors = vec![vec![0.2, 1.0], vec![-1.0, 0.0], vec![1.0, 0.1]];
+        let merged = hybrid_rank(&ranked, &[1.0, 0.0], &vectors);
+        assert_eq!(merged[0].0, 2);
+        assert!(merged.iter().any(|(idx, _)| *idx == 0));
+    }
+}
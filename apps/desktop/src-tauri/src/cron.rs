@@ -0,0 +1,236 @@
+//! Five-field cron expressions (`minute hour day-of-month month day-of-week`)
+//! for `scheduled_sql`, evaluated in local time.
+//!
+//! Fields take `*`, values, `a-b` ranges, `/step` and comma lists; months and
+//! weekdays also take three-letter names, and weekday `7` is Sunday. As in
+//! Vixie cron, when both day fields are restricted a day matching either one
+//! fires. `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` are
+//! accepted as shorthands.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+
+/// How far ahead [`CronSchedule::next_after`] looks before giving up on an
+/// expression that never fires (e.g. `0 0 30 2 *`).
+const SEARCH_DAYS: i64 = 366 * 5;
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    /// Day-of-month / day-of-week field started with `*`.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+fn invalid(field: &str, detail: impl std::fmt::Display) -> String {
+    format!("cron_invalid: {} field {}", field, detail)
+}
+
+fn parse_value(raw: &str, field: &str, names: &[&str], offset: u32) -> Result<u32, String> {
+    if let Ok(value) = raw.parse::<u32>() {
+        return Ok(value);
+    }
+    let lower = raw.to_ascii_lowercase();
+    names
+        .iter()
+        .position(|name| *name == lower)
+        .map(|index| index as u32 + offset)
+        .ok_or_else(|| invalid(field, format!("has unknown value '{}'", raw)))
+}
+
+/// Bitmask of the values a field allows, bit `n` for value `n`.
+fn parse_field(
+    raw: &str,
+    field: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+    name_offset: u32,
+) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for item in raw.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| invalid(field, format!("has bad step '{}'", step)))?;
+                (range, step)
+            }
+            None => (item, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                parse_value(start, field, names, name_offset)?,
+                parse_value(end, field, names, name_offset)?,
+            )
+        } else {
+            let value = parse_value(range, field, names, name_offset)?;
+            // `5/15` means from 5 to the end of the range.
+            (value, if item.contains('/') { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            return Err(invalid(
+                field,
+                format!("'{}' is outside {}-{}", item, min, max),
+            ));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = match expr.trim().to_ascii_lowercase().as_str() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            _ => expr.trim(),
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "cron_invalid: expected 5 fields, got {}",
+                fields.len()
+            ));
+        };
+        let mut weekdays = parse_field(weekday, "day-of-week", 0, 7, &DAY_NAMES, 0)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & 0x7f;
+        }
+        Ok(Self {
+            minutes: parse_field(minute, "minute", 0, 59, &[], 0)?,
+            hours: parse_field(hour, "hour", 0, 23, &[], 0)? as u32,
+            days: parse_field(day, "day-of-month", 1, 31, &[], 0)? as u32,
+            months: parse_field(month, "month", 1, 12, &MONTH_NAMES, 1)? as u16,
+            weekdays: weekdays as u8,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        })
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// First matching minute strictly after `after`, in wall-clock time.
+    pub fn next_naive(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let end = t + Duration::days(SEARCH_DAYS);
+        while t < end {
+            if !self.day_matches(t.date()) {
+                t = t.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & (1 << t.hour()) == 0 {
+                t = t.with_minute(0)? + Duration::hours(1);
+            } else if self.minutes & (1 << t.minute()) == 0 {
+                t += Duration::minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+        None
+    }
+
+    /// Next run in local time. Minutes skipped by a DST jump never fire; in
+    /// a repeated hour the earlier instant is used.
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let mut naive = after.naive_local();
+        loop {
+            naive = self.next_naive(naive)?;
+            if let Some(at) = Local.from_local_datetime(&naive).earliest() {
+                if at > after {
+                    return Some(at);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn parses_fields_and_shorthands() {
+        assert_eq!(
+            CronSchedule::parse("@daily").unwrap(),
+            CronSchedule::parse("0 0 * * *").unwrap()
+        );
+        assert_eq!(
+            CronSchedule::parse("0 9 * * MON-FRI").unwrap(),
+            CronSchedule::parse("0 9 * * 1-5").unwrap()
+        );
+        assert_eq!(
+            CronSchedule::parse("0 0 * * 7").unwrap(),
+            CronSchedule::parse("0 0 * * sun").unwrap()
+        );
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("0 0 * foo *").is_err());
+    }
+
+    #[test]
+    fn finds_next_run() {
+        let every_15 = CronSchedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(
+            every_15.next_naive(at("2026-03-02 10:07")),
+            Some(at("2026-03-02 10:15"))
+        );
+        assert_eq!(
+            every_15.next_naive(at("2026-03-02 10:15")),
+            Some(at("2026-03-02 10:30"))
+        );
+        // 2026-03-02 is a Monday.
+        let weekdays = CronSchedule::parse("30 8 * * mon-fri").unwrap();
+        assert_eq!(
+            weekdays.next_naive(at("2026-03-06 09:00")),
+            Some(at("2026-03-09 08:30"))
+        );
+        // Both day fields restricted: the 1st of the month or any Sunday.
+        let either = CronSchedule::parse("0 0 1 * 0").unwrap();
+        assert_eq!(
+            either.next_naive(at("2026-03-02 00:00")),
+            Some(at("2026-03-08 00:00"))
+        );
+        let leap = CronSchedule::parse("0 12 29 2 *").unwrap();
+        assert_eq!(
+            leap.next_naive(at("2026-01-01 00:00")),
+            Some(at("2028-02-29 12:00"))
+        );
+        assert_eq!(
+            CronSchedule::parse("0 0 30 2 *")
+                .unwrap()
+                .next_naive(at("2026-01-01 00:00")),
+            None
+        );
+    }
+}
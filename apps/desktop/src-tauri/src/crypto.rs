@@ -0,0 +1,83 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, OsRng};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use pbkdf2::pbkdf2_hmac;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+/// Same envelope as `AesCipher` in `lib/aes.ts`: base64 IV and base64
+/// ciphertext with the GCM tag appended (Web Crypto layout).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AesCipher {
+    pub alg: String,
+    pub iv: String,
+    pub ct: String,
+}
+
+fn cipher_from_key(key_base64: &str) -> Result<Aes256Gcm, String> {
+    let raw = BASE64
+        .decode(key_base64.trim())
+        .map_err(|_| "invalid_device_key".to_string())?;
+    if raw.len() != 32 {
+        return Err("invalid_device_key".to_string());
+    }
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&raw)))
+}
+
+pub fn aes_decrypt_to_string(key_base64: &str, cipher: &AesCipher) -> Result<String, String> {
+    if cipher.alg != "A256GCM" {
+        return Err(format!("unsupported_cipher_alg:{}", cipher.alg));
+    }
+    let aes = cipher_from_key(key_base64)?;
+    let iv = BASE64
+        .decode(&cipher.iv)
+        .map_err(|_| "invalid_cipher_iv".to_string())?;
+    if iv.len() != 12 {
+        return Err("invalid_cipher_iv".to_string());
+    }
+    let ct = BASE64
+        .decode(&cipher.ct)
+        .map_err(|_| "invalid_cipher_payload".to_string())?;
+    let plain = aes
+        .decrypt(Nonce::from_slice(&iv), ct.as_ref())
+        .map_err(|_| "local_cipher_decrypt_failed".to_string())?;
+    String::from_utf8(plain).map_err(|_| "local_cipher_decrypt_failed".to_string())
+}
+
+pub fn aes_encrypt_string(key_base64: &str, plain: &str) -> Result<AesCipher, String> {
+    let aes = cipher_from_key(key_base64)?;
+    let mut iv = [0u8; 12];
+    OsRng.fill_bytes(&mut iv);
+    let ct = aes
+        .encrypt(Nonce::from_slice(&iv), plain.as_bytes())
+        .map_err(|_| "local_cipher_encrypt_failed".to_string())?;
+    Ok(AesCipher {
+        alg: "A256GCM".to_string(),
+        iv: BASE64.encode(iv),
+        ct: BASE64.encode(ct),
+    })
+}
+
+/// Base64 AES-256 key derived from a passphrase with PBKDF2-HMAC-SHA256,
+/// the same derivation Web Crypto offers, so bundles stay portable.
+pub fn passphrase_key(passphrase: &str, salt: &[u8], iterations: u32) -> String {
+    let mut key = [0u8; 32];
+    pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    BASE64.encode(key)
+}
+
+/// Random base64 bytes, e.g. a new device key or a KDF salt.
+pub fn random_base64(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buf);
+    BASE64.encode(buf)
+}
+
+/// Random hex string from the OS RNG, for one-shot tokens.
+pub fn random_token(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buf);
+    hex::encode(buf)
+}
@@ -0,0 +1,261 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::postgres::PgRow;
+use sqlx::{Column, Executor, PgConnection, Row};
+use std::time::Instant;
+use tauri::State;
+
+use crate::local_store::LocalStore;
+use crate::ops_audit::{self, AuditEntry};
+use crate::pg::{
+    begin_guarded, fetch_json, pg_arguments, pg_error, strict_read_only, strip_trailing_semicolons,
+    PgPools,
+};
+use crate::query_history::{self, HistoryEntry};
+use crate::sql_guard::{is_read_only_sql, read_only_override, split_statements};
+
+const EXECUTE_STATEMENT_TIMEOUT_MS: u64 = 30_000;
+/// `ops_audit.action` for strict read-only violations.
+const STRICT_AUDIT_ACTION: &str = "strict_read_only";
+/// SQLSTATE `read_only_sql_transaction`, as formatted by `pg_error`.
+const READ_ONLY_SQLSTATE: &str = "[25006]";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecuteResult {
+    pub sql: String,
+    pub params: Vec<Value>,
+    pub columns: Vec<String>,
+    pub rows: Vec<Value>,
+    pub row_count: usize,
+    /// Set for statements that return no rows.
+    pub rows_affected: Option<u64>,
+    pub strict_read_only: bool,
+    pub elapsed_ms: u64,
+}
+
+/// Best-effort JSON for statements `fetch_json` can't wrap (`SHOW`,
+/// `EXPLAIN`, `... RETURNING`); anything else falls back to text or null.
+fn row_value(row: &PgRow, index: usize) -> Value {
+    if let Ok(value) = row.try_get::<Option<String>, _>(index) {
+        return value.map(Value::String).unwrap_or(Value::Null);
+    }
+    if let Ok(value) = row.try_get::<Option<i64>, _>(index) {
+        return json!(value);
+    }
+    if let Ok(value) = row.try_get::<Option<i32>, _>(index) {
+        return json!(value);
+    }
+    if let Ok(value) = row.try_get::<Option<f64>, _>(index) {
+        return json!(value);
+    }
+    if let Ok(value) = row.try_get::<Option<bool>, _>(index) {
+        return json!(value);
+    }
+    if let Ok(value) = row.try_get::<Option<Value>, _>(index) {
+        return value.unwrap_or(Value::Null);
+    }
+    Value::Null
+}
+
+async fn record_violation(
+    store: &LocalStore,
+    conn_id: &str,
+    message: String,
+) -> Result<(), String> {
+    ops_audit::append(
+        store,
+        AuditEntry {
+            conn_id,
+            action: STRICT_AUDIT_ACTION,
+            target_pid: None,
+            status: "refused",
+            message: Some(message),
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+/// Runs one statement and returns its rows, or the affected row count when
+/// it returns none.
+async fn run_statement(
+    conn: &mut PgConnection,
+    sql: &str,
+    params: &[Value],
+) -> Result<(Vec<String>, Vec<Value>, Option<u64>), String> {
+    let described = conn.describe(sql).await.map_err(pg_error)?;
+    let columns: Vec<String> = described
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect();
+    if columns.is_empty() {
+        let done = sqlx::query_with(sql, pg_arguments(params)?)
+            .execute(&mut *conn)
+            .await
+            .map_err(pg_error)?;
+        return Ok((columns, Vec::new(), Some(done.rows_affected())));
+    }
+    if is_read_only_sql(sql) {
+        let rows = fetch_json(conn, sql, params).await?;
+        return Ok((columns, rows, None));
+    }
+    let rows = sqlx::query_with(sql, pg_arguments(params)?)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(pg_error)?
+        .iter()
+        .map(|row| {
+            let object = columns
+                .iter()
+                .enumerate()
+                .map(|(index, name)| (name.clone(), row_value(row, index)))
+                .collect();
+            Value::Object(object)
+        })
+        .collect();
+    Ok((columns, rows, None))
+}
+
+/// Runs `body` in its own guarded transaction, verifying strict sessions
+/// first and auditing writes the server rejects.
+async fn run_in_transaction(
+    store: &LocalStore,
+    pools: &PgPools,
+    conn_id: &str,
+    body: &str,
+    params: &[Value],
+    strict: bool,
+    read_only: bool,
+) -> Result<(Vec<String>, Vec<Value>, Option<u64>), String> {
+    let pool = pools.pool_for(store, conn_id).await?;
+    // Strict transactions take the session default so the check below
+    // verifies the connection layer, not our own `SET TRANSACTION`.
+    let mut tx = begin_guarded(&pool, EXECUTE_STATEMENT_TIMEOUT_MS, read_only && !strict).await?;
+    if strict {
+        let state: String = sqlx::query_scalar("SHOW transaction_read_only")
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(pg_error)?;
+        if state != "on" {
+            drop(tx);
+            record_violation(
+                store,
+                conn_id,
+                format!("session not read-only (transaction_read_only = {})", state),
+            )
+            .await?;
+            return Err("strict_read_only_unverified".to_string());
+        }
+    }
+
+    let (columns, rows, rows_affected) = match run_statement(&mut tx, body, params).await {
+        Ok(result) => result,
+        Err(err) => {
+            drop(tx);
+            if strict && err.contains(READ_ONLY_SQLSTATE) {
+                record_violation(store, conn_id, format!("write rejected: {}", err)).await?;
+            }
+            return Err(err);
+        }
+    };
+    if read_only {
+        // Nothing to keep; rolling back also discards any session-level SET.
+        tx.rollback().await.map_err(pg_error)?;
+    } else {
+        tx.commit().await.map_err(pg_error)?;
+    }
+    Ok((columns, rows, rows_affected))
+}
+
+/// Executes a single statement. Strict read-only profiles run on sessions
+/// opened with `SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY`;
+/// statements that would undo that are refused, every batch checks `SHOW
+/// transaction_read_only` first, and each violation (including writes
+/// rejected by the server) is written to `ops_audit`. Every statement that
+/// reaches the server is recorded in `query_history`.
+pub async fn execute(
+    store: &LocalStore,
+    pools: &PgPools,
+    conn_id: &str,
+    sql: &str,
+    params: Vec<Value>,
+    allow_write: bool,
+    saved_sql_id: Option<&str>,
+) -> Result<ExecuteResult, String> {
+    let body = strip_trailing_semicolons(sql).to_string();
+    if body.is_empty() {
+        return Err("empty_sql".to_string());
+    }
+    if split_statements(&body).len() > 1 {
+        return Err("multiple_statements".to_string());
+    }
+    let strict = strict_read_only(store, conn_id).await?;
+    let read_only = strict || is_read_only_sql(&body);
+    if strict {
+        if let Some(statement) = read_only_override(&body) {
+            record_violation(store, conn_id, format!("override refused: {}", statement)).await?;
+            return Err(
+                "strict_read_only_violation: statement would lift read-only mode".to_string(),
+            );
+        }
+    } else if !read_only && !allow_write {
+        return Err("write_requires_confirmation".to_string());
+    }
+
+    let started = Instant::now();
+    let outcome =
+        run_in_transaction(store, pools, conn_id, &body, &params, strict, read_only).await;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    // History is best effort: a failed insert must not turn a committed
+    // statement into an error.
+    let _ = query_history::record(
+        store,
+        HistoryEntry {
+            conn_id,
+            saved_sql_id,
+            sql: &body,
+            params: &params,
+            duration_ms: elapsed_ms,
+            row_count: outcome.as_ref().ok().map(|(_, rows, rows_affected)| {
+                rows_affected.map(|n| n as i64).unwrap_or(rows.len() as i64)
+            }),
+            error: outcome.as_ref().err().map(String::as_str),
+        },
+    )
+    .await;
+    let (columns, rows, rows_affected) = outcome?;
+    Ok(ExecuteResult {
+        sql: body,
+        params,
+        row_count: rows.len(),
+        columns,
+        rows,
+        rows_affected,
+        strict_read_only: strict,
+        elapsed_ms,
+    })
+}
+
+/// Executes a single statement for the webview; see [`execute`].
+#[tauri::command]
+pub async fn execute_sql(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    conn_id: String,
+    sql: String,
+    params: Option<Vec<Value>>,
+    allow_write: Option<bool>,
+    saved_sql_id: Option<String>,
+) -> Result<ExecuteResult, String> {
+    execute(
+        &store,
+        &pools,
+        &conn_id,
+        &sql,
+        params.unwrap_or_default(),
+        allow_write.unwrap_or(false),
+        saved_sql_id.as_deref(),
+    )
+    .await
+}
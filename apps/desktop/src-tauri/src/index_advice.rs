@@ -0,0 +1,781 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::PgConnection;
+use tauri::State;
+
+use crate::local_store::{now_sec, LocalStore};
+use crate::pg::{
+    begin_read_only, fetch_as, fetch_one_as, pg_error, PgPools, ANALYSIS_STATEMENT_TIMEOUT_MS,
+};
+use crate::schema_cache::{quote_ident, quote_qualified};
+use crate::stat_statements::explain_with_samples;
+
+/// `REINDEX ... CONCURRENTLY` needs PostgreSQL 12.
+const REINDEX_CONCURRENTLY_MIN_VERSION: i64 = 120_000;
+/// Tables smaller than this are cheap to scan; sequential scans are expected.
+const SEQ_SCAN_MIN_ROWS: i64 = 10_000;
+const MIB: f64 = 1024.0 * 1024.0;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexStat {
+    pub schema: String,
+    pub table: String,
+    pub index: String,
+    pub definition: String,
+    pub method: String,
+    /// Key columns in index order; expression keys appear as `NULL`.
+    pub columns: Vec<Option<String>>,
+    pub key_count: usize,
+    pub opclasses: String,
+    pub predicate: Option<String>,
+    pub has_expressions: bool,
+    pub is_unique: bool,
+    pub is_primary: bool,
+    pub is_valid: bool,
+    pub backs_constraint: bool,
+    /// Attached to a partitioned parent index, which owns it: PostgreSQL
+    /// refuses to drop it on its own.
+    #[serde(default)]
+    pub has_parent: bool,
+    pub idx_scan: i64,
+    pub size_bytes: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TableStat {
+    pub schema: String,
+    pub table: String,
+    pub seq_scan: i64,
+    pub seq_tup_read: i64,
+    pub idx_scan: i64,
+    pub n_live_tup: i64,
+    pub size_bytes: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ForeignKeyStat {
+    pub schema: String,
+    pub table: String,
+    pub constraint_name: String,
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatsWindow {
+    stats_reset: Option<String>,
+    postmaster_start: Option<String>,
+    server_version: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexFinding {
+    /// `invalid_index`, `duplicate_index`, `overlapping_index`,
+    /// `unused_index`, `unindexed_foreign_key`, `seq_scan_heavy` or
+    /// `seq_scan_in_plan`.
+    pub kind: String,
+    pub severity: String,
+    pub score: f64,
+    pub schema: String,
+    pub table: String,
+    pub index: Option<String>,
+    pub title: String,
+    pub evidence: Value,
+    /// Suggested statement for the user to review; never executed here.
+    pub suggested_ddl: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexAdvice {
+    /// Usage counters are cumulative since this point.
+    pub stats_since: Option<String>,
+    pub findings: Vec<IndexFinding>,
+    /// Requested queries that could not be planned, with the error.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unexplained: Vec<UnexplainedQuery>,
+    pub generated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnexplainedQuery {
+    pub query: String,
+    pub error: String,
+}
+
+/// A query and its `EXPLAIN (FORMAT JSON)` output.
+#[derive(Debug, Clone)]
+pub struct PlannedQuery {
+    pub query: String,
+    pub plan: Value,
+}
+
+const INDEXES_SQL: &str = r#"
+SELECT
+  ns.nspname AS schema,
+  t.relname AS "table",
+  i.relname AS index,
+  pg_catalog.pg_get_indexdef(ix.indexrelid) AS definition,
+  am.amname AS method,
+  ARRAY(
+    SELECT a.attname::text
+    FROM unnest(ix.indkey::int2[]) WITH ORDINALITY AS k(attnum, ord)
+    LEFT JOIN pg_catalog.pg_attribute a ON a.attrelid = ix.indrelid AND a.attnum = k.attnum
+    ORDER BY k.ord
+  ) AS columns,
+  ix.indnkeyatts::int AS key_count,
+  ix.indclass::text AS opclasses,
+  pg_catalog.pg_get_expr(ix.indpred, ix.indrelid) AS predicate,
+  (ix.indexprs IS NOT NULL) AS has_expressions,
+  ix.indisunique AS is_unique,
+  ix.indisprimary AS is_primary,
+  ix.indisvalid AS is_valid,
+  EXISTS (SELECT 1 FROM pg_catalog.pg_constraint c WHERE c.conindid = ix.indexrelid) AS backs_constraint,
+  EXISTS (SELECT 1 FROM pg_catalog.pg_inherits inh WHERE inh.inhrelid = ix.indexrelid) AS has_parent,
+  COALESCE(st.idx_scan, 0)::bigint AS idx_scan,
+  pg_catalog.pg_relation_size(i.oid)::bigint AS size_bytes
+FROM pg_catalog.pg_index ix
+JOIN pg_catalog.pg_class t ON t.oid = ix.indrelid
+JOIN pg_catalog.pg_namespace ns ON ns.oid = t.relnamespace
+JOIN pg_catalog.pg_class i ON i.oid = ix.indexrelid
+JOIN pg_catalog.pg_am am ON am.oid = i.relam
+LEFT JOIN pg_catalog.pg_stat_user_indexes st ON st.indexrelid = ix.indexrelid
+-- Indexes on partitioned parents (relkind 'I') have no statistics of their
+-- own and reject DROP INDEX CONCURRENTLY; their partitions' indexes are listed
+-- but flagged by has_parent so they are never suggested for removal.
+WHERE i.relkind = 'i'
+  AND ns.nspname NOT IN ('pg_catalog', 'information_schema')
+  AND ns.nspname NOT LIKE 'pg_toast%'
+ORDER BY 1, 2, 3"#;
+
+const TABLES_SQL: &str = r#"
+SELECT
+  schemaname AS schema,
+  relname AS "table",
+  COALESCE(seq_scan, 0)::bigint AS seq_scan,
+  COALESCE(seq_tup_read, 0)::bigint AS seq_tup_read,
+  COALESCE(idx_scan, 0)::bigint AS idx_scan,
+  COALESCE(n_live_tup, 0)::bigint AS n_live_tup,
+  pg_catalog.pg_relation_size(relid)::bigint AS size_bytes
+FROM pg_catalog.pg_stat_user_tables
+ORDER BY 1, 2"#;
+
+const FOREIGN_KEYS_SQL: &str = r#"
+SELECT
+  n.nspname AS schema,
+  c.relname AS "table",
+  con.conname AS constraint_name,
+  ARRAY(
+    SELECT a.attname::text
+    FROM unnest(con.conkey) WITH ORDINALITY AS k(attnum, ord)
+    JOIN pg_catalog.pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum
+    ORDER BY k.ord
+  ) AS columns
+FROM pg_catalog.pg_constraint con
+JOIN pg_catalog.pg_class c ON c.oid = con.conrelid
+JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+WHERE con.contype = 'f'
+  AND n.nspname NOT IN ('pg_catalog', 'information_schema')
+ORDER BY 1, 2, 3"#;
+
+const STATS_WINDOW_SQL: &str = r#"
+SELECT
+  (SELECT stats_reset::text FROM pg_catalog.pg_stat_database WHERE datname = current_database()) AS stats_reset,
+  pg_catalog.pg_postmaster_start_time()::text AS postmaster_start,
+  pg_catalog.current_setting('server_version_num')::bigint AS server_version"#;
+
+fn severity(score: f64) -> &'static str {
+    if score >= 70.0 {
+        "high"
+    } else if score >= 45.0 {
+        "medium"
+    } else {
+        "low"
+    }
+}
+
+fn size_weight(bytes: i64) -> f64 {
+    ((1.0 + bytes as f64 / MIB).ln() * 8.0).min(30.0)
+}
+
+fn drop_index_ddl(index: &IndexStat) -> String {
+    format!(
+        "DROP INDEX CONCURRENTLY IF EXISTS {};",
+        quote_qualified(&index.schema, &index.index)
+    )
+}
+
+fn qualified(index: &IndexStat) -> (&str, &str) {
+    (&index.schema, &index.index)
+}
+
+fn key_columns(index: &IndexStat) -> &[Option<String>] {
+    &index.columns[..index.key_count.min(index.columns.len())]
+}
+
+fn finding(
+    kind: &str,
+    score: f64,
+    schema: &str,
+    table: &str,
+    title: String,
+    evidence: Value,
+) -> IndexFinding {
+    let score = (score * 10.0).round() / 10.0;
+    IndexFinding {
+        kind: kind.to_string(),
+        severity: severity(score).to_string(),
+        score,
+        schema: schema.to_string(),
+        table: table.to_string(),
+        index: None,
+        title,
+        evidence,
+        suggested_ddl: None,
+    }
+}
+
+fn index_evidence(index: &IndexStat) -> Value {
+    json!({
+        "definition": index.definition,
+        "idx_scan": index.idx_scan,
+        "size_bytes": index.size_bytes,
+        "is_unique": index.is_unique,
+        "backs_constraint": index.backs_constraint,
+    })
+}
+
+/// Of two equivalent indexes, keeps the one enforcing a constraint, then the
+/// more used one.
+fn keeper_first<'a>(a: &'a IndexStat, b: &'a IndexStat) -> (&'a IndexStat, &'a IndexStat) {
+    let rank = |i: &IndexStat| (i.is_primary, i.backs_constraint, i.is_unique, i.idx_scan);
+    if rank(b) > rank(a) {
+        (b, a)
+    } else {
+        (a, b)
+    }
+}
+
+fn same_shape(a: &IndexStat, b: &IndexStat) -> bool {
+    a.method == b.method && a.predicate == b.predicate && !a.has_expressions && !b.has_expressions
+}
+
+/// Ranks index findings from catalog and usage statistics. Pure so it can be
+/// tested without a server; `server_version` is `server_version_num`.
+pub fn analyze(
+    indexes: &[IndexStat],
+    tables: &[TableStat],
+    foreign_keys: &[ForeignKeyStat],
+    server_version: i64,
+) -> Vec<IndexFinding> {
+    let mut findings = Vec::new();
+    // Keyed by (schema, index): index names are only unique per schema.
+    let mut flagged: Vec<(&str, &str)> = Vec::new();
+    // Indexes another finding relies on; never suggested for removal.
+    let mut kept: Vec<(&str, &str)> = Vec::new();
+
+    for index in indexes.iter().filter(|i| !i.is_valid) {
+        flagged.push(qualified(index));
+        findings.push(IndexFinding {
+            index: Some(index.index.clone()),
+            suggested_ddl: Some(format!(
+                "REINDEX INDEX {}{};",
+                if server_version >= REINDEX_CONCURRENTLY_MIN_VERSION {
+                    "CONCURRENTLY "
+                } else {
+                    ""
+                },
+                quote_qualified(&index.schema, &index.index)
+            )),
+            ..finding(
+                "invalid_index",
+                90.0,
+                &index.schema,
+                &index.table,
+                format!("Index {} is invalid", index.index),
+                index_evidence(index),
+            )
+        });
+    }
+
+    let valid: Vec<&IndexStat> = indexes.iter().filter(|i| i.is_valid).collect();
+    for (pos, a) in valid.iter().enumerate() {
+        for b in valid.iter().skip(pos + 1) {
+            if a.schema != b.schema || a.table != b.table || !same_shape(a, b) {
+                continue;
+            }
+            let (ka, kb) = (key_columns(a), key_columns(b));
+            if ka == kb && a.opclasses == b.opclasses {
+                let (keep, drop) = keeper_first(a, b);
+                if drop.is_primary
+                    || drop.backs_constraint
+                    || drop.has_parent
+                    || flagged.contains(&qualified(drop))
+                {
+                    continue;
+                }
+                flagged.push(qualified(drop));
+                kept.push(qualified(keep));
+                findings.push(IndexFinding {
+                    index: Some(drop.index.clone()),
+                    suggested_ddl: Some(drop_index_ddl(drop)),
+                    ..finding(
+                        "duplicate_index",
+                        80.0 + size_weight(drop.size_bytes) / 3.0,
+                        &drop.schema,
+                        &drop.table,
+                        format!("{} duplicates {}", drop.index, keep.index),
+                        json!({
+                            "index": index_evidence(drop),
+                            "kept": index_evidence(keep),
+                        }),
+                    )
+                });
+                continue;
+            }
+            if a.method != "btree" {
+                continue;
+            }
+            // A non-unique btree whose keys prefix another index is redundant.
+            let (short, long) = if ka.len() < kb.len() { (a, b) } else { (b, a) };
+            let (ks, kl) = (key_columns(short), key_columns(long));
+            if ks.len() < kl.len()
+                && kl.starts_with(ks)
+                && !short.is_unique
+                && !short.backs_constraint
+                && !short.has_parent
+                && !flagged.contains(&qualified(short))
+            {
+                flagged.push(qualified(short));
+                kept.push(qualified(long));
+                findings.push(IndexFinding {
+                    index: Some(short.index.clone()),
+                    suggested_ddl: Some(drop_index_ddl(short)),
+                    ..finding(
+                        "overlapping_index",
+                        60.0 + size_weight(short.size_bytes) / 3.0,
+                        &short.schema,
+                        &short.table,
+                        format!("{} is a prefix of {}", short.index, long.index),
+                        json!({
+                            "index": index_evidence(short),
+                            "covered_by": index_evidence(long),
+                        }),
+                    )
+                });
+            }
+        }
+    }
+
+    for index in &valid {
+        if index.idx_scan > 0
+            || index.is_unique
+            || index.is_primary
+            || index.backs_constraint
+            || index.has_parent
+            || flagged.contains(&qualified(index))
+            || kept.contains(&qualified(index))
+        {
+            continue;
+        }
+        findings.push(IndexFinding {
+            index: Some(index.index.clone()),
+            suggested_ddl: Some(drop_index_ddl(index)),
+            ..finding(
+                "unused_index",
+                35.0 + size_weight(index.size_bytes) * 1.5,
+                &index.schema,
+                &index.table,
+                format!("{} has never been scanned", index.index),
+                index_evidence(index),
+            )
+        });
+    }
+
+    for table in tables {
+        let heavy = table.n_live_tup >= SEQ_SCAN_MIN_ROWS && table.seq_scan > table.idx_scan;
+        let unindexed: Vec<&ForeignKeyStat> = foreign_keys
+            .iter()
+            .filter(|fk| fk.schema == table.schema && fk.table == table.table)
+            .filter(|fk| {
+                !valid.iter().any(|i| {
+                    i.schema == fk.schema
+                        && i.table == fk.table
+                        && i.predicate.is_none()
+                        && key_columns(i).len() >= fk.columns.len()
+                        && fk.columns.iter().all(|col| {
+                            key_columns(i)[..fk.columns.len()].contains(&Some(col.clone()))
+                        })
+                })
+            })
+            .collect();
+        for fk in &unindexed {
+            let columns: Vec<String> = fk.columns.iter().map(|c| quote_ident(c)).collect();
+            findings.push(IndexFinding {
+                suggested_ddl: Some(format!(
+                    "CREATE INDEX CONCURRENTLY ON {} ({});",
+                    quote_qualified(&fk.schema, &fk.table),
+                    columns.join(", ")
+                )),
+                ..finding(
+                    "unindexed_foreign_key",
+                    if heavy { 55.0 } else { 35.0 },
+                    &fk.schema,
+                    &fk.table,
+                    format!(
+                        "Foreign key {} ({}) has no supporting index",
+                        fk.constraint_name,
+                        fk.columns.join(", ")
+                    ),
+                    json!({
+                        "constraint": fk.constraint_name,
+                        "columns": fk.columns,
+                        "seq_scan": table.seq_scan,
+                        "n_live_tup": table.n_live_tup,
+                    }),
+                )
+            });
+        }
+        if heavy {
+            let avg_rows = table.seq_tup_read / table.seq_scan.max(1);
+            findings.push(finding(
+                "seq_scan_heavy",
+                30.0 + ((1.0 + table.seq_tup_read as f64 / 1e6).ln() * 6.0).min(35.0),
+                &table.schema,
+                &table.table,
+                format!(
+                    "{}.{} is read mostly by sequential scans",
+                    table.schema, table.table
+                ),
+                json!({
+                    "seq_scan": table.seq_scan,
+                    "idx_scan": table.idx_scan,
+                    "seq_tup_read": table.seq_tup_read,
+                    "avg_rows_per_seq_scan": avg_rows,
+                    "n_live_tup": table.n_live_tup,
+                    "size_bytes": table.size_bytes,
+                }),
+            ));
+        }
+    }
+
+    sort_findings(&mut findings);
+    findings
+}
+
+fn sort_findings(findings: &mut [IndexFinding]) {
+    findings.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.schema.cmp(&b.schema))
+            .then_with(|| a.table.cmp(&b.table))
+    });
+}
+
+/// Sequential scan nodes anywhere in an `EXPLAIN (FORMAT JSON)` plan.
+fn seq_scan_nodes<'a>(node: &'a Value, out: &mut Vec<&'a Value>) {
+    match node {
+        Value::Array(items) => items.iter().for_each(|item| seq_scan_nodes(item, out)),
+        Value::Object(map) => {
+            if map.get("Node Type").and_then(Value::as_str) == Some("Seq Scan") {
+                out.push(node);
+            }
+            if let Some(plan) = map.get("Plan") {
+                seq_scan_nodes(plan, out);
+            }
+            if let Some(children) = map.get("Plans") {
+                seq_scan_nodes(children, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Flags filtered sequential scans of large tables in the given plans; each
+/// `(table, filter)` pair is reported once. Plans without `VERBOSE` carry no
+/// schema, so the relation is matched by name.
+pub fn plan_findings(plans: &[PlannedQuery], tables: &[TableStat]) -> Vec<IndexFinding> {
+    let mut findings: Vec<IndexFinding> = Vec::new();
+    for planned in plans {
+        let mut nodes = Vec::new();
+        seq_scan_nodes(&planned.plan, &mut nodes);
+        for node in nodes {
+            let (Some(relation), Some(filter)) = (
+                node.get("Relation Name").and_then(Value::as_str),
+                node.get("Filter").and_then(Value::as_str),
+            ) else {
+                continue;
+            };
+            let schema = node.get("Schema").and_then(Value::as_str);
+            let Some(table) = tables
+                .iter()
+                .find(|t| t.table == relation && schema.is_none_or(|schema| t.schema == schema))
+            else {
+                continue;
+            };
+            if table.n_live_tup < SEQ_SCAN_MIN_ROWS
+                || findings.iter().any(|f| {
+                    f.schema == table.schema
+                        && f.table == table.table
+                        && f.evidence.get("filter").and_then(Value::as_str) == Some(filter)
+                })
+            {
+                continue;
+            }
+            findings.push(finding(
+                "seq_scan_in_plan",
+                40.0 + ((1.0 + table.n_live_tup as f64 / 1e5).ln() * 6.0).min(30.0),
+                &table.schema,
+                &table.table,
+                format!(
+                    "Plan scans {}.{} sequentially to filter {}",
+                    table.schema, table.table, filter
+                ),
+                json!({
+                    "query": planned.query,
+                    "filter": filter,
+                    "plan_rows": node.get("Plan Rows"),
+                    "n_live_tup": table.n_live_tup,
+                }),
+            ));
+        }
+    }
+    findings
+}
+
+/// Plans each query with sample parameters inside its own savepoint, so one
+/// failing query does not abort the rest of the read-only transaction.
+async fn explain_queries(
+    conn: &mut PgConnection,
+    queries: &[String],
+) -> Result<(Vec<PlannedQuery>, Vec<UnexplainedQuery>), String> {
+    let mut planned = Vec::new();
+    let mut unexplained = Vec::new();
+    for query in queries.iter().filter(|q| !q.trim().is_empty()) {
+        sqlx::query("SAVEPOINT rdv_index_advice")
+            .execute(&mut *conn)
+            .await
+            .map_err(pg_error)?;
+        match explain_with_samples(conn, query).await {
+            Ok(explained) => planned.push(PlannedQuery {
+                query: query.clone(),
+                plan: explained.plan,
+            }),
+            Err(error) => {
+                sqlx::query("ROLLBACK TO SAVEPOINT rdv_index_advice")
+                    .execute(&mut *conn)
+                    .await
+                    .map_err(pg_error)?;
+                unexplained.push(UnexplainedQuery {
+                    query: query.clone(),
+                    error,
+                });
+            }
+        }
+    }
+    Ok((planned, unexplained))
+}
+
+/// Statistics-based findings, plus plan findings for `queries` (normalised
+/// statements, e.g. from `pg_stat_statements`; `$n` get sample values).
+pub async fn collect_advice(
+    conn: &mut PgConnection,
+    queries: &[String],
+) -> Result<IndexAdvice, String> {
+    let indexes: Vec<IndexStat> = fetch_as(conn, INDEXES_SQL, &[]).await?;
+    let tables: Vec<TableStat> = fetch_as(conn, TABLES_SQL, &[]).await?;
+    let foreign_keys: Vec<ForeignKeyStat> = fetch_as(conn, FOREIGN_KEYS_SQL, &[]).await?;
+    let window: Option<StatsWindow> = fetch_one_as(conn, STATS_WINDOW_SQL, &[]).await?;
+    let server_version = window.as_ref().map_or(0, |w| w.server_version);
+    let (planned, unexplained) = explain_queries(conn, queries).await?;
+    let mut findings = analyze(&indexes, &tables, &foreign_keys, server_version);
+    findings.extend(plan_findings(&planned, &tables));
+    sort_findings(&mut findings);
+    Ok(IndexAdvice {
+        stats_since: window.and_then(|w| w.stats_reset.or(w.postmaster_start)),
+        findings,
+        unexplained,
+        generated_at: now_sec(),
+    })
+}
+
+#[tauri::command]
+pub async fn index_advice(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    conn_id: String,
+    queries: Option<Vec<String>>,
+) -> Result<IndexAdvice, String> {
+    let pool = pools.pool_for(&store, &conn_id).await?;
+    let mut tx = begin_read_only(&pool, ANALYSIS_STATEMENT_TIMEOUT_MS).await?;
+    collect_advice(&mut tx, &queries.unwrap_or_default()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(name: &str, columns: &[&str], idx_scan: i64) -> IndexStat {
+        IndexStat {
+            schema: "public".into(),
+            table: "orders".into(),
+            index: name.into(),
+            definition: format!("CREATE INDEX {} ON public.orders", name),
+            method: "btree".into(),
+            columns: columns.iter().map(|c| Some(c.to_string())).collect(),
+            key_count: columns.len(),
+            opclasses: "3124".into(),
+            predicate: None,
+            has_expressions: false,
+            is_unique: false,
+            is_primary: false,
+            is_valid: true,
+            backs_constraint: false,
+            has_parent: false,
+            idx_scan,
+            size_bytes: 8 * 1024 * 1024,
+        }
+    }
+
+    #[test]
+    fn flags_duplicates_prefixes_and_unused() {
+        let mut pkey = index("orders_pkey", &["id"], 900);
+        pkey.is_primary = true;
+        pkey.is_unique = true;
+        pkey.backs_constraint = true;
+        let indexes = vec![
+            pkey,
+            index("orders_id_copy", &["id"], 0),
+            index("orders_customer_idx", &["customer_id"], 40),
+            index("orders_customer_status_idx", &["customer_id", "status"], 3),
+            index("orders_note_idx", &["note"], 0),
+        ];
+        let findings = analyze(&indexes, &[], &[], 160_000);
+        let kinds: Vec<(&str, Option<&str>)> = findings
+            .iter()
+            .map(|f| (f.kind.as_str(), f.index.as_deref()))
+            .collect();
+        assert_eq!(kinds[0], ("duplicate_index", Some("orders_id_copy")));
+        assert!(kinds.contains(&("overlapping_index", Some("orders_customer_idx"))));
+        assert!(kinds.contains(&("unused_index", Some("orders_note_idx"))));
+        assert!(!kinds.contains(&("unused_index", Some("orders_id_copy"))));
+        assert_eq!(
+            findings[0].suggested_ddl.as_deref(),
+            Some("DROP INDEX CONCURRENTLY IF EXISTS \"public\".\"orders_id_copy\";")
+        );
+    }
+
+    #[test]
+    fn reports_unindexed_foreign_keys_on_scanned_tables() {
+        let tables = vec![TableStat {
+            schema: "public".into(),
+            table: "orders".into(),
+            seq_scan: 500,
+            seq_tup_read: 50_000_000,
+            idx_scan: 20,
+            n_live_tup: 100_000,
+            size_bytes: 64 * 1024 * 1024,
+        }];
+        let fks = vec![
+            ForeignKeyStat {
+                schema: "public".into(),
+                table: "orders".into(),
+                constraint_name: "orders_customer_id_fkey".into(),
+                columns: vec!["customer_id".into()],
+            },
+            ForeignKeyStat {
+                schema: "public".into(),
+                table: "orders".into(),
+                constraint_name: "orders_store_id_fkey".into(),
+                columns: vec!["store_id".into()],
+            },
+        ];
+        let indexes = vec![index("orders_customer_idx", &["customer_id"], 10)];
+        let findings = analyze(&indexes, &tables, &fks, 160_000);
+        let fk: Vec<&IndexFinding> = findings
+            .iter()
+            .filter(|f| f.kind == "unindexed_foreign_key")
+            .collect();
+        assert_eq!(fk.len(), 1);
+        assert_eq!(
+            fk[0].suggested_ddl.as_deref(),
+            Some("CREATE INDEX CONCURRENTLY ON \"public\".\"orders\" (\"store_id\");")
+        );
+        assert!(findings.iter().any(|f| f.kind == "seq_scan_heavy"));
+    }
+
+    #[test]
+    fn reindexes_concurrently_only_on_12_and_later() {
+        let mut broken = index("orders_broken_idx", &["status"], 0);
+        broken.is_valid = false;
+        let ddl = |version| {
+            analyze(std::slice::from_ref(&broken), &[], &[], version)[0]
+                .suggested_ddl
+                .clone()
+        };
+        assert_eq!(
+            ddl(120_000).as_deref(),
+            Some("REINDEX INDEX CONCURRENTLY \"public\".\"orders_broken_idx\";")
+        );
+        assert_eq!(
+            ddl(110_022).as_deref(),
+            Some("REINDEX INDEX \"public\".\"orders_broken_idx\";")
+        );
+    }
+
+    #[test]
+    fn never_drops_partition_indexes_and_keys_by_schema() {
+        let mut attached = index("orders_2024_note_idx", &["note"], 0);
+        attached.has_parent = true;
+        let mut archived = index("orders_customer_idx", &["customer_id"], 0);
+        archived.schema = "archive".into();
+        let indexes = vec![
+            attached,
+            index("orders_customer_idx", &["customer_id"], 40),
+            index("orders_customer_status_idx", &["customer_id", "status"], 3),
+            archived,
+        ];
+        let findings = analyze(&indexes, &[], &[], 160_000);
+        assert!(findings
+            .iter()
+            .all(|f| f.index.as_deref() != Some("orders_2024_note_idx")));
+        // Flagging public.orders_customer_idx must not hide archive's unused copy.
+        assert!(findings
+            .iter()
+            .any(|f| f.kind == "unused_index" && f.schema == "archive"));
+    }
+
+    #[test]
+    fn flags_filtered_seq_scans_in_plans() {
+        let tables = vec![TableStat {
+            schema: "public".into(),
+            table: "orders".into(),
+            seq_scan: 5,
+            seq_tup_read: 500_000,
+            idx_scan: 50,
+            n_live_tup: 100_000,
+            size_bytes: 64 * 1024 * 1024,
+        }];
+        let plan = json!([{ "Plan": {
+            "Node Type": "Hash Join",
+            "Plans": [
+                { "Node Type": "Seq Scan", "Relation Name": "orders", "Alias": "o",
+                  "Filter": "(status = 'open'::text)", "Plan Rows": 900 },
+                { "Node Type": "Seq Scan", "Relation Name": "customers", "Alias": "c",
+                  "Filter": "(active)", "Plan Rows": 10 }
+            ]
+        }}]);
+        let planned = vec![
+            PlannedQuery {
+                query: "SELECT * FROM orders o JOIN customers c ON c.id = o.customer_id WHERE o.status = $1".into(),
+                plan: plan.clone(),
+            },
+            PlannedQuery {
+                query: "SELECT count(*) FROM orders WHERE status = $1".into(),
+                plan,
+            },
+        ];
+        let findings = plan_findings(&planned, &tables);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, "seq_scan_in_plan");
+        assert_eq!(findings[0].evidence["filter"], "(status = 'open'::text)");
+    }
+}
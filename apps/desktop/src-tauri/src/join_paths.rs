@@ -0,0 +1,480 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tauri::State;
+
+use crate::local_store::LocalStore;
+use crate::schema_cache::{
+    load_schema_cache, quote_ident, quote_qualified, resolve_table, SchemaCachePayload,
+};
+
+const DEFAULT_MAX_DEPTH: usize = 4;
+const MAX_DEPTH_CAP: usize = 8;
+const DEFAULT_PATH_LIMIT: usize = 5;
+const MAX_EXPANSIONS: usize = 20_000;
+const UNGROUPED_CACHE_WARNING: &str = "schema_cache_stale: the cached schema lacks foreign key \
+     constraint names, so composite keys are joined per column; refresh the schema cache";
+
+type PathStep = (usize, bool);
+
+/// One foreign key, `child.(columns) -> parent.(columns)`; composite keys
+/// carry every `(child column, parent column)` pair in key order.
+#[derive(Debug, Clone)]
+struct FkEdge {
+    child: usize,
+    parent: usize,
+    constraint: Option<String>,
+    columns: Vec<(String, String)>,
+}
+
+#[derive(Debug)]
+struct FkGraph {
+    tables: Vec<(String, String)>,
+    edges: Vec<FkEdge>,
+    // Some reference had no constraint name, so its columns got separate edges.
+    ungrouped: bool,
+    // (edge index, traversed child -> parent, neighbour)
+    adjacency: Vec<Vec<(usize, bool, usize)>>,
+}
+
+/// `col` for one column, `(a, b)` for a composite key.
+fn column_list<'a>(columns: impl ExactSizeIterator<Item = &'a String>) -> String {
+    if columns.len() == 1 {
+        columns.cloned().collect()
+    } else {
+        format!("({})", columns.cloned().collect::<Vec<_>>().join(", "))
+    }
+}
+
+impl FkGraph {
+    fn from_cache(payload: &SchemaCachePayload) -> Self {
+        let tables: Vec<(String, String)> = payload
+            .tables
+            .iter()
+            .map(|t| (t.schema.clone(), t.name.clone()))
+            .collect();
+        let index_of =
+            |schema: &str, name: &str| tables.iter().position(|(s, n)| s == schema && n == name);
+        let mut edges: Vec<FkEdge> = Vec::new();
+        let mut ungrouped = false;
+        for (child, table) in payload.tables.iter().enumerate() {
+            for (column, reference) in table
+                .columns
+                .iter()
+                .flat_map(|column| column.all_references().map(move |r| (column, r)))
+            {
+                let Some(parent) = index_of(&reference.schema, &reference.table) else {
+                    continue;
+                };
+                if parent == child {
+                    continue;
+                }
+                let pair = (column.name.clone(), reference.column.clone());
+                // Caches without constraint names get one edge per column.
+                ungrouped |= reference.constraint.is_none();
+                let existing = reference.constraint.as_ref().and_then(|name| {
+                    edges.iter_mut().find(|edge| {
+                        edge.child == child
+                            && edge.parent == parent
+                            && edge.constraint.as_ref() == Some(name)
+                    })
+                });
+                match existing {
+                    Some(edge) => edge.columns.push(pair),
+                    None => edges.push(FkEdge {
+                        child,
+                        parent,
+                        constraint: reference.constraint.clone(),
+                        columns: vec![pair],
+                    }),
+                }
+            }
+        }
+        let mut adjacency = vec![Vec::new(); tables.len()];
+        for (idx, edge) in edges.iter().enumerate() {
+            adjacency[edge.child].push((idx, true, edge.parent));
+            adjacency[edge.parent].push((idx, false, edge.child));
+        }
+        Self {
+            tables,
+            edges,
+            ungrouped,
+            adjacency,
+        }
+    }
+
+    fn label(&self, idx: usize) -> String {
+        let (schema, name) = &self.tables[idx];
+        format!("{}.{}", schema, name)
+    }
+
+    /// Breadth-first enumeration of simple paths, shortest first. Paths longer
+    /// than the shortest hit plus `extra_hops` are not explored.
+    fn search(
+        &self,
+        from: usize,
+        to: usize,
+        max_depth: usize,
+        extra_hops: usize,
+        limit: usize,
+    ) -> Vec<Vec<PathStep>> {
+        let mut found: Vec<Vec<PathStep>> = Vec::new();
+        let mut best: Option<usize> = None;
+        let mut min_depth: Vec<usize> = vec![usize::MAX; self.tables.len()];
+        min_depth[from] = 0;
+        let mut queue: VecDeque<(Vec<usize>, Vec<PathStep>)> = VecDeque::new();
+        queue.push_back((vec![from], Vec::new()));
+        let mut expansions = 0usize;
+
+        while let Some((nodes, steps)) = queue.pop_front() {
+            let depth = steps.len() + 1;
+            if depth > max_depth {
+                continue;
+            }
+            if let Some(best_len) = best {
+                if depth > best_len + extra_hops {
+                    break;
+                }
+            }
+            expansions += 1;
+            if expansions > MAX_EXPANSIONS {
+                break;
+            }
+            let current = *nodes.last().unwrap_or(&from);
+            for &(edge, forward, next) in &self.adjacency[current] {
+                if nodes.contains(&next) {
+                    continue;
+                }
+                let mut next_steps = steps.clone();
+                next_steps.push((edge, forward));
+                if next == to {
+                    best.get_or_insert(depth);
+                    found.push(next_steps);
+                    if found.len() >= limit {
+                        return found;
+                    }
+                    continue;
+                }
+                if depth > min_depth[next].saturating_add(extra_hops) {
+                    continue;
+                }
+                min_depth[next] = min_depth[next].min(depth);
+                let mut next_nodes = nodes.clone();
+                next_nodes.push(next);
+                queue.push_back((next_nodes, next_steps));
+            }
+        }
+        found
+    }
+
+    fn describe(&self, from: usize, steps: &[PathStep]) -> JoinPath {
+        let mut hops = Vec::with_capacity(steps.len());
+        let mut current = from;
+        let (schema, name) = &self.tables[from];
+        let mut sql = format!("FROM {} t0", quote_qualified(schema, name));
+        for (idx, &(edge_idx, forward)) in steps.iter().enumerate() {
+            let edge = &self.edges[edge_idx];
+            let next = if forward { edge.parent } else { edge.child };
+            let left_alias = format!("t{}", idx);
+            let right_alias = format!("t{}", idx + 1);
+            let on = edge
+                .columns
+                .iter()
+                .map(|(child_column, parent_column)| {
+                    let (left_column, right_column) = if forward {
+                        (child_column, parent_column)
+                    } else {
+                        (parent_column, child_column)
+                    };
+                    format!(
+                        "{}.{} = {}.{}",
+                        left_alias,
+                        quote_ident(left_column),
+                        right_alias,
+                        quote_ident(right_column)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            let (next_schema, next_name) = &self.tables[next];
+            sql.push_str(&format!(
+                "\nJOIN {} {} ON {}",
+                quote_qualified(next_schema, next_name),
+                right_alias,
+                on
+            ));
+            hops.push(JoinHop {
+                from_table: self.label(current),
+                from_alias: left_alias,
+                to_table: self.label(next),
+                to_alias: right_alias,
+                on,
+                foreign_key: format!(
+                    "{}.{} -> {}.{}",
+                    self.label(edge.child),
+                    column_list(edge.columns.iter().map(|(child, _)| child)),
+                    self.label(edge.parent),
+                    column_list(edge.columns.iter().map(|(_, parent)| parent))
+                ),
+                direction: if forward {
+                    "child_to_parent".to_string()
+                } else {
+                    "parent_to_child".to_string()
+                },
+            });
+            current = next;
+        }
+        JoinPath {
+            length: hops.len(),
+            hops,
+            sql,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct JoinHop {
+    pub from_table: String,
+    pub from_alias: String,
+    pub to_table: String,
+    pub to_alias: String,
+    pub on: String,
+    pub foreign_key: String,
+    pub direction: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JoinPath {
+    pub length: usize,
+    pub hops: Vec<JoinHop>,
+    pub sql: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JoinPathResult {
+    pub from: String,
+    pub to: String,
+    pub paths: Vec<JoinPath>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JoinPathRequest {
+    pub conn_id: String,
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    #[serde(default)]
+    pub extra_hops: Option<usize>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+pub fn find_paths(
+    payload: &SchemaCachePayload,
+    from: &str,
+    to: &str,
+    max_depth: Option<usize>,
+    extra_hops: Option<usize>,
+    limit: Option<usize>,
+) -> Result<JoinPathResult, String> {
+    let from_table = resolve_table(payload, from)?;
+    let to_table = resolve_table(payload, to)?;
+    let graph = FkGraph::from_cache(payload);
+    let from_idx = graph
+        .tables
+        .iter()
+        .position(|(s, n)| *s == from_table.schema && *n == from_table.name)
+        .ok_or_else(|| format!("table_not_found:{}", from))?;
+    let to_idx = graph
+        .tables
+        .iter()
+        .position(|(s, n)| *s == to_table.schema && *n == to_table.name)
+        .ok_or_else(|| format!("table_not_found:{}", to))?;
+    if from_idx == to_idx {
+        return Err("same_table".to_string());
+    }
+    let max_depth = max_depth
+        .unwrap_or(DEFAULT_MAX_DEPTH)
+        .clamp(1, MAX_DEPTH_CAP);
+    let limit = limit.unwrap_or(DEFAULT_PATH_LIMIT).clamp(1, 50);
+    let steps = graph.search(from_idx, to_idx, max_depth, extra_hops.unwrap_or(0), limit);
+    Ok(JoinPathResult {
+        from: graph.label(from_idx),
+        to: graph.label(to_idx),
+        paths: steps
+            .iter()
+            .map(|path| graph.describe(from_idx, path))
+            .collect(),
+        warnings: if graph.ungrouped {
+            vec![UNGROUPED_CACHE_WARNING.to_string()]
+        } else {
+            Vec::new()
+        },
+    })
+}
+
+pub async fn find_paths_for_conn(
+    store: &LocalStore,
+    request: &JoinPathRequest,
+) -> Result<JoinPathResult, String> {
+    let payload = load_schema_cache(&store.pool()?, &request.conn_id)
+        .await?
+        .ok_or_else(|| "schema_cache_missing".to_string())?;
+    find_paths(
+        &payload,
+        &request.from,
+        &request.to,
+        request.max_depth,
+        request.extra_hops,
+        request.limit,
+    )
+}
+
+#[tauri::command]
+pub async fn find_join_paths(
+    store: State<'_, LocalStore>,
+    payload: JoinPathRequest,
+) -> Result<JoinPathResult, String> {
+    find_paths_for_conn(&store, &payload).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample_schema() -> SchemaCachePayload {
+        serde_json::from_value(json!({
+            "schemas": ["public"],
+            "tables": [
+                { "schema": "public", "name": "customers", "columns": [
+                    { "name": "id", "dataType": "int8", "isPrimaryKey": true }
+                ]},
+                { "schema": "public", "name": "orders", "columns": [
+                    { "name": "id", "dataType": "int8", "isPrimaryKey": true },
+                    { "name": "customer_id", "dataType": "int8", "isForeignKey": true,
+                      "references": { "schema": "public", "table": "customers", "column": "id" } }
+                ]},
+                { "schema": "public", "name": "order_items", "columns": [
+                    { "name": "order_id", "dataType": "int8", "isForeignKey": true,
+                      "references": { "schema": "public", "table": "orders", "column": "id" } },
+                    { "name": "product_id", "dataType": "int8", "isForeignKey": true,
+                      "references": { "schema": "public", "table": "products", "column": "id" } }
+                ]},
+                { "schema": "public", "name": "products", "columns": [
+                    { "name": "id", "dataType": "int8", "isPrimaryKey": true }
+                ]}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn finds_shortest_path_across_link_table() {
+        let result =
+            find_paths(&sample_schema(), "customers", "products", None, None, None).unwrap();
+        assert_eq!(result.paths.len(), 1);
+        let path = &result.paths[0];
+        assert_eq!(path.length, 3);
+        assert_eq!(path.hops[0].on, "t0.\"id\" = t1.\"customer_id\"");
+        assert_eq!(path.hops[0].direction, "parent_to_child");
+        assert_eq!(path.hops[2].on, "t2.\"product_id\" = t3.\"id\"");
+        assert!(path.sql.starts_with("FROM \"public\".\"customers\" t0"));
+    }
+
+    #[test]
+    fn warns_when_cache_lacks_constraint_names() {
+        let result = find_paths(&sample_schema(), "customers", "orders", None, None, None).unwrap();
+        assert_eq!(result.warnings, vec![UNGROUPED_CACHE_WARNING.to_string()]);
+    }
+
+    #[test]
+    fn respects_depth_limit_and_unknown_tables() {
+        let schema = sample_schema();
+        let result = find_paths(&schema, "customers", "products", Some(2), None, None).unwrap();
+        assert!(result.paths.is_empty());
+        assert_eq!(
+            find_paths(&schema, "customers", "missing", None, None, None).unwrap_err(),
+            "table_not_found:missing"
+        );
+    }
+
+    #[test]
+    fn joins_composite_foreign_keys_on_every_column() {
+        let schema: SchemaCachePayload = serde_json::from_value(json!({
+            "schemas": ["public"],
+            "tables": [
+                { "schema": "public", "name": "orders", "columns": [
+                    { "name": "tenant_id", "dataType": "int8", "isPrimaryKey": true },
+                    { "name": "id", "dataType": "int8", "isPrimaryKey": true }
+                ]},
+                { "schema": "public", "name": "order_items", "columns": [
+                    { "name": "tenant_id", "dataType": "int8", "isForeignKey": true,
+                      "references": { "schema": "public", "table": "orders", "column": "tenant_id",
+                                      "constraint": "order_items_order_fkey" } },
+                    { "name": "order_id", "dataType": "int8", "isForeignKey": true,
+                      "references": { "schema": "public", "table": "orders", "column": "id",
+                                      "constraint": "order_items_order_fkey" } }
+                ]}
+            ]
+        }))
+        .unwrap();
+        let result = find_paths(&schema, "orders", "order_items", None, Some(2), None).unwrap();
+        assert_eq!(result.paths.len(), 1);
+        let hop = &result.paths[0].hops[0];
+        assert_eq!(
+            hop.on,
+            "t0.\"tenant_id\" = t1.\"tenant_id\" AND t0.\"id\" = t1.\"order_id\""
+        );
+        assert_eq!(
+            hop.foreign_key,
+            "public.order_items.(tenant_id, order_id) -> public.orders.(tenant_id, id)"
+        );
+    }
+
+    #[test]
+    fn keeps_every_foreign_key_a_column_belongs_to() {
+        let orders_ref = |column: &str| {
+            json!({ "schema": "public", "table": "orders", "column": column,
+                    "constraint": "shipments_order_fkey" })
+        };
+        let schema: SchemaCachePayload = serde_json::from_value(json!({
+            "schemas": ["public"],
+            "tables": [
+                { "schema": "public", "name": "tenants", "columns": [
+                    { "name": "id", "dataType": "int8", "isPrimaryKey": true }
+                ]},
+                { "schema": "public", "name": "orders", "columns": [
+                    { "name": "tenant_id", "dataType": "int8", "isPrimaryKey": true },
+                    { "name": "id", "dataType": "int8", "isPrimaryKey": true }
+                ]},
+                { "schema": "public", "name": "shipments", "columns": [
+                    { "name": "tenant_id", "dataType": "int8", "isForeignKey": true,
+                      "references": orders_ref("tenant_id"),
+                      "foreignKeys": [
+                          orders_ref("tenant_id"),
+                          { "schema": "public", "table": "tenants", "column": "id",
+                            "constraint": "shipments_tenant_fkey" }
+                      ] },
+                    { "name": "order_id", "dataType": "int8", "isForeignKey": true,
+                      "references": orders_ref("id"),
+                      "foreignKeys": [orders_ref("id")] }
+                ]}
+            ]
+        }))
+        .unwrap();
+        let to_orders = find_paths(&schema, "shipments", "orders", None, None, None).unwrap();
+        assert_eq!(
+            to_orders.paths[0].hops[0].on,
+            "t0.\"tenant_id\" = t1.\"tenant_id\" AND t0.\"order_id\" = t1.\"id\""
+        );
+        assert!(to_orders.warnings.is_empty());
+        let to_tenants = find_paths(&schema, "shipments", "tenants", None, None, None).unwrap();
+        assert_eq!(
+            to_tenants.paths[0].hops[0].on,
+            "t0.\"tenant_id\" = t1.\"id\""
+        );
+    }
+}
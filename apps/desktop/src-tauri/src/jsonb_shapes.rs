@@ -0,0 +1,442 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sqlx::PgConnection;
+use std::collections::BTreeMap;
+use tauri::State;
+
+use crate::local_store::{db_error, now_sec, row_text, LocalStore};
+use crate::pg::{
+    begin_read_only, fetch_as, fetch_json, split_qualified, PgPools, ANALYSIS_STATEMENT_TIMEOUT_MS,
+};
+use crate::schema_cache::{quote_ident, quote_qualified};
+
+const DEFAULT_SAMPLE_ROWS: i64 = 500;
+const MAX_SAMPLE_ROWS: i64 = 10_000;
+const SAMPLE_SEED: i64 = 20_240_601;
+const MAX_DEPTH: usize = 8;
+const MAX_PROPERTIES: usize = 200;
+const MAX_ARRAY_ITEMS: usize = 50;
+const MAX_EXAMPLES: usize = 3;
+const MAX_EXAMPLE_CHARS: usize = 80;
+
+/// Accumulates every value seen at one JSON path.
+#[derive(Debug, Default, Clone)]
+pub struct ShapeNode {
+    seen: u64,
+    types: BTreeMap<&'static str, u64>,
+    objects: u64,
+    properties: BTreeMap<String, ShapeNode>,
+    items: Option<Box<ShapeNode>>,
+    examples: Vec<Value>,
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn example_of(value: &Value) -> Value {
+    match value {
+        Value::String(text) if text.chars().count() > MAX_EXAMPLE_CHARS => {
+            let cut: String = text.chars().take(MAX_EXAMPLE_CHARS).collect();
+            Value::String(format!("{}…", cut))
+        }
+        other => other.clone(),
+    }
+}
+
+impl ShapeNode {
+    pub fn observe(&mut self, value: &Value) {
+        self.observe_at(value, 0);
+    }
+
+    fn observe_at(&mut self, value: &Value, depth: usize) {
+        self.seen += 1;
+        *self.types.entry(json_type(value)).or_default() += 1;
+        match value {
+            Value::Object(map) => {
+                self.objects += 1;
+                if depth >= MAX_DEPTH {
+                    return;
+                }
+                for (key, child) in map {
+                    if !self.properties.contains_key(key) && self.properties.len() >= MAX_PROPERTIES
+                    {
+                        continue;
+                    }
+                    self.properties
+                        .entry(key.clone())
+                        .or_default()
+                        .observe_at(child, depth + 1);
+                }
+            }
+            Value::Array(values) => {
+                if depth >= MAX_DEPTH {
+                    return;
+                }
+                let items = self.items.get_or_insert_with(Box::default);
+                for item in values.iter().take(MAX_ARRAY_ITEMS) {
+                    items.observe_at(item, depth + 1);
+                }
+            }
+            Value::Null => {}
+            scalar => {
+                let example = example_of(scalar);
+                if self.examples.len() < MAX_EXAMPLES && !self.examples.contains(&example) {
+                    self.examples.push(example);
+                }
+            }
+        }
+    }
+
+    /// Renders the merged shape as JSON Schema. Non-standard facts use `x-`
+    /// keys: `x-count` (values seen), `x-type-counts` and, on properties,
+    /// `x-presence` (share of parent objects that contain the key).
+    pub fn to_json_schema(&self) -> Value {
+        let mut schema = Map::new();
+        let types: Vec<&str> = self.types.keys().copied().collect();
+        schema.insert(
+            "type".to_string(),
+            match types.as_slice() {
+                [single] => json!(single),
+                many => json!(many),
+            },
+        );
+        schema.insert("x-count".to_string(), json!(self.seen));
+        if self.types.len() > 1 {
+            schema.insert("x-type-counts".to_string(), json!(self.types));
+        }
+        if !self.properties.is_empty() {
+            let mut properties = Map::new();
+            let mut required = Vec::new();
+            for (key, child) in &self.properties {
+                let mut child_schema = child.to_json_schema();
+                let presence = if self.objects > 0 {
+                    child.seen as f64 / self.objects as f64
+                } else {
+                    0.0
+                };
+                if let Some(obj) = child_schema.as_object_mut() {
+                    obj.insert(
+                        "x-presence".to_string(),
+                        json!((presence * 10_000.0).round() / 10_000.0),
+                    );
+                }
+                if child.seen == self.objects {
+                    required.push(key.clone());
+                }
+                properties.insert(key.clone(), child_schema);
+            }
+            schema.insert("properties".to_string(), Value::Object(properties));
+            if !required.is_empty() {
+                schema.insert("required".to_string(), json!(required));
+            }
+        }
+        if let Some(items) = &self.items {
+            if items.seen > 0 {
+                schema.insert("items".to_string(), items.to_json_schema());
+            }
+        }
+        if !self.examples.is_empty() {
+            schema.insert("examples".to_string(), json!(self.examples));
+        }
+        Value::Object(schema)
+    }
+}
+
+pub fn infer_schema<'a>(values: impl IntoIterator<Item = &'a Value>) -> Value {
+    let mut root = ShapeNode::default();
+    for value in values {
+        root.observe(value);
+    }
+    root.to_json_schema()
+}
+
+fn type_label(schema: &Value) -> String {
+    match schema.get("type") {
+        Some(Value::String(single)) => single.clone(),
+        Some(Value::Array(many)) => many
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(" | "),
+        _ => "unknown".to_string(),
+    }
+}
+
+fn walk_outline(path: &str, schema: &Value, lines: &mut Vec<String>) {
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        for (key, child) in properties {
+            let child_path = format!("{}.{}", path, key);
+            let mut line = format!("{}: {}", child_path, type_label(child));
+            if let Some(presence) = child.get("x-presence").and_then(Value::as_f64) {
+                if presence < 1.0 {
+                    line.push_str(&format!(" ({:.0}% of rows)", presence * 100.0));
+                }
+            }
+            if let Some(examples) = child.get("examples").and_then(Value::as_array) {
+                let rendered: Vec<String> = examples.iter().map(Value::to_string).collect();
+                line.push_str(&format!(" e.g. {}", rendered.join(", ")));
+            }
+            lines.push(line);
+            walk_outline(&child_path, child, lines);
+        }
+    }
+    if let Some(items) = schema.get("items") {
+        let item_path = format!("{}[]", path);
+        if items.get("properties").is_some() {
+            walk_outline(&item_path, items, lines);
+        } else {
+            let mut line = format!("{}: {}", item_path, type_label(items));
+            if let Some(examples) = items.get("examples").and_then(Value::as_array) {
+                let rendered: Vec<String> = examples.iter().map(Value::to_string).collect();
+                line.push_str(&format!(" e.g. {}", rendered.join(", ")));
+            }
+            lines.push(line);
+        }
+    }
+}
+
+/// Flattens an inferred schema into `column.key.sub: type` lines for prompts.
+pub fn shape_outline(column: &str, schema: &Value) -> Vec<String> {
+    let mut lines = Vec::new();
+    walk_outline(column, schema, &mut lines);
+    if lines.is_empty() {
+        lines.push(format!("{}: {}", column, type_label(schema)));
+    }
+    lines
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JsonbShape {
+    pub schema: String,
+    pub table: String,
+    pub column: String,
+    pub data_type: String,
+    pub sampled_rows: i64,
+    pub json_schema: Value,
+    pub inferred_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonColumnRow {
+    column: String,
+    data_type: String,
+    reltuples: f64,
+}
+
+const JSON_COLUMNS_SQL: &str = r#"
+SELECT
+  a.attname AS "column",
+  pg_catalog.format_type(a.atttypid, a.atttypmod) AS data_type,
+  GREATEST(c.reltuples, 0)::float8 AS reltuples
+FROM pg_catalog.pg_attribute a
+JOIN pg_catalog.pg_class c ON c.oid = a.attrelid
+JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+WHERE n.nspname = $1
+  AND c.relname = $2
+  AND a.attnum > 0
+  AND NOT a.attisdropped
+  AND a.atttypid IN ('pg_catalog.json'::regtype, 'pg_catalog.jsonb'::regtype)
+ORDER BY a.attnum"#;
+
+async fn sample_values(
+    conn: &mut PgConnection,
+    schema: &str,
+    table: &str,
+    column: &JsonColumnRow,
+    rows: i64,
+) -> Result<Vec<Value>, String> {
+    let ident = quote_ident(&column.column);
+    let relation = quote_qualified(schema, table);
+    // Large tables are sampled by page so we don't just read the oldest rows.
+    if column.reltuples > (rows * 4) as f64 {
+        let percent = ((rows * 4) as f64 / column.reltuples * 100.0).clamp(0.01, 100.0);
+        let sampled = fetch_json(
+            conn,
+            &format!(
+                "SELECT {ident} AS value FROM {relation} TABLESAMPLE SYSTEM ({percent}) \
+                 REPEATABLE ({SAMPLE_SEED}) WHERE {ident} IS NOT NULL LIMIT {rows}"
+            ),
+            &[],
+        )
+        .await?;
+        if !sampled.is_empty() {
+            return Ok(sampled);
+        }
+    }
+    fetch_json(
+        conn,
+        &format!("SELECT {ident} AS value FROM {relation} WHERE {ident} IS NOT NULL LIMIT {rows}"),
+        &[],
+    )
+    .await
+}
+
+pub async fn infer_table_shapes(
+    conn: &mut PgConnection,
+    table: &str,
+    column: Option<&str>,
+    sample_rows: i64,
+) -> Result<Vec<JsonbShape>, String> {
+    let (schema, name) = split_qualified(table);
+    let mut columns: Vec<JsonColumnRow> =
+        fetch_as(conn, JSON_COLUMNS_SQL, &[json!(schema), json!(name)]).await?;
+    if let Some(wanted) = column {
+        columns.retain(|c| c.column == wanted);
+        if columns.is_empty() {
+            return Err(format!(
+                "json_column_not_found:{}.{}.{}",
+                schema, name, wanted
+            ));
+        }
+    }
+    let mut shapes = Vec::with_capacity(columns.len());
+    for column in &columns {
+        let rows = sample_values(conn, &schema, &name, column, sample_rows).await?;
+        let values: Vec<Value> = rows
+            .into_iter()
+            .map(|mut row| row.get_mut("value").map(Value::take).unwrap_or_default())
+            .collect();
+        shapes.push(JsonbShape {
+            schema: schema.clone(),
+            table: name.clone(),
+            column: column.column.clone(),
+            data_type: column.data_type.clone(),
+            sampled_rows: values.len() as i64,
+            json_schema: infer_schema(&values),
+            inferred_at: now_sec(),
+        });
+    }
+    Ok(shapes)
+}
+
+async fn store_shape(store: &LocalStore, conn_id: &str, shape: &JsonbShape) -> Result<(), String> {
+    let content = serde_json::to_string(shape).map_err(|err| err.to_string())?;
+    sqlx::query(
+        "INSERT INTO jsonb_shapes (conn_id, schema_name, table_name, column_name, content, sampled_rows, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(conn_id, schema_name, table_name, column_name) DO UPDATE SET
+           content = excluded.content, sampled_rows = excluded.sampled_rows, created_at = excluded.created_at",
+    )
+    .bind(conn_id)
+    .bind(&shape.schema)
+    .bind(&shape.table)
+    .bind(&shape.column)
+    .bind(content)
+    .bind(shape.sampled_rows)
+    .bind(shape.inferred_at)
+    .execute(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// All cached shapes for a connection, ordered by table and column.
+pub async fn load_shapes(store: &LocalStore, conn_id: &str) -> Result<Vec<JsonbShape>, String> {
+    let rows = sqlx::query(
+        "SELECT content FROM jsonb_shapes WHERE conn_id = ?1
+         ORDER BY schema_name, table_name, column_name",
+    )
+    .bind(conn_id)
+    .fetch_all(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| row_text(row, "content"))
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect())
+}
+
+/// Groups shapes as `{ column: json_schema }` for one table, the form carried
+/// in `schema-table` chunk content under `jsonShapes`.
+pub fn shapes_for_table(shapes: &[JsonbShape], schema: &str, table: &str) -> Option<Value> {
+    let columns: Map<String, Value> = shapes
+        .iter()
+        .filter(|s| s.schema == schema && s.table == table)
+        .map(|s| (s.column.clone(), s.json_schema.clone()))
+        .collect();
+    (!columns.is_empty()).then_some(Value::Object(columns))
+}
+
+#[tauri::command]
+pub async fn infer_jsonb_shapes(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    conn_id: String,
+    table: String,
+    column: Option<String>,
+    sample_rows: Option<i64>,
+) -> Result<Vec<JsonbShape>, String> {
+    let rows = sample_rows
+        .unwrap_or(DEFAULT_SAMPLE_ROWS)
+        .clamp(1, MAX_SAMPLE_ROWS);
+    let pool = pools.pool_for(&store, &conn_id).await?;
+    let mut tx = begin_read_only(&pool, ANALYSIS_STATEMENT_TIMEOUT_MS).await?;
+    let shapes = infer_table_shapes(&mut tx, &table, column.as_deref(), rows).await?;
+    drop(tx);
+    for shape in &shapes {
+        store_shape(&store, &conn_id, shape).await?;
+    }
+    Ok(shapes)
+}
+
+#[tauri::command]
+pub async fn cached_jsonb_shapes(
+    store: State<'_, LocalStore>,
+    conn_id: String,
+    table: Option<String>,
+) -> Result<Vec<JsonbShape>, String> {
+    let mut shapes = load_shapes(&store, &conn_id).await?;
+    if let Some(table) = table {
+        let (schema, name) = split_qualified(&table);
+        shapes.retain(|s| s.schema == schema && s.table == name);
+    }
+    Ok(shapes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_presence_types_and_arrays() {
+        let rows = vec![
+            json!({"source": "app", "tags": ["a", "b"], "coupon": null}),
+            json!({"source": "web", "tags": [], "coupon": "SPRING", "gift": true}),
+            json!({"source": 3, "tags": [{"id": 1}]}),
+            json!({"source": "app", "tags": ["c"], "coupon": null}),
+        ];
+        let schema = infer_schema(&rows);
+        assert_eq!(schema["type"], json!("object"));
+        assert_eq!(schema["required"], json!(["source", "tags"]));
+        let props = &schema["properties"];
+        assert_eq!(props["source"]["type"], json!(["integer", "string"]));
+        assert_eq!(props["source"]["x-type-counts"]["string"], json!(3));
+        assert_eq!(props["gift"]["x-presence"], json!(0.25));
+        assert_eq!(props["coupon"]["x-presence"], json!(0.75));
+        assert_eq!(props["coupon"]["type"], json!(["null", "string"]));
+        assert_eq!(props["tags"]["items"]["type"], json!(["object", "string"]));
+        assert_eq!(props["tags"]["items"]["examples"], json!(["a", "b", "c"]));
+    }
+
+    #[test]
+    fn outlines_nested_paths() {
+        let rows = vec![
+            json!({"shipping": {"city": "Oslo"}, "lines": [{"sku": "x1"}]}),
+            json!({"shipping": {"city": "Lima", "zip": "150"}, "lines": []}),
+        ];
+        let lines = shape_outline("meta", &infer_schema(&rows));
+        assert!(lines.contains(&"meta.shipping: object".to_string()));
+        assert!(lines.contains(&"meta.shipping.zip: string (50% of rows) e.g. \"150\"".to_string()));
+        assert!(lines.contains(&"meta.lines[].sku: string e.g. \"x1\"".to_string()));
+    }
+}
@@ -0,0 +1,432 @@
+//! Backup, restore and maintenance of `rdv_local.db`.
+//!
+//! Backups are `VACUUM INTO` snapshots taken while the app runs; those of an
+//! encrypted store stay encrypted under its key. A restore cannot replace the
+//! file under the open pool, so it is validated and staged next to the
+//! database, then swapped in by [`apply_pending_restore`] on the next launch.
+//! [`rollback`] reverts migrations for support cases, after a safety backup.
+
+use serde::Serialize;
+use sha2::{Digest, Sha384};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection, Row, SqliteConnection};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, State};
+use tauri_plugin_sql::MigrationKind;
+
+use crate::local_store::{db_error, now_sec, LocalStore, LOCAL_DB_FILE};
+use crate::migrations::migrations;
+
+const BACKUP_PREFIX: &str = "rdv_local-";
+const BACKUP_EXT: &str = ".db";
+const DEFAULT_KEEP: usize = 10;
+const STAGED_SUFFIX: &str = ".restore";
+const PREVIOUS_SUFFIX: &str = ".pre-restore";
+/// Next to the database; rollback backups rotate separately from user ones.
+const ROLLBACK_BACKUP_DIR: &str = "pre-rollback";
+const ROLLBACK_KEEP: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupInfo {
+    pub path: String,
+    pub size_bytes: u64,
+    pub created_at: i64,
+    pub schema_version: Option<i64>,
+    /// Older backups deleted by rotation.
+    pub removed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreInfo {
+    pub source: String,
+    pub schema_version: i64,
+    /// The current database is kept under this name after the swap.
+    pub previous: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RollbackInfo {
+    pub from_version: i64,
+    pub to_version: i64,
+    /// Taken before any migration was reverted.
+    pub backup: BackupInfo,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ForeignKeyViolation {
+    pub table: String,
+    pub rowid: Option<i64>,
+    pub parent: String,
+    pub fkid: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreCheck {
+    pub ok: bool,
+    /// `["ok"]` when the database is intact.
+    pub integrity: Vec<String>,
+    pub foreign_key_violations: Vec<ForeignKeyViolation>,
+    /// Skipped when the integrity check fails.
+    pub vacuumed: bool,
+    pub size_before: i64,
+    pub size_after: i64,
+}
+
+fn io_error(err: std::io::Error) -> String {
+    format!("local_store_io_error: {}", err)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Highest migration recorded in `_sqlx_migrations`, checked against
+/// `migrations::migrations()`: every applied version must be known, applied in
+/// order and carry the checksum sqlx computes for our SQL.
+async fn schema_version(conn: &mut SqliteConnection) -> Result<i64, String> {
+    let rows = sqlx::query(
+        "SELECT version, checksum FROM _sqlx_migrations WHERE success = 1 ORDER BY version",
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| "backup_schema_unknown: no migration history".to_string())?;
+    let known: Vec<_> = migrations()
+        .into_iter()
+        .filter(|m| matches!(m.kind, MigrationKind::Up))
+        .collect();
+    let mut version = 0;
+    for row in rows {
+        let applied: i64 = row.try_get("version").map_err(db_error)?;
+        let checksum: Vec<u8> = row.try_get("checksum").map_err(db_error)?;
+        let Some(migration) = known.iter().find(|m| m.version == applied) else {
+            return Err(format!(
+                "backup_schema_newer: migration {} is unknown to this version",
+                applied
+            ));
+        };
+        if applied != version + 1 {
+            return Err(format!(
+                "backup_schema_invalid: migration {} missing",
+                version + 1
+            ));
+        }
+        if Sha384::digest(migration.sql.as_bytes()).as_slice() != checksum.as_slice() {
+            return Err(format!(
+                "backup_schema_invalid: migration {} was modified",
+                applied
+            ));
+        }
+        version = applied;
+    }
+    if version == 0 {
+        return Err("backup_schema_unknown: no migration history".to_string());
+    }
+    Ok(version)
+}
+
+async fn page_bytes(conn: &mut SqliteConnection) -> Result<i64, String> {
+    // SQLCipher reports some pragmas as TEXT.
+    sqlx::query_scalar(
+        "SELECT CAST(c.page_count AS INTEGER) * CAST(s.page_size AS INTEGER) FROM pragma_page_count() c, pragma_page_size() s",
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(db_error)
+}
+
+/// Writes a consistent snapshot of `conn`'s database to `target`, through a
+/// `.partial` file so an interrupted copy never looks like a backup.
+async fn vacuum_into(conn: &mut SqliteConnection, target: &Path) -> Result<(), String> {
+    let partial = with_suffix(target, ".partial");
+    let _ = std::fs::remove_file(&partial);
+    sqlx::query("VACUUM INTO ?1")
+        .bind(partial.to_string_lossy().into_owned())
+        .execute(&mut *conn)
+        .await
+        .map_err(db_error)?;
+    std::fs::rename(&partial, target).map_err(io_error)
+}
+
+/// Backups in `dir`, newest first.
+fn list_backups(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.starts_with(BACKUP_PREFIX) && n.ends_with(BACKUP_EXT))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files.reverse();
+    files
+}
+
+/// Snapshots the local store into `dir` as `rdv_local-<millis>.db`, keeping
+/// the newest `keep` backups there.
+pub async fn backup(store: &LocalStore, dir: &Path, keep: usize) -> Result<BackupInfo, String> {
+    std::fs::create_dir_all(dir).map_err(io_error)?;
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let target = dir.join(format!("{}{:013}{}", BACKUP_PREFIX, millis, BACKUP_EXT));
+    let mut conn = store.pool()?.acquire().await.map_err(db_error)?;
+    vacuum_into(&mut conn, &target).await?;
+    let schema_version = schema_version(&mut conn).await.ok();
+
+    let removed = list_backups(dir)
+        .into_iter()
+        .skip(keep.max(1))
+        .filter(|path| std::fs::remove_file(path).is_ok())
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    Ok(BackupInfo {
+        size_bytes: std::fs::metadata(&target).map_err(io_error)?.len(),
+        path: target.to_string_lossy().into_owned(),
+        created_at: now_sec(),
+        schema_version,
+        removed,
+    })
+}
+
+/// Opens a backup under the store's key, or as plaintext if it was taken
+/// before the store was encrypted.
+async fn open_backup(store: &LocalStore, source: &Path) -> Result<SqliteConnection, String> {
+    if !source.is_file() {
+        return Err(format!("backup_not_found: {}", source.display()));
+    }
+    let keyed = store.options_for(source)?;
+    // Create mode lets `ATTACH` create the staged file.
+    let plain = SqliteConnectOptions::new()
+        .filename(source)
+        .create_if_missing(true);
+    for options in [keyed, plain] {
+        if let Ok(mut conn) = options.connect().await {
+            if sqlx::query("SELECT count(*) FROM sqlite_master")
+                .fetch_one(&mut conn)
+                .await
+                .is_ok()
+            {
+                return Ok(conn);
+            }
+        }
+    }
+    Err("backup_corrupt: not a database, or encrypted under another passphrase".to_string())
+}
+
+/// Validates `source` and stages a copy of it, re-encoded under the store's
+/// current key, to replace the database on the next launch.
+pub async fn stage_restore(store: &LocalStore, source: &Path) -> Result<RestoreInfo, String> {
+    let mut conn = open_backup(store, source).await?;
+    let quick: String = sqlx::query_scalar("PRAGMA quick_check")
+        .fetch_one(&mut conn)
+        .await
+        .map_err(|err| format!("backup_corrupt: {}", err))?;
+    if quick != "ok" {
+        return Err(format!("backup_corrupt: {}", quick));
+    }
+    let schema_version = schema_version(&mut conn).await?;
+    let staged = with_suffix(store.path(), STAGED_SUFFIX);
+    let _ = std::fs::remove_file(&staged);
+    // ATTACH takes no bound key; the key literal comes from the store.
+    sqlx::query(&format!(
+        "ATTACH DATABASE '{}' AS staged KEY {}",
+        staged.to_string_lossy().replace('\'', "''"),
+        store.attach_key()?
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(db_error)?;
+    let exported = sqlx::query("SELECT sqlcipher_export('staged')")
+        .execute(&mut conn)
+        .await;
+    let _ = sqlx::query("DETACH DATABASE staged")
+        .execute(&mut conn)
+        .await;
+    let _ = conn.close().await;
+    if let Err(err) = exported {
+        let _ = std::fs::remove_file(&staged);
+        return Err(db_error(err));
+    }
+    Ok(RestoreInfo {
+        source: source.to_string_lossy().into_owned(),
+        schema_version,
+        previous: with_suffix(store.path(), PREVIOUS_SUFFIX)
+            .to_string_lossy()
+            .into_owned(),
+    })
+}
+
+/// Swaps a staged restore in, keeping the current database (and any WAL
+/// files) as `rdv_local.db.pre-restore`. Returns whether a swap happened.
+pub fn apply_pending_restore(config_dir: &Path) -> std::io::Result<bool> {
+    let db_path = config_dir.join(LOCAL_DB_FILE);
+    let staged = with_suffix(&db_path, STAGED_SUFFIX);
+    if !staged.exists() {
+        return Ok(false);
+    }
+    let previous = with_suffix(&db_path, PREVIOUS_SUFFIX);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(with_suffix(&previous, suffix));
+        let current = with_suffix(&db_path, suffix);
+        if current.exists() {
+            std::fs::rename(&current, with_suffix(&previous, suffix))?;
+        }
+    }
+    std::fs::rename(&staged, &db_path)?;
+    Ok(true)
+}
+
+/// Runs `integrity_check` and `foreign_key_check`, then `VACUUM` if the
+/// database is intact.
+pub async fn check(store: &LocalStore) -> Result<StoreCheck, String> {
+    let mut conn = store.pool()?.acquire().await.map_err(db_error)?;
+    let size_before = page_bytes(&mut conn).await?;
+    let integrity: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(db_error)?;
+    let foreign_key_violations: Vec<ForeignKeyViolation> = sqlx::query("PRAGMA foreign_key_check")
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(db_error)?
+        .iter()
+        .map(|row| ForeignKeyViolation {
+            table: row.try_get("table").unwrap_or_default(),
+            rowid: row.try_get("rowid").ok().flatten(),
+            parent: row.try_get("parent").unwrap_or_default(),
+            fkid: row.try_get("fkid").unwrap_or_default(),
+        })
+        .collect();
+    let intact = integrity.len() == 1 && integrity[0] == "ok";
+    if intact {
+        sqlx::query("VACUUM")
+            .execute(&mut *conn)
+            .await
+            .map_err(db_error)?;
+    }
+    Ok(StoreCheck {
+        ok: intact && foreign_key_violations.is_empty(),
+        integrity,
+        foreign_key_violations,
+        vacuumed: intact,
+        size_before,
+        size_after: page_bytes(&mut conn).await?,
+    })
+}
+
+/// Reverts the store to `target` for handing it to an older release. Refuses
+/// unless `expected_current` is the applied version, every applied migration
+/// matches this build, and a backup has been written first.
+pub async fn rollback(
+    store: &LocalStore,
+    target: i64,
+    expected_current: i64,
+) -> Result<RollbackInfo, String> {
+    let applied = store.applied_migrations().await?;
+    if let Some(m) = applied.iter().find(|m| !m.success) {
+        return Err(format!(
+            "local_store_migration_dirty: migration {} did not finish",
+            m.version
+        ));
+    }
+    if let Some(m) = applied.iter().find(|m| !m.verified) {
+        return Err(format!(
+            "local_store_migration_mismatch: migration {} is unknown or modified",
+            m.version
+        ));
+    }
+    let current = applied.iter().map(|m| m.version).max().unwrap_or_default();
+    if current != expected_current {
+        return Err(format!(
+            "rollback_version_mismatch: store is at {}",
+            current
+        ));
+    }
+    if target < 1 || target >= current {
+        return Err(format!(
+            "rollback_target_invalid: expected 1..{}",
+            current - 1
+        ));
+    }
+    let dir = store
+        .path()
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(ROLLBACK_BACKUP_DIR);
+    let backup = backup(store, &dir, ROLLBACK_KEEP).await?;
+    store.rollback(target).await?;
+    Ok(RollbackInfo {
+        from_version: current,
+        to_version: target,
+        backup,
+    })
+}
+
+#[tauri::command]
+pub async fn backup_local_store(
+    store: State<'_, LocalStore>,
+    dir: String,
+    keep: Option<usize>,
+) -> Result<BackupInfo, String> {
+    backup(&store, Path::new(&dir), keep.unwrap_or(DEFAULT_KEEP)).await
+}
+
+/// Stages `path` and restarts the app to swap it in.
+#[tauri::command]
+pub async fn restore_local_store(
+    app: AppHandle,
+    store: State<'_, LocalStore>,
+    path: String,
+) -> Result<RestoreInfo, String> {
+    let info = stage_restore(&store, Path::new(&path)).await?;
+    app.request_restart();
+    Ok(info)
+}
+
+#[tauri::command]
+pub async fn rollback_local_store(
+    store: State<'_, LocalStore>,
+    target_version: i64,
+    current_version: i64,
+) -> Result<RollbackInfo, String> {
+    rollback(&store, target_version, current_version).await
+}
+
+#[tauri::command]
+pub async fn check_local_store(store: State<'_, LocalStore>) -> Result<StoreCheck, String> {
+    check(&store).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn swaps_staged_restore_and_keeps_previous() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let db = dir.join(LOCAL_DB_FILE);
+        assert!(!apply_pending_restore(dir).unwrap());
+
+        std::fs::write(&db, "current").unwrap();
+        std::fs::write(with_suffix(&db, "-wal"), "wal").unwrap();
+        std::fs::write(with_suffix(&db, STAGED_SUFFIX), "backup").unwrap();
+        assert!(apply_pending_restore(dir).unwrap());
+        assert_eq!(std::fs::read_to_string(&db).unwrap(), "backup");
+        let previous = with_suffix(&db, PREVIOUS_SUFFIX);
+        assert_eq!(std::fs::read_to_string(&previous).unwrap(), "current");
+        assert!(with_suffix(&previous, "-wal").exists());
+        assert!(!with_suffix(&db, "-wal").exists());
+        assert!(!with_suffix(&db, STAGED_SUFFIX).exists());
+    }
+}
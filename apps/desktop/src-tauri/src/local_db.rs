@@ -0,0 +1,185 @@
+//! Webview access to `rdv_local.db`. The backend is the only opener of the
+//! file (it may be SQLCipher-encrypted), so the frontend's `select`/`execute`
+//! calls come through here with the SQL plugin's binding and decoding rules,
+//! next to the lock and migration state that drives the unlock screen.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::sqlite::{SqliteArguments, SqliteValueRef};
+use sqlx::{Column, Row, Sqlite, TypeInfo, ValueRef};
+use tauri::{AppHandle, State};
+
+use crate::local_store::{db_error, latest_version, AppliedMigration, LocalStore};
+use crate::monitor;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalStoreStatus {
+    pub encrypted: bool,
+    pub unlocked: bool,
+    /// Highest applied migration; `None` while locked.
+    pub schema_version: Option<i64>,
+    /// Highest migration this build ships.
+    pub latest_version: i64,
+    /// `_sqlx_migrations`, empty while locked.
+    pub migrations: Vec<AppliedMigration>,
+    /// Set after `rollback_local_store`, until the app is restarted.
+    pub rolled_back_to: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalExecuteResult {
+    pub rows_affected: u64,
+    pub last_insert_id: i64,
+}
+
+pub async fn status(store: &LocalStore) -> Result<LocalStoreStatus, String> {
+    let migrations = if store.is_unlocked() {
+        store.applied_migrations().await?
+    } else {
+        Vec::new()
+    };
+    Ok(LocalStoreStatus {
+        encrypted: store.is_encrypted(),
+        unlocked: store.is_unlocked(),
+        schema_version: store
+            .is_unlocked()
+            .then(|| {
+                migrations
+                    .iter()
+                    .filter(|m| m.success)
+                    .map(|m| m.version)
+                    .max()
+            })
+            .map(Option::unwrap_or_default),
+        latest_version: latest_version(),
+        migrations,
+        rolled_back_to: store.rolled_back_to(),
+    })
+}
+
+/// Same rules as the SQL plugin: every number binds as REAL, objects and
+/// booleans as JSON text.
+fn bind_values(
+    sql: &str,
+    values: Vec<Value>,
+) -> sqlx::query::Query<'_, Sqlite, SqliteArguments<'_>> {
+    let mut query = sqlx::query(sql);
+    for value in values {
+        query = match value {
+            Value::Null => query.bind(None::<String>),
+            Value::String(text) => query.bind(text),
+            Value::Number(number) => query.bind(number.as_f64().unwrap_or_default()),
+            other => query.bind(other),
+        };
+    }
+    query
+}
+
+fn to_json(value: SqliteValueRef<'_>) -> Value {
+    if value.is_null() {
+        return Value::Null;
+    }
+    let owned = sqlx::ValueRef::to_owned(&value);
+    let decoded = match value.type_info().name() {
+        "REAL" => sqlx::Value::try_decode::<f64>(&owned).ok().map(Value::from),
+        "INTEGER" | "NUMERIC" => sqlx::Value::try_decode::<i64>(&owned).ok().map(Value::from),
+        "BOOLEAN" => sqlx::Value::try_decode::<bool>(&owned)
+            .ok()
+            .map(Value::Bool),
+        "BLOB" => sqlx::Value::try_decode::<Vec<u8>>(&owned)
+            .ok()
+            .map(|bytes| Value::Array(bytes.into_iter().map(Value::from).collect())),
+        _ => sqlx::Value::try_decode::<String>(&owned)
+            .ok()
+            .map(Value::String),
+    };
+    decoded.unwrap_or(Value::Null)
+}
+
+#[tauri::command]
+pub async fn local_db_select(
+    store: State<'_, LocalStore>,
+    query: String,
+    values: Option<Vec<Value>>,
+) -> Result<Vec<Map<String, Value>>, String> {
+    let rows = bind_values(&query, values.unwrap_or_default())
+        .fetch_all(&store.pool()?)
+        .await
+        .map_err(db_error)?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            row.columns()
+                .iter()
+                .map(|column| {
+                    let value = row
+                        .try_get_raw(column.ordinal())
+                        .map(to_json)
+                        .unwrap_or(Value::Null);
+                    (column.name().to_string(), value)
+                })
+                .collect()
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn local_db_execute(
+    store: State<'_, LocalStore>,
+    query: String,
+    values: Option<Vec<Value>>,
+) -> Result<LocalExecuteResult, String> {
+    let done = bind_values(&query, values.unwrap_or_default())
+        .execute(&store.pool()?)
+        .await
+        .map_err(db_error)?;
+    Ok(LocalExecuteResult {
+        rows_affected: done.rows_affected(),
+        last_insert_id: done.last_insert_rowid(),
+    })
+}
+
+#[tauri::command]
+pub async fn local_store_status(store: State<'_, LocalStore>) -> Result<LocalStoreStatus, String> {
+    status(&store).await
+}
+
+/// Opens an encrypted store, then starts what startup skipped while locked.
+#[tauri::command]
+pub async fn unlock_local_store(
+    app: AppHandle,
+    store: State<'_, LocalStore>,
+    passphrase: String,
+) -> Result<LocalStoreStatus, String> {
+    let was_locked = !store.is_unlocked();
+    store.unlock(&passphrase).await?;
+    if was_locked {
+        monitor::resume_saved(app);
+    }
+    status(&store).await
+}
+
+#[tauri::command]
+pub async fn lock_local_store(store: State<'_, LocalStore>) -> Result<LocalStoreStatus, String> {
+    store.lock().await?;
+    status(&store).await
+}
+
+/// Encrypts the store in place, changes its passphrase, or with `None`
+/// decrypts it. An encrypted store asks for its current passphrase first.
+#[tauri::command]
+pub async fn set_local_store_passphrase(
+    store: State<'_, LocalStore>,
+    current: Option<String>,
+    passphrase: Option<String>,
+) -> Result<LocalStoreStatus, String> {
+    if store.is_encrypted() && !store.verify_passphrase(current.as_deref().unwrap_or_default())? {
+        return Err("local_store_passphrase_invalid".to_string());
+    }
+    let passphrase = passphrase.filter(|p| !p.is_empty());
+    if passphrase.as_deref().is_some_and(|p| p.chars().count() < 8) {
+        return Err("local_store_passphrase_too_short".to_string());
+    }
+    store.set_passphrase(passphrase.as_deref()).await?;
+    status(&store).await
+}
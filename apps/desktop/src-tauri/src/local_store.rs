@@ -0,0 +1,677 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sqlx::migrate::{Migration, MigrationType, Migrator};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::Row;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri_plugin_sql::MigrationKind;
+
+use crate::crypto::{passphrase_key, random_base64};
+use crate::migrations::migrations;
+
+/// File name the store has always used (`sqlite:rdv_local.db` in the SQL
+/// plugin days), inside the app config dir.
+pub const LOCAL_DB_FILE: &str = "rdv_local.db";
+/// Sidecar holding the KDF parameters of an encrypted store; its presence is
+/// what marks the database as encrypted.
+const KDF_SUFFIX: &str = ".kdf.json";
+/// Copy written by `set_passphrase` before it replaces the database.
+const REKEY_SUFFIX: &str = ".rekey";
+/// Present while `set_passphrase` swaps files; [`LocalStore::open`] finishes
+/// or abandons the swap it describes.
+const SWAP_SUFFIX: &str = ".swap.json";
+const KDF_ALG: &str = "PBKDF2-SHA256";
+const KDF_ITERATIONS: u32 = 256_000;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StoreKdf {
+    pub alg: String,
+    pub iterations: u32,
+    pub salt: String,
+}
+
+/// A row of `_sqlx_migrations`, checked against this build's migrations.
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub description: String,
+    pub installed_on: Option<String>,
+    pub success: bool,
+    /// Hex SHA-384 of the migration SQL as recorded.
+    pub checksum: String,
+    pub execution_ms: i64,
+    /// This build has the migration and the checksum matches.
+    pub verified: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct SwapState {
+    /// Whether the replacement database is encrypted.
+    encrypted: bool,
+}
+
+struct Opened {
+    pool: SqlitePool,
+    /// SQLCipher raw key literal (`"x'..'"`) when the store is encrypted.
+    key: Option<String>,
+}
+
+/// Rust-side handle on `rdv_local.db`, the only opener of the file: the
+/// webview reaches it through `local_db`. An encrypted store (SQLCipher) stays
+/// locked until [`LocalStore::unlock`] is given the passphrase.
+pub struct LocalStore {
+    path: PathBuf,
+    opened: RwLock<Option<Opened>>,
+    /// Set by [`LocalStore::rollback`]; the store stays closed afterwards so
+    /// this build does not migrate it straight back up.
+    rolled_back_to: Mutex<Option<i64>>,
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn io_error(err: std::io::Error) -> String {
+    format!("local_store_io_error: {}", err)
+}
+
+/// Raw 256-bit SQLCipher key, so SQLCipher skips its own KDF.
+fn cipher_key(passphrase: &str, kdf: &StoreKdf) -> Result<String, String> {
+    let salt = BASE64
+        .decode(&kdf.salt)
+        .map_err(|err| format!("local_store_kdf_invalid: {}", err))?;
+    let key = passphrase_key(passphrase, &salt, kdf.iterations);
+    let bytes = BASE64.decode(key).unwrap_or_default();
+    Ok(format!("\"x'{}'\"", hex::encode(bytes)))
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Puts the KDF sidecar in the state the replaced database needs.
+fn finish_swap(path: &Path, encrypted: bool) -> std::io::Result<()> {
+    let kdf_path = with_suffix(path, KDF_SUFFIX);
+    let staged_kdf = with_suffix(&with_suffix(path, REKEY_SUFFIX), KDF_SUFFIX);
+    if !encrypted {
+        return remove_if_exists(&kdf_path);
+    }
+    if staged_kdf.exists() {
+        std::fs::rename(&staged_kdf, &kdf_path)?;
+    }
+    Ok(())
+}
+
+/// Resolves a passphrase change interrupted by a crash: while the rekeyed
+/// copy is still there the database was never replaced and the copy is
+/// dropped; otherwise the sidecar step is completed.
+fn resolve_swap(path: &Path) -> std::io::Result<()> {
+    let swap = with_suffix(path, SWAP_SUFFIX);
+    let Ok(raw) = std::fs::read(&swap) else {
+        return Ok(());
+    };
+    let target = with_suffix(path, REKEY_SUFFIX);
+    match serde_json::from_slice::<SwapState>(&raw) {
+        Ok(state) if !target.exists() => finish_swap(path, state.encrypted)?,
+        _ => {
+            remove_if_exists(&target)?;
+            remove_if_exists(&with_suffix(&target, KDF_SUFFIX))?;
+        }
+    }
+    std::fs::remove_file(&swap)
+}
+
+impl LocalStore {
+    /// Opens a plaintext store right away; an encrypted one starts locked.
+    /// An interrupted passphrase change is resolved first.
+    pub fn open(config_dir: &Path) -> std::io::Result<Self> {
+        let path = config_dir.join(LOCAL_DB_FILE);
+        resolve_swap(&path)?;
+        let store = Self {
+            path,
+            opened: RwLock::new(None),
+            rolled_back_to: Mutex::new(None),
+        };
+        if !store.is_encrypted() {
+            let pool = SqlitePoolOptions::new()
+                .max_connections(4)
+                .connect_lazy_with(store.connect_options(&store.path, None));
+            store.install(pool, None);
+        }
+        Ok(store)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn pool(&self) -> Result<SqlitePool, String> {
+        self.opened
+            .read()
+            .ok()
+            .and_then(|opened| opened.as_ref().map(|o| o.pool.clone()))
+            .ok_or_else(|| "local_store_locked".to_string())
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        with_suffix(&self.path, KDF_SUFFIX).exists()
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.opened.read().is_ok_and(|opened| opened.is_some())
+    }
+
+    pub fn rolled_back_to(&self) -> Option<i64> {
+        self.rolled_back_to.lock().ok().and_then(|v| *v)
+    }
+
+    fn kdf(&self) -> Result<StoreKdf, String> {
+        let raw = std::fs::read(with_suffix(&self.path, KDF_SUFFIX)).map_err(io_error)?;
+        let kdf: StoreKdf = serde_json::from_slice(&raw)
+            .map_err(|err| format!("local_store_kdf_invalid: {}", err))?;
+        if kdf.alg != KDF_ALG {
+            return Err(format!(
+                "local_store_kdf_invalid: unknown algorithm {}",
+                kdf.alg
+            ));
+        }
+        Ok(kdf)
+    }
+
+    fn connect_options(&self, path: &Path, key: Option<&str>) -> SqliteConnectOptions {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .foreign_keys(true);
+        match key {
+            Some(key) => options.pragma("key", key.to_string()),
+            None => options,
+        }
+    }
+
+    /// Options for another database file (e.g. a backup) under this store's
+    /// current key.
+    pub fn options_for(&self, path: &Path) -> Result<SqliteConnectOptions, String> {
+        let opened = self
+            .opened
+            .read()
+            .map_err(|_| "local_store_locked".to_string())?;
+        let opened = opened.as_ref().ok_or("local_store_locked")?;
+        Ok(self.connect_options(path, opened.key.as_deref()))
+    }
+
+    /// Key literal for `ATTACH ... KEY`, empty for a plaintext store.
+    pub fn attach_key(&self) -> Result<String, String> {
+        let opened = self
+            .opened
+            .read()
+            .map_err(|_| "local_store_locked".to_string())?;
+        let opened = opened.as_ref().ok_or("local_store_locked")?;
+        Ok(opened.key.clone().unwrap_or_else(|| "''".to_string()))
+    }
+
+    fn install(&self, pool: SqlitePool, key: Option<String>) {
+        if let Ok(mut opened) = self.opened.write() {
+            *opened = Some(Opened { pool, key });
+        }
+    }
+
+    fn take(&self) -> Option<Opened> {
+        self.opened
+            .write()
+            .ok()
+            .and_then(|mut opened| opened.take())
+    }
+
+    /// Applies `migrations::migrations()`, recording them in
+    /// `_sqlx_migrations` exactly like the SQL plugin did.
+    pub async fn migrate(&self) -> Result<(), String> {
+        migrator()
+            .run(&self.pool()?)
+            .await
+            .map_err(|err| format!("local_store_migration_failed: {}", err))
+    }
+
+    pub async fn applied_migrations(&self) -> Result<Vec<AppliedMigration>, String> {
+        let rows = sqlx::query(
+            "SELECT version, description, installed_on, success, checksum, execution_time \
+             FROM _sqlx_migrations ORDER BY version",
+        )
+        .fetch_all(&self.pool()?)
+        .await
+        .map_err(db_error)?;
+        let known = migrator();
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let version: i64 = row.try_get("version").unwrap_or_default();
+                let checksum: Vec<u8> = row.try_get("checksum").unwrap_or_default();
+                let verified = known.iter().any(|m| {
+                    m.version == version
+                        && m.migration_type.is_up_migration()
+                        && *m.checksum == *checksum
+                });
+                AppliedMigration {
+                    version,
+                    description: row_text(row, "description").unwrap_or_default(),
+                    installed_on: row_text(row, "installed_on"),
+                    success: row.try_get("success").unwrap_or_default(),
+                    checksum: hex::encode(checksum),
+                    execution_ms: row.try_get::<i64, _>("execution_time").unwrap_or_default()
+                        / 1_000_000,
+                    verified,
+                }
+            })
+            .collect())
+    }
+
+    /// Reverts every applied migration above `target`, newest first, then
+    /// closes the store. Launching this build again migrates it back up, so
+    /// this is for handing the file to an older release.
+    pub async fn rollback(&self, target: i64) -> Result<(), String> {
+        migrator()
+            .undo(&self.pool()?, target)
+            .await
+            .map_err(|err| format!("local_store_rollback_failed: {}", err))?;
+        if let Some(opened) = self.take() {
+            opened.pool.close().await;
+        }
+        if let Ok(mut rolled_back_to) = self.rolled_back_to.lock() {
+            *rolled_back_to = Some(target);
+        }
+        Ok(())
+    }
+
+    async fn connect(&self, key: Option<String>) -> Result<SqlitePool, String> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(self.connect_options(&self.path, key.as_deref()))
+            .await
+            .map_err(|err| match &err {
+                sqlx::Error::Database(db) if db.code().as_deref() == Some("26") => {
+                    "local_store_passphrase_invalid".to_string()
+                }
+                _ => db_error(err),
+            })?;
+        // SQLCipher only notices a wrong key on first read.
+        if let Err(err) = sqlx::query("SELECT count(*) FROM sqlite_master")
+            .fetch_one(&pool)
+            .await
+        {
+            pool.close().await;
+            return Err(match &err {
+                sqlx::Error::Database(db) if db.code().as_deref() == Some("26") => {
+                    "local_store_passphrase_invalid".to_string()
+                }
+                _ => db_error(err),
+            });
+        }
+        Ok(pool)
+    }
+
+    /// Opens an encrypted store with its passphrase and brings its schema up
+    /// to date.
+    pub async fn unlock(&self, passphrase: &str) -> Result<(), String> {
+        if self.is_unlocked() {
+            return Ok(());
+        }
+        if self.rolled_back_to().is_some() {
+            return Err("local_store_rolled_back".to_string());
+        }
+        let key = cipher_key(passphrase, &self.kdf()?)?;
+        let pool = self.connect(Some(key.clone())).await?;
+        self.install(pool, Some(key));
+        self.migrate().await
+    }
+
+    /// Whether `passphrase` is the one the unlocked store was opened with.
+    pub fn verify_passphrase(&self, passphrase: &str) -> Result<bool, String> {
+        let key = cipher_key(passphrase, &self.kdf()?)?;
+        let opened = self
+            .opened
+            .read()
+            .map_err(|_| "local_store_locked".to_string())?;
+        let opened = opened.as_ref().ok_or("local_store_locked")?;
+        Ok(opened.key.as_deref() == Some(key.as_str()))
+    }
+
+    /// Closes an encrypted store; commands fail with `local_store_locked`
+    /// until it is unlocked again.
+    pub async fn lock(&self) -> Result<(), String> {
+        if !self.is_encrypted() {
+            return Err("local_store_not_encrypted".to_string());
+        }
+        if let Some(opened) = self.take() {
+            opened.pool.close().await;
+        }
+        Ok(())
+    }
+
+    /// Rewrites the database in place under `passphrase`: encrypts a
+    /// plaintext store, re-keys an encrypted one, or with `None` decrypts it.
+    /// The copy is made with `sqlcipher_export` and swapped in once complete.
+    pub async fn set_passphrase(&self, passphrase: Option<&str>) -> Result<(), String> {
+        let pool = self.pool()?;
+        let kdf = passphrase.map(|_| StoreKdf {
+            alg: KDF_ALG.to_string(),
+            iterations: KDF_ITERATIONS,
+            salt: random_base64(16),
+        });
+        let key = passphrase
+            .zip(kdf.as_ref())
+            .map(|(p, kdf)| cipher_key(p, kdf))
+            .transpose()?;
+        let target = with_suffix(&self.path, REKEY_SUFFIX);
+        let staged_kdf = with_suffix(&target, KDF_SUFFIX);
+        remove_if_exists(&target).map_err(io_error)?;
+
+        let mut conn = pool.acquire().await.map_err(db_error)?;
+        // ATTACH takes no bound key; both values are generated above.
+        sqlx::query(&format!(
+            "ATTACH DATABASE '{}' AS rekeyed KEY {}",
+            target.to_string_lossy().replace('\'', "''"),
+            key.as_deref().unwrap_or("''")
+        ))
+        .execute(&mut *conn)
+        .await
+        .map_err(db_error)?;
+        let exported = sqlx::query("SELECT sqlcipher_export('rekeyed')")
+            .execute(&mut *conn)
+            .await;
+        sqlx::query("DETACH DATABASE rekeyed")
+            .execute(&mut *conn)
+            .await
+            .map_err(db_error)?;
+        if let Err(err) = exported {
+            let _ = std::fs::remove_file(&target);
+            return Err(db_error(err));
+        }
+        drop(conn);
+
+        let swap = with_suffix(&self.path, SWAP_SUFFIX);
+        let staged = kdf
+            .as_ref()
+            .map(|kdf| {
+                let raw = serde_json::to_vec_pretty(kdf).unwrap_or_default();
+                std::fs::write(&staged_kdf, raw)
+            })
+            .transpose()
+            .and_then(|_| {
+                let state = SwapState {
+                    encrypted: kdf.is_some(),
+                };
+                std::fs::write(&swap, serde_json::to_vec(&state).unwrap_or_default())
+            });
+        if let Err(err) = staged {
+            let _ = remove_if_exists(&swap);
+            let _ = remove_if_exists(&target);
+            let _ = remove_if_exists(&staged_kdf);
+            return Err(io_error(err));
+        }
+
+        let old_key = match self.take() {
+            Some(opened) => {
+                opened.pool.close().await;
+                opened.key
+            }
+            None => None,
+        };
+        for suffix in ["-wal", "-shm"] {
+            let _ = std::fs::remove_file(with_suffix(&self.path, suffix));
+        }
+        if let Err(err) = std::fs::rename(&target, &self.path) {
+            // Nothing was replaced: drop the copy and reopen as before.
+            let _ = resolve_swap(&self.path);
+            let pool = self.connect(old_key.clone()).await?;
+            self.install(pool, old_key);
+            return Err(io_error(err));
+        }
+        // The file is under the new key from here on. If the sidecar step
+        // fails, the swap state stays behind and the next open finishes it.
+        let finished = finish_swap(&self.path, kdf.is_some())
+            .and_then(|_| std::fs::remove_file(&swap))
+            .map_err(io_error);
+        let pool = self.connect(key.clone()).await?;
+        self.install(pool, key);
+        finished
+    }
+}
+
+/// Up and down migrations as a sqlx migrator; versions pair up as
+/// reversible migrations.
+fn migrator() -> Migrator {
+    let list: Vec<Migration> = migrations()
+        .into_iter()
+        .map(|m| {
+            let migration_type = match m.kind {
+                MigrationKind::Up => MigrationType::ReversibleUp,
+                MigrationKind::Down => MigrationType::ReversibleDown,
+            };
+            Migration::new(
+                m.version,
+                m.description.into(),
+                migration_type,
+                m.sql.into(),
+                false,
+            )
+        })
+        .collect();
+    Migrator {
+        migrations: Cow::Owned(list),
+        ..Migrator::DEFAULT
+    }
+}
+
+/// Highest up migration this build knows.
+pub fn latest_version() -> i64 {
+    migrations()
+        .iter()
+        .filter(|m| matches!(m.kind, MigrationKind::Up))
+        .map(|m| m.version)
+        .max()
+        .unwrap_or_default()
+}
+
+pub fn now_sec() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Milliseconds, the unit the webview writes `saved_sql` timestamps in.
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Time-ordered id for rows created on the Rust side.
+pub fn new_id(prefix: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let seq = COUNTER.fetch_add(1, Ordering::Relaxed) & 0xffff;
+    format!("{}_{:x}{:04x}", prefix, nanos, seq)
+}
+
+/// Reads a TEXT column that may have been written as a BLOB by the webview
+/// (mirrors `decodeSqliteText` on the frontend).
+pub fn row_text(row: &SqliteRow, column: &str) -> Option<String> {
+    if let Ok(value) = row.try_get::<Option<String>, _>(column) {
+        return value;
+    }
+    row.try_get::<Option<Vec<u8>>, _>(column)
+        .ok()
+        .flatten()
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub fn db_error(err: sqlx::Error) -> String {
+    format!("local_store_error: {}", err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn cipher_key_is_a_raw_hex_key_bound_to_the_salt() {
+        let kdf = StoreKdf {
+            alg: KDF_ALG.to_string(),
+            iterations: 1_000,
+            salt: "c2FsdA==".to_string(),
+        };
+        let key = cipher_key("correct horse", &kdf).unwrap();
+        assert!(key.starts_with("\"x'") && key.ends_with("'\""));
+        assert_eq!(key.len(), 64 + 5);
+        assert_eq!(key, cipher_key("correct horse", &kdf).unwrap());
+
+        let other = StoreKdf {
+            salt: "b3RoZXI=".to_string(),
+            ..kdf.clone()
+        };
+        assert_ne!(key, cipher_key("correct horse", &other).unwrap());
+        let raw = StoreKdf {
+            salt: "not base64!".to_string(),
+            ..kdf.clone()
+        };
+        assert!(cipher_key("correct horse", &raw).is_err());
+    }
+
+    #[test]
+    fn resolves_interrupted_swap() {
+        let dir = temp_dir();
+        let db = dir.path().join(LOCAL_DB_FILE);
+        let target = with_suffix(&db, REKEY_SUFFIX);
+        let swap = with_suffix(&db, SWAP_SUFFIX);
+        let kdf_path = with_suffix(&db, KDF_SUFFIX);
+        let staged_kdf = with_suffix(&target, KDF_SUFFIX);
+
+        // Crash before the database was replaced: the old file stays.
+        std::fs::write(&db, "plain").unwrap();
+        std::fs::write(&target, "encrypted").unwrap();
+        std::fs::write(&staged_kdf, "kdf").unwrap();
+        std::fs::write(&swap, r#"{"encrypted":true}"#).unwrap();
+        resolve_swap(&db).unwrap();
+        assert_eq!(std::fs::read_to_string(&db).unwrap(), "plain");
+        assert!(!target.exists() && !staged_kdf.exists() && !kdf_path.exists() && !swap.exists());
+
+        // Crash after the database was replaced: the sidecar follows it.
+        std::fs::write(&staged_kdf, "kdf").unwrap();
+        std::fs::write(&swap, r#"{"encrypted":true}"#).unwrap();
+        resolve_swap(&db).unwrap();
+        assert_eq!(std::fs::read_to_string(&kdf_path).unwrap(), "kdf");
+        assert!(!swap.exists());
+
+        std::fs::write(&swap, r#"{"encrypted":false}"#).unwrap();
+        resolve_swap(&db).unwrap();
+        assert!(!kdf_path.exists() && !swap.exists());
+    }
+
+    /// Objects and their columns, skipping sqlx's and SQLite's own tables.
+    async fn schema(pool: &SqlitePool) -> Vec<String> {
+        let rows = sqlx::query(
+            "SELECT m.type, m.name, group_concat(c.name || ' ' || c.type || ' ' || c.\"notnull\" || ' ' || ifnull(c.dflt_value, ''), ', ') AS columns \
+             FROM sqlite_master m LEFT JOIN pragma_table_info(m.name) c \
+             WHERE m.name NOT LIKE 'sqlite_%' AND m.name != '_sqlx_migrations' \
+             GROUP BY m.type, m.name ORDER BY m.type, m.name",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        rows.iter()
+            .map(|row| {
+                format!(
+                    "{} {} ({})",
+                    row_text(row, "type").unwrap_or_default(),
+                    row_text(row, "name").unwrap_or_default(),
+                    row_text(row, "columns").unwrap_or_default()
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn applies_every_up_and_down_migration() {
+        let dir = temp_dir();
+        let full = migrator();
+        let latest = latest_version();
+        assert!(full
+            .iter()
+            .filter(|m| m.migration_type.is_up_migration())
+            .all(|m| full
+                .iter()
+                .any(|d| d.version == m.version && d.migration_type.is_down_migration())));
+
+        tauri::async_runtime::block_on(async {
+            // The lazy pool needs a runtime to be created in.
+            let store = LocalStore::open(dir.path()).unwrap();
+            let pool = store.pool().unwrap();
+            // Schema after each up migration, applied one version at a time.
+            let mut expected = vec![schema(&pool).await];
+            for version in 1..=latest {
+                let partial = Migrator {
+                    migrations: Cow::Owned(
+                        full.iter()
+                            .filter(|m| m.version <= version)
+                            .cloned()
+                            .collect(),
+                    ),
+                    ..Migrator::DEFAULT
+                };
+                partial.run(&pool).await.unwrap();
+                expected.push(schema(&pool).await);
+            }
+            assert_eq!(
+                store.applied_migrations().await.unwrap().len() as i64,
+                latest
+            );
+            assert!(store
+                .applied_migrations()
+                .await
+                .unwrap()
+                .iter()
+                .all(|m| m.verified));
+
+            sqlx::query("INSERT INTO saved_sql (id, name, sql, variables) VALUES ('q1', 'q', 'select 1', '[]')")
+                .execute(&pool)
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO ops_audit (id, conn_id, action, status, created_at, seq) VALUES ('a1', 'c', 'cancel', 'ok', 1, 1)")
+                .execute(&pool)
+                .await
+                .unwrap();
+
+            for version in (0..latest).rev() {
+                full.undo(&pool, version).await.unwrap();
+                assert_eq!(
+                    schema(&pool).await,
+                    expected[version as usize],
+                    "down to {}",
+                    version
+                );
+            }
+            store.migrate().await.unwrap();
+            assert_eq!(schema(&pool).await, expected[latest as usize]);
+
+            store.rollback(latest - 1).await.unwrap();
+            assert_eq!(store.rolled_back_to(), Some(latest - 1));
+            assert_eq!(store.pool().err().as_deref(), Some("local_store_locked"));
+        });
+    }
+}
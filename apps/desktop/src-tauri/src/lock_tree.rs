@@ -0,0 +1,517 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use tauri::State;
+
+use crate::local_store::{now_sec, LocalStore};
+use crate::pg::{begin_read_only, fetch_as, fetch_one_as, PgPools};
+
+const LOCK_TREE_STATEMENT_TIMEOUT_MS: u64 = 10_000;
+const MAX_DEPTH: usize = 32;
+/// Caps expansion when many sessions share blockers (each path is expanded).
+const MAX_NODES: usize = 2_000;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionRow {
+    pub pid: i64,
+    pub usename: Option<String>,
+    pub application_name: Option<String>,
+    pub client_addr: Option<String>,
+    pub state: Option<String>,
+    pub wait_event_type: Option<String>,
+    pub wait_event: Option<String>,
+    pub backend_type: Option<String>,
+    pub query: Option<String>,
+    pub xact_seconds: Option<f64>,
+    pub query_seconds: Option<f64>,
+    #[serde(default)]
+    pub blocked_by: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LockPairRow {
+    pub blocked_pid: i64,
+    pub blocking_pid: i64,
+    pub locktype: String,
+    pub requested_mode: String,
+    pub held_mode: String,
+    /// `false` when the blocker is only queued ahead for a conflicting lock.
+    pub held: bool,
+    pub relation: Option<String>,
+    pub wait_seconds: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LockEdge {
+    pub blocking_pid: i64,
+    pub locktype: Option<String>,
+    pub requested_mode: Option<String>,
+    pub held_mode: Option<String>,
+    pub held: Option<bool>,
+    pub relation: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LockTreeNode {
+    pub pid: i64,
+    pub usename: Option<String>,
+    pub application_name: Option<String>,
+    pub client_addr: Option<String>,
+    pub state: Option<String>,
+    pub wait_event_type: Option<String>,
+    pub wait_event: Option<String>,
+    pub backend_type: Option<String>,
+    pub query: Option<String>,
+    pub xact_seconds: Option<f64>,
+    /// Time spent waiting for the lock (`pg_locks.waitstart` on 14+, else
+    /// the age of the waiting statement).
+    pub wait_seconds: Option<f64>,
+    pub depth: usize,
+    /// How this session waits on its parent; `None` for roots.
+    pub edge: Option<LockEdge>,
+    pub blocked_by: Vec<i64>,
+    /// Distinct sessions waiting behind this one, directly or transitively.
+    pub blocking_count: usize,
+    pub in_cycle: bool,
+    /// Set on the repeated entry that closes a cycle; it is not expanded.
+    pub cycle_ref: bool,
+    pub children: Vec<LockTreeNode>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LockTree {
+    pub roots: Vec<LockTreeNode>,
+    /// Each cycle lists its pids in ascending order.
+    pub cycles: Vec<Vec<i64>>,
+    pub blocked_sessions: usize,
+    pub max_depth: usize,
+    pub truncated: bool,
+    pub generated_at: i64,
+}
+
+const SESSIONS_SQL: &str = r#"
+SELECT
+  a.pid::bigint AS pid,
+  a.usename::text AS usename,
+  a.application_name,
+  a.client_addr::text AS client_addr,
+  a.state,
+  a.wait_event_type,
+  a.wait_event,
+  a.backend_type,
+  left(a.query, 2000) AS query,
+  EXTRACT(EPOCH FROM now() - a.xact_start)::float8 AS xact_seconds,
+  EXTRACT(EPOCH FROM now() - a.query_start)::float8 AS query_seconds,
+  pg_catalog.pg_blocking_pids(a.pid)::bigint[] AS blocked_by
+FROM pg_catalog.pg_stat_activity a
+WHERE a.pid <> pg_catalog.pg_backend_pid()"#;
+
+fn lock_pairs_sql(has_waitstart: bool) -> String {
+    let wait_seconds = if has_waitstart {
+        "EXTRACT(EPOCH FROM now() - w.waitstart)::float8"
+    } else {
+        "NULL::float8"
+    };
+    format!(
+        r#"
+SELECT
+  w.pid::bigint AS blocked_pid,
+  h.pid::bigint AS blocking_pid,
+  w.locktype,
+  w.mode AS requested_mode,
+  h.mode AS held_mode,
+  h.granted AS held,
+  CASE WHEN w.relation IS NOT NULL THEN w.relation::regclass::text END AS relation,
+  {wait_seconds} AS wait_seconds
+FROM pg_catalog.pg_locks w
+JOIN pg_catalog.pg_locks h
+  ON h.pid = ANY (pg_catalog.pg_blocking_pids(w.pid))
+ AND h.locktype = w.locktype
+ AND h.database IS NOT DISTINCT FROM w.database
+ AND h.relation IS NOT DISTINCT FROM w.relation
+ AND h.page IS NOT DISTINCT FROM w.page
+ AND h.tuple IS NOT DISTINCT FROM w.tuple
+ AND h.virtualxid IS NOT DISTINCT FROM w.virtualxid
+ AND h.transactionid IS NOT DISTINCT FROM w.transactionid
+ AND h.classid IS NOT DISTINCT FROM w.classid
+ AND h.objid IS NOT DISTINCT FROM w.objid
+ AND h.objsubid IS NOT DISTINCT FROM w.objsubid
+WHERE NOT w.granted
+ORDER BY h.granted DESC"#
+    )
+}
+
+/// Strongly connected components of the waits-for graph with more than one
+/// member (or a self-loop). Tarjan's algorithm; graphs here are small.
+fn find_cycles(waits_for: &BTreeMap<i64, Vec<i64>>) -> Vec<Vec<i64>> {
+    struct Tarjan<'a> {
+        graph: &'a BTreeMap<i64, Vec<i64>>,
+        index: HashMap<i64, usize>,
+        low: HashMap<i64, usize>,
+        stack: Vec<i64>,
+        on_stack: BTreeSet<i64>,
+        next: usize,
+        cycles: Vec<Vec<i64>>,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, pid: i64) {
+            self.index.insert(pid, self.next);
+            self.low.insert(pid, self.next);
+            self.next += 1;
+            self.stack.push(pid);
+            self.on_stack.insert(pid);
+            for &next in self.graph.get(&pid).into_iter().flatten() {
+                if !self.graph.contains_key(&next) {
+                    continue;
+                }
+                if !self.index.contains_key(&next) {
+                    self.visit(next);
+                    let low = self.low[&pid].min(self.low[&next]);
+                    self.low.insert(pid, low);
+                } else if self.on_stack.contains(&next) {
+                    let low = self.low[&pid].min(self.index[&next]);
+                    self.low.insert(pid, low);
+                }
+            }
+            if self.low[&pid] == self.index[&pid] {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(&member);
+                    component.push(member);
+                    if member == pid {
+                        break;
+                    }
+                }
+                let self_loop = self.graph[&pid].contains(&pid);
+                if component.len() > 1 || self_loop {
+                    component.sort_unstable();
+                    self.cycles.push(component);
+                }
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        graph: waits_for,
+        index: HashMap::new(),
+        low: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: BTreeSet::new(),
+        next: 0,
+        cycles: Vec::new(),
+    };
+    for &pid in waits_for.keys() {
+        if !tarjan.index.contains_key(&pid) {
+            tarjan.visit(pid);
+        }
+    }
+    tarjan.cycles.sort();
+    tarjan.cycles
+}
+
+struct TreeBuilder<'a> {
+    sessions: HashMap<i64, &'a SessionRow>,
+    /// blocker -> sessions waiting on it
+    blocks: BTreeMap<i64, Vec<i64>>,
+    pairs: HashMap<(i64, i64), &'a LockPairRow>,
+    waiting: HashMap<i64, &'a LockPairRow>,
+    in_cycle: BTreeSet<i64>,
+    emitted: usize,
+    max_depth: usize,
+    truncated: bool,
+}
+
+impl TreeBuilder<'_> {
+    fn blocking_count(&self, pid: i64) -> usize {
+        let mut seen = BTreeSet::new();
+        let mut queue = VecDeque::from([pid]);
+        while let Some(current) = queue.pop_front() {
+            for &child in self.blocks.get(&current).into_iter().flatten() {
+                if child != pid && seen.insert(child) {
+                    queue.push_back(child);
+                }
+            }
+        }
+        seen.len()
+    }
+
+    fn edge(&self, blocked: i64, blocking: i64) -> LockEdge {
+        match self.pairs.get(&(blocked, blocking)) {
+            Some(pair) => LockEdge {
+                blocking_pid: blocking,
+                locktype: Some(pair.locktype.clone()),
+                requested_mode: Some(pair.requested_mode.clone()),
+                held_mode: Some(pair.held_mode.clone()),
+                held: Some(pair.held),
+                relation: pair.relation.clone(),
+            },
+            None => {
+                let waiting = self.waiting.get(&blocked);
+                LockEdge {
+                    blocking_pid: blocking,
+                    locktype: waiting.map(|w| w.locktype.clone()),
+                    requested_mode: waiting.map(|w| w.requested_mode.clone()),
+                    held_mode: None,
+                    held: None,
+                    relation: waiting.and_then(|w| w.relation.clone()),
+                }
+            }
+        }
+    }
+
+    fn node(
+        &mut self,
+        pid: i64,
+        depth: usize,
+        parent: Option<i64>,
+        path: &mut Vec<i64>,
+    ) -> LockTreeNode {
+        self.emitted += 1;
+        self.max_depth = self.max_depth.max(depth);
+        let session = self.sessions.get(&pid).copied();
+        let cycle_ref = path.contains(&pid);
+        let wait_seconds = self
+            .waiting
+            .get(&pid)
+            .and_then(|w| w.wait_seconds)
+            .or_else(|| {
+                session
+                    .filter(|s| !s.blocked_by.is_empty())
+                    .and_then(|s| s.query_seconds)
+            });
+        let mut node = LockTreeNode {
+            pid,
+            usename: session.and_then(|s| s.usename.clone()),
+            application_name: session.and_then(|s| s.application_name.clone()),
+            client_addr: session.and_then(|s| s.client_addr.clone()),
+            state: session.and_then(|s| s.state.clone()),
+            wait_event_type: session.and_then(|s| s.wait_event_type.clone()),
+            wait_event: session.and_then(|s| s.wait_event.clone()),
+            backend_type: session.and_then(|s| s.backend_type.clone()),
+            query: session.and_then(|s| s.query.clone()),
+            xact_seconds: session.and_then(|s| s.xact_seconds),
+            wait_seconds,
+            depth,
+            edge: parent.map(|blocking| self.edge(pid, blocking)),
+            blocked_by: session.map(|s| s.blocked_by.clone()).unwrap_or_default(),
+            blocking_count: self.blocking_count(pid),
+            in_cycle: self.in_cycle.contains(&pid),
+            cycle_ref,
+            children: Vec::new(),
+        };
+        if cycle_ref {
+            return node;
+        }
+        if depth >= MAX_DEPTH {
+            self.truncated |= self.blocks.contains_key(&pid);
+            return node;
+        }
+        path.push(pid);
+        let children = self.blocks.get(&pid).cloned().unwrap_or_default();
+        for child in children {
+            if self.emitted >= MAX_NODES {
+                self.truncated = true;
+                break;
+            }
+            let child_node = self.node(child, depth + 1, Some(pid), path);
+            node.children.push(child_node);
+        }
+        path.pop();
+        node
+    }
+}
+
+/// Builds the blocker forest. Roots are sessions that block others without
+/// waiting themselves; a cycle with no such root is entered at its lowest pid.
+pub fn build_tree(sessions: &[SessionRow], pairs: &[LockPairRow]) -> LockTree {
+    let mut waits_for: BTreeMap<i64, Vec<i64>> = BTreeMap::new();
+    let mut blocks: BTreeMap<i64, Vec<i64>> = BTreeMap::new();
+    for session in sessions.iter().filter(|s| !s.blocked_by.is_empty()) {
+        let mut blockers = session.blocked_by.clone();
+        blockers.sort_unstable();
+        blockers.dedup();
+        for &blocker in &blockers {
+            blocks.entry(blocker).or_default().push(session.pid);
+            waits_for.entry(blocker).or_default();
+        }
+        waits_for.insert(session.pid, blockers);
+    }
+    for children in blocks.values_mut() {
+        children.sort_unstable();
+        children.dedup();
+    }
+
+    let cycles = find_cycles(&waits_for);
+    let mut builder = TreeBuilder {
+        sessions: sessions.iter().map(|s| (s.pid, s)).collect(),
+        blocks,
+        pairs: HashMap::new(),
+        waiting: HashMap::new(),
+        in_cycle: cycles.iter().flatten().copied().collect(),
+        emitted: 0,
+        max_depth: 0,
+        truncated: false,
+    };
+    for pair in pairs {
+        builder
+            .pairs
+            .entry((pair.blocked_pid, pair.blocking_pid))
+            .or_insert(pair);
+        builder.waiting.entry(pair.blocked_pid).or_insert(pair);
+    }
+
+    let mut root_pids: Vec<i64> = builder
+        .blocks
+        .keys()
+        .copied()
+        .filter(|pid| waits_for.get(pid).is_none_or(Vec::is_empty))
+        .collect();
+    let mut reached: BTreeSet<i64> = BTreeSet::new();
+    for &root in &root_pids {
+        reached.insert(root);
+        let mut queue = VecDeque::from([root]);
+        while let Some(current) = queue.pop_front() {
+            for &child in builder.blocks.get(&current).into_iter().flatten() {
+                if reached.insert(child) {
+                    queue.push_back(child);
+                }
+            }
+        }
+    }
+    for cycle in &cycles {
+        if !cycle.iter().any(|pid| reached.contains(pid)) {
+            root_pids.push(cycle[0]);
+        }
+    }
+
+    let mut roots: Vec<LockTreeNode> = root_pids
+        .into_iter()
+        .map(|pid| builder.node(pid, 0, None, &mut Vec::new()))
+        .collect();
+    roots.sort_by(|a, b| {
+        b.blocking_count.cmp(&a.blocking_count).then_with(|| {
+            b.xact_seconds
+                .unwrap_or(0.0)
+                .total_cmp(&a.xact_seconds.unwrap_or(0.0))
+        })
+    });
+
+    LockTree {
+        roots,
+        cycles,
+        blocked_sessions: sessions.iter().filter(|s| !s.blocked_by.is_empty()).count(),
+        max_depth: builder.max_depth,
+        truncated: builder.truncated,
+        generated_at: now_sec(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerVersion {
+    version_num: i64,
+}
+
+pub async fn collect_lock_tree(conn: &mut PgConnection) -> Result<LockTree, String> {
+    let version: Option<ServerVersion> = fetch_one_as(
+        conn,
+        "SELECT current_setting('server_version_num')::bigint AS version_num",
+        &[],
+    )
+    .await?;
+    let has_waitstart = version.is_some_and(|v| v.version_num >= 140_000);
+    let sessions: Vec<SessionRow> = fetch_as(conn, SESSIONS_SQL, &[]).await?;
+    let pairs: Vec<LockPairRow> = fetch_as(conn, &lock_pairs_sql(has_waitstart), &[]).await?;
+    Ok(build_tree(&sessions, &pairs))
+}
+
+#[tauri::command]
+pub async fn lock_tree(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    conn_id: String,
+) -> Result<LockTree, String> {
+    let pool = pools.pool_for(&store, &conn_id).await?;
+    let mut tx = begin_read_only(&pool, LOCK_TREE_STATEMENT_TIMEOUT_MS).await?;
+    collect_lock_tree(&mut tx).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(pid: i64, blocked_by: &[i64]) -> SessionRow {
+        SessionRow {
+            pid,
+            usename: Some("app".into()),
+            application_name: None,
+            client_addr: None,
+            state: Some("active".into()),
+            wait_event_type: (!blocked_by.is_empty()).then(|| "Lock".to_string()),
+            wait_event: None,
+            backend_type: Some("client backend".into()),
+            query: None,
+            xact_seconds: Some(pid as f64),
+            query_seconds: Some(1.5),
+            blocked_by: blocked_by.to_vec(),
+        }
+    }
+
+    #[test]
+    fn nests_waiters_under_root_blockers() {
+        let sessions = vec![
+            session(10, &[]),
+            session(11, &[10]),
+            session(12, &[11]),
+            session(13, &[10]),
+            session(99, &[]),
+        ];
+        let pairs = vec![LockPairRow {
+            blocked_pid: 11,
+            blocking_pid: 10,
+            locktype: "relation".into(),
+            requested_mode: "AccessExclusiveLock".into(),
+            held_mode: "RowExclusiveLock".into(),
+            held: true,
+            relation: Some("public.orders".into()),
+            wait_seconds: Some(4.0),
+        }];
+        let tree = build_tree(&sessions, &pairs);
+        assert_eq!(tree.roots.len(), 1);
+        let root = &tree.roots[0];
+        assert_eq!((root.pid, root.blocking_count), (10, 3));
+        let child = &root.children[0];
+        assert_eq!(child.pid, 11);
+        assert_eq!(child.wait_seconds, Some(4.0));
+        let edge = child.edge.as_ref().unwrap();
+        assert_eq!(edge.relation.as_deref(), Some("public.orders"));
+        assert_eq!(edge.held_mode.as_deref(), Some("RowExclusiveLock"));
+        assert_eq!(child.children[0].depth, 2);
+        assert_eq!(root.children[1].wait_seconds, Some(1.5));
+        assert_eq!(tree.max_depth, 2);
+        assert!(tree.cycles.is_empty());
+    }
+
+    #[test]
+    fn flags_cycles_without_root_blockers() {
+        let sessions = vec![
+            session(5, &[6]),
+            session(6, &[7]),
+            session(7, &[5]),
+            session(8, &[7]),
+        ];
+        let tree = build_tree(&sessions, &[]);
+        assert_eq!(tree.cycles, vec![vec![5, 6, 7]]);
+        assert_eq!(tree.roots.len(), 1);
+        let root = &tree.roots[0];
+        assert_eq!(root.pid, 5);
+        assert!(root.in_cycle);
+        // 5 blocks 7, which blocks 6 and 8; 6 blocks 5 again.
+        let seven = &root.children[0];
+        assert_eq!(seven.pid, 7);
+        let six = seven.children.iter().find(|c| c.pid == 6).unwrap();
+        assert!(six.children[0].cycle_ref);
+        assert_eq!(six.children[0].pid, 5);
+        assert!(seven.children.iter().any(|c| c.pid == 8 && !c.in_cycle));
+    }
+}
@@ -1,12 +1,46 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console on Windows in release
 
+mod assistant_tools;
+mod autovacuum;
+mod bloat;
+mod context_retrieval;
+mod cron;
+mod crypto;
+mod executor;
+mod index_advice;
+mod join_paths;
+mod jsonb_shapes;
+mod local_backup;
+mod local_db;
+mod local_store;
+mod lock_tree;
 mod migrations;
+mod monitor;
+mod ops;
+mod ops_audit;
+mod ops_catalog;
+mod pg;
+mod privileges;
+mod profiling;
+mod query_history;
+mod replication;
+mod saved_sql;
+mod scheduled_sql;
+mod schema_cache;
+mod sql_guard;
+mod sql_template;
+mod stat_statements;
+#[cfg(test)]
+mod test_support;
+mod workspace_bundle;
 
 use regex::Regex;
 use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use sql_guard::is_read_only_sql;
 use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
 
 const SYSTEM_PROMPT: &str = r#"You are the Rei DbView desktop assistant, a PostgreSQL read-only database copilot. Your goal is to help users understand data, design safe SQL, and diagnose issues using the context supplied by the host application.
 
@@ -48,11 +82,17 @@ Context interpretation:
 
 Tooling note:
 - The host may show a simulated read-only preview for SQL code blocks. Do not claim queries were executed; describe expected outcomes instead.
+- When the find_join_paths tool is available, call it before joining tables you have not seen joined in the context, and use the returned ON predicates verbatim.
 
 When a decline is required, acknowledge the request, state the policy reason, and propose a safe diagnostic or alternative query.
 "#;
 
 const MAX_CONTEXT_CHUNKS: usize = 6;
+const MAX_TOOL_ROUNDS: usize = 3;
+const TOOL_BUDGET_EXHAUSTED: &str =
+    "⚠️ 已达到工具调用次数上限，模型未能给出最终回答。请缩小问题范围后重试。";
+const MAX_PROFILE_COLUMNS: usize = 24;
+const MAX_JSON_SHAPE_LINES: usize = 40;
 
 fn sanitize_markdown_text(input: &str) -> String {
     input.replace('&', "&amp;").replace('<', "&lt;")
@@ -83,6 +123,8 @@ struct AssistantProviderSettings {
     max_tokens: Option<u32>,
     #[serde(default, rename = "baseUrl")]
     base_url: Option<String>,
+    #[serde(default, rename = "embeddingModel")]
+    embedding_model: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -95,6 +137,14 @@ struct AssistantChatRequest {
     provider: AssistantProviderSettings,
     #[serde(default, rename = "apiKey")]
     api_key: Option<String>,
+    #[serde(default, rename = "connId")]
+    conn_id: Option<String>,
+    #[serde(default = "default_auto_context", rename = "autoContext")]
+    auto_context: bool,
+}
+
+fn default_auto_context() -> bool {
+    true
 }
 
 #[derive(Debug, Deserialize)]
@@ -117,11 +167,6 @@ struct SafetyEvaluation {
     triggers: Vec<SafetyTrigger>,
 }
 
-#[derive(Debug, Serialize)]
-struct SimulatedToolInput {
-    sql: String,
-}
-
 #[derive(Debug, Serialize)]
 struct SimulatedToolResult {
     columns: Vec<String>,
@@ -135,7 +180,7 @@ struct SimulatedToolCall {
     id: String,
     name: String,
     kind: String,
-    input: SimulatedToolInput,
+    input: Value,
     status: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     result: Option<SimulatedToolResult>,
@@ -168,6 +213,21 @@ struct AssistantChatResponse {
 struct OpenAiMessage {
     role: String,
     content: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OpenAiToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+impl OpenAiMessage {
+    fn text(role: &str, content: String) -> Self {
+        Self {
+            role: role.to_string(),
+            content,
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -177,11 +237,35 @@ struct OpenAiChatRequest {
     temperature: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct OpenAiFunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct OpenAiToolCall {
+    id: String,
+    #[serde(default = "default_tool_call_type", rename = "type")]
+    kind: String,
+    function: OpenAiFunctionCall,
+}
+
+fn default_tool_call_type() -> String {
+    "function".to_string()
 }
 
 #[derive(Debug, Deserialize)]
 struct OpenAiChoiceMessage {
+    #[serde(default)]
     content: Value,
+    #[serde(default)]
+    tool_calls: Vec<OpenAiToolCall>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -288,30 +372,52 @@ fn missing_api_key_response(provider: &str) -> AssistantChatResponse {
     }
 }
 
+/// A failed chat completion call; `status` is `None` when no HTTP response
+/// came back.
+struct ChatCallError {
+    status: Option<StatusCode>,
+    detail: String,
+}
+
+impl ChatCallError {
+    fn transport(detail: impl ToString) -> Self {
+        Self {
+            status: None,
+            detail: detail.to_string(),
+        }
+    }
+}
+
 async fn post_openai_chat(
     base_url: &str,
     bearer: Option<&str>,
     request_body: &OpenAiChatRequest,
-) -> Result<OpenAiChatResponse, String> {
+) -> Result<OpenAiChatResponse, ChatCallError> {
     let endpoint = format!("{}/chat/completions", base_url.trim_end_matches('/'));
     let client = Client::new();
     let mut request = client.post(endpoint).json(request_body);
     if let Some(token) = bearer {
         request = request.bearer_auth(token);
     }
-    let response = request.send().await.map_err(|err| err.to_string())?;
+    let response = request.send().await.map_err(ChatCallError::transport)?;
     let status = response.status();
-    let body: Value = response.json().await.map_err(|err| err.to_string())?;
+    let body: Value = response.json().await.map_err(|err| ChatCallError {
+        status: Some(status),
+        detail: err.to_string(),
+    })?;
     if !status.is_success() {
         let message = body
             .get("error")
-            .and_then(|err| err.get("message"))
+            .and_then(|err| err.get("message").or(Some(err)))
             .and_then(Value::as_str)
             .unwrap_or("模型返回未知错误")
             .to_string();
-        return Err(message);
+        return Err(ChatCallError {
+            status: Some(status),
+            detail: message,
+        });
     }
-    serde_json::from_value(body).map_err(|err| err.to_string())
+    serde_json::from_value(body).map_err(ChatCallError::transport)
 }
 
 async fn fetch_openai_models(base_url: &str, bearer: Option<&str>) -> Result<Vec<String>, String> {
@@ -431,6 +537,19 @@ fn format_schema_table_chunk(chunk: &AssistantContextChunkPayload) -> Option<Str
         table,
         column_defs.join(",\n")
     );
+    let shape_lines: Vec<String> = chunk
+        .content
+        .get("jsonShapes")
+        .and_then(Value::as_object)
+        .map(|shapes| {
+            shapes
+                .iter()
+                .flat_map(|(column, shape)| jsonb_shapes::shape_outline(column, shape))
+                .take(MAX_JSON_SHAPE_LINES)
+                .map(|line| format!("  - {}", sanitize_markdown_text(&line)))
+                .collect()
+        })
+        .unwrap_or_default();
 
     let mut lines: Vec<String> = Vec::new();
     let schema_label = sanitize_markdown_text(schema);
@@ -444,11 +563,87 @@ fn format_schema_table_chunk(chunk: &AssistantContextChunkPayload) -> Option<Str
     lines.push("```sql".to_string());
     lines.push(ddl);
     lines.push("```".to_string());
+    if !shape_lines.is_empty() {
+        lines.push("JSON shapes (inferred from sampled rows):".to_string());
+        lines.extend(shape_lines);
+    }
 
     Some(lines.join("\n"))
 }
 
-fn format_context_chunks(chunks: &[AssistantContextChunkPayload]) -> Option<String> {
+fn format_table_profile_chunk(chunk: &AssistantContextChunkPayload) -> Option<String> {
+    let schema = value_as_str(&chunk.content, "schema")?;
+    let table = value_as_str(&chunk.content, "table")?;
+    let columns = chunk.content.get("columns")?.as_array()?;
+    let reltuples = chunk
+        .content
+        .get("reltuples")
+        .and_then(Value::as_f64)
+        .unwrap_or(0.0);
+
+    let mut lines: Vec<String> = Vec::new();
+    lines.push(format!(
+        "Profile of \"{}\".\"{}\" — ~{} rows",
+        sanitize_markdown_text(schema),
+        sanitize_markdown_text(table),
+        reltuples.round() as i64
+    ));
+    for column in columns.iter().take(MAX_PROFILE_COLUMNS) {
+        let name = value_as_str(column, "name")?;
+        let data_type = value_as_str(column, "data_type").unwrap_or("unknown");
+        let mut facts: Vec<String> = Vec::new();
+        if let Some(null_frac) = column.get("null_frac").and_then(Value::as_f64) {
+            facts.push(format!("nulls {:.1}%", null_frac * 100.0));
+        }
+        if let Some(distinct) = column.get("distinct_estimate").and_then(Value::as_f64) {
+            facts.push(format!("~{} distinct", distinct.round() as i64));
+        }
+        let common: Vec<String> = column
+            .get("most_common")
+            .and_then(Value::as_array)
+            .map(|values| {
+                values
+                    .iter()
+                    .take(3)
+                    .filter_map(|entry| {
+                        let value = entry.get("value").and_then(Value::as_str)?;
+                        let freq = entry.get("frequency").and_then(Value::as_f64)?;
+                        Some(format!("{} ({:.0}%)", value, freq * 100.0))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        if !common.is_empty() {
+            facts.push(format!("common: {}", common.join(", ")));
+        }
+        if let Some(sample) = column.get("sample") {
+            if let (Some(min), Some(max)) =
+                (value_as_str(sample, "min"), value_as_str(sample, "max"))
+            {
+                facts.push(format!("range {} .. {}", min, max));
+            }
+        }
+        lines.push(sanitize_markdown_text(&format!(
+            "  - {} {}: {}",
+            name,
+            data_type,
+            if facts.is_empty() {
+                "no statistics".to_string()
+            } else {
+                facts.join("; ")
+            }
+        )));
+    }
+    if columns.len() > MAX_PROFILE_COLUMNS {
+        lines.push(format!(
+            "  (+{} more columns)",
+            columns.len() - MAX_PROFILE_COLUMNS
+        ));
+    }
+    Some(lines.join("\n"))
+}
+
+fn format_context_chunks(heading: &str, chunks: &[AssistantContextChunkPayload]) -> Option<String> {
     if chunks.is_empty() {
         return None;
     }
@@ -460,6 +655,12 @@ fn format_context_chunks(chunks: &[AssistantContextChunkPayload]) -> Option<Stri
                 continue;
             }
         }
+        if chunk.kind == "table-profile" {
+            if let Some(formatted) = format_table_profile_chunk(chunk) {
+                blocks.push(format!("{}. {}", idx + 1, formatted));
+                continue;
+            }
+        }
 
         let mut line = format!(
             "{}. {} — {}",
@@ -487,15 +688,12 @@ fn format_context_chunks(chunks: &[AssistantContextChunkPayload]) -> Option<Stri
             chunks.len() - MAX_CONTEXT_CHUNKS
         ));
     }
-    Some(format!("Context summary:\n{}", blocks.join("\n")))
+    Some(format!("{}:\n{}", heading, blocks.join("\n")))
 }
 
 fn build_openai_messages(payload: &AssistantChatRequest) -> Vec<OpenAiMessage> {
     let mut messages = Vec::new();
-    messages.push(OpenAiMessage {
-        role: "system".to_string(),
-        content: SYSTEM_PROMPT.to_string(),
-    });
+    messages.push(OpenAiMessage::text("system", SYSTEM_PROMPT.to_string()));
     if let Some(summary) = payload.context_summary.as_ref().and_then(|text| {
         if text.trim().is_empty() {
             None
@@ -503,15 +701,10 @@ fn build_openai_messages(payload: &AssistantChatRequest) -> Vec<OpenAiMessage> {
             Some(text.clone())
         }
     }) {
-        messages.push(OpenAiMessage {
-            role: "system".to_string(),
-            content: summary,
-        });
-    } else if let Some(context) = format_context_chunks(&payload.context_chunks) {
-        messages.push(OpenAiMessage {
-            role: "system".to_string(),
-            content: context,
-        });
+        messages.push(OpenAiMessage::text("system", summary));
+    } else if let Some(context) = format_context_chunks("Context summary", &payload.context_chunks)
+    {
+        messages.push(OpenAiMessage::text("system", context));
     }
     for entry in &payload.messages {
         let role = match entry.role.as_str() {
@@ -522,16 +715,14 @@ fn build_openai_messages(payload: &AssistantChatRequest) -> Vec<OpenAiMessage> {
         if entry.text.trim().is_empty() {
             continue;
         }
-        messages.push(OpenAiMessage {
-            role: role.to_string(),
-            content: entry.text.clone(),
-        });
+        messages.push(OpenAiMessage::text(role, entry.text.clone()));
     }
     messages
 }
 
 fn extract_message_text(choice: &OpenAiChoice) -> String {
     match &choice.message.content {
+        Value::Null => String::new(),
         Value::String(text) => text.clone(),
         Value::Array(parts) => {
             let mut combined = String::new();
@@ -594,52 +785,6 @@ fn evaluate_response_safety(text: &str) -> SafetyEvaluation {
     }
 }
 
-#[allow(dead_code)]
-fn strip_sql_comments(sql: &str) -> String {
-    let without_block = Regex::new(r"(?s)/\*.*?\*/")
-        .unwrap()
-        .replace_all(sql, "")
-        .to_string();
-    Regex::new(r"--.*")
-        .unwrap()
-        .replace_all(&without_block, "")
-        .to_string()
-}
-
-#[allow(dead_code)]
-fn is_read_only_sql(sql: &str) -> bool {
-    let stripped = strip_sql_comments(sql);
-    let normalized = stripped.trim();
-    if normalized.is_empty() {
-        return false;
-    }
-    let lowered = normalized.to_lowercase();
-    let disallowed = [
-        " insert ",
-        " update ",
-        " delete ",
-        " drop ",
-        " truncate ",
-        " alter ",
-        " create ",
-        " grant ",
-        " revoke ",
-        " comment ",
-        " merge ",
-        " call ",
-        " do ",
-        " begin ",
-        " commit ",
-        " rollback ",
-    ];
-    for keyword in disallowed.iter() {
-        if lowered.contains(keyword) {
-            return false;
-        }
-    }
-    lowered.starts_with("select ") || lowered.starts_with("with ")
-}
-
 #[allow(dead_code)]
 fn generate_tool_id() -> String {
     let now = SystemTime::now()
@@ -671,9 +816,7 @@ fn simulate_tool_calls(text: &str) -> Vec<SimulatedToolCall> {
                     id: generate_tool_id(),
                     name: "readonly-sql-preview".to_string(),
                     kind: "sql_preview".to_string(),
-                    input: SimulatedToolInput {
-                        sql: sql.to_string(),
-                    },
+                    input: json!({ "sql": sql }),
                     status: "success".to_string(),
                     result: Some(SimulatedToolResult {
                         columns: vec!["example".to_string(), "detail".to_string()],
@@ -687,9 +830,7 @@ fn simulate_tool_calls(text: &str) -> Vec<SimulatedToolCall> {
                     id: generate_tool_id(),
                     name: "readonly-sql-preview".to_string(),
                     kind: "sql_preview".to_string(),
-                    input: SimulatedToolInput {
-                        sql: sql.to_string(),
-                    },
+                    input: json!({ "sql": sql }),
                     status: "error".to_string(),
                     result: None,
                     message: Some("Only read-only SELECT/WITH statements are allowed.".to_string()),
@@ -741,17 +882,209 @@ async fn assistant_list_models(payload: AssistantListModelsRequest) -> Result<Ve
         .map_err(|err| format!("获取模型列表失败：{}", err))
 }
 
+fn friendly_provider_error(provider_name: &str, base_url: &str, detail: String) -> String {
+    let (label, start_hint) = match provider_name {
+        "lmstudio" => ("LM Studio", "lms server start"),
+        "ollama" => ("Ollama", "ollama serve"),
+        _ => return detail,
+    };
+    let lowered = detail.to_lowercase();
+    if lowered.contains("connection refused")
+        || lowered.contains("could not connect")
+        || lowered.contains("connection reset")
+        || lowered.contains("timed out")
+    {
+        format!(
+            "无法连接到 {} 服务。请确认已运行 `{}` 并监听 {}。原始错误：{}",
+            label, start_hint, base_url, detail
+        )
+    } else {
+        format!("{} 返回错误：{}", label, detail)
+    }
+}
+
+fn accumulate_usage(total: &mut Option<ResponseUsage>, usage: Option<OpenAiUsage>) {
+    let Some(usage) = usage else {
+        return;
+    };
+    let add = |acc: Option<u32>, value: Option<u32>| match (acc, value) {
+        (Some(a), Some(b)) => Some(a + b),
+        (a, b) => a.or(b),
+    };
+    let current = total.get_or_insert(ResponseUsage {
+        prompt_tokens: None,
+        completion_tokens: None,
+        total_tokens: None,
+    });
+    current.prompt_tokens = add(current.prompt_tokens, usage.prompt_tokens);
+    current.completion_tokens = add(current.completion_tokens, usage.completion_tokens);
+    current.total_tokens = add(current.total_tokens, usage.total_tokens);
+}
+
+async fn execute_tool_call(
+    app: &AppHandle,
+    conn_id: &str,
+    call: &OpenAiToolCall,
+) -> (OpenAiMessage, SimulatedToolCall) {
+    let input = serde_json::from_str::<Value>(&call.function.arguments).unwrap_or(Value::Null);
+    let outcome =
+        assistant_tools::run_tool(app, conn_id, &call.function.name, &call.function.arguments)
+            .await;
+    let (content, record) = match outcome {
+        Ok(value) => {
+            let rows = match value.get("paths").and_then(Value::as_array) {
+                Some(paths) => paths.clone(),
+                None => vec![value.clone()],
+            };
+            let columns = rows
+                .first()
+                .and_then(Value::as_object)
+                .map(|row| row.keys().cloned().collect())
+                .unwrap_or_default();
+            (
+                value.to_string(),
+                SimulatedToolCall {
+                    id: call.id.clone(),
+                    name: call.function.name.clone(),
+                    kind: call.function.name.clone(),
+                    input,
+                    status: "success".to_string(),
+                    result: Some(SimulatedToolResult {
+                        columns,
+                        rows,
+                        summary: None,
+                    }),
+                    message: None,
+                },
+            )
+        }
+        Err(err) => (
+            json!({ "error": err }).to_string(),
+            SimulatedToolCall {
+                id: call.id.clone(),
+                name: call.function.name.clone(),
+                kind: call.function.name.clone(),
+                input,
+                status: "error".to_string(),
+                result: None,
+                message: Some(err),
+            },
+        ),
+    };
+    let message = OpenAiMessage {
+        role: "tool".to_string(),
+        content,
+        tool_calls: Vec::new(),
+        tool_call_id: Some(call.id.clone()),
+    };
+    (message, record)
+}
+
+/// Adds cached jsonb shapes to schema chunks the webview attached, so the
+/// model sees inferred keys instead of a bare `jsonb`.
+async fn attach_jsonb_shapes(
+    app: &AppHandle,
+    conn_id: &str,
+    chunks: &mut [AssistantContextChunkPayload],
+) {
+    if !chunks.iter().any(|chunk| chunk.kind == "schema-table") {
+        return;
+    }
+    let store = app.state::<local_store::LocalStore>();
+    let Ok(shapes) = jsonb_shapes::load_shapes(&store, conn_id).await else {
+        return;
+    };
+    for chunk in chunks
+        .iter_mut()
+        .filter(|chunk| chunk.kind == "schema-table")
+    {
+        if chunk.content.get("jsonShapes").is_some() {
+            continue;
+        }
+        let (Some(schema), Some(table)) = (
+            value_as_str(&chunk.content, "schema"),
+            value_as_str(&chunk.content, "table"),
+        ) else {
+            continue;
+        };
+        if let Some(found) = jsonb_shapes::shapes_for_table(&shapes, schema, table) {
+            if let Some(content) = chunk.content.as_object_mut() {
+                content.insert("jsonShapes".to_string(), found);
+            }
+        }
+    }
+}
+
+/// Picks the schema tables and saved SQL most relevant to the latest user
+/// message, skipping chunks the user already attached by hand.
+async fn retrieved_context_message(
+    app: &AppHandle,
+    payload: &AssistantChatRequest,
+    conn_id: Option<&str>,
+    embeddings: Option<&context_retrieval::EmbeddingConfig>,
+) -> Option<String> {
+    let query = payload
+        .messages
+        .iter()
+        .rev()
+        .find(|message| message.role == "user" && !message.text.trim().is_empty())?;
+    let store = app.state::<local_store::LocalStore>();
+    let retrieved = context_retrieval::retrieve(
+        &store,
+        conn_id,
+        &query.text,
+        MAX_CONTEXT_CHUNKS + payload.context_chunks.len(),
+        embeddings,
+    )
+    .await
+    .ok()?;
+    let chunks: Vec<AssistantContextChunkPayload> = retrieved
+        .into_iter()
+        .filter(|scored| {
+            !payload
+                .context_chunks
+                .iter()
+                .any(|chunk| chunk.id == scored.chunk.id)
+        })
+        .take(MAX_CONTEXT_CHUNKS)
+        .map(|scored| AssistantContextChunkPayload {
+            id: scored.chunk.id,
+            title: scored.chunk.title,
+            kind: scored.chunk.kind,
+            summary: scored.chunk.summary,
+            content: scored.chunk.content,
+        })
+        .collect();
+    format_context_chunks("Retrieved context", &chunks)
+}
+
 #[tauri::command]
-async fn assistant_chat(payload: AssistantChatRequest) -> Result<AssistantChatResponse, String> {
+async fn assistant_chat(
+    app: AppHandle,
+    mut payload: AssistantChatRequest,
+) -> Result<AssistantChatResponse, String> {
     ensure_supported_provider(&payload.provider.provider)?;
     let provider_name = payload.provider.provider.to_lowercase();
     let base_url = resolve_base_url(&payload.provider);
+    let conn_id = payload
+        .conn_id
+        .as_ref()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    if let Some(conn_id) = conn_id.as_deref() {
+        attach_jsonb_shapes(&app, conn_id, &mut payload.context_chunks).await;
+    }
     let messages = build_openai_messages(&payload);
-    let request_body = OpenAiChatRequest {
+    let mut request_body = OpenAiChatRequest {
         model: payload.provider.model.clone(),
         messages,
         temperature: payload.provider.temperature,
         max_tokens: payload.provider.max_tokens,
+        tools: if conn_id.is_some() {
+            assistant_tools::tool_definitions()
+        } else {
+            Vec::new()
+        },
     };
     let api_key = payload
         .api_key
@@ -759,89 +1092,114 @@ async fn assistant_chat(payload: AssistantChatRequest) -> Result<AssistantChatRe
         .map(|value| value.trim().to_string())
         .filter(|value| !value.is_empty());
 
-    let chat_response = match provider_name.as_str() {
-        "openai" | "custom" => {
-            let token = match api_key {
-                Some(ref value) => value.clone(),
-                None => return Ok(missing_api_key_response(&payload.provider.provider)),
-            };
-            match post_openai_chat(&base_url, Some(token.as_str()), &request_body).await {
-                Ok(response) => response,
-                Err(detail) => return Ok(model_error_response(detail)),
-            }
+    let bearer: Option<String> = match provider_name.as_str() {
+        "openai" | "custom" => match api_key {
+            Some(ref value) => Some(value.clone()),
+            None => return Ok(missing_api_key_response(&payload.provider.provider)),
+        },
+        "lmstudio" => Some(api_key.clone().unwrap_or_else(|| "lm-studio".to_string())),
+        "ollama" => api_key.clone(),
+        _ => return Err("unsupported_provider".to_string()),
+    };
+
+    if payload.auto_context {
+        let embeddings = payload
+            .provider
+            .embedding_model
+            .as_ref()
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .map(|model| context_retrieval::EmbeddingConfig {
+                base_url: base_url.clone(),
+                bearer: bearer.clone(),
+                model: model.to_string(),
+            });
+        if let Some(context) =
+            retrieved_context_message(&app, &payload, conn_id.as_deref(), embeddings.as_ref()).await
+        {
+            let position = request_body
+                .messages
+                .iter()
+                .take_while(|message| message.role == "system")
+                .count();
+            request_body
+                .messages
+                .insert(position, OpenAiMessage::text("system", context));
         }
-        "lmstudio" => {
-            let token = api_key.clone().unwrap_or_else(|| "lm-studio".to_string());
-            match post_openai_chat(&base_url, Some(token.as_str()), &request_body).await {
+    }
+
+    let mut tool_calls = Vec::new();
+    let mut usage: Option<ResponseUsage> = None;
+    let mut round = 0;
+    let assistant_text = loop {
+        let chat_response =
+            match post_openai_chat(&base_url, bearer.as_deref(), &request_body).await {
                 Ok(response) => response,
-                Err(detail) => {
-                    let lowered = detail.to_lowercase();
-                    let friendly = if lowered.contains("connection refused")
-                        || lowered.contains("could not connect")
-                        || lowered.contains("connection reset")
-                        || lowered.contains("timed out")
-                    {
-                        format!(
-                            "无法连接到 LM Studio 服务。请确认已运行 `lms server start` 并监听 {}。原始错误：{}",
-                            base_url,
-                            detail
-                        )
-                    } else {
-                        format!("LM Studio 返回错误：{}", detail)
-                    };
-                    return Ok(model_error_response(friendly));
+                // Local models without function calling reject `tools` with a
+                // 400; ask again without them before any tool has run.
+                Err(err)
+                    if err.status == Some(StatusCode::BAD_REQUEST)
+                        && round == 0
+                        && !request_body.tools.is_empty() =>
+                {
+                    request_body.tools.clear();
+                    continue;
                 }
-            }
-        }
-        "ollama" => {
-            let bearer = api_key.as_ref().map(|value| value.as_str());
-            match post_openai_chat(&base_url, bearer, &request_body).await {
-                Ok(response) => response,
-                Err(detail) => {
-                    let lowered = detail.to_lowercase();
-                    let friendly = if lowered.contains("connection refused")
-                        || lowered.contains("could not connect")
-                        || lowered.contains("connection reset")
-                        || lowered.contains("timed out")
-                    {
-                        format!(
-                            "无法连接到 Ollama 服务。请确认已运行 `ollama serve` 并监听 {}。原始错误：{}",
-                            base_url,
-                            detail
-                        )
-                    } else {
-                        format!("Ollama 返回错误：{}", detail)
-                    };
-                    return Ok(model_error_response(friendly));
+                Err(err) => {
+                    return Ok(model_error_response(friendly_provider_error(
+                        &provider_name,
+                        &base_url,
+                        err.detail,
+                    )))
                 }
+            };
+        accumulate_usage(&mut usage, chat_response.usage);
+        let choice = chat_response
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| "model_returned_no_choices".to_string())?;
+        let requested = choice.message.tool_calls.clone();
+        let (Some(conn), false, false) = (
+            conn_id.as_deref(),
+            requested.is_empty(),
+            request_body.tools.is_empty(),
+        ) else {
+            let text = extract_message_text(&choice);
+            if text.trim().is_empty() && round >= MAX_TOOL_ROUNDS {
+                break TOOL_BUDGET_EXHAUSTED.to_string();
             }
+            break text;
+        };
+        if round >= MAX_TOOL_ROUNDS {
+            // Out of tool rounds: drop the pending calls and ask for a plain
+            // answer from what the earlier tool results already provided.
+            request_body.tools.clear();
+            continue;
+        }
+        round += 1;
+        request_body.messages.push(OpenAiMessage {
+            role: "assistant".to_string(),
+            content: extract_message_text(&choice),
+            tool_calls: requested.clone(),
+            tool_call_id: None,
+        });
+        for call in &requested {
+            let (message, record) = execute_tool_call(&app, conn, call).await;
+            request_body.messages.push(message);
+            tool_calls.push(record);
         }
-        _ => return Err("unsupported_provider".to_string()),
     };
 
-    let choice = chat_response
-        .choices
-        .get(0)
-        .ok_or_else(|| "model_returned_no_choices".to_string())?;
-    let assistant_text = extract_message_text(choice);
-
     let safety = evaluate_response_safety(&assistant_text);
     let mut final_message = assistant_text.clone();
 
-    let tool_calls = Vec::new();
-
     if safety.severity == "block" {
         final_message = format_blocked_message(&safety);
     } else if safety.severity == "warn" {
         final_message.push_str("\n\n> ⚠️ 检测到可能的敏感信息，请谨慎处理。");
     }
 
-    let usage = chat_response.usage.map(|usage| ResponseUsage {
-        prompt_tokens: usage.prompt_tokens,
-        completion_tokens: usage.completion_tokens,
-        total_tokens: usage.total_tokens,
-    });
-
     Ok(AssistantChatResponse {
         message: final_message,
         tool_calls,
@@ -852,14 +1210,89 @@ async fn assistant_chat(payload: AssistantChatRequest) -> Result<AssistantChatRe
 
 fn main() {
     tauri::Builder::default()
-        .plugin(
-            tauri_plugin_sql::Builder::default()
-                .add_migrations("sqlite:rdv_local.db", migrations::migrations())
-                .build(),
-        )
+        .setup(|app| {
+            let config_dir = app.path().app_config_dir()?;
+            std::fs::create_dir_all(&config_dir)?;
+            local_backup::apply_pending_restore(&config_dir)?;
+            let store = local_store::LocalStore::open(&config_dir)?;
+            if store.is_unlocked() {
+                tauri::async_runtime::block_on(store.migrate())?;
+            }
+            app.manage(store);
+            app.manage(pg::PgPools::default());
+            app.manage(ops::OpsConfirmations::default());
+            let catalog_dir = app.path().app_data_dir()?.join(ops_catalog::CATALOG_DIR);
+            std::fs::create_dir_all(&catalog_dir)?;
+            app.manage(ops_catalog::OpsCatalog::load(Some(catalog_dir)));
+            app.manage(monitor::ActivityMonitors::default());
+            app.manage(scheduled_sql::ScheduleRuns::default());
+            monitor::resume_saved(app.handle().clone());
+            scheduled_sql::start(app.handle().clone());
+            Ok(())
+        })
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_sql::Builder::default().build())
         .invoke_handler(tauri::generate_handler![
             assistant_chat,
-            assistant_list_models
+            assistant_list_models,
+            context_retrieval::retrieve_context,
+            join_paths::find_join_paths,
+            profiling::profile_table,
+            profiling::cached_table_profile,
+            jsonb_shapes::infer_jsonb_shapes,
+            jsonb_shapes::cached_jsonb_shapes,
+            index_advice::index_advice,
+            ops::prepare_ops_signal,
+            ops::run_ops_signal,
+            ops_audit::verify_ops_audit,
+            lock_tree::lock_tree,
+            monitor::start_activity_monitor,
+            monitor::stop_activity_monitor,
+            monitor::activity_monitor_status,
+            monitor::activity_samples,
+            stat_statements::snapshot_stat_statements,
+            stat_statements::list_stat_statements_snapshots,
+            stat_statements::stat_statements_delta,
+            stat_statements::explain_statement,
+            bloat::estimate_bloat,
+            bloat::bloat_history,
+            autovacuum::autovacuum_report,
+            replication::replication_status,
+            ops_catalog::list_ops_actions,
+            ops_catalog::run_ops_action,
+            privileges::role_privileges_report,
+            privileges::set_session_read_only,
+            privileges::session_read_only,
+            executor::execute_sql,
+            sql_template::compile_sql_template,
+            saved_sql::execute_saved_sql,
+            saved_sql::list_saved_sql_revisions,
+            saved_sql::diff_saved_sql_revisions,
+            saved_sql::restore_saved_sql_revision,
+            saved_sql::search_saved_sql,
+            scheduled_sql::save_sql_schedule,
+            scheduled_sql::list_sql_schedules,
+            scheduled_sql::delete_sql_schedule,
+            scheduled_sql::run_sql_schedule_now,
+            scheduled_sql::list_sql_snapshots,
+            scheduled_sql::get_sql_snapshot,
+            scheduled_sql::diff_sql_snapshots,
+            query_history::search_query_history,
+            query_history::purge_query_history,
+            query_history::record_query_history,
+            query_history::set_query_history_redaction,
+            workspace_bundle::export_workspace_bundle,
+            workspace_bundle::import_workspace_bundle,
+            local_backup::backup_local_store,
+            local_backup::restore_local_store,
+            local_backup::rollback_local_store,
+            local_backup::check_local_store,
+            local_db::local_db_select,
+            local_db::local_db_execute,
+            local_db::local_store_status,
+            local_db::unlock_local_store,
+            local_db::lock_local_store,
+            local_db::set_local_store_passphrase
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -68,5 +68,424 @@ pub fn migrations() -> Vec<Migration> {
         "#,
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 3,
+            description: "table_profiles_cache",
+            sql: r#"
+        CREATE TABLE IF NOT EXISTS table_profiles (
+          conn_id TEXT NOT NULL,
+          schema_name TEXT NOT NULL,
+          table_name TEXT NOT NULL,
+          content TEXT NOT NULL,         -- JSON string (TableProfile)
+          sampled INTEGER DEFAULT 0,
+          created_at INTEGER NOT NULL,
+          PRIMARY KEY (conn_id, schema_name, table_name)
+        );
+        "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 4,
+            description: "jsonb_shapes_cache",
+            sql: r#"
+        CREATE TABLE IF NOT EXISTS jsonb_shapes (
+          conn_id TEXT NOT NULL,
+          schema_name TEXT NOT NULL,
+          table_name TEXT NOT NULL,
+          column_name TEXT NOT NULL,
+          content TEXT NOT NULL,         -- JSON string (JsonbShape)
+          sampled_rows INTEGER DEFAULT 0,
+          created_at INTEGER NOT NULL,
+          PRIMARY KEY (conn_id, schema_name, table_name, column_name)
+        );
+        "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 5,
+            description: "ops_audit_hash_chain",
+            sql: r#"
+        ALTER TABLE ops_audit ADD COLUMN seq INTEGER NULL;
+        ALTER TABLE ops_audit ADD COLUMN prev_hash TEXT NULL;
+        ALTER TABLE ops_audit ADD COLUMN hash TEXT NULL;
+
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_ops_audit_seq ON ops_audit(seq);
+        "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 6,
+            description: "activity_monitor",
+            sql: r#"
+        CREATE TABLE IF NOT EXISTS activity_monitors (
+          conn_id TEXT PRIMARY KEY,
+          config TEXT NOT NULL,          -- JSON string (MonitorConfig)
+          enabled INTEGER DEFAULT 1,
+          updated_at INTEGER
+        );
+
+        CREATE TABLE IF NOT EXISTS activity_samples (
+          conn_id TEXT NOT NULL,
+          slot INTEGER NOT NULL,         -- seq % capacity (ring buffer)
+          seq INTEGER NOT NULL,
+          sampled_at INTEGER NOT NULL,
+          content TEXT NOT NULL,         -- JSON string (ActivitySample)
+          alerts INTEGER DEFAULT 0,
+          PRIMARY KEY (conn_id, slot)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_activity_samples_seq ON activity_samples(conn_id, seq);
+        "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 7,
+            description: "stat_statements_snapshots",
+            sql: r#"
+        CREATE TABLE IF NOT EXISTS stat_statements_snapshots (
+          id TEXT PRIMARY KEY,
+          conn_id TEXT NOT NULL,
+          taken_at INTEGER NOT NULL,
+          stats_reset TEXT NULL,         -- pg_stat_statements_info.stats_reset (1.9+)
+          entry_count INTEGER DEFAULT 0
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_stat_statements_snapshots_conn ON stat_statements_snapshots(conn_id, taken_at);
+
+        CREATE TABLE IF NOT EXISTS stat_statements_entries (
+          snapshot_id TEXT NOT NULL REFERENCES stat_statements_snapshots(id) ON DELETE CASCADE,
+          queryid TEXT NOT NULL,
+          userid INTEGER NOT NULL,
+          dbid INTEGER NOT NULL,
+          username TEXT NULL,
+          query TEXT NOT NULL,
+          calls INTEGER NOT NULL,
+          total_ms REAL NOT NULL,
+          rows INTEGER NOT NULL,
+          shared_blks_hit INTEGER NOT NULL,
+          shared_blks_read INTEGER NOT NULL,
+          PRIMARY KEY (snapshot_id, queryid, userid, dbid)
+        );
+        "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 8,
+            description: "bloat_history",
+            sql: r#"
+        CREATE TABLE IF NOT EXISTS bloat_history (
+          conn_id TEXT NOT NULL,
+          taken_at INTEGER NOT NULL,
+          kind TEXT NOT NULL,            -- table | index
+          schema_name TEXT NOT NULL,
+          object_name TEXT NOT NULL,
+          table_name TEXT NOT NULL,
+          real_bytes INTEGER NOT NULL,
+          bloat_bytes INTEGER NOT NULL,
+          bloat_ratio REAL NOT NULL,
+          is_na INTEGER DEFAULT 0,
+          PRIMARY KEY (conn_id, taken_at, kind, schema_name, object_name)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_bloat_history_object ON bloat_history(conn_id, kind, schema_name, object_name, taken_at);
+        "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 9,
+            description: "connection_strict_read_only",
+            sql: r#"
+        ALTER TABLE user_connections ADD COLUMN strict_read_only INTEGER NOT NULL DEFAULT 0;
+        "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 10,
+            description: "saved_sql_revisions",
+            sql: r#"
+        CREATE TABLE IF NOT EXISTS saved_sql_revisions (
+          id TEXT PRIMARY KEY,
+          saved_id TEXT NOT NULL,
+          revision INTEGER NOT NULL,     -- 1-based per saved_id
+          name TEXT NOT NULL,
+          description TEXT NULL,
+          sql TEXT NOT NULL,
+          variables TEXT NOT NULL,
+          dynamic_columns TEXT NULL,
+          calc_items TEXT NULL,
+          created_at INTEGER,            -- when this version was saved
+          UNIQUE (saved_id, revision)
+        );
+
+        -- Existing queries start their history at revision 1.
+        INSERT INTO saved_sql_revisions (id, saved_id, revision, name, description, sql, variables, dynamic_columns, calc_items, created_at)
+          SELECT 'rev_' || lower(hex(randomblob(8))), id, 1, name, description, sql, variables, dynamic_columns, calc_items, COALESCE(updated_at, created_at)
+          FROM saved_sql;
+
+        -- Triggers so writes from the webview (SQL plugin) are captured too.
+        -- Revisions outlive their query so a deleted query can be restored; a
+        -- query re-created under the same id continues its numbering.
+        CREATE TRIGGER IF NOT EXISTS trg_saved_sql_revision_insert AFTER INSERT ON saved_sql
+        BEGIN
+          INSERT INTO saved_sql_revisions (id, saved_id, revision, name, description, sql, variables, dynamic_columns, calc_items, created_at)
+          VALUES ('rev_' || lower(hex(randomblob(8))), NEW.id,
+                  COALESCE((SELECT MAX(revision) FROM saved_sql_revisions WHERE saved_id = NEW.id), 0) + 1,
+                  NEW.name, NEW.description, NEW.sql, NEW.variables, NEW.dynamic_columns, NEW.calc_items,
+                  COALESCE(NEW.updated_at, NEW.created_at, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)));
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_saved_sql_revision_update AFTER UPDATE ON saved_sql
+        WHEN OLD.name IS NOT NEW.name OR OLD.description IS NOT NEW.description OR OLD.sql IS NOT NEW.sql
+          OR OLD.variables IS NOT NEW.variables OR OLD.dynamic_columns IS NOT NEW.dynamic_columns OR OLD.calc_items IS NOT NEW.calc_items
+        BEGIN
+          INSERT INTO saved_sql_revisions (id, saved_id, revision, name, description, sql, variables, dynamic_columns, calc_items, created_at)
+          VALUES ('rev_' || lower(hex(randomblob(8))), NEW.id,
+                  COALESCE((SELECT MAX(revision) FROM saved_sql_revisions WHERE saved_id = NEW.id), 0) + 1,
+                  NEW.name, NEW.description, NEW.sql, NEW.variables, NEW.dynamic_columns, NEW.calc_items,
+                  COALESCE(NEW.updated_at, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)));
+        END;
+        "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 11,
+            description: "saved_sql_fts",
+            sql: r#"
+        -- Search index over saved_sql; `variables` holds the variable names only.
+        CREATE VIRTUAL TABLE IF NOT EXISTS saved_sql_fts USING fts5(
+          saved_id UNINDEXED,
+          name,
+          description,
+          sql,
+          variables,
+          tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        INSERT INTO saved_sql_fts (saved_id, name, description, sql, variables)
+          SELECT s.id, s.name, s.description, s.sql, (SELECT group_concat(json_extract(value, '$.name'), ' ') FROM json_each(CASE WHEN json_valid(s.variables) THEN s.variables ELSE '[]' END) WHERE type = 'object')
+          FROM saved_sql s;
+
+        CREATE TRIGGER IF NOT EXISTS trg_saved_sql_fts_insert AFTER INSERT ON saved_sql
+        BEGIN
+          INSERT INTO saved_sql_fts (saved_id, name, description, sql, variables)
+          VALUES (NEW.id, NEW.name, NEW.description, NEW.sql, (SELECT group_concat(json_extract(value, '$.name'), ' ') FROM json_each(CASE WHEN json_valid(NEW.variables) THEN NEW.variables ELSE '[]' END) WHERE type = 'object'));
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_saved_sql_fts_update AFTER UPDATE ON saved_sql
+        BEGIN
+          DELETE FROM saved_sql_fts WHERE saved_id = OLD.id;
+          INSERT INTO saved_sql_fts (saved_id, name, description, sql, variables)
+          VALUES (NEW.id, NEW.name, NEW.description, NEW.sql, (SELECT group_concat(json_extract(value, '$.name'), ' ') FROM json_each(CASE WHEN json_valid(NEW.variables) THEN NEW.variables ELSE '[]' END) WHERE type = 'object'));
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_saved_sql_fts_delete AFTER DELETE ON saved_sql
+        BEGIN
+          DELETE FROM saved_sql_fts WHERE saved_id = OLD.id;
+        END;
+        "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 12,
+            description: "query_history",
+            sql: r#"
+        CREATE TABLE IF NOT EXISTS query_history (
+          id TEXT PRIMARY KEY,
+          conn_id TEXT NOT NULL,
+          saved_sql_id TEXT NULL,
+          sql_hash TEXT NOT NULL,        -- sha256 of the whitespace-normalised SQL
+          sql TEXT NOT NULL,
+          params TEXT NULL,              -- JSON array; NULL when redacted
+          param_count INTEGER NOT NULL DEFAULT 0,
+          params_redacted INTEGER NOT NULL DEFAULT 0,
+          duration_ms INTEGER NOT NULL,
+          row_count INTEGER NULL,        -- rows returned, or affected for writes
+          error_code TEXT NULL,          -- SQLSTATE or our error code
+          error_message TEXT NULL,
+          executed_at INTEGER NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_query_history_executed ON query_history(executed_at);
+        CREATE INDEX IF NOT EXISTS idx_query_history_conn ON query_history(conn_id, executed_at);
+        CREATE INDEX IF NOT EXISTS idx_query_history_hash ON query_history(sql_hash);
+        CREATE INDEX IF NOT EXISTS idx_query_history_saved ON query_history(saved_sql_id, executed_at);
+        "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 13,
+            description: "saved_sql_schedules",
+            sql: r#"
+        CREATE TABLE IF NOT EXISTS saved_sql_schedules (
+          id TEXT PRIMARY KEY,
+          saved_id TEXT NOT NULL REFERENCES saved_sql(id) ON DELETE CASCADE,
+          conn_id TEXT NOT NULL,
+          cron TEXT NOT NULL,            -- 5-field cron, local time
+          vars TEXT NOT NULL,            -- JSON object of variable values
+          condition TEXT NULL,           -- JSON string (ScheduleCondition)
+          notify INTEGER NOT NULL DEFAULT 1,
+          retention_days INTEGER NOT NULL DEFAULT 30,
+          max_snapshots INTEGER NOT NULL DEFAULT 100,
+          enabled INTEGER NOT NULL DEFAULT 1,
+          last_run_at INTEGER NULL,
+          created_at INTEGER NOT NULL,
+          updated_at INTEGER NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_saved_sql_schedules_saved ON saved_sql_schedules(saved_id);
+
+        CREATE TABLE IF NOT EXISTS saved_sql_snapshots (
+          id TEXT PRIMARY KEY,
+          schedule_id TEXT NOT NULL REFERENCES saved_sql_schedules(id) ON DELETE CASCADE,
+          saved_id TEXT NOT NULL,
+          conn_id TEXT NOT NULL,
+          run_at INTEGER NOT NULL,
+          status TEXT NOT NULL,          -- ok | error
+          duration_ms INTEGER NOT NULL,
+          row_count INTEGER NULL,        -- all rows returned, even when `rows` is capped
+          columns TEXT NULL,             -- JSON array of column names
+          rows TEXT NULL,                -- JSON array of row objects
+          truncated INTEGER NOT NULL DEFAULT 0,
+          triggered INTEGER NOT NULL DEFAULT 0,
+          message TEXT NULL              -- condition message or error
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_saved_sql_snapshots_schedule ON saved_sql_snapshots(schedule_id, run_at);
+        "#,
+            kind: MigrationKind::Up,
+        },
+        // Down migrations, one per version: `LocalStore::rollback` applies
+        // them newest first. Never edit an Up entry above; sqlx checks its
+        // checksum against `_sqlx_migrations`.
+        Migration {
+            version: 1,
+            description: "init_local_store",
+            sql: r#"
+        DROP INDEX IF EXISTS idx_schema_cache_conn;
+        DROP INDEX IF EXISTS idx_saved_sql_updated_at;
+        DROP TABLE IF EXISTS app_prefs;
+        DROP TABLE IF EXISTS schema_cache;
+        DROP TABLE IF EXISTS saved_sql;
+        DROP TABLE IF EXISTS user_connections;
+        "#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 2,
+            description: "ops_audit_table",
+            sql: r#"
+        DROP INDEX IF EXISTS idx_ops_audit_created_at;
+        DROP TABLE IF EXISTS ops_audit;
+        "#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 3,
+            description: "table_profiles_cache",
+            sql: r#"
+        DROP TABLE IF EXISTS table_profiles;
+        "#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 4,
+            description: "jsonb_shapes_cache",
+            sql: r#"
+        DROP TABLE IF EXISTS jsonb_shapes;
+        "#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 5,
+            description: "ops_audit_hash_chain",
+            sql: r#"
+        DROP INDEX IF EXISTS idx_ops_audit_seq;
+        ALTER TABLE ops_audit DROP COLUMN hash;
+        ALTER TABLE ops_audit DROP COLUMN prev_hash;
+        ALTER TABLE ops_audit DROP COLUMN seq;
+        "#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 6,
+            description: "activity_monitor",
+            sql: r#"
+        DROP INDEX IF EXISTS idx_activity_samples_seq;
+        DROP TABLE IF EXISTS activity_samples;
+        DROP TABLE IF EXISTS activity_monitors;
+        "#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 7,
+            description: "stat_statements_snapshots",
+            sql: r#"
+        DROP TABLE IF EXISTS stat_statements_entries;
+        DROP INDEX IF EXISTS idx_stat_statements_snapshots_conn;
+        DROP TABLE IF EXISTS stat_statements_snapshots;
+        "#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 8,
+            description: "bloat_history",
+            sql: r#"
+        DROP INDEX IF EXISTS idx_bloat_history_object;
+        DROP TABLE IF EXISTS bloat_history;
+        "#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 9,
+            description: "connection_strict_read_only",
+            sql: r#"
+        ALTER TABLE user_connections DROP COLUMN strict_read_only;
+        "#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 10,
+            description: "saved_sql_revisions",
+            sql: r#"
+        DROP TRIGGER IF EXISTS trg_saved_sql_revision_update;
+        DROP TRIGGER IF EXISTS trg_saved_sql_revision_insert;
+        DROP TABLE IF EXISTS saved_sql_revisions;
+        "#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 11,
+            description: "saved_sql_fts",
+            sql: r#"
+        DROP TRIGGER IF EXISTS trg_saved_sql_fts_delete;
+        DROP TRIGGER IF EXISTS trg_saved_sql_fts_update;
+        DROP TRIGGER IF EXISTS trg_saved_sql_fts_insert;
+        DROP TABLE IF EXISTS saved_sql_fts;
+        "#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 12,
+            description: "query_history",
+            sql: r#"
+        DROP INDEX IF EXISTS idx_query_history_saved;
+        DROP INDEX IF EXISTS idx_query_history_hash;
+        DROP INDEX IF EXISTS idx_query_history_conn;
+        DROP INDEX IF EXISTS idx_query_history_executed;
+        DROP TABLE IF EXISTS query_history;
+        "#,
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 13,
+            description: "saved_sql_schedules",
+            sql: r#"
+        DROP INDEX IF EXISTS idx_saved_sql_snapshots_schedule;
+        DROP TABLE IF EXISTS saved_sql_snapshots;
+        DROP INDEX IF EXISTS idx_saved_sql_schedules_saved;
+        DROP TABLE IF EXISTS saved_sql_schedules;
+        "#,
+            kind: MigrationKind::Down,
+        },
     ]
 }
@@ -0,0 +1,766 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgConnection;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::local_store::{db_error, now_sec, row_text, LocalStore};
+use crate::pg::{begin_read_only, fetch_as, fetch_one_as, PgPools};
+
+pub const SAMPLE_EVENT: &str = "activity-monitor://sample";
+pub const ALERT_EVENT: &str = "activity-monitor://alert";
+pub const ERROR_EVENT: &str = "activity-monitor://error";
+
+const SAMPLE_STATEMENT_TIMEOUT_MS: u64 = 5_000;
+const MIN_INTERVAL_SECS: u64 = 5;
+const MAX_INTERVAL_SECS: u64 = 3_600;
+const MAX_CAPACITY: i64 = 100_000;
+const MAX_LONG_TRANSACTIONS: i64 = 5;
+const DEFAULT_LONG_TRANSACTION_SECS: f64 = 300.0;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MonitorThresholds {
+    /// Oldest open transaction, in seconds.
+    pub long_transaction_secs: Option<f64>,
+    /// Sessions waiting on another session's lock.
+    pub blocked_sessions: Option<i64>,
+    /// Client connections as a share of `max_connections` minus reserved slots.
+    pub connection_usage_percent: Option<f64>,
+    pub replication_lag_bytes: Option<i64>,
+}
+
+impl Default for MonitorThresholds {
+    fn default() -> Self {
+        Self {
+            long_transaction_secs: Some(DEFAULT_LONG_TRANSACTION_SECS),
+            blocked_sessions: Some(1),
+            connection_usage_percent: Some(80.0),
+            replication_lag_bytes: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MonitorConfig {
+    pub interval_secs: u64,
+    /// Ring-buffer size in `activity_samples`; oldest slots are overwritten.
+    pub capacity: i64,
+    pub notify: bool,
+    pub thresholds: MonitorThresholds,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 30,
+            capacity: 1_440,
+            notify: true,
+            thresholds: MonitorThresholds::default(),
+        }
+    }
+}
+
+impl MonitorConfig {
+    fn normalized(mut self) -> Self {
+        self.interval_secs = self
+            .interval_secs
+            .clamp(MIN_INTERVAL_SECS, MAX_INTERVAL_SECS);
+        self.capacity = self.capacity.clamp(10, MAX_CAPACITY);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConnectionCounts {
+    pub total: i64,
+    pub active: i64,
+    pub idle: i64,
+    pub idle_in_transaction: i64,
+    pub blocked: i64,
+    pub max_connections: i64,
+    pub reserved_connections: i64,
+    pub longest_xact_seconds: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LongTransaction {
+    pub pid: i64,
+    pub usename: Option<String>,
+    pub application_name: Option<String>,
+    pub state: Option<String>,
+    pub xact_seconds: f64,
+    pub query: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DatabaseCounters {
+    pub xact_commit: i64,
+    pub xact_rollback: i64,
+    pub blks_read: i64,
+    pub blks_hit: i64,
+    pub tup_inserted: i64,
+    pub tup_updated: i64,
+    pub tup_deleted: i64,
+    pub temp_bytes: i64,
+    pub deadlocks: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReplicaLag {
+    pub application_name: Option<String>,
+    pub client_addr: Option<String>,
+    pub state: Option<String>,
+    pub sync_state: Option<String>,
+    pub replay_lag_bytes: Option<i64>,
+    pub replay_lag_seconds: Option<f64>,
+}
+
+/// Per-second rates against the previous sample of the same monitor.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SampleRates {
+    pub commits_per_sec: f64,
+    pub rollbacks_per_sec: f64,
+    pub deadlocks: i64,
+    pub cache_hit_ratio: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ActivitySample {
+    pub sampled_at: i64,
+    pub connections: ConnectionCounts,
+    pub connection_usage_percent: f64,
+    pub long_transactions: Vec<LongTransaction>,
+    pub database: Option<DatabaseCounters>,
+    #[serde(default)]
+    pub rates: Option<SampleRates>,
+    pub replication: Vec<ReplicaLag>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorAlert {
+    pub conn_id: String,
+    /// `long_transaction`, `blocked_sessions`, `connection_saturation` or
+    /// `replication_lag`.
+    pub kind: String,
+    /// `firing` when the threshold is first crossed, `resolved` once clear.
+    pub state: String,
+    pub message: String,
+    pub value: f64,
+    pub threshold: f64,
+    pub sampled_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct MonitorRuntime {
+    last_sample_at: Option<i64>,
+    last_error: Option<String>,
+    firing: Vec<String>,
+    paused: bool,
+}
+
+struct RunningMonitor {
+    config: MonitorConfig,
+    stop: Arc<AtomicBool>,
+    runtime: Arc<Mutex<MonitorRuntime>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorStatus {
+    pub conn_id: String,
+    pub config: MonitorConfig,
+    pub last_sample_at: Option<i64>,
+    pub last_error: Option<String>,
+    pub firing: Vec<String>,
+    /// The local store is locked; sampling resumes once it is unlocked.
+    pub paused: bool,
+}
+
+/// One sampler task per connection. Restarting a monitor flags the previous
+/// task to stop; it exits on its next wake-up without sampling again.
+#[derive(Default)]
+pub struct ActivityMonitors {
+    running: Mutex<HashMap<String, RunningMonitor>>,
+}
+
+const CONNECTIONS_SQL: &str = r#"
+SELECT
+  count(*)::bigint AS total,
+  count(*) FILTER (WHERE state = 'active')::bigint AS active,
+  count(*) FILTER (WHERE state = 'idle')::bigint AS idle,
+  count(*) FILTER (WHERE state LIKE 'idle in transaction%')::bigint AS idle_in_transaction,
+  count(*) FILTER (WHERE cardinality(pg_catalog.pg_blocking_pids(pid)) > 0)::bigint AS blocked,
+  current_setting('max_connections')::bigint AS max_connections,
+  current_setting('superuser_reserved_connections')::bigint AS reserved_connections,
+  COALESCE(max(EXTRACT(EPOCH FROM now() - xact_start))
+    FILTER (WHERE pid <> pg_catalog.pg_backend_pid()), 0)::float8 AS longest_xact_seconds
+FROM pg_catalog.pg_stat_activity
+WHERE backend_type = 'client backend'"#;
+
+const LONG_TRANSACTIONS_SQL: &str = r#"
+SELECT
+  pid::bigint AS pid,
+  usename::text AS usename,
+  application_name,
+  state,
+  EXTRACT(EPOCH FROM now() - xact_start)::float8 AS xact_seconds,
+  left(query, 300) AS query
+FROM pg_catalog.pg_stat_activity
+WHERE xact_start IS NOT NULL
+  AND pid <> pg_catalog.pg_backend_pid()
+  AND EXTRACT(EPOCH FROM now() - xact_start) > $1
+ORDER BY xact_start
+LIMIT $2"#;
+
+const DATABASE_SQL: &str = r#"
+SELECT
+  xact_commit::bigint AS xact_commit,
+  xact_rollback::bigint AS xact_rollback,
+  blks_read::bigint AS blks_read,
+  blks_hit::bigint AS blks_hit,
+  tup_inserted::bigint AS tup_inserted,
+  tup_updated::bigint AS tup_updated,
+  tup_deleted::bigint AS tup_deleted,
+  temp_bytes::bigint AS temp_bytes,
+  deadlocks::bigint AS deadlocks
+FROM pg_catalog.pg_stat_database
+WHERE datname = current_database()"#;
+
+const REPLICATION_SQL: &str = r#"
+SELECT
+  application_name,
+  client_addr::text AS client_addr,
+  state,
+  sync_state,
+  CASE WHEN pg_catalog.pg_is_in_recovery() THEN NULL
+       ELSE pg_catalog.pg_wal_lsn_diff(pg_catalog.pg_current_wal_lsn(), replay_lsn)::bigint
+  END AS replay_lag_bytes,
+  EXTRACT(EPOCH FROM replay_lag)::float8 AS replay_lag_seconds
+FROM pg_catalog.pg_stat_replication"#;
+
+fn usage_percent(counts: &ConnectionCounts) -> f64 {
+    let usable = (counts.max_connections - counts.reserved_connections).max(1);
+    (counts.total as f64 / usable as f64) * 100.0
+}
+
+fn rates(previous: &ActivitySample, current: &ActivitySample) -> Option<SampleRates> {
+    let (before, after) = (previous.database.as_ref()?, current.database.as_ref()?);
+    let elapsed = (current.sampled_at - previous.sampled_at).max(1) as f64;
+    let reads = (after.blks_read - before.blks_read) + (after.blks_hit - before.blks_hit);
+    Some(SampleRates {
+        commits_per_sec: (after.xact_commit - before.xact_commit).max(0) as f64 / elapsed,
+        rollbacks_per_sec: (after.xact_rollback - before.xact_rollback).max(0) as f64 / elapsed,
+        deadlocks: (after.deadlocks - before.deadlocks).max(0),
+        cache_hit_ratio: (reads > 0)
+            .then(|| (after.blks_hit - before.blks_hit) as f64 / reads as f64),
+    })
+}
+
+pub async fn collect_sample(
+    conn: &mut PgConnection,
+    long_transaction_secs: f64,
+) -> Result<ActivitySample, String> {
+    let connections: ConnectionCounts = fetch_one_as(conn, CONNECTIONS_SQL, &[])
+        .await?
+        .ok_or_else(|| "activity_sample_empty".to_string())?;
+    let long_transactions: Vec<LongTransaction> = fetch_as(
+        conn,
+        LONG_TRANSACTIONS_SQL,
+        &[json!(long_transaction_secs), json!(MAX_LONG_TRANSACTIONS)],
+    )
+    .await?;
+    let database: Option<DatabaseCounters> = fetch_one_as(conn, DATABASE_SQL, &[]).await?;
+    let replication: Vec<ReplicaLag> = fetch_as(conn, REPLICATION_SQL, &[]).await?;
+    Ok(ActivitySample {
+        sampled_at: now_sec(),
+        connection_usage_percent: usage_percent(&connections),
+        connections,
+        long_transactions,
+        database,
+        rates: None,
+        replication,
+    })
+}
+
+/// Thresholds crossed by one sample, keyed by alert kind.
+pub fn evaluate(
+    conn_id: &str,
+    sample: &ActivitySample,
+    thresholds: &MonitorThresholds,
+) -> Vec<MonitorAlert> {
+    let mut alerts = Vec::new();
+    let mut push = |kind: &str, message: String, value: f64, threshold: f64| {
+        alerts.push(MonitorAlert {
+            conn_id: conn_id.to_string(),
+            kind: kind.to_string(),
+            state: "firing".to_string(),
+            message,
+            value,
+            threshold,
+            sampled_at: sample.sampled_at,
+        });
+    };
+    if let Some(limit) = thresholds.long_transaction_secs {
+        let longest = sample.connections.longest_xact_seconds;
+        if longest >= limit {
+            let pid = sample
+                .long_transactions
+                .first()
+                .map(|tx| format!(" (pid {})", tx.pid))
+                .unwrap_or_default();
+            push(
+                "long_transaction",
+                format!("Transaction open for {:.0}s{}", longest, pid),
+                longest,
+                limit,
+            );
+        }
+    }
+    if let Some(limit) = thresholds.blocked_sessions {
+        let blocked = sample.connections.blocked;
+        if blocked >= limit {
+            push(
+                "blocked_sessions",
+                format!("{} session(s) waiting on locks", blocked),
+                blocked as f64,
+                limit as f64,
+            );
+        }
+    }
+    if let Some(limit) = thresholds.connection_usage_percent {
+        let usage = sample.connection_usage_percent;
+        if usage >= limit {
+            push(
+                "connection_saturation",
+                format!(
+                    "{:.0}% of connections in use ({}/{})",
+                    usage, sample.connections.total, sample.connections.max_connections
+                ),
+                usage,
+                limit,
+            );
+        }
+    }
+    if let Some(limit) = thresholds.replication_lag_bytes {
+        let worst = sample
+            .replication
+            .iter()
+            .filter_map(|replica| replica.replay_lag_bytes)
+            .max()
+            .unwrap_or(0);
+        if worst >= limit {
+            push(
+                "replication_lag",
+                format!("Replica replay lag {} bytes", worst),
+                worst as f64,
+                limit as f64,
+            );
+        }
+    }
+    alerts
+}
+
+async fn store_sample(
+    store: &LocalStore,
+    conn_id: &str,
+    capacity: i64,
+    sample: &ActivitySample,
+    alert_count: usize,
+) -> Result<(), String> {
+    let content = serde_json::to_string(sample).map_err(|err| err.to_string())?;
+    sqlx::query(
+        "INSERT INTO activity_samples (conn_id, slot, seq, sampled_at, content, alerts)
+         SELECT ?1, (COALESCE(MAX(seq), 0) + 1) % ?2, COALESCE(MAX(seq), 0) + 1, ?3, ?4, ?5
+         FROM activity_samples WHERE conn_id = ?1
+         ON CONFLICT(conn_id, slot) DO UPDATE SET
+           seq = excluded.seq, sampled_at = excluded.sampled_at,
+           content = excluded.content, alerts = excluded.alerts",
+    )
+    .bind(conn_id)
+    .bind(capacity)
+    .bind(sample.sampled_at)
+    .bind(content)
+    .bind(alert_count as i64)
+    .execute(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    Ok(())
+}
+
+pub async fn load_samples(
+    store: &LocalStore,
+    conn_id: &str,
+    limit: i64,
+) -> Result<Vec<ActivitySample>, String> {
+    let rows = sqlx::query(
+        "SELECT content FROM (
+           SELECT content, seq FROM activity_samples WHERE conn_id = ?1 ORDER BY seq DESC LIMIT ?2
+         ) ORDER BY seq",
+    )
+    .bind(conn_id)
+    .bind(limit)
+    .fetch_all(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| row_text(row, "content"))
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect())
+}
+
+async fn sample_once(
+    app: &AppHandle,
+    conn_id: &str,
+    config: &MonitorConfig,
+) -> Result<ActivitySample, String> {
+    let store = app.state::<LocalStore>();
+    let pools = app.state::<PgPools>();
+    let pool = pools.pool_for(&store, conn_id).await?;
+    let mut tx = begin_read_only(&pool, SAMPLE_STATEMENT_TIMEOUT_MS).await?;
+    let long_secs = config
+        .thresholds
+        .long_transaction_secs
+        .unwrap_or(DEFAULT_LONG_TRANSACTION_SECS);
+    collect_sample(&mut tx, long_secs).await
+}
+
+fn notify(app: &AppHandle, alert: &MonitorAlert) {
+    let _ = app
+        .notification()
+        .builder()
+        .title("reiDbView monitor")
+        .body(&alert.message)
+        .show();
+}
+
+async fn run_monitor(
+    app: AppHandle,
+    conn_id: String,
+    config: MonitorConfig,
+    stop: Arc<AtomicBool>,
+    runtime: Arc<Mutex<MonitorRuntime>>,
+) {
+    let mut firing: BTreeSet<String> = BTreeSet::new();
+    let mut previous: Option<ActivitySample> = None;
+    while !stop.load(Ordering::Relaxed) {
+        // Samples are stored and the DSN is read from the local store, so a
+        // locked store pauses the monitor instead of failing every interval.
+        let locked = app.state::<LocalStore>().pool().is_err();
+        if let Ok(mut state) = runtime.lock() {
+            state.paused = locked;
+        }
+        if locked {
+            previous = None;
+            tokio::time::sleep(Duration::from_secs(config.interval_secs)).await;
+            continue;
+        }
+        let result = sample_once(&app, &conn_id, &config).await;
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        match result {
+            Ok(mut sample) => {
+                sample.rates = previous.as_ref().and_then(|prev| rates(prev, &sample));
+                let alerts = evaluate(&conn_id, &sample, &config.thresholds);
+                let store = app.state::<LocalStore>();
+                let stored =
+                    store_sample(&store, &conn_id, config.capacity, &sample, alerts.len()).await;
+                let current: BTreeSet<String> = alerts.iter().map(|a| a.kind.clone()).collect();
+                for alert in alerts.iter().filter(|a| !firing.contains(&a.kind)) {
+                    let _ = app.emit(ALERT_EVENT, alert.clone());
+                    if config.notify {
+                        notify(&app, alert);
+                    }
+                }
+                for kind in firing.difference(&current) {
+                    let _ = app.emit(
+                        ALERT_EVENT,
+                        MonitorAlert {
+                            conn_id: conn_id.clone(),
+                            kind: kind.clone(),
+                            state: "resolved".to_string(),
+                            message: format!("{} cleared", kind),
+                            value: 0.0,
+                            threshold: 0.0,
+                            sampled_at: sample.sampled_at,
+                        },
+                    );
+                }
+                firing = current;
+                let _ = app.emit(
+                    SAMPLE_EVENT,
+                    json!({ "conn_id": conn_id, "sample": sample }),
+                );
+                if let Ok(mut state) = runtime.lock() {
+                    state.last_sample_at = Some(sample.sampled_at);
+                    state.last_error = stored.err();
+                    state.firing = firing.iter().cloned().collect();
+                }
+                previous = Some(sample);
+            }
+            Err(err) => {
+                let _ = app.emit(ERROR_EVENT, json!({ "conn_id": conn_id, "error": err }));
+                if let Ok(mut state) = runtime.lock() {
+                    state.last_error = Some(err);
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(config.interval_secs)).await;
+    }
+}
+
+impl ActivityMonitors {
+    fn start(&self, app: &AppHandle, conn_id: &str, config: MonitorConfig) -> Result<(), String> {
+        let mut running = self
+            .running
+            .lock()
+            .map_err(|_| "activity_monitor_poisoned".to_string())?;
+        if let Some(previous) = running.remove(conn_id) {
+            previous.stop.store(true, Ordering::Relaxed);
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let runtime = Arc::new(Mutex::new(MonitorRuntime::default()));
+        running.insert(
+            conn_id.to_string(),
+            RunningMonitor {
+                config: config.clone(),
+                stop: stop.clone(),
+                runtime: runtime.clone(),
+            },
+        );
+        tauri::async_runtime::spawn(run_monitor(
+            app.clone(),
+            conn_id.to_string(),
+            config,
+            stop,
+            runtime,
+        ));
+        Ok(())
+    }
+
+    fn stop(&self, conn_id: &str) -> Result<bool, String> {
+        let mut running = self
+            .running
+            .lock()
+            .map_err(|_| "activity_monitor_poisoned".to_string())?;
+        Ok(match running.remove(conn_id) {
+            Some(monitor) => {
+                monitor.stop.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        })
+    }
+
+    fn statuses(&self) -> Result<Vec<MonitorStatus>, String> {
+        let running = self
+            .running
+            .lock()
+            .map_err(|_| "activity_monitor_poisoned".to_string())?;
+        let mut statuses: Vec<MonitorStatus> = running
+            .iter()
+            .map(|(conn_id, monitor)| {
+                let runtime = monitor
+                    .runtime
+                    .lock()
+                    .map(|state| state.clone())
+                    .unwrap_or_default();
+                MonitorStatus {
+                    conn_id: conn_id.clone(),
+                    config: monitor.config.clone(),
+                    last_sample_at: runtime.last_sample_at,
+                    last_error: runtime.last_error,
+                    firing: runtime.firing,
+                    paused: runtime.paused,
+                }
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.conn_id.cmp(&b.conn_id));
+        Ok(statuses)
+    }
+}
+
+async fn save_monitor(
+    store: &LocalStore,
+    conn_id: &str,
+    config: &MonitorConfig,
+    enabled: bool,
+) -> Result<(), String> {
+    let content = serde_json::to_string(config).map_err(|err| err.to_string())?;
+    sqlx::query(
+        "INSERT INTO activity_monitors (conn_id, config, enabled, updated_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(conn_id) DO UPDATE SET
+           config = excluded.config, enabled = excluded.enabled, updated_at = excluded.updated_at",
+    )
+    .bind(conn_id)
+    .bind(content)
+    .bind(enabled)
+    .bind(now_sec())
+    .execute(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    // Drop slots left over from a larger ring.
+    sqlx::query("DELETE FROM activity_samples WHERE conn_id = ?1 AND slot >= ?2")
+        .bind(conn_id)
+        .bind(config.capacity)
+        .execute(&store.pool()?)
+        .await
+        .map_err(db_error)?;
+    Ok(())
+}
+
+/// Restarts monitors left enabled in a previous session, at setup or once an
+/// encrypted store is unlocked. A store that is still locked has nothing to
+/// read yet, and an unreadable row is skipped.
+pub fn resume_saved(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let Ok(pool) = app.state::<LocalStore>().pool() else {
+            return;
+        };
+        let Ok(rows) =
+            sqlx::query("SELECT conn_id, config FROM activity_monitors WHERE enabled = 1")
+                .fetch_all(&pool)
+                .await
+        else {
+            return;
+        };
+        let monitors = app.state::<ActivityMonitors>();
+        for row in rows {
+            let (Some(conn_id), Some(config)) =
+                (row_text(&row, "conn_id"), row_text(&row, "config"))
+            else {
+                continue;
+            };
+            let config: MonitorConfig = serde_json::from_str(&config).unwrap_or_default();
+            let _ = monitors.start(&app, &conn_id, config.normalized());
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn start_activity_monitor(
+    app: AppHandle,
+    store: State<'_, LocalStore>,
+    monitors: State<'_, ActivityMonitors>,
+    conn_id: String,
+    config: Option<MonitorConfig>,
+) -> Result<Vec<MonitorStatus>, String> {
+    let config = config.unwrap_or_default().normalized();
+    save_monitor(&store, &conn_id, &config, true).await?;
+    monitors.start(&app, &conn_id, config)?;
+    monitors.statuses()
+}
+
+#[tauri::command]
+pub async fn stop_activity_monitor(
+    store: State<'_, LocalStore>,
+    monitors: State<'_, ActivityMonitors>,
+    conn_id: String,
+) -> Result<bool, String> {
+    sqlx::query("UPDATE activity_monitors SET enabled = 0, updated_at = ?2 WHERE conn_id = ?1")
+        .bind(&conn_id)
+        .bind(now_sec())
+        .execute(&store.pool()?)
+        .await
+        .map_err(db_error)?;
+    monitors.stop(&conn_id)
+}
+
+#[tauri::command]
+pub async fn activity_monitor_status(
+    monitors: State<'_, ActivityMonitors>,
+) -> Result<Vec<MonitorStatus>, String> {
+    monitors.statuses()
+}
+
+#[tauri::command]
+pub async fn activity_samples(
+    store: State<'_, LocalStore>,
+    conn_id: String,
+    limit: Option<i64>,
+) -> Result<Vec<ActivitySample>, String> {
+    load_samples(
+        &store,
+        &conn_id,
+        limit.unwrap_or(120).clamp(1, MAX_CAPACITY),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(total: i64, blocked: i64, longest: f64) -> ActivitySample {
+        ActivitySample {
+            sampled_at: 100,
+            connections: ConnectionCounts {
+                total,
+                active: 1,
+                idle: 0,
+                idle_in_transaction: 0,
+                blocked,
+                max_connections: 103,
+                reserved_connections: 3,
+                longest_xact_seconds: longest,
+            },
+            connection_usage_percent: total as f64,
+            long_transactions: Vec::new(),
+            database: None,
+            rates: None,
+            replication: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn evaluates_default_thresholds() {
+        let thresholds = MonitorThresholds::default();
+        assert!(evaluate("c", &sample(10, 0, 12.0), &thresholds).is_empty());
+        let kinds: Vec<String> = evaluate("c", &sample(85, 2, 900.0), &thresholds)
+            .into_iter()
+            .map(|alert| alert.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "long_transaction",
+                "blocked_sessions",
+                "connection_saturation"
+            ]
+        );
+        let disabled = MonitorThresholds {
+            blocked_sessions: None,
+            ..MonitorThresholds::default()
+        };
+        assert_eq!(evaluate("c", &sample(10, 5, 0.0), &disabled).len(), 0);
+    }
+
+    #[test]
+    fn computes_rates_between_samples() {
+        let counters = |commit, hit, read| DatabaseCounters {
+            xact_commit: commit,
+            xact_rollback: 0,
+            blks_read: read,
+            blks_hit: hit,
+            tup_inserted: 0,
+            tup_updated: 0,
+            tup_deleted: 0,
+            temp_bytes: 0,
+            deadlocks: 0,
+        };
+        let mut before = sample(1, 0, 0.0);
+        before.database = Some(counters(100, 1_000, 0));
+        let mut after = sample(1, 0, 0.0);
+        after.sampled_at = 110;
+        after.database = Some(counters(150, 1_090, 10));
+        let rates = rates(&before, &after).unwrap();
+        assert_eq!(rates.commits_per_sec, 5.0);
+        assert_eq!(rates.cache_hit_ratio, Some(0.9));
+    }
+}
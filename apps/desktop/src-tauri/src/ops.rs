@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::State;
+
+use crate::crypto::random_token;
+use crate::local_store::{now_sec, LocalStore};
+use crate::ops_audit::{self, AuditEntry};
+use crate::pg::{begin_read_only, fetch_one_as, pg_error, PgPools};
+
+/// How long a confirmation token from `prepare_ops_signal` stays valid.
+const CONFIRM_TTL_SECS: i64 = 120;
+const SIGNAL_STATEMENT_TIMEOUT_MS: u64 = 5_000;
+const LOOKUP_STATEMENT_TIMEOUT_MS: u64 = 5_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignalMode {
+    Cancel,
+    Terminate,
+}
+
+impl SignalMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            SignalMode::Cancel => "cancel",
+            SignalMode::Terminate => "terminate",
+        }
+    }
+
+    fn function(self) -> &'static str {
+        match self {
+            SignalMode::Cancel => "pg_cancel_backend",
+            SignalMode::Terminate => "pg_terminate_backend",
+        }
+    }
+}
+
+struct PendingSignal {
+    conn_id: String,
+    mode: SignalMode,
+    pid: i64,
+    expires_at: i64,
+}
+
+/// Outstanding confirmation tokens. Each token authorises exactly one signal
+/// against the backend it was issued for.
+#[derive(Default)]
+pub struct OpsConfirmations {
+    pending: Mutex<HashMap<String, PendingSignal>>,
+}
+
+impl OpsConfirmations {
+    fn issue(&self, conn_id: &str, mode: SignalMode, pid: i64) -> Result<(String, i64), String> {
+        let mut pending = self
+            .pending
+            .lock()
+            .map_err(|_| "ops_confirmations_poisoned".to_string())?;
+        let now = now_sec();
+        pending.retain(|_, signal| signal.expires_at > now);
+        let token = random_token(16);
+        let expires_at = now + CONFIRM_TTL_SECS;
+        pending.insert(
+            token.clone(),
+            PendingSignal {
+                conn_id: conn_id.to_string(),
+                mode,
+                pid,
+                expires_at,
+            },
+        );
+        Ok((token, expires_at))
+    }
+
+    /// Consumes the token; it can't be replayed even if the checks fail.
+    fn redeem(&self, token: &str, conn_id: &str, mode: SignalMode, pid: i64) -> Result<(), String> {
+        let signal = self
+            .pending
+            .lock()
+            .map_err(|_| "ops_confirmations_poisoned".to_string())?
+            .remove(token)
+            .ok_or_else(|| "confirmation_required".to_string())?;
+        if signal.expires_at <= now_sec() {
+            return Err("confirmation_expired".to_string());
+        }
+        if signal.conn_id != conn_id || signal.mode != mode || signal.pid != pid {
+            return Err("confirmation_mismatch".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BackendTarget {
+    pub pid: i64,
+    pub usename: Option<String>,
+    pub datname: Option<String>,
+    pub application_name: Option<String>,
+    pub client_addr: Option<String>,
+    pub state: Option<String>,
+    pub backend_type: Option<String>,
+    pub query: Option<String>,
+    pub is_self: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpsSignalConfirmation {
+    pub token: String,
+    pub mode: SignalMode,
+    pub pid: i64,
+    pub target: BackendTarget,
+    pub expires_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpsSignalResult {
+    pub ok: bool,
+    pub audit_id: String,
+    pub audit_hash: String,
+}
+
+const BACKEND_SQL: &str = r#"
+SELECT
+  pid::bigint AS pid,
+  usename::text AS usename,
+  datname::text AS datname,
+  application_name,
+  client_addr::text AS client_addr,
+  state,
+  backend_type,
+  left(query, 500) AS query,
+  pid = pg_catalog.pg_backend_pid() AS is_self
+FROM pg_catalog.pg_stat_activity
+WHERE pid = $1::int"#;
+
+#[tauri::command]
+pub async fn prepare_ops_signal(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    confirmations: State<'_, OpsConfirmations>,
+    conn_id: String,
+    mode: SignalMode,
+    pid: i64,
+) -> Result<OpsSignalConfirmation, String> {
+    let pool = pools.pool_for(&store, &conn_id).await?;
+    let mut tx = begin_read_only(&pool, LOOKUP_STATEMENT_TIMEOUT_MS).await?;
+    let target: BackendTarget = fetch_one_as(&mut tx, BACKEND_SQL, &[json!(pid)])
+        .await?
+        .ok_or_else(|| format!("backend_not_found:{}", pid))?;
+    drop(tx);
+    if target.is_self {
+        return Err("cannot_signal_own_backend".to_string());
+    }
+    let (token, expires_at) = confirmations.issue(&conn_id, mode, pid)?;
+    Ok(OpsSignalConfirmation {
+        token,
+        mode,
+        pid,
+        target,
+        expires_at,
+    })
+}
+
+/// Sends the signal after redeeming the confirmation token. A `started` row is
+/// committed to `ops_audit` before anything reaches Postgres; if that write
+/// fails the signal is not sent. The outcome is appended afterwards.
+#[tauri::command]
+pub async fn run_ops_signal(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    confirmations: State<'_, OpsConfirmations>,
+    conn_id: String,
+    mode: SignalMode,
+    pid: i64,
+    confirm_token: String,
+) -> Result<OpsSignalResult, String> {
+    confirmations.redeem(confirm_token.trim(), &conn_id, mode, pid)?;
+    ops_audit::append(
+        &store,
+        AuditEntry {
+            conn_id: &conn_id,
+            action: mode.as_str(),
+            target_pid: Some(pid),
+            status: "started",
+            message: None,
+        },
+    )
+    .await?;
+
+    let outcome = async {
+        let pool = pools.pool_for(&store, &conn_id).await?;
+        let mut tx = pool.begin().await.map_err(pg_error)?;
+        sqlx::query(&format!(
+            "SET LOCAL statement_timeout = {}",
+            SIGNAL_STATEMENT_TIMEOUT_MS
+        ))
+        .execute(&mut *tx)
+        .await
+        .map_err(pg_error)?;
+        let ok: bool = sqlx::query_scalar(&format!("SELECT {}($1::int)", mode.function()))
+            .bind(pid)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(pg_error)?;
+        tx.commit().await.map_err(pg_error)?;
+        Ok::<bool, String>(ok)
+    }
+    .await;
+
+    let (status, message) = match &outcome {
+        Ok(true) => ("success", None),
+        Ok(false) => ("failed", Some("signal_not_delivered".to_string())),
+        Err(err) => ("failed", Some(err.clone())),
+    };
+    let record = ops_audit::append(
+        &store,
+        AuditEntry {
+            conn_id: &conn_id,
+            action: mode.as_str(),
+            target_pid: Some(pid),
+            status,
+            message,
+        },
+    )
+    .await?;
+    let ok = outcome.map_err(|err| format!("signal_failed: {}", err))?;
+    Ok(OpsSignalResult {
+        ok,
+        audit_id: record.id,
+        audit_hash: record.hash,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_single_use_and_bound_to_target() {
+        let confirmations = OpsConfirmations::default();
+        let (token, _) = confirmations
+            .issue("conn", SignalMode::Terminate, 42)
+            .unwrap();
+        assert_eq!(
+            confirmations.redeem(&token, "conn", SignalMode::Cancel, 42),
+            Err("confirmation_mismatch".to_string())
+        );
+        assert_eq!(
+            confirmations.redeem(&token, "conn", SignalMode::Terminate, 42),
+            Err("confirmation_required".to_string())
+        );
+        let (token, _) = confirmations
+            .issue("conn", SignalMode::Terminate, 42)
+            .unwrap();
+        assert!(confirmations
+            .redeem(&token, "conn", SignalMode::Terminate, 42)
+            .is_ok());
+    }
+}
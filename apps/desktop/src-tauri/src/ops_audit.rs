@@ -0,0 +1,362 @@
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::{Row, SqliteConnection};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::crypto::random_token;
+use crate::local_store::{db_error, new_id, now_sec, row_text, LocalStore};
+
+/// `prev_hash` of the first chained row.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+/// Sidecar next to the local store holding the chain's HMAC key. It is kept
+/// out of the database, so rewriting rows in the SQLite file is not enough to
+/// produce hashes that verify.
+const AUDIT_KEY_FILE: &str = "rdv_local.db.audit-key";
+const AUDIT_KEY_BYTES: usize = 32;
+
+pub struct AuditEntry<'a> {
+    pub conn_id: &'a str,
+    pub action: &'a str,
+    pub target_pid: Option<i64>,
+    pub status: &'a str,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditRecord {
+    pub id: String,
+    pub seq: i64,
+    pub hash: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditVerification {
+    pub ok: bool,
+    pub checked: i64,
+    /// Rows written before the chain existed; not covered by it.
+    pub unchained: i64,
+    pub broken_at_seq: Option<i64>,
+    pub reason: Option<String>,
+}
+
+fn audit_key_path(store: &LocalStore) -> PathBuf {
+    store.path().with_file_name(AUDIT_KEY_FILE)
+}
+
+fn key_io_error(err: std::io::Error) -> String {
+    format!("ops_audit_key_io_error: {}", err)
+}
+
+/// The HMAC key, or `None` when the sidecar does not exist.
+fn read_audit_key(path: &Path) -> Result<Option<Vec<u8>>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => hex::decode(text.trim())
+            .map(Some)
+            .map_err(|_| "ops_audit_key_invalid".to_string()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(key_io_error(err)),
+    }
+}
+
+/// Reads the HMAC key, creating it (owner-only on Unix) on first use.
+fn audit_key(path: &Path) -> Result<Vec<u8>, String> {
+    if let Some(key) = read_audit_key(path)? {
+        return Ok(key);
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let key = random_token(AUDIT_KEY_BYTES);
+    match options.open(path) {
+        Ok(mut file) => {
+            file.write_all(key.as_bytes()).map_err(key_io_error)?;
+            file.sync_all().map_err(key_io_error)?;
+            hex::decode(key).map_err(|_| "ops_audit_key_invalid".to_string())
+        }
+        // Another writer created it first.
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+            read_audit_key(path)?.ok_or_else(|| "ops_audit_key_missing".to_string())
+        }
+        Err(err) => Err(key_io_error(err)),
+    }
+}
+
+/// `HMAC-SHA256(key, prev_hash || canonical row)` as lowercase hex. The
+/// canonical row is a JSON array so field boundaries can't be shifted between
+/// columns.
+pub fn chain_hash(
+    key: &[u8],
+    prev_hash: &str,
+    seq: i64,
+    id: &str,
+    created_at: i64,
+    entry: &AuditEntry<'_>,
+) -> String {
+    let canonical = json!([
+        seq,
+        id,
+        entry.conn_id,
+        entry.action,
+        entry.target_pid,
+        entry.status,
+        entry.message,
+        created_at
+    ])
+    .to_string();
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(prev_hash.as_bytes());
+    mac.update(b"\n");
+    mac.update(canonical.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+async fn insert_chained(
+    conn: &mut SqliteConnection,
+    key: &[u8],
+    entry: &AuditEntry<'_>,
+) -> Result<AuditRecord, String> {
+    let head = sqlx::query(
+        "SELECT seq, hash FROM ops_audit WHERE seq IS NOT NULL ORDER BY seq DESC LIMIT 1",
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(db_error)?;
+    let (prev_seq, prev_hash) = match head {
+        Some(row) => (
+            row.try_get::<i64, _>("seq").map_err(db_error)?,
+            row_text(&row, "hash").unwrap_or_default(),
+        ),
+        None => (0, GENESIS_HASH.to_string()),
+    };
+    let seq = prev_seq + 1;
+    let id = new_id("audit");
+    let created_at = now_sec();
+    let hash = chain_hash(key, &prev_hash, seq, &id, created_at, entry);
+    sqlx::query(
+        "INSERT INTO ops_audit (id, conn_id, action, target_pid, status, message, created_at, seq, prev_hash, hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )
+    .bind(&id)
+    .bind(entry.conn_id)
+    .bind(entry.action)
+    .bind(entry.target_pid)
+    .bind(entry.status)
+    .bind(entry.message.as_deref())
+    .bind(created_at)
+    .bind(seq)
+    .bind(&prev_hash)
+    .bind(&hash)
+    .execute(&mut *conn)
+    .await
+    .map_err(db_error)?;
+    Ok(AuditRecord {
+        id,
+        seq,
+        hash,
+        created_at,
+    })
+}
+
+/// Appends one row to the chain. `BEGIN IMMEDIATE` takes the write lock
+/// before the head is read so concurrent writers can't fork the chain.
+pub async fn append(store: &LocalStore, entry: AuditEntry<'_>) -> Result<AuditRecord, String> {
+    let key =
+        audit_key(&audit_key_path(store)).map_err(|err| format!("ops_audit_failed: {}", err))?;
+    let mut conn = store.pool()?.acquire().await.map_err(db_error)?;
+    sqlx::query("BEGIN IMMEDIATE")
+        .execute(&mut *conn)
+        .await
+        .map_err(db_error)?;
+    match insert_chained(&mut conn, &key, &entry).await {
+        Ok(record) => {
+            sqlx::query("COMMIT")
+                .execute(&mut *conn)
+                .await
+                .map_err(db_error)?;
+            Ok(record)
+        }
+        Err(err) => {
+            let _ = sqlx::query("ROLLBACK").execute(&mut *conn).await;
+            Err(format!("ops_audit_failed: {}", err))
+        }
+    }
+}
+
+/// Walks the chain in `seq` order and recomputes every HMAC. Edits, inserts
+/// and deletions in the middle of the log break the chain; removing rows
+/// from the tail is only visible as a shorter `checked` count. Without the
+/// key sidecar a non-empty chain cannot be verified.
+pub async fn verify(store: &LocalStore) -> Result<AuditVerification, String> {
+    let rows = sqlx::query(
+        "SELECT id, conn_id, action, target_pid, status, message, created_at, seq, prev_hash, hash
+         FROM ops_audit WHERE seq IS NOT NULL ORDER BY seq",
+    )
+    .fetch_all(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    let unchained: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM ops_audit WHERE seq IS NULL")
+        .fetch_one(&store.pool()?)
+        .await
+        .map_err(db_error)?;
+
+    let key = match read_audit_key(&audit_key_path(store))? {
+        Some(key) => key,
+        None if rows.is_empty() => Vec::new(),
+        None => {
+            return Ok(AuditVerification {
+                ok: false,
+                checked: 0,
+                unchained,
+                broken_at_seq: rows.first().and_then(|row| row.try_get("seq").ok()),
+                reason: Some("audit_key_missing".to_string()),
+            })
+        }
+    };
+    let mut expected_prev = GENESIS_HASH.to_string();
+    let mut checked = 0;
+    for (pos, row) in rows.iter().enumerate() {
+        let seq: i64 = row.try_get("seq").map_err(db_error)?;
+        let broken = |reason: &str| AuditVerification {
+            ok: false,
+            checked,
+            unchained,
+            broken_at_seq: Some(seq),
+            reason: Some(reason.to_string()),
+        };
+        if seq != pos as i64 + 1 {
+            return Ok(broken("sequence_gap"));
+        }
+        if row_text(row, "prev_hash").as_deref() != Some(expected_prev.as_str()) {
+            return Ok(broken("prev_hash_mismatch"));
+        }
+        let conn_id = row_text(row, "conn_id").unwrap_or_default();
+        let action = row_text(row, "action").unwrap_or_default();
+        let status = row_text(row, "status").unwrap_or_default();
+        let entry = AuditEntry {
+            conn_id: &conn_id,
+            action: &action,
+            target_pid: row.try_get("target_pid").map_err(db_error)?,
+            status: &status,
+            message: row_text(row, "message"),
+        };
+        let hash = chain_hash(
+            &key,
+            &expected_prev,
+            seq,
+            &row_text(row, "id").unwrap_or_default(),
+            row.try_get("created_at").map_err(db_error)?,
+            &entry,
+        );
+        if row_text(row, "hash").as_deref() != Some(hash.as_str()) {
+            return Ok(broken("hash_mismatch"));
+        }
+        expected_prev = hash;
+        checked += 1;
+    }
+    Ok(AuditVerification {
+        ok: true,
+        checked,
+        unchained,
+        broken_at_seq: None,
+        reason: None,
+    })
+}
+
+#[tauri::command]
+pub async fn verify_ops_audit(store: State<'_, LocalStore>) -> Result<AuditVerification, String> {
+    verify(&store).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::with_store;
+    use sha2::Digest;
+
+    fn entry(target_pid: i64) -> AuditEntry<'static> {
+        AuditEntry {
+            conn_id: "c1",
+            action: "cancel",
+            target_pid: Some(target_pid),
+            status: "started",
+            message: None,
+        }
+    }
+
+    #[test]
+    fn hash_depends_on_key_previous_link_and_fields() {
+        let key = b"k1";
+        let first = chain_hash(key, GENESIS_HASH, 1, "a", 10, &entry(42));
+        assert_eq!(first, chain_hash(key, GENESIS_HASH, 1, "a", 10, &entry(42)));
+        assert_eq!(first.len(), 64);
+        assert_ne!(first, chain_hash(key, GENESIS_HASH, 1, "a", 10, &entry(43)));
+        assert_ne!(first, chain_hash(key, &first, 1, "a", 10, &entry(42)));
+        assert_ne!(
+            first,
+            chain_hash(b"k2", GENESIS_HASH, 1, "a", 10, &entry(42))
+        );
+    }
+
+    #[test]
+    fn rewritten_rows_with_plain_hashes_fail_verification() {
+        with_store(|store| async move {
+            for pid in [41, 42] {
+                append(&store, entry(pid)).await.unwrap();
+            }
+            assert!(verify(&store).await.unwrap().ok);
+
+            // Rewrite row 1 and re-chain both rows the way an unkeyed chain
+            // could be forged: sha256(prev_hash || canonical row).
+            let pool = store.pool().unwrap();
+            let rows = sqlx::query("SELECT id, seq, created_at FROM ops_audit ORDER BY seq")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+            let mut prev = GENESIS_HASH.to_string();
+            for row in &rows {
+                let seq: i64 = row.try_get("seq").unwrap();
+                let id = row_text(row, "id").unwrap();
+                let created_at: i64 = row.try_get("created_at").unwrap();
+                let forged = AuditEntry {
+                    target_pid: Some(if seq == 1 { 7 } else { 42 }),
+                    ..entry(0)
+                };
+                let canonical = json!([
+                    seq,
+                    id,
+                    forged.conn_id,
+                    forged.action,
+                    forged.target_pid,
+                    forged.status,
+                    forged.message,
+                    created_at
+                ])
+                .to_string();
+                let hash = hex::encode(Sha256::digest(format!("{}\n{}", prev, canonical)));
+                sqlx::query("UPDATE ops_audit SET target_pid = ?1, prev_hash = ?2, hash = ?3 WHERE seq = ?4")
+                    .bind(forged.target_pid)
+                    .bind(&prev)
+                    .bind(&hash)
+                    .bind(seq)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+                prev = hash;
+            }
+            let result = verify(&store).await.unwrap();
+            assert!(!result.ok);
+            assert_eq!(result.broken_at_seq, Some(1));
+            assert_eq!(result.reason.as_deref(), Some("hash_mismatch"));
+
+            std::fs::remove_file(audit_key_path(&store)).unwrap();
+            let result = verify(&store).await.unwrap();
+            assert_eq!(result.reason.as_deref(), Some("audit_key_missing"));
+        });
+    }
+}
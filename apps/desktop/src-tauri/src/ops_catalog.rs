@@ -0,0 +1,599 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sqlx::{Column, Executor};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+use std::time::Instant;
+use tauri::State;
+
+use crate::local_store::LocalStore;
+use crate::pg::{
+    begin_read_only, fetch_json, fetch_one_as, pg_error, strip_trailing_semicolons, PgPools,
+    ANALYSIS_STATEMENT_TIMEOUT_MS,
+};
+use crate::sql_guard::{is_read_only_sql, strip_sql_comments};
+
+/// Subdirectory of the app data dir scanned for user catalogs.
+pub const CATALOG_DIR: &str = "ops_actions";
+const BUILTIN_SOURCE: &str = "builtin";
+const BUILTIN_CATALOG: &str = include_str!("../ops_actions/builtin.toml");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamKind {
+    Integer,
+    Number,
+    Boolean,
+    Text,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ParamSpec {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: ParamKind,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub default: Option<Value>,
+    /// Inclusive bounds for `integer` and `number`.
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+    #[serde(default)]
+    pub max_length: Option<usize>,
+    /// Allowed values for `text`; empty means any.
+    #[serde(default)]
+    pub choices: Vec<String>,
+    /// Optional parameters without a default bind as NULL.
+    #[serde(default)]
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ActionSpec {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub params: Vec<ParamSpec>,
+    pub sql: String,
+    /// e.g. `"13"` or `"9.6"`.
+    #[serde(default)]
+    pub min_server_version: Option<String>,
+    /// Role the session user must be a member of; `superuser` requires
+    /// `rolsuper`.
+    #[serde(default)]
+    pub required_role: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogFile {
+    #[serde(default)]
+    actions: Vec<ActionSpec>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpsAction {
+    #[serde(flatten)]
+    pub spec: ActionSpec,
+    /// `builtin` or the file the action was loaded from.
+    pub source: String,
+    #[serde(skip)]
+    min_version_num: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogError {
+    pub source: String,
+    pub id: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OpsCatalogListing {
+    pub directory: Option<String>,
+    pub actions: Vec<OpsAction>,
+    /// Files or entries that were rejected; valid entries still load.
+    pub errors: Vec<CatalogError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpsActionResult {
+    pub action_id: String,
+    pub sql: String,
+    /// Bound values in `$n` order.
+    pub params: Vec<Value>,
+    pub columns: Vec<String>,
+    pub rows: Vec<Value>,
+    pub row_count: usize,
+    pub elapsed_ms: u128,
+}
+
+/// Catalog loaded at startup; `list_ops_actions` can reload it from disk.
+pub struct OpsCatalog {
+    dir: Option<PathBuf>,
+    listing: RwLock<OpsCatalogListing>,
+}
+
+impl OpsCatalog {
+    pub fn load(dir: Option<PathBuf>) -> Self {
+        let listing = load_catalog(dir.as_deref());
+        Self {
+            dir,
+            listing: RwLock::new(listing),
+        }
+    }
+
+    /// User catalog directory, when the app data dir is known.
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    pub fn reload(&self) -> Result<OpsCatalogListing, String> {
+        let listing = load_catalog(self.dir.as_deref());
+        *self
+            .listing
+            .write()
+            .map_err(|_| "ops_catalog_poisoned".to_string())? = listing.clone();
+        Ok(listing)
+    }
+
+    fn snapshot(&self) -> Result<OpsCatalogListing, String> {
+        self.listing
+            .read()
+            .map(|listing| listing.clone())
+            .map_err(|_| "ops_catalog_poisoned".to_string())
+    }
+
+    fn find(&self, id: &str) -> Result<OpsAction, String> {
+        self.listing
+            .read()
+            .map_err(|_| "ops_catalog_poisoned".to_string())?
+            .actions
+            .iter()
+            .find(|action| action.spec.id == id)
+            .cloned()
+            .ok_or_else(|| format!("ops_action_not_found:{}", id))
+    }
+}
+
+fn ident_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_]{0,62}$").expect("valid regex"))
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\$(\d+)").expect("valid regex"))
+}
+
+/// `"13"` → 130000, `"14.2"` → 140002, `"9.6"` → 90600.
+pub fn parse_server_version(version: &str) -> Option<i64> {
+    let parts: Vec<i64> = version
+        .trim()
+        .split('.')
+        .map(|part| part.parse::<i64>().ok())
+        .collect::<Option<_>>()?;
+    match parts.as_slice() {
+        [major] => Some(major * 10_000),
+        [major, minor] if *major >= 10 => Some(major * 10_000 + minor),
+        [major, minor] => Some(major * 10_000 + minor * 100),
+        [major, minor, patch] if *major < 10 => Some(major * 10_000 + minor * 100 + patch),
+        _ => None,
+    }
+}
+
+fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+/// Checks one supplied (or default) value against its spec and returns the
+/// JSON value to bind.
+fn coerce_param(param: &ParamSpec, value: &Value) -> Result<Value, String> {
+    let invalid = |reason: String| format!("invalid_param:{}: {}", param.name, reason);
+    if value.is_null() {
+        return if param.optional {
+            Ok(Value::Null)
+        } else {
+            Err(invalid("required".to_string()))
+        };
+    }
+    let check_bounds = |number: f64| {
+        if param.min.is_some_and(|min| number < min) || param.max.is_some_and(|max| number > max) {
+            Err(invalid(format!(
+                "{} is outside [{}, {}]",
+                number,
+                param.min.map_or("-inf".to_string(), |v| v.to_string()),
+                param.max.map_or("inf".to_string(), |v| v.to_string())
+            )))
+        } else {
+            Ok(())
+        }
+    };
+    match param.kind {
+        ParamKind::Integer => {
+            let number = as_f64(value).ok_or_else(|| invalid("expected an integer".into()))?;
+            if number.fract() != 0.0 || number.abs() > i64::MAX as f64 {
+                return Err(invalid("expected an integer".into()));
+            }
+            check_bounds(number)?;
+            Ok(json!(number as i64))
+        }
+        ParamKind::Number => {
+            let number = as_f64(value)
+                .filter(|n| n.is_finite())
+                .ok_or_else(|| invalid("expected a number".into()))?;
+            check_bounds(number)?;
+            Ok(json!(number))
+        }
+        ParamKind::Boolean => match value {
+            Value::Bool(flag) => Ok(json!(flag)),
+            Value::String(text) if text == "true" || text == "false" => Ok(json!(text == "true")),
+            _ => Err(invalid("expected a boolean".into())),
+        },
+        ParamKind::Text => {
+            let text = value
+                .as_str()
+                .ok_or_else(|| invalid("expected a string".into()))?;
+            if param
+                .max_length
+                .is_some_and(|max| text.chars().count() > max)
+            {
+                return Err(invalid("too long".into()));
+            }
+            if !param.choices.is_empty() && !param.choices.iter().any(|c| c == text) {
+                return Err(invalid(format!(
+                    "must be one of {}",
+                    param.choices.join(", ")
+                )));
+            }
+            Ok(json!(text))
+        }
+    }
+}
+
+/// Resolves the caller's params (keyed by name) into positional values,
+/// applying defaults. Unknown names are rejected so typos don't silently fall
+/// back to defaults.
+pub fn resolve_params(spec: &ActionSpec, input: &Map<String, Value>) -> Result<Vec<Value>, String> {
+    if let Some(unknown) = input
+        .keys()
+        .find(|key| !spec.params.iter().any(|p| &p.name == *key))
+    {
+        return Err(format!("unknown_param:{}", unknown));
+    }
+    spec.params
+        .iter()
+        .map(|param| {
+            let value = input
+                .get(&param.name)
+                .or(param.default.as_ref())
+                .cloned()
+                .unwrap_or(Value::Null);
+            coerce_param(param, &value)
+        })
+        .collect()
+}
+
+/// Load-time checks. The SQL must pass the read-only screen, be a single
+/// statement and use exactly `$1..$n` for the declared params.
+pub fn validate_action(spec: &ActionSpec) -> Result<Option<i64>, String> {
+    if !ident_regex().is_match(&spec.id) {
+        return Err("invalid_id".to_string());
+    }
+    if spec.title.trim().is_empty() {
+        return Err("missing_title".to_string());
+    }
+    if !is_read_only_sql(&spec.sql) {
+        return Err("sql_not_read_only".to_string());
+    }
+    let body = strip_sql_comments(&spec.sql);
+    if strip_trailing_semicolons(&body).contains(';') {
+        return Err("multiple_statements".to_string());
+    }
+    let mut used: Vec<usize> = placeholder_regex()
+        .captures_iter(&body)
+        .filter_map(|caps| caps[1].parse().ok())
+        .collect();
+    used.sort_unstable();
+    used.dedup();
+    let expected: Vec<usize> = (1..=spec.params.len()).collect();
+    if used != expected {
+        return Err(format!(
+            "placeholder_mismatch: {} params declared, SQL uses {:?}",
+            spec.params.len(),
+            used
+        ));
+    }
+    let mut names = HashSet::new();
+    for param in &spec.params {
+        if !ident_regex().is_match(&param.name) || !names.insert(param.name.as_str()) {
+            return Err(format!("invalid_param_name:{}", param.name));
+        }
+        if let (Some(min), Some(max)) = (param.min, param.max) {
+            if min > max {
+                return Err(format!("invalid_param_bounds:{}", param.name));
+            }
+        }
+        if let Some(default) = &param.default {
+            coerce_param(param, default)?;
+        }
+    }
+    if spec
+        .required_role
+        .as_deref()
+        .is_some_and(|role| role.trim().is_empty())
+    {
+        return Err("invalid_required_role".to_string());
+    }
+    spec.min_server_version
+        .as_deref()
+        .map(|version| {
+            parse_server_version(version).ok_or_else(|| "invalid_min_server_version".to_string())
+        })
+        .transpose()
+}
+
+fn parse_catalog(source: &str, text: &str, ext: &str) -> Result<Vec<ActionSpec>, String> {
+    let file: CatalogFile = match ext {
+        "toml" => toml::from_str(text).map_err(|err| err.to_string())?,
+        "yaml" | "yml" => serde_yaml::from_str(text).map_err(|err| err.to_string())?,
+        _ => return Err(format!("unsupported_catalog_format:{}", source)),
+    };
+    Ok(file.actions)
+}
+
+fn add_actions(listing: &mut OpsCatalogListing, source: &str, specs: Vec<ActionSpec>) {
+    for spec in specs {
+        let reject = |message: String| CatalogError {
+            source: source.to_string(),
+            id: Some(spec.id.clone()),
+            message,
+        };
+        if listing.actions.iter().any(|a| a.spec.id == spec.id) {
+            listing.errors.push(reject("duplicate_id".to_string()));
+            continue;
+        }
+        match validate_action(&spec) {
+            Ok(min_version_num) => listing.actions.push(OpsAction {
+                spec,
+                source: source.to_string(),
+                min_version_num,
+            }),
+            Err(message) => listing.errors.push(reject(message)),
+        }
+    }
+}
+
+pub fn catalog_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| {
+                    path.is_file()
+                        && matches!(
+                            path.extension().and_then(|e| e.to_str()),
+                            Some("toml" | "yaml" | "yml")
+                        )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// Built-ins first, then user files in name order. A broken file or entry is
+/// reported in `errors` and skipped; it never hides the rest of the catalog.
+pub fn load_catalog(dir: Option<&Path>) -> OpsCatalogListing {
+    let mut listing = OpsCatalogListing {
+        directory: dir.map(|d| d.display().to_string()),
+        ..Default::default()
+    };
+    match parse_catalog(BUILTIN_SOURCE, BUILTIN_CATALOG, "toml") {
+        Ok(specs) => add_actions(&mut listing, BUILTIN_SOURCE, specs),
+        Err(message) => listing.errors.push(CatalogError {
+            source: BUILTIN_SOURCE.to_string(),
+            id: None,
+            message,
+        }),
+    }
+    for path in dir.map(catalog_files).unwrap_or_default() {
+        let source = path.display().to_string();
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| parse_catalog(&source, &text, ext));
+        match parsed {
+            Ok(specs) => add_actions(&mut listing, &source, specs),
+            Err(message) => listing.errors.push(CatalogError {
+                source,
+                id: None,
+                message: format!("parse_failed: {}", message),
+            }),
+        }
+    }
+    listing
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionInfo {
+    server_version: i64,
+    has_role: bool,
+}
+
+const SESSION_INFO_SQL: &str = r#"
+SELECT
+  pg_catalog.current_setting('server_version_num')::bigint AS server_version,
+  CASE
+    WHEN $1::text IS NULL THEN true
+    WHEN $1::text = 'superuser' THEN (SELECT rolsuper FROM pg_catalog.pg_roles WHERE rolname = current_user)
+    WHEN EXISTS (SELECT 1 FROM pg_catalog.pg_roles WHERE rolname = $1::text)
+      THEN pg_catalog.pg_has_role(current_user, $1::text, 'member')
+    ELSE false
+  END AS has_role"#;
+
+#[tauri::command]
+pub async fn list_ops_actions(
+    catalog: State<'_, OpsCatalog>,
+    reload: Option<bool>,
+) -> Result<OpsCatalogListing, String> {
+    if reload.unwrap_or(false) {
+        catalog.reload()
+    } else {
+        catalog.snapshot()
+    }
+}
+
+/// Runs a catalog action inside a read-only transaction after checking the
+/// server version and role requirements.
+#[tauri::command]
+pub async fn run_ops_action(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    catalog: State<'_, OpsCatalog>,
+    conn_id: String,
+    action_id: String,
+    params: Option<Map<String, Value>>,
+) -> Result<OpsActionResult, String> {
+    let action = catalog.find(&action_id)?;
+    let values = resolve_params(&action.spec, &params.unwrap_or_default())?;
+    let pool = pools.pool_for(&store, &conn_id).await?;
+    let mut tx = begin_read_only(&pool, ANALYSIS_STATEMENT_TIMEOUT_MS).await?;
+
+    let session: SessionInfo = fetch_one_as(
+        &mut tx,
+        SESSION_INFO_SQL,
+        &[json!(action.spec.required_role)],
+    )
+    .await?
+    .ok_or_else(|| "ops_session_info_unavailable".to_string())?;
+    if let Some(min) = action.min_version_num {
+        if session.server_version < min {
+            return Err(format!(
+                "ops_action_requires_server:{} (connected to {})",
+                action
+                    .spec
+                    .min_server_version
+                    .as_deref()
+                    .unwrap_or_default(),
+                session.server_version
+            ));
+        }
+    }
+    if !session.has_role {
+        return Err(format!(
+            "ops_action_requires_role:{}",
+            action.spec.required_role.as_deref().unwrap_or_default()
+        ));
+    }
+
+    let sql = strip_trailing_semicolons(&action.spec.sql).to_string();
+    let columns = (&mut *tx)
+        .describe(&sql)
+        .await
+        .map_err(pg_error)?
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect();
+    let started = Instant::now();
+    let rows = fetch_json(&mut tx, &sql, &values).await?;
+    Ok(OpsActionResult {
+        action_id,
+        row_count: rows.len(),
+        elapsed_ms: started.elapsed().as_millis(),
+        sql,
+        params: values,
+        columns,
+        rows,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_catalog_loads_cleanly() {
+        let listing = load_catalog(None);
+        assert!(listing.errors.is_empty(), "{:?}", listing.errors);
+        let ids: Vec<&str> = listing.actions.iter().map(|a| a.spec.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "long_running_activity",
+                "blocking_activity",
+                "long_transactions",
+                "waiting_locks",
+                "connections_overview"
+            ]
+        );
+        let long_running = &listing.actions[0].spec;
+        let mut input = Map::new();
+        input.insert("limit".into(), json!("50"));
+        assert_eq!(
+            resolve_params(long_running, &input).unwrap(),
+            vec![json!(5), json!(50), json!(true)]
+        );
+        input.insert("minMinutes".into(), json!(0));
+        assert!(resolve_params(long_running, &input).is_err());
+    }
+
+    #[test]
+    fn rejects_writes_and_placeholder_gaps() {
+        let text = r#"
+[[actions]]
+id = "bad_write"
+title = "Bad"
+sql = "DELETE FROM t"
+
+[[actions]]
+id = "gap"
+title = "Gap"
+sql = "SELECT $2"
+
+[[actions.params]]
+name = "a"
+type = "integer"
+
+[[actions]]
+id = "ok"
+title = "Ok"
+min_server_version = "14"
+sql = "SELECT relname FROM pg_catalog.pg_class LIMIT $1"
+
+[[actions.params]]
+name = "limit"
+type = "integer"
+max = 10
+default = 20
+"#;
+        let mut listing = OpsCatalogListing::default();
+        add_actions(
+            &mut listing,
+            "x.toml",
+            parse_catalog("x.toml", text, "toml").unwrap(),
+        );
+        let errors: Vec<&str> = listing.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(errors[0], "sql_not_read_only");
+        assert!(errors[1].starts_with("placeholder_mismatch"));
+        assert!(errors[2].starts_with("invalid_param:limit"));
+        assert!(listing.actions.is_empty());
+        assert_eq!(parse_server_version("9.6"), Some(90600));
+        assert_eq!(parse_server_version("14.2"), Some(140002));
+    }
+}
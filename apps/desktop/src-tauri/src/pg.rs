@@ -0,0 +1,254 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use sqlx::postgres::{PgArguments, PgPool, PgPoolOptions};
+use sqlx::{Arguments, Executor, PgConnection, Postgres, Row, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::crypto::{aes_decrypt_to_string, AesCipher};
+use crate::local_store::{db_error, row_text, LocalStore};
+
+pub const DEVICE_KEY_PREF: &str = "device_aes_key_base64";
+/// Catalog scans (bloat, profiling samples) get a longer budget.
+pub const ANALYSIS_STATEMENT_TIMEOUT_MS: u64 = 30_000;
+const JSON_ROW_ALIAS: &str = "__rdv_row_json__";
+
+/// Postgres pools keyed by `user_connections.id`. A pool is rebuilt when the
+/// stored DSN changes or its read-only session default is toggled.
+#[derive(Default)]
+pub struct PgPools {
+    pools: Mutex<HashMap<String, (String, bool, PgPool)>>,
+    /// Connections whose sessions start read-only for the rest of the app
+    /// session, on top of profiles in strict read-only mode.
+    read_only: Mutex<HashSet<String>>,
+}
+
+impl PgPools {
+    pub async fn pool_for(&self, store: &LocalStore, conn_id: &str) -> Result<PgPool, String> {
+        let dsn = resolve_dsn(store, conn_id).await?;
+        let read_only =
+            strict_read_only(store, conn_id).await? || self.session_read_only(conn_id)?;
+        let mut pools = self
+            .pools
+            .lock()
+            .map_err(|_| "pg_pool_poisoned".to_string())?;
+        if let Some((cached_dsn, cached_read_only, pool)) = pools.get(conn_id) {
+            if *cached_dsn == dsn && *cached_read_only == read_only && !pool.is_closed() {
+                return Ok(pool.clone());
+            }
+        }
+        let mut options = PgPoolOptions::new()
+            .max_connections(3)
+            .acquire_timeout(Duration::from_secs(10));
+        if read_only {
+            options = options.after_connect(|conn, _meta| {
+                Box::pin(async move {
+                    conn.execute("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY")
+                        .await?;
+                    Ok(())
+                })
+            });
+        }
+        let pool = options
+            .connect_lazy(&dsn)
+            .map_err(|err| format!("invalid_dsn: {}", err))?;
+        if let Some((_, stale_read_only, stale)) =
+            pools.insert(conn_id.to_string(), (dsn, read_only, pool.clone()))
+        {
+            if stale_read_only != read_only {
+                // Idle connections in the old pool keep the old session default.
+                tauri::async_runtime::spawn(async move { stale.close().await });
+            }
+        }
+        Ok(pool)
+    }
+
+    pub fn session_read_only(&self, conn_id: &str) -> Result<bool, String> {
+        let read_only = self
+            .read_only
+            .lock()
+            .map_err(|_| "pg_pool_poisoned".to_string())?;
+        Ok(read_only.contains(conn_id))
+    }
+
+    /// Toggles the read-only session default; the next `pool_for` call builds
+    /// a fresh pool so every connection picks it up.
+    pub fn set_session_read_only(&self, conn_id: &str, enabled: bool) -> Result<(), String> {
+        let mut read_only = self
+            .read_only
+            .lock()
+            .map_err(|_| "pg_pool_poisoned".to_string())?;
+        if enabled {
+            read_only.insert(conn_id.to_string());
+        } else {
+            read_only.remove(conn_id);
+        }
+        Ok(())
+    }
+}
+
+/// `user_connections.strict_read_only`: every session of the profile starts
+/// read-only and the executor refuses statements that would undo it.
+pub async fn strict_read_only(store: &LocalStore, conn_id: &str) -> Result<bool, String> {
+    let row = sqlx::query("SELECT strict_read_only FROM user_connections WHERE id = ?1")
+        .bind(conn_id)
+        .fetch_optional(&store.pool()?)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| "connection_not_found".to_string())?;
+    Ok(row
+        .try_get::<i64, _>("strict_read_only")
+        .map_err(db_error)?
+        != 0)
+}
+
+/// Decrypts `user_connections.dsn_cipher` with the device key stored in
+/// `app_prefs`, like `getDsnForConn` in `lib/localStore.ts`.
+pub async fn resolve_dsn(store: &LocalStore, conn_id: &str) -> Result<String, String> {
+    let row = sqlx::query("SELECT dsn_cipher FROM user_connections WHERE id = ?1")
+        .bind(conn_id)
+        .fetch_optional(&store.pool()?)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| "connection_not_found".to_string())?;
+    let cipher_text = row_text(&row, "dsn_cipher")
+        .filter(|text| !text.trim().is_empty())
+        .ok_or_else(|| "dsn_cipher_missing".to_string())?;
+    let cipher: AesCipher = serde_json::from_str(&cipher_text)
+        .map_err(|err| format!("local_cipher_decrypt_failed: {}", err))?;
+    let key_row = sqlx::query("SELECT v FROM app_prefs WHERE k = ?1")
+        .bind(DEVICE_KEY_PREF)
+        .fetch_optional(&store.pool()?)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| "device_key_missing".to_string())?;
+    let key = row_text(&key_row, "v").ok_or_else(|| "device_key_missing".to_string())?;
+    aes_decrypt_to_string(&key, &cipher)
+}
+
+pub fn pg_error(err: sqlx::Error) -> String {
+    match err {
+        sqlx::Error::Database(db) => match db.code() {
+            Some(code) => format!("db_query_failed: [{}] {}", code, db.message()),
+            None => format!("db_query_failed: {}", db.message()),
+        },
+        other => format!("db_query_failed: {}", other),
+    }
+}
+
+/// Opens a read-only transaction with the same guards as
+/// `applySessionGuards` in `lib/db-session.ts`. Dropping the transaction
+/// rolls it back.
+pub async fn begin_read_only(
+    pool: &PgPool,
+    timeout_ms: u64,
+) -> Result<Transaction<'static, Postgres>, String> {
+    begin_guarded(pool, timeout_ms, true).await
+}
+
+/// `begin_read_only` without `SET TRANSACTION READ ONLY`: the transaction
+/// takes the session default, which is read-only for strict profiles.
+pub async fn begin_guarded(
+    pool: &PgPool,
+    timeout_ms: u64,
+    read_only: bool,
+) -> Result<Transaction<'static, Postgres>, String> {
+    let mut tx = pool.begin().await.map_err(pg_error)?;
+    let mut statements = vec![
+        format!("SET LOCAL statement_timeout = {}", timeout_ms),
+        format!(
+            "SET LOCAL idle_in_transaction_session_timeout = {}",
+            timeout_ms
+        ),
+        "SET LOCAL search_path = pg_catalog, \"$user\"".to_string(),
+    ];
+    if read_only {
+        statements.insert(0, "SET TRANSACTION READ ONLY".to_string());
+    }
+    for statement in statements {
+        sqlx::query(&statement)
+            .execute(&mut *tx)
+            .await
+            .map_err(pg_error)?;
+    }
+    Ok(tx)
+}
+
+/// Binds JSON values positionally: numbers as int8/float8, strings as text,
+/// objects and arrays as jsonb.
+pub fn pg_arguments(params: &[Value]) -> Result<PgArguments, String> {
+    let mut args = PgArguments::default();
+    for value in params {
+        let added = match value {
+            Value::Null => args.add(Option::<String>::None),
+            Value::Bool(flag) => args.add(*flag),
+            Value::Number(number) => match number.as_i64() {
+                Some(int) => args.add(int),
+                None => args.add(number.as_f64().unwrap_or_default()),
+            },
+            Value::String(text) => args.add(text.clone()),
+            other => args.add(sqlx::types::Json(other.clone())),
+        };
+        added.map_err(|err| format!("invalid_param: {}", err))?;
+    }
+    Ok(args)
+}
+
+pub fn strip_trailing_semicolons(sql: &str) -> &str {
+    sql.trim_end().trim_end_matches(';').trim_end()
+}
+
+/// Runs `sql` and returns each row as a JSON object. Rows are serialised by
+/// Postgres (`row_to_json`) so any column type survives the round trip, the
+/// same trick the webview uses for unsupported datatypes.
+pub async fn fetch_json(
+    conn: &mut PgConnection,
+    sql: &str,
+    params: &[Value],
+) -> Result<Vec<Value>, String> {
+    let wrapped = format!(
+        "SELECT row_to_json(__rdv_row_source__)::text AS {} FROM (\n{}\n) __rdv_row_source__",
+        JSON_ROW_ALIAS,
+        strip_trailing_semicolons(sql)
+    );
+    let rows: Vec<String> = sqlx::query_scalar_with(&wrapped, pg_arguments(params)?)
+        .fetch_all(conn)
+        .await
+        .map_err(pg_error)?;
+    rows.iter()
+        .map(|text| serde_json::from_str(text).map_err(|err| err.to_string()))
+        .collect()
+}
+
+pub async fn fetch_as<T: DeserializeOwned>(
+    conn: &mut PgConnection,
+    sql: &str,
+    params: &[Value],
+) -> Result<Vec<T>, String> {
+    fetch_json(conn, sql, params)
+        .await?
+        .into_iter()
+        .map(|row| serde_json::from_value(row).map_err(|err| format!("decode_failed: {}", err)))
+        .collect()
+}
+
+pub async fn fetch_one_as<T: DeserializeOwned>(
+    conn: &mut PgConnection,
+    sql: &str,
+    params: &[Value],
+) -> Result<Option<T>, String> {
+    Ok(fetch_as(conn, sql, params).await?.into_iter().next())
+}
+
+/// Splits `schema.table` (quotes optional); bare names default to `public`.
+pub fn split_qualified(name: &str) -> (String, String) {
+    let trimmed = name.trim();
+    match trimmed.split_once('.') {
+        Some((schema, table)) => (
+            schema.trim().trim_matches('"').to_string(),
+            table.trim().trim_matches('"').to_string(),
+        ),
+        None => ("public".to_string(), trimmed.trim_matches('"').to_string()),
+    }
+}
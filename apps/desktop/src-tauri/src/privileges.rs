@@ -0,0 +1,813 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgConnection;
+use std::cmp::Reverse;
+use tauri::State;
+
+use crate::local_store::{now_sec, LocalStore};
+use crate::pg::{begin_read_only, fetch_as, fetch_one_as, pg_error, strict_read_only, PgPools};
+use crate::replication::WarningLevel;
+
+const REPORT_STATEMENT_TIMEOUT_MS: u64 = 15_000;
+/// Tables and columns are listed writable-first; the counts in
+/// `TableSummary` always cover everything.
+const MAX_LISTED_TABLES: i64 = 2_000;
+const MAX_LISTED_COLUMNS: i64 = 2_000;
+const SAMPLE_NAMES: usize = 5;
+/// Predefined roles that reach outside the database.
+const SERVER_ACCESS_ROLES: [&str; 3] = [
+    "pg_execute_server_program",
+    "pg_read_server_files",
+    "pg_write_server_files",
+];
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RoleSession {
+    pub server_version: i64,
+    pub database: String,
+    pub session_role: String,
+    pub current_role: String,
+    pub in_recovery: bool,
+    /// Session default, not the report's own read-only transaction.
+    pub default_transaction_read_only: bool,
+    pub superuser: bool,
+    pub bypassrls: bool,
+    pub createrole: bool,
+    pub createdb: bool,
+    pub replication: bool,
+    pub inherit: bool,
+    pub can_login: bool,
+    pub connection_limit: i64,
+    pub valid_until: Option<String>,
+    pub db_connect: bool,
+    pub db_create: bool,
+    pub db_temporary: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RoleMembership {
+    pub role: String,
+    /// Role holding the grant: the current role for direct memberships.
+    pub via: String,
+    pub depth: i64,
+    pub admin_option: bool,
+    /// Privileges of `role` apply without `SET ROLE`.
+    pub inherits: bool,
+    pub can_set_role: bool,
+    pub superuser: bool,
+    pub bypassrls: bool,
+    pub createrole: bool,
+    pub createdb: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SchemaPrivilege {
+    pub schema: String,
+    pub owner: String,
+    pub is_owner: bool,
+    pub can_use: bool,
+    pub can_create: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TablePrivilege {
+    pub schema: String,
+    pub name: String,
+    /// `pg_class.relkind`: r, p, v, m or f.
+    pub kind: String,
+    pub owner: String,
+    pub is_owner: bool,
+    pub schema_usage: bool,
+    pub can_select: bool,
+    pub can_insert: bool,
+    pub can_update: bool,
+    pub can_delete: bool,
+    pub can_truncate: bool,
+    pub can_references: bool,
+    pub can_trigger: bool,
+    pub rls_enabled: bool,
+    pub rls_forced: bool,
+}
+
+impl TablePrivilege {
+    pub fn can_write(&self) -> bool {
+        self.is_owner || self.can_insert || self.can_update || self.can_delete || self.can_truncate
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TableSummary {
+    pub visible: i64,
+    pub writable: i64,
+    pub owned: i64,
+    pub rls_enabled: i64,
+}
+
+/// Column grants the role holds without the matching table-level privilege.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ColumnPrivilege {
+    pub schema: String,
+    pub table_name: String,
+    pub column_name: String,
+    pub can_select: bool,
+    pub can_insert: bool,
+    pub can_update: bool,
+    pub can_references: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RlsPolicy {
+    pub schema: String,
+    pub table_name: String,
+    pub policy: String,
+    /// `PERMISSIVE` or `RESTRICTIVE`.
+    pub permissive: String,
+    pub roles: Vec<String>,
+    pub command: String,
+    pub using_expr: Option<String>,
+    pub check_expr: Option<String>,
+    /// False when RLS is off for the table or the role bypasses it (superuser,
+    /// BYPASSRLS, or owner without FORCE ROW LEVEL SECURITY).
+    pub enforced: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PrivilegeFinding {
+    /// `superuser`, `member_of_superuser`, `bypassrls`, `createrole`,
+    /// `createdb`, `replication`, `server_access`, `table_write`,
+    /// `table_owner`, `schema_create`, `database_create`, `rls_bypassed`,
+    /// `read_only_overridable` or `not_read_only`.
+    pub kind: String,
+    pub level: WarningLevel,
+    pub subject: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WriteAccess {
+    /// No write path at all: a standby, or a role without write privileges.
+    pub effectively_read_only: bool,
+    /// Transactions start read-only (standby or `default_transaction_read_only`).
+    pub session_read_only: bool,
+    /// The app's own pools already start sessions read-only.
+    pub pool_read_only: bool,
+    /// Offer `set_session_read_only` to the user.
+    pub can_enable_session_read_only: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PrivilegeReport {
+    pub session: RoleSession,
+    pub memberships: Vec<RoleMembership>,
+    pub schemas: Vec<SchemaPrivilege>,
+    pub table_summary: TableSummary,
+    pub tables: Vec<TablePrivilege>,
+    pub tables_truncated: bool,
+    pub columns: Vec<ColumnPrivilege>,
+    pub columns_truncated: bool,
+    pub policies: Vec<RlsPolicy>,
+    pub write_access: WriteAccess,
+    pub level: Option<WarningLevel>,
+    pub findings: Vec<PrivilegeFinding>,
+    pub generated_at: i64,
+}
+
+const SCHEMA_FILTER: &str = "n.nspname NOT IN ('pg_catalog', 'information_schema') \
+     AND n.nspname !~ '^pg_(toast|temp_)'";
+
+/// `rolbypassrls` and row security exist from PostgreSQL 9.5.
+fn has_rls(server_version: i64) -> bool {
+    server_version >= 90_500
+}
+
+fn session_sql(server_version: i64) -> String {
+    let bypassrls = if has_rls(server_version) {
+        "r.rolbypassrls"
+    } else {
+        "false"
+    };
+    format!(
+        r#"
+SELECT
+  pg_catalog.current_setting('server_version_num')::bigint AS server_version,
+  pg_catalog.current_database()::text AS database,
+  session_user::text AS session_role,
+  current_user::text AS current_role,
+  pg_catalog.pg_is_in_recovery() AS in_recovery,
+  pg_catalog.current_setting('default_transaction_read_only') = 'on' AS default_transaction_read_only,
+  r.rolsuper AS superuser,
+  {bypassrls} AS bypassrls,
+  r.rolcreaterole AS createrole,
+  r.rolcreatedb AS createdb,
+  r.rolreplication AS replication,
+  r.rolinherit AS inherit,
+  r.rolcanlogin AS can_login,
+  r.rolconnlimit::bigint AS connection_limit,
+  r.rolvaliduntil::text AS valid_until,
+  pg_catalog.has_database_privilege(pg_catalog.current_database(), 'CONNECT') AS db_connect,
+  pg_catalog.has_database_privilege(pg_catalog.current_database(), 'CREATE') AS db_create,
+  pg_catalog.has_database_privilege(pg_catalog.current_database(), 'TEMPORARY') AS db_temporary
+FROM pg_catalog.pg_roles r
+WHERE r.rolname = current_user"#
+    )
+}
+
+/// Walks `pg_auth_members` upwards from the current role, keeping the
+/// shortest path to each granted role. PostgreSQL 16 split `SET ROLE` from
+/// membership, so `can_set_role` asks for the `SET` privilege there.
+fn memberships_sql(server_version: i64) -> String {
+    let bypassrls = if has_rls(server_version) {
+        "g.rolbypassrls"
+    } else {
+        "false"
+    };
+    let set_mode = if server_version >= 160_000 {
+        "SET"
+    } else {
+        "MEMBER"
+    };
+    format!(
+        r#"
+WITH RECURSIVE m AS (
+  SELECT am.roleid, am.member, am.admin_option, 1 AS depth
+  FROM pg_catalog.pg_auth_members am
+  WHERE am.member = (SELECT oid FROM pg_catalog.pg_roles WHERE rolname = current_user)
+  UNION ALL
+  SELECT am.roleid, am.member, am.admin_option, m.depth + 1
+  FROM pg_catalog.pg_auth_members am
+  JOIN m ON am.member = m.roleid
+  WHERE m.depth < 16
+)
+SELECT DISTINCT ON (g.rolname)
+  g.rolname::text AS role,
+  v.rolname::text AS via,
+  m.depth::bigint AS depth,
+  m.admin_option,
+  pg_catalog.pg_has_role(g.oid, 'USAGE') AS inherits,
+  pg_catalog.pg_has_role(g.oid, '{set_mode}') AS can_set_role,
+  g.rolsuper AS superuser,
+  {bypassrls} AS bypassrls,
+  g.rolcreaterole AS createrole,
+  g.rolcreatedb AS createdb
+FROM m
+JOIN pg_catalog.pg_roles g ON g.oid = m.roleid
+JOIN pg_catalog.pg_roles v ON v.oid = m.member
+ORDER BY g.rolname, m.depth"#
+    )
+}
+
+fn schemas_sql() -> String {
+    format!(
+        r#"
+SELECT
+  n.nspname::text AS schema,
+  pg_catalog.pg_get_userbyid(n.nspowner)::text AS owner,
+  pg_catalog.pg_has_role(n.nspowner, 'USAGE') AS is_owner,
+  pg_catalog.has_schema_privilege(n.oid, 'USAGE') AS can_use,
+  pg_catalog.has_schema_privilege(n.oid, 'CREATE') AS can_create
+FROM pg_catalog.pg_namespace n
+WHERE {SCHEMA_FILTER}
+ORDER BY n.nspname"#
+    )
+}
+
+/// Ownership counts through role inheritance: owners can ALTER and DROP
+/// whatever the grants say.
+fn table_privileges_sql(server_version: i64) -> String {
+    let (rls_enabled, rls_forced) = if has_rls(server_version) {
+        ("c.relrowsecurity", "c.relforcerowsecurity")
+    } else {
+        ("false", "false")
+    };
+    format!(
+        r#"
+SELECT
+  n.nspname::text AS schema,
+  c.relname::text AS name,
+  c.relkind::text AS kind,
+  pg_catalog.pg_get_userbyid(c.relowner)::text AS owner,
+  pg_catalog.pg_has_role(c.relowner, 'USAGE') AS is_owner,
+  pg_catalog.has_schema_privilege(n.oid, 'USAGE') AS schema_usage,
+  pg_catalog.has_table_privilege(c.oid, 'SELECT') AS can_select,
+  pg_catalog.has_table_privilege(c.oid, 'INSERT') AS can_insert,
+  pg_catalog.has_table_privilege(c.oid, 'UPDATE') AS can_update,
+  pg_catalog.has_table_privilege(c.oid, 'DELETE') AS can_delete,
+  pg_catalog.has_table_privilege(c.oid, 'TRUNCATE') AS can_truncate,
+  pg_catalog.has_table_privilege(c.oid, 'REFERENCES') AS can_references,
+  pg_catalog.has_table_privilege(c.oid, 'TRIGGER') AS can_trigger,
+  {rls_enabled} AS rls_enabled,
+  {rls_forced} AS rls_forced
+FROM pg_catalog.pg_class c
+JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+WHERE c.relkind IN ('r', 'p', 'v', 'm', 'f')
+  AND {SCHEMA_FILTER}"#
+    )
+}
+
+const TABLE_WRITE_EXPR: &str =
+    "(t.is_owner OR t.can_insert OR t.can_update OR t.can_delete OR t.can_truncate)";
+const TABLE_ANY_EXPR: &str = "(t.is_owner OR t.can_select OR t.can_insert OR t.can_update \
+     OR t.can_delete OR t.can_truncate OR t.can_references OR t.can_trigger)";
+
+fn tables_sql(server_version: i64) -> String {
+    format!(
+        "SELECT t.* FROM ({}\n) t\nWHERE {TABLE_ANY_EXPR}\nORDER BY {TABLE_WRITE_EXPR} DESC, t.schema, t.name\nLIMIT $1",
+        table_privileges_sql(server_version)
+    )
+}
+
+fn table_summary_sql(server_version: i64) -> String {
+    format!(
+        r#"
+SELECT
+  count(*) FILTER (WHERE {TABLE_ANY_EXPR})::bigint AS visible,
+  count(*) FILTER (WHERE {TABLE_WRITE_EXPR})::bigint AS writable,
+  count(*) FILTER (WHERE t.is_owner)::bigint AS owned,
+  count(*) FILTER (WHERE t.rls_enabled)::bigint AS rls_enabled
+FROM ({}
+) t"#,
+        table_privileges_sql(server_version)
+    )
+}
+
+fn columns_sql() -> String {
+    format!(
+        r#"
+SELECT t.* FROM (
+  SELECT
+    n.nspname::text AS schema,
+    c.relname::text AS table_name,
+    a.attname::text AS column_name,
+    pg_catalog.has_column_privilege(c.oid, a.attnum, 'SELECT')
+      AND NOT pg_catalog.has_table_privilege(c.oid, 'SELECT') AS can_select,
+    pg_catalog.has_column_privilege(c.oid, a.attnum, 'INSERT')
+      AND NOT pg_catalog.has_table_privilege(c.oid, 'INSERT') AS can_insert,
+    pg_catalog.has_column_privilege(c.oid, a.attnum, 'UPDATE')
+      AND NOT pg_catalog.has_table_privilege(c.oid, 'UPDATE') AS can_update,
+    pg_catalog.has_column_privilege(c.oid, a.attnum, 'REFERENCES')
+      AND NOT pg_catalog.has_table_privilege(c.oid, 'REFERENCES') AS can_references
+  FROM pg_catalog.pg_attribute a
+  JOIN pg_catalog.pg_class c ON c.oid = a.attrelid
+  JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+  WHERE a.attacl IS NOT NULL
+    AND a.attnum > 0
+    AND NOT a.attisdropped
+    AND {SCHEMA_FILTER}
+) t
+WHERE t.can_select OR t.can_insert OR t.can_update OR t.can_references
+ORDER BY t.can_insert OR t.can_update DESC, t.schema, t.table_name, t.column_name
+LIMIT $1"#
+    )
+}
+
+/// Policies naming `public` or any role the current role belongs to.
+/// `pg_policies.permissive` exists from PostgreSQL 10.
+fn policies_sql(server_version: i64) -> String {
+    let permissive = if server_version >= 100_000 {
+        "p.permissive::text"
+    } else {
+        "'PERMISSIVE'"
+    };
+    format!(
+        r#"
+SELECT
+  p.schemaname::text AS schema,
+  p.tablename::text AS table_name,
+  p.policyname::text AS policy,
+  {permissive} AS permissive,
+  p.roles::text[] AS roles,
+  p.cmd::text AS command,
+  p.qual::text AS using_expr,
+  p.with_check::text AS check_expr,
+  (c.relrowsecurity
+    AND NOT r.rolsuper
+    AND NOT r.rolbypassrls
+    AND (c.relforcerowsecurity OR NOT pg_catalog.pg_has_role(c.relowner, 'USAGE'))) AS enforced
+FROM pg_catalog.pg_policies p
+JOIN pg_catalog.pg_namespace n ON n.nspname = p.schemaname
+JOIN pg_catalog.pg_class c ON c.relnamespace = n.oid AND c.relname = p.tablename
+CROSS JOIN (
+  SELECT rolsuper, rolbypassrls FROM pg_catalog.pg_roles WHERE rolname = current_user
+) r
+WHERE EXISTS (
+  SELECT 1 FROM pg_catalog.unnest(p.roles) AS pr(name)
+  WHERE pr.name = 'public' OR pg_catalog.pg_has_role(pr.name, 'MEMBER')
+)
+ORDER BY p.schemaname, p.tablename, p.policyname"#
+    )
+}
+
+fn finding(kind: &str, level: WarningLevel, subject: &str, message: String) -> PrivilegeFinding {
+    PrivilegeFinding {
+        kind: kind.to_string(),
+        level,
+        subject: subject.to_string(),
+        message,
+    }
+}
+
+fn sample(names: &[String], total: i64) -> String {
+    let shown = names.iter().take(SAMPLE_NAMES).cloned().collect::<Vec<_>>();
+    let rest = total - shown.len() as i64;
+    if rest > 0 {
+        format!("{} and {} more", shown.join(", "), rest)
+    } else {
+        shown.join(", ")
+    }
+}
+
+/// Decides whether the connection can change anything and lists why.
+/// Findings are sorted most severe first.
+pub fn evaluate(
+    session: &RoleSession,
+    memberships: &[RoleMembership],
+    schemas: &[SchemaPrivilege],
+    summary: &TableSummary,
+    tables: &[TablePrivilege],
+    pool_read_only: bool,
+) -> (WriteAccess, Vec<PrivilegeFinding>) {
+    let role = session.current_role.as_str();
+    let mut findings = Vec::new();
+
+    if session.superuser {
+        findings.push(finding(
+            "superuser",
+            WarningLevel::Critical,
+            role,
+            format!(
+                "{} is a superuser: privilege checks and row security do not apply",
+                role
+            ),
+        ));
+    }
+    let superuser_roles: Vec<&RoleMembership> = memberships
+        .iter()
+        .filter(|m| m.superuser && (m.inherits || m.can_set_role))
+        .collect();
+    for member in &superuser_roles {
+        findings.push(finding(
+            "member_of_superuser",
+            WarningLevel::Critical,
+            &member.role,
+            format!("{} can SET ROLE to superuser {}", role, member.role),
+        ));
+    }
+    let bypass_via = memberships
+        .iter()
+        .find(|m| m.bypassrls && !m.superuser && m.can_set_role)
+        .map(|m| m.role.clone());
+    if !session.superuser && (session.bypassrls || bypass_via.is_some()) {
+        let message = match &bypass_via {
+            Some(via) if !session.bypassrls => {
+                format!(
+                    "{} can SET ROLE to {}, which bypasses row security",
+                    role, via
+                )
+            }
+            _ => format!("{} bypasses every row security policy", role),
+        };
+        findings.push(finding("bypassrls", WarningLevel::Warning, role, message));
+    }
+    if session.createrole || memberships.iter().any(|m| m.createrole && m.can_set_role) {
+        findings.push(finding(
+            "createrole",
+            WarningLevel::Warning,
+            role,
+            format!("{} can create and alter roles", role),
+        ));
+    }
+    if session.createdb {
+        findings.push(finding(
+            "createdb",
+            WarningLevel::Info,
+            role,
+            format!("{} can create databases", role),
+        ));
+    }
+    if session.replication {
+        findings.push(finding(
+            "replication",
+            WarningLevel::Info,
+            role,
+            format!("{} can open replication connections", role),
+        ));
+    }
+    for member in memberships
+        .iter()
+        .filter(|m| SERVER_ACCESS_ROLES.contains(&m.role.as_str()))
+    {
+        findings.push(finding(
+            "server_access",
+            WarningLevel::Warning,
+            &member.role,
+            format!(
+                "{} can act on the server host through {}",
+                role, member.role
+            ),
+        ));
+    }
+
+    let writable_names: Vec<String> = tables
+        .iter()
+        .filter(|t| t.can_write())
+        .map(|t| format!("{}.{}", t.schema, t.name))
+        .collect();
+    if summary.writable > 0 && !session.superuser {
+        findings.push(finding(
+            "table_write",
+            WarningLevel::Warning,
+            role,
+            format!(
+                "Can modify {} table(s): {}",
+                summary.writable,
+                sample(&writable_names, summary.writable)
+            ),
+        ));
+    }
+    if summary.owned > 0 && !session.superuser {
+        let owned_names: Vec<String> = tables
+            .iter()
+            .filter(|t| t.is_owner)
+            .map(|t| format!("{}.{}", t.schema, t.name))
+            .collect();
+        findings.push(finding(
+            "table_owner",
+            WarningLevel::Warning,
+            role,
+            format!(
+                "Owns {} table(s) and may ALTER or DROP them: {}",
+                summary.owned,
+                sample(&owned_names, summary.owned)
+            ),
+        ));
+    }
+    let creatable: Vec<String> = schemas
+        .iter()
+        .filter(|s| s.can_create)
+        .map(|s| s.schema.clone())
+        .collect();
+    if !creatable.is_empty() && !session.superuser {
+        findings.push(finding(
+            "schema_create",
+            WarningLevel::Warning,
+            role,
+            format!(
+                "Can create objects in schema(s): {}",
+                sample(&creatable, creatable.len() as i64)
+            ),
+        ));
+    }
+    if session.db_create && !session.superuser {
+        findings.push(finding(
+            "database_create",
+            WarningLevel::Info,
+            &session.database,
+            format!("Can create schemas in database {}", session.database),
+        ));
+    }
+    let bypassed: Vec<String> = tables
+        .iter()
+        .filter(|t| t.rls_enabled && t.is_owner && !t.rls_forced && !session.superuser)
+        .map(|t| format!("{}.{}", t.schema, t.name))
+        .collect();
+    if !bypassed.is_empty() {
+        findings.push(finding(
+            "rls_bypassed",
+            WarningLevel::Info,
+            role,
+            format!(
+                "Row security is skipped on owned table(s) without FORCE: {}",
+                sample(&bypassed, bypassed.len() as i64)
+            ),
+        ));
+    }
+
+    let can_write = session.superuser
+        || !superuser_roles.is_empty()
+        || summary.writable > 0
+        || !creatable.is_empty()
+        || session.db_create;
+    let effectively_read_only = session.in_recovery || !can_write;
+    let session_read_only =
+        session.in_recovery || session.default_transaction_read_only || pool_read_only;
+    if session.in_recovery {
+        // Nothing can be written on a hot standby, whatever the grants say.
+        for f in findings.iter_mut().filter(|f| f.level > WarningLevel::Info) {
+            if f.kind != "superuser" && f.kind != "member_of_superuser" {
+                f.level = WarningLevel::Info;
+            }
+        }
+    } else if !effectively_read_only {
+        if session_read_only {
+            findings.push(finding(
+                "read_only_overridable",
+                WarningLevel::Warning,
+                role,
+                "Sessions start read-only, but the role can still write after SET default_transaction_read_only = off".to_string(),
+            ));
+        } else {
+            findings.push(finding(
+                "not_read_only",
+                WarningLevel::Critical,
+                role,
+                format!(
+                    "The connection can modify {}; consider enabling default_transaction_read_only for this session",
+                    session.database
+                ),
+            ));
+        }
+    }
+    findings.sort_by_key(|f| Reverse(f.level));
+    let write_access = WriteAccess {
+        effectively_read_only,
+        session_read_only,
+        pool_read_only,
+        can_enable_session_read_only: !session.in_recovery && !pool_read_only,
+    };
+    (write_access, findings)
+}
+
+pub async fn collect_report(
+    conn: &mut PgConnection,
+    pool_read_only: bool,
+) -> Result<PrivilegeReport, String> {
+    let version: i64 =
+        sqlx::query_scalar("SELECT pg_catalog.current_setting('server_version_num')::bigint")
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(pg_error)?;
+    let session: RoleSession = fetch_one_as(conn, &session_sql(version), &[])
+        .await?
+        .ok_or_else(|| "role_not_found".to_string())?;
+    let memberships: Vec<RoleMembership> = fetch_as(conn, &memberships_sql(version), &[]).await?;
+    let schemas: Vec<SchemaPrivilege> = fetch_as(conn, &schemas_sql(), &[]).await?;
+    let table_summary: TableSummary = fetch_one_as(conn, &table_summary_sql(version), &[])
+        .await?
+        .unwrap_or_default();
+    let mut tables: Vec<TablePrivilege> =
+        fetch_as(conn, &tables_sql(version), &[json!(MAX_LISTED_TABLES + 1)]).await?;
+    let tables_truncated = tables.len() as i64 > MAX_LISTED_TABLES;
+    tables.truncate(MAX_LISTED_TABLES as usize);
+    let mut columns: Vec<ColumnPrivilege> =
+        fetch_as(conn, &columns_sql(), &[json!(MAX_LISTED_COLUMNS + 1)]).await?;
+    let columns_truncated = columns.len() as i64 > MAX_LISTED_COLUMNS;
+    columns.truncate(MAX_LISTED_COLUMNS as usize);
+    let policies: Vec<RlsPolicy> = if has_rls(version) {
+        fetch_as(conn, &policies_sql(version), &[]).await?
+    } else {
+        Vec::new()
+    };
+    let (write_access, findings) = evaluate(
+        &session,
+        &memberships,
+        &schemas,
+        &table_summary,
+        &tables,
+        pool_read_only,
+    );
+    Ok(PrivilegeReport {
+        session,
+        memberships,
+        schemas,
+        table_summary,
+        tables,
+        tables_truncated,
+        columns,
+        columns_truncated,
+        policies,
+        write_access,
+        level: findings.first().map(|f| f.level),
+        findings,
+        generated_at: now_sec(),
+    })
+}
+
+#[tauri::command]
+pub async fn role_privileges_report(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    conn_id: String,
+) -> Result<PrivilegeReport, String> {
+    let pool_read_only =
+        strict_read_only(&store, &conn_id).await? || pools.session_read_only(&conn_id)?;
+    let pool = pools.pool_for(&store, &conn_id).await?;
+    let mut tx = begin_read_only(&pool, REPORT_STATEMENT_TIMEOUT_MS).await?;
+    collect_report(&mut tx, pool_read_only).await
+}
+
+/// Starts every new session of this connection read-only until the app
+/// exits: the Rust pools via `after_connect`, webview sessions because
+/// `openSession` asks `session_read_only` first. Strict read-only profiles
+/// are read-only regardless. A guard, not a permission: a role with write
+/// privileges can still `SET` it off.
+#[tauri::command]
+pub async fn set_session_read_only(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    conn_id: String,
+    enabled: bool,
+) -> Result<PrivilegeReport, String> {
+    pools.set_session_read_only(&conn_id, enabled)?;
+    let pool = pools.pool_for(&store, &conn_id).await?;
+    let mut tx = begin_read_only(&pool, REPORT_STATEMENT_TIMEOUT_MS).await?;
+    let pool_read_only = enabled || strict_read_only(&store, &conn_id).await?;
+    collect_report(&mut tx, pool_read_only).await
+}
+
+#[tauri::command]
+pub fn session_read_only(pools: State<'_, PgPools>, conn_id: String) -> Result<bool, String> {
+    pools.session_read_only(&conn_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> RoleSession {
+        RoleSession {
+            server_version: 150_000,
+            database: "app".into(),
+            session_role: "reporter".into(),
+            current_role: "reporter".into(),
+            in_recovery: false,
+            default_transaction_read_only: false,
+            superuser: false,
+            bypassrls: false,
+            createrole: false,
+            createdb: false,
+            replication: false,
+            inherit: true,
+            can_login: true,
+            connection_limit: -1,
+            valid_until: None,
+            db_connect: true,
+            db_create: false,
+            db_temporary: true,
+        }
+    }
+
+    fn table(name: &str, can_insert: bool) -> TablePrivilege {
+        TablePrivilege {
+            schema: "public".into(),
+            name: name.into(),
+            kind: "r".into(),
+            owner: "app".into(),
+            is_owner: false,
+            schema_usage: true,
+            can_select: true,
+            can_insert,
+            can_update: false,
+            can_delete: false,
+            can_truncate: false,
+            can_references: false,
+            can_trigger: false,
+            rls_enabled: false,
+            rls_forced: false,
+        }
+    }
+
+    #[test]
+    fn select_only_role_is_read_only() {
+        let summary = TableSummary {
+            visible: 2,
+            ..TableSummary::default()
+        };
+        let tables = [table("orders", false), table("users", false)];
+        let (access, findings) = evaluate(&session(), &[], &[], &summary, &tables, false);
+        assert!(access.effectively_read_only);
+        assert!(!access.session_read_only);
+        assert!(access.can_enable_session_read_only);
+        assert!(findings.is_empty());
+    }
+
+    #[test]
+    fn write_grants_warn_until_session_is_read_only() {
+        let summary = TableSummary {
+            visible: 2,
+            writable: 1,
+            ..TableSummary::default()
+        };
+        let tables = [table("orders", true), table("users", false)];
+        let (access, findings) = evaluate(&session(), &[], &[], &summary, &tables, false);
+        assert!(!access.effectively_read_only);
+        assert_eq!(findings[0].kind, "not_read_only");
+        assert_eq!(findings[0].level, WarningLevel::Critical);
+        assert!(findings.iter().any(|f| f.kind == "table_write"
+            && f.message.contains("public.orders")
+            && !f.message.contains("public.users")));
+
+        let (access, findings) = evaluate(&session(), &[], &[], &summary, &tables, true);
+        assert!(access.session_read_only);
+        assert!(!access.can_enable_session_read_only);
+        assert!(findings.iter().all(|f| f.level == WarningLevel::Warning));
+        assert!(findings.iter().any(|f| f.kind == "read_only_overridable"));
+
+        let standby = RoleSession {
+            in_recovery: true,
+            ..session()
+        };
+        let (access, findings) = evaluate(&standby, &[], &[], &summary, &tables, false);
+        assert!(access.effectively_read_only);
+        assert!(findings.iter().all(|f| f.level == WarningLevel::Info));
+    }
+}
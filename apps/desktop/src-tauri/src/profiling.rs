@@ -0,0 +1,480 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::PgConnection;
+use tauri::State;
+
+use crate::local_store::{db_error, now_sec, row_text, LocalStore};
+use crate::pg::{
+    begin_read_only, fetch_as, fetch_one_as, split_qualified, PgPools,
+    ANALYSIS_STATEMENT_TIMEOUT_MS,
+};
+use crate::schema_cache::{quote_ident, quote_qualified};
+
+const DEFAULT_TOP_N: usize = 5;
+const DEFAULT_SAMPLE_ROWS: i64 = 10_000;
+const MAX_SAMPLED_COLUMNS: usize = 64;
+const MAX_MOST_COMMON: usize = 10;
+const SAMPLE_SEED: i64 = 20_240_601;
+
+#[derive(Debug, Deserialize)]
+struct RelationInfo {
+    schema: String,
+    table: String,
+    oid: i64,
+    reltuples: f64,
+    relpages: i64,
+    total_bytes: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ColumnStatsRow {
+    column: String,
+    data_type: String,
+    type_category: String,
+    not_null: bool,
+    null_frac: Option<f64>,
+    n_distinct: Option<f64>,
+    avg_width: Option<i64>,
+    correlation: Option<f64>,
+    most_common_vals: Option<Vec<Option<String>>>,
+    most_common_freqs: Option<Vec<f64>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ValueFrequency {
+    pub value: Option<String>,
+    pub frequency: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ValueCount {
+    pub value: Option<String>,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LengthBucket {
+    /// Inclusive upper bound of the bucket (powers of two).
+    pub max_length: i64,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ColumnSample {
+    pub non_null: i64,
+    pub null_percent: f64,
+    pub distinct: i64,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub top_values: Vec<ValueCount>,
+    pub length_histogram: Vec<LengthBucket>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ColumnProfile {
+    pub name: String,
+    pub data_type: String,
+    pub not_null: bool,
+    pub null_frac: Option<f64>,
+    /// Raw `pg_stats.n_distinct`; negative values are a fraction of rows.
+    pub n_distinct: Option<f64>,
+    pub distinct_estimate: Option<f64>,
+    pub avg_width: Option<i64>,
+    pub correlation: Option<f64>,
+    pub most_common: Vec<ValueFrequency>,
+    #[serde(default)]
+    pub sample: Option<ColumnSample>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TableProfile {
+    pub schema: String,
+    pub table: String,
+    pub reltuples: f64,
+    pub relpages: i64,
+    pub total_bytes: i64,
+    pub analyzed: bool,
+    pub sample_percent: Option<f64>,
+    pub sampled_rows: Option<i64>,
+    pub columns: Vec<ColumnProfile>,
+    pub profiled_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProfileOptions {
+    /// `TABLESAMPLE SYSTEM` percentage; statistics only when absent.
+    #[serde(default)]
+    pub sample_percent: Option<f64>,
+    #[serde(default)]
+    pub sample_rows: Option<i64>,
+    #[serde(default)]
+    pub top_n: Option<usize>,
+}
+
+const RELATION_SQL: &str = r#"
+SELECT
+  n.nspname AS schema,
+  c.relname AS "table",
+  c.oid::bigint AS oid,
+  GREATEST(c.reltuples, 0)::float8 AS reltuples,
+  c.relpages::bigint AS relpages,
+  pg_catalog.pg_total_relation_size(c.oid)::bigint AS total_bytes
+FROM pg_catalog.pg_class c
+JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+WHERE n.nspname = $1
+  AND c.relname = $2
+  AND c.relkind IN ('r', 'p', 'm', 'f')"#;
+
+const COLUMN_STATS_SQL: &str = r#"
+SELECT
+  a.attname AS "column",
+  pg_catalog.format_type(a.atttypid, a.atttypmod) AS data_type,
+  t.typcategory::text AS type_category,
+  a.attnotnull AS not_null,
+  s.null_frac::float8 AS null_frac,
+  s.n_distinct::float8 AS n_distinct,
+  s.avg_width::bigint AS avg_width,
+  s.correlation::float8 AS correlation,
+  s.most_common_vals::text::text[] AS most_common_vals,
+  s.most_common_freqs::float8[] AS most_common_freqs
+FROM pg_catalog.pg_attribute a
+JOIN pg_catalog.pg_type t ON t.oid = a.atttypid
+LEFT JOIN pg_catalog.pg_stats s
+  ON s.schemaname = $1 AND s.tablename = $2 AND s.attname = a.attname
+WHERE a.attrelid = $3::bigint::oid
+  AND a.attnum > 0
+  AND NOT a.attisdropped
+ORDER BY a.attnum"#;
+
+fn distinct_estimate(n_distinct: Option<f64>, reltuples: f64) -> Option<f64> {
+    n_distinct.map(|n| if n < 0.0 { (-n * reltuples).round() } else { n })
+}
+
+/// Columns whose type has `min`/`max` aggregates: numbers (but not the
+/// `reg*` OID aliases), date/times, strings and intervals. uuid has a btree
+/// ordering but no `min`/`max`.
+fn is_orderable(stats: &ColumnStatsRow) -> bool {
+    match stats.type_category.as_str() {
+        "N" => !stats.data_type.starts_with("reg"),
+        "D" | "S" | "T" => true,
+        _ => false,
+    }
+}
+
+fn has_length_histogram(stats: &ColumnStatsRow) -> bool {
+    !matches!(stats.type_category.as_str(), "N" | "D" | "B" | "T")
+}
+
+fn sample_source(relation: &RelationInfo, percent: f64, rows: i64) -> String {
+    format!(
+        "SELECT * FROM {} TABLESAMPLE SYSTEM ({}) REPEATABLE ({}) LIMIT {}",
+        quote_qualified(&relation.schema, &relation.table),
+        percent,
+        SAMPLE_SEED,
+        rows
+    )
+}
+
+/// One statement profiling every column of the sample. The sample CTE is
+/// referenced by every subquery, so PostgreSQL materializes it and the table
+/// is scanned once; top values and length buckets come back as JSON arrays.
+fn sample_sql(source: &str, columns: &[&ColumnStatsRow], top_n: usize) -> String {
+    let mut aggregates = vec!["count(*)::bigint AS total".to_string()];
+    let mut per_column = Vec::new();
+    for (idx, column) in columns.iter().enumerate() {
+        let ident = quote_ident(&column.column);
+        aggregates.push(format!("count({ident})::bigint AS nn_{idx}"));
+        aggregates.push(format!("count(DISTINCT {ident}::text)::bigint AS d_{idx}"));
+        if is_orderable(column) {
+            aggregates.push(format!("min({ident})::text AS min_{idx}"));
+            aggregates.push(format!("max({ident})::text AS max_{idx}"));
+        }
+        per_column.push(format!(
+            "(SELECT COALESCE(json_agg(json_build_object('value', v, 'count', n) ORDER BY n DESC, v), '[]') \
+             FROM (SELECT {ident}::text AS v, count(*)::bigint AS n FROM s WHERE {ident} IS NOT NULL \
+             GROUP BY 1 ORDER BY 2 DESC, 1 LIMIT {top_n}) t) AS top_{idx}"
+        ));
+        if has_length_histogram(column) {
+            per_column.push(format!(
+                "(SELECT COALESCE(json_agg(json_build_object('max_length', b, 'count', n) ORDER BY b), '[]') \
+                 FROM (SELECT (2 ^ ceil(log(2, GREATEST(length({ident}::text), 1)::numeric)))::bigint AS b, \
+                 count(*)::bigint AS n FROM s WHERE {ident} IS NOT NULL GROUP BY 1) t) AS hist_{idx}"
+            ));
+        }
+    }
+    let mut select = vec!["a.*".to_string()];
+    select.extend(per_column);
+    format!(
+        "WITH s AS ({source}),\na AS (SELECT {} FROM s)\nSELECT {}\nFROM a",
+        aggregates.join(",\n  "),
+        select.join(",\n  ")
+    )
+}
+
+async fn sample_columns(
+    conn: &mut PgConnection,
+    relation: &RelationInfo,
+    stats: &[ColumnStatsRow],
+    percent: f64,
+    rows: i64,
+    top_n: usize,
+) -> Result<(i64, Vec<ColumnSample>), String> {
+    let source = sample_source(relation, percent, rows);
+    let columns: Vec<&ColumnStatsRow> = stats.iter().take(MAX_SAMPLED_COLUMNS).collect();
+    let row: Value = fetch_one_as(conn, &sample_sql(&source, &columns, top_n), &[])
+        .await?
+        .unwrap_or(Value::Null);
+    let total = row.get("total").and_then(Value::as_i64).unwrap_or(0);
+    let int_at = |key: String| row.get(key).and_then(Value::as_i64).unwrap_or(0);
+    let text_at = |key: String| row.get(key).and_then(Value::as_str).map(str::to_string);
+    let list_at = |key: String| row.get(key).cloned().unwrap_or_else(|| json!([]));
+
+    let mut samples = Vec::with_capacity(columns.len());
+    for idx in 0..columns.len() {
+        let non_null = int_at(format!("nn_{idx}"));
+        let top_values: Vec<ValueCount> = serde_json::from_value(list_at(format!("top_{idx}")))
+            .map_err(|err| format!("decode_failed: {}", err))?;
+        let length_histogram: Vec<LengthBucket> =
+            serde_json::from_value(list_at(format!("hist_{idx}")))
+                .map_err(|err| format!("decode_failed: {}", err))?;
+        samples.push(ColumnSample {
+            non_null,
+            null_percent: if total > 0 {
+                ((total - non_null) as f64 / total as f64) * 100.0
+            } else {
+                0.0
+            },
+            distinct: int_at(format!("d_{idx}")),
+            min: text_at(format!("min_{idx}")),
+            max: text_at(format!("max_{idx}")),
+            top_values,
+            length_histogram,
+        });
+    }
+    Ok((total, samples))
+}
+
+pub async fn build_profile(
+    conn: &mut PgConnection,
+    table: &str,
+    options: &ProfileOptions,
+) -> Result<TableProfile, String> {
+    let (schema, name) = split_qualified(table);
+    let relation: RelationInfo = fetch_one_as(conn, RELATION_SQL, &[json!(schema), json!(name)])
+        .await?
+        .ok_or_else(|| format!("table_not_found:{}.{}", schema, name))?;
+    let stats: Vec<ColumnStatsRow> = fetch_as(
+        conn,
+        COLUMN_STATS_SQL,
+        &[
+            json!(relation.schema),
+            json!(relation.table),
+            json!(relation.oid),
+        ],
+    )
+    .await?;
+
+    let sample_percent = options
+        .sample_percent
+        .filter(|p| p.is_finite() && *p > 0.0)
+        .map(|p| p.min(100.0));
+    let (sampled_rows, mut samples) = match sample_percent {
+        Some(percent) => {
+            let rows = options
+                .sample_rows
+                .unwrap_or(DEFAULT_SAMPLE_ROWS)
+                .clamp(1, 1_000_000);
+            let top_n = options.top_n.unwrap_or(DEFAULT_TOP_N).clamp(1, 50);
+            let (total, samples) =
+                sample_columns(conn, &relation, &stats, percent, rows, top_n).await?;
+            (Some(total), samples.into_iter().map(Some).collect())
+        }
+        None => (None, Vec::new()),
+    };
+    samples.resize(stats.len(), None);
+
+    let analyzed = stats.iter().any(|s| s.null_frac.is_some());
+    let columns = stats
+        .into_iter()
+        .zip(samples)
+        .map(|(s, sample)| {
+            let most_common = match (s.most_common_vals, s.most_common_freqs) {
+                (Some(values), Some(freqs)) => values
+                    .into_iter()
+                    .zip(freqs)
+                    .take(MAX_MOST_COMMON)
+                    .map(|(value, frequency)| ValueFrequency { value, frequency })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            ColumnProfile {
+                distinct_estimate: distinct_estimate(s.n_distinct, relation.reltuples),
+                name: s.column,
+                data_type: s.data_type,
+                not_null: s.not_null,
+                null_frac: s.null_frac,
+                n_distinct: s.n_distinct,
+                avg_width: s.avg_width,
+                correlation: s.correlation,
+                most_common,
+                sample,
+            }
+        })
+        .collect();
+
+    Ok(TableProfile {
+        schema: relation.schema,
+        table: relation.table,
+        reltuples: relation.reltuples,
+        relpages: relation.relpages,
+        total_bytes: relation.total_bytes,
+        analyzed,
+        sample_percent,
+        sampled_rows,
+        columns,
+        profiled_at: now_sec(),
+    })
+}
+
+async fn store_profile(
+    store: &LocalStore,
+    conn_id: &str,
+    profile: &TableProfile,
+) -> Result<(), String> {
+    let content = serde_json::to_string(profile).map_err(|err| err.to_string())?;
+    sqlx::query(
+        "INSERT INTO table_profiles (conn_id, schema_name, table_name, content, sampled, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(conn_id, schema_name, table_name) DO UPDATE SET
+           content = excluded.content, sampled = excluded.sampled, created_at = excluded.created_at",
+    )
+    .bind(conn_id)
+    .bind(&profile.schema)
+    .bind(&profile.table)
+    .bind(content)
+    .bind(profile.sampled_rows.is_some())
+    .bind(profile.profiled_at)
+    .execute(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    Ok(())
+}
+
+pub async fn load_cached_profile(
+    store: &LocalStore,
+    conn_id: &str,
+    table: &str,
+) -> Result<Option<TableProfile>, String> {
+    let (schema, name) = split_qualified(table);
+    let row = sqlx::query(
+        "SELECT content FROM table_profiles WHERE conn_id = ?1 AND schema_name = ?2 AND table_name = ?3",
+    )
+    .bind(conn_id)
+    .bind(schema)
+    .bind(name)
+    .fetch_optional(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    Ok(row
+        .and_then(|row| row_text(&row, "content"))
+        .and_then(|content| serde_json::from_str(&content).ok()))
+}
+
+/// Shapes a profile as an assistant context chunk of kind `table-profile`.
+pub fn profile_chunk(conn_id: &str, profile: &TableProfile) -> Value {
+    let label = format!("{}.{}", profile.schema, profile.table);
+    json!({
+        "id": format!("profile:{}:{}", conn_id, label),
+        "kind": "table-profile",
+        "title": format!("{} profile", label),
+        "summary": format!(
+            "~{} rows • {} columns{}",
+            profile.reltuples.round() as i64,
+            profile.columns.len(),
+            if profile.analyzed { "" } else { " • not analyzed" }
+        ),
+        "content": profile,
+    })
+}
+
+#[tauri::command]
+pub async fn profile_table(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    conn_id: String,
+    table: String,
+    options: Option<ProfileOptions>,
+) -> Result<TableProfile, String> {
+    let options = options.unwrap_or(ProfileOptions {
+        sample_percent: None,
+        sample_rows: None,
+        top_n: None,
+    });
+    let pool = pools.pool_for(&store, &conn_id).await?;
+    let mut tx = begin_read_only(&pool, ANALYSIS_STATEMENT_TIMEOUT_MS).await?;
+    let profile = build_profile(&mut tx, &table, &options).await?;
+    drop(tx);
+    store_profile(&store, &conn_id, &profile).await?;
+    Ok(profile)
+}
+
+#[tauri::command]
+pub async fn cached_table_profile(
+    store: State<'_, LocalStore>,
+    conn_id: String,
+    table: String,
+) -> Result<Option<Value>, String> {
+    Ok(load_cached_profile(&store, &conn_id, &table)
+        .await?
+        .map(|profile| profile_chunk(&conn_id, &profile)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, type_category: &str) -> ColumnStatsRow {
+        ColumnStatsRow {
+            column: name.to_string(),
+            data_type: data_type.to_string(),
+            type_category: type_category.to_string(),
+            not_null: false,
+            null_frac: None,
+            n_distinct: None,
+            avg_width: None,
+            correlation: None,
+            most_common_vals: None,
+            most_common_freqs: None,
+        }
+    }
+
+    #[test]
+    fn skips_min_max_for_uuid_and_reg_types() {
+        let id = column("id", "uuid", "U");
+        let rel = column("rel", "regclass", "N");
+        let total = column("total", "numeric(12,2)", "N");
+        assert!(!is_orderable(&id));
+        assert!(!is_orderable(&rel));
+        assert!(is_orderable(&total));
+        let sql = sample_sql("SELECT * FROM t", &[&id, &rel, &total], 5);
+        assert!(!sql.contains("min(\"id\")"));
+        assert!(!sql.contains("min(\"rel\")"));
+        assert!(sql.contains("min(\"total\")::text AS min_2"));
+        assert!(sql.contains("count(DISTINCT \"id\"::text)::bigint AS d_0"));
+    }
+
+    #[test]
+    fn samples_every_column_in_one_statement() {
+        let name = column("name", "text", "S");
+        let created = column("created_at", "timestamp with time zone", "D");
+        let sql = sample_sql(
+            "SELECT * FROM t TABLESAMPLE SYSTEM (1)",
+            &[&name, &created],
+            3,
+        );
+        assert_eq!(sql.matches("TABLESAMPLE").count(), 1);
+        assert!(sql.contains("AS top_0") && sql.contains("AS top_1"));
+        assert!(sql.contains("AS hist_0") && !sql.contains("AS hist_1"));
+        assert!(sql.contains("LIMIT 3"));
+    }
+}
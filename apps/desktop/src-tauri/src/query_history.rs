@@ -0,0 +1,422 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{QueryBuilder, Row, Sqlite};
+use tauri::State;
+
+use crate::local_store::{db_error, new_id, now_sec, row_text, LocalStore};
+
+/// `app_prefs` key; `true` stores parameters as NULL (their count is kept).
+pub const REDACT_PARAMS_PREF: &str = "query_history_redact_params";
+const PAGE_LIMIT: i64 = 50;
+
+pub struct HistoryEntry<'a> {
+    pub conn_id: &'a str,
+    pub saved_sql_id: Option<&'a str>,
+    pub sql: &'a str,
+    pub params: &'a [Value],
+    pub duration_ms: u64,
+    pub row_count: Option<i64>,
+    /// The command's error string, if the statement failed.
+    pub error: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryRecord {
+    pub id: String,
+    pub conn_id: String,
+    pub saved_sql_id: Option<String>,
+    pub sql_hash: String,
+    pub sql: String,
+    pub params: Option<Value>,
+    pub param_count: i64,
+    pub params_redacted: bool,
+    pub duration_ms: i64,
+    pub row_count: Option<i64>,
+    pub error_code: Option<String>,
+    pub error_message: Option<String>,
+    pub executed_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryRecord>,
+    pub total: i64,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+/// Hash of the SQL with whitespace collapsed, so reformatted copies of a
+/// statement group together.
+pub fn sql_hash(sql: &str) -> String {
+    let normalized = sql.split_whitespace().collect::<Vec<_>>().join(" ");
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+fn is_sqlstate(token: &str) -> bool {
+    token.len() == 5
+        && token
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b.is_ascii_uppercase())
+}
+
+/// Error code of a failed statement: the SQLSTATE from `pg_error`
+/// (`db_query_failed: [42P01] ...`), or one of our own snake_case codes
+/// before the first `:`. Driver messages relayed by the webview are free
+/// text and get no code.
+pub fn error_code(err: &str) -> Option<String> {
+    if let Some(start) = err.find(": [") {
+        let token = &err[start + 3..];
+        if let Some(len) = token.find(']').filter(|len| is_sqlstate(&token[..*len])) {
+            return Some(token[..len].to_string());
+        }
+    }
+    let prefix = err.split(':').next().unwrap_or(err).trim();
+    let is_code = prefix.starts_with(|c: char| c.is_ascii_lowercase())
+        && prefix
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    is_code.then(|| prefix.to_string())
+}
+
+async fn redact_params(store: &LocalStore) -> Result<bool, String> {
+    let value: Option<String> = sqlx::query_scalar("SELECT v FROM app_prefs WHERE k = ?1")
+        .bind(REDACT_PARAMS_PREF)
+        .fetch_optional(&store.pool()?)
+        .await
+        .map_err(db_error)?;
+    Ok(value.is_some_and(|v| v.trim() == "true"))
+}
+
+pub async fn record(store: &LocalStore, entry: HistoryEntry<'_>) -> Result<String, String> {
+    let redacted = redact_params(store).await?;
+    let params = (!redacted).then(|| Value::Array(entry.params.to_vec()).to_string());
+    let id = new_id("qh");
+    sqlx::query(
+        "INSERT INTO query_history (id, conn_id, saved_sql_id, sql_hash, sql, params, param_count, params_redacted, duration_ms, row_count, error_code, error_message, executed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+    )
+    .bind(&id)
+    .bind(entry.conn_id)
+    .bind(entry.saved_sql_id)
+    .bind(sql_hash(entry.sql))
+    .bind(entry.sql)
+    .bind(params)
+    .bind(entry.params.len() as i64)
+    .bind(redacted)
+    .bind(entry.duration_ms as i64)
+    .bind(entry.row_count)
+    .bind(entry.error.and_then(error_code))
+    .bind(entry.error)
+    .bind(now_sec())
+    .execute(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    Ok(id)
+}
+
+fn record_from_row(row: &SqliteRow) -> HistoryRecord {
+    HistoryRecord {
+        id: row_text(row, "id").unwrap_or_default(),
+        conn_id: row_text(row, "conn_id").unwrap_or_default(),
+        saved_sql_id: row_text(row, "saved_sql_id"),
+        sql_hash: row_text(row, "sql_hash").unwrap_or_default(),
+        sql: row_text(row, "sql").unwrap_or_default(),
+        params: row_text(row, "params").and_then(|raw| serde_json::from_str(&raw).ok()),
+        param_count: row.try_get("param_count").unwrap_or_default(),
+        params_redacted: row.try_get::<i64, _>("params_redacted").unwrap_or_default() != 0,
+        duration_ms: row.try_get("duration_ms").unwrap_or_default(),
+        row_count: row.try_get("row_count").ok().flatten(),
+        error_code: row_text(row, "error_code"),
+        error_message: row_text(row, "error_message"),
+        executed_at: row.try_get("executed_at").unwrap_or_default(),
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HistoryFilter {
+    /// Substring of the SQL, or an exact `sql_hash`.
+    pub query: Option<String>,
+    pub conn_id: Option<String>,
+    pub saved_sql_id: Option<String>,
+    pub errors_only: bool,
+    /// Unix seconds, inclusive.
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+}
+
+fn push_filter(builder: &mut QueryBuilder<'_, Sqlite>, filter: &HistoryFilter) {
+    builder.push(" WHERE 1 = 1");
+    if let Some(query) = filter
+        .query
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+    {
+        let pattern = format!(
+            "%{}%",
+            query
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        builder
+            .push(" AND (sql LIKE ")
+            .push_bind(pattern)
+            .push(" ESCAPE '\\' OR sql_hash = ")
+            .push_bind(query.to_lowercase())
+            .push(")");
+    }
+    if let Some(conn_id) = &filter.conn_id {
+        builder.push(" AND conn_id = ").push_bind(conn_id.clone());
+    }
+    if let Some(saved_sql_id) = &filter.saved_sql_id {
+        builder
+            .push(" AND saved_sql_id = ")
+            .push_bind(saved_sql_id.clone());
+    }
+    if filter.errors_only {
+        builder.push(" AND error_message IS NOT NULL");
+    }
+    if let Some(since) = filter.since {
+        builder.push(" AND executed_at >= ").push_bind(since);
+    }
+    if let Some(until) = filter.until {
+        builder.push(" AND executed_at <= ").push_bind(until);
+    }
+}
+
+/// Newest first.
+pub async fn search(
+    store: &LocalStore,
+    filter: &HistoryFilter,
+    offset: i64,
+    limit: i64,
+) -> Result<HistoryPage, String> {
+    let (offset, limit) = (offset.max(0), limit.clamp(1, 500));
+    let mut count = QueryBuilder::new("SELECT COUNT(*) FROM query_history");
+    push_filter(&mut count, filter);
+    let total: i64 = count
+        .build_query_scalar()
+        .fetch_one(&store.pool()?)
+        .await
+        .map_err(db_error)?;
+    let mut select = QueryBuilder::new("SELECT * FROM query_history");
+    push_filter(&mut select, filter);
+    select
+        .push(" ORDER BY executed_at DESC, id DESC LIMIT ")
+        .push_bind(limit)
+        .push(" OFFSET ")
+        .push_bind(offset);
+    let rows = select
+        .build()
+        .fetch_all(&store.pool()?)
+        .await
+        .map_err(db_error)?;
+    Ok(HistoryPage {
+        entries: rows.iter().map(record_from_row).collect(),
+        total,
+        offset,
+        limit,
+    })
+}
+
+/// Deletes entries executed more than `older_than_days` days ago.
+pub async fn purge(store: &LocalStore, older_than_days: i64) -> Result<u64, String> {
+    let cutoff = now_sec() - older_than_days.max(0) * 86_400;
+    let done = sqlx::query("DELETE FROM query_history WHERE executed_at < ?1")
+        .bind(cutoff)
+        .execute(&store.pool()?)
+        .await
+        .map_err(db_error)?;
+    Ok(done.rows_affected())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueryHistoryRequest {
+    #[serde(flatten)]
+    pub filter: HistoryFilter,
+    #[serde(default)]
+    pub offset: Option<i64>,
+    #[serde(default)]
+    pub limit: Option<i64>,
+}
+
+#[tauri::command]
+pub async fn search_query_history(
+    store: State<'_, LocalStore>,
+    payload: QueryHistoryRequest,
+) -> Result<HistoryPage, String> {
+    search(
+        &store,
+        &payload.filter,
+        payload.offset.unwrap_or(0),
+        payload.limit.unwrap_or(PAGE_LIMIT),
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn purge_query_history(
+    store: State<'_, LocalStore>,
+    older_than_days: i64,
+) -> Result<u64, String> {
+    purge(&store, older_than_days).await
+}
+
+/// A statement run on a webview session (profiles without strict read-only);
+/// the executor records its own runs.
+#[derive(Debug, Deserialize)]
+pub struct RecordQueryHistoryRequest {
+    pub conn_id: String,
+    #[serde(default)]
+    pub saved_sql_id: Option<String>,
+    pub sql: String,
+    #[serde(default)]
+    pub params: Vec<Value>,
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub row_count: Option<i64>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+pub async fn record_request(
+    store: &LocalStore,
+    payload: &RecordQueryHistoryRequest,
+) -> Result<String, String> {
+    if payload.conn_id.trim().is_empty() || payload.sql.trim().is_empty() {
+        return Err("query_history_invalid: conn_id and sql are required".into());
+    }
+    record(
+        store,
+        HistoryEntry {
+            conn_id: &payload.conn_id,
+            saved_sql_id: payload.saved_sql_id.as_deref(),
+            sql: &payload.sql,
+            params: &payload.params,
+            duration_ms: payload.duration_ms,
+            row_count: payload.row_count,
+            error: payload.error.as_deref(),
+        },
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn record_query_history(
+    store: State<'_, LocalStore>,
+    payload: RecordQueryHistoryRequest,
+) -> Result<String, String> {
+    record_request(&store, &payload).await
+}
+
+/// Turns parameter redaction on or off for entries recorded from now on.
+#[tauri::command]
+pub async fn set_query_history_redaction(
+    store: State<'_, LocalStore>,
+    enabled: bool,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO app_prefs (k, v) VALUES (?1, ?2) ON CONFLICT(k) DO UPDATE SET v = excluded.v",
+    )
+    .bind(REDACT_PARAMS_PREF)
+    .bind(if enabled { "true" } else { "false" })
+    .execute(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::with_store;
+
+    #[test]
+    fn hashes_normalised_sql_and_extracts_error_codes() {
+        assert_eq!(sql_hash("select *\n  from t"), sql_hash("select * from t "));
+        assert_ne!(sql_hash("select 1"), sql_hash("select 2"));
+        let code = |err: &str| error_code(err);
+        assert_eq!(
+            code("db_query_failed: [42P01] relation \"t\" does not exist").as_deref(),
+            Some("42P01")
+        );
+        assert_eq!(
+            code("write_requires_confirmation").as_deref(),
+            Some("write_requires_confirmation")
+        );
+        assert_eq!(
+            code("strict_read_only_violation: statement would lift read-only mode").as_deref(),
+            Some("strict_read_only_violation")
+        );
+        // Webview driver messages.
+        assert_eq!(
+            code("error returned from database: relation \"t\" does not exist"),
+            None
+        );
+        assert_eq!(code("syntax error: [at line 3] near \"form\""), None);
+        assert_eq!(code("relation \"t\" does not exist"), None);
+    }
+
+    #[test]
+    fn records_webview_runs_with_redaction() {
+        with_store(|store| async move {
+            let mut payload = RecordQueryHistoryRequest {
+                conn_id: "conn_a".into(),
+                saved_sql_id: Some("saved_a".into()),
+                sql: "select * from t where id = $1".into(),
+                params: vec![Value::from(7)],
+                duration_ms: 12,
+                row_count: Some(1),
+                error: None,
+            };
+            record_request(&store, &payload).await.unwrap();
+            sqlx::query("INSERT INTO app_prefs (k, v) VALUES (?1, 'true')")
+                .bind(REDACT_PARAMS_PREF)
+                .execute(&store.pool().unwrap())
+                .await
+                .unwrap();
+            payload.error = Some("db_query_failed: [42P01] relation \"t\" does not exist".into());
+            payload.row_count = None;
+            record_request(&store, &payload).await.unwrap();
+
+            let filter = HistoryFilter {
+                conn_id: Some("conn_a".into()),
+                ..Default::default()
+            };
+            let page = search(&store, &filter, 0, 10).await.unwrap();
+            assert_eq!(page.total, 2);
+            let failed = page
+                .entries
+                .iter()
+                .find(|e| e.error_code.is_some())
+                .unwrap();
+            assert_eq!(failed.error_code.as_deref(), Some("42P01"));
+            assert!(failed.params_redacted && failed.params.is_none());
+            assert_eq!(failed.param_count, 1);
+            let ok = page
+                .entries
+                .iter()
+                .find(|e| e.error_code.is_none())
+                .unwrap();
+            assert_eq!(ok.params, Some(serde_json::json!([7])));
+            assert_eq!(ok.saved_sql_id.as_deref(), Some("saved_a"));
+
+            payload.error = Some("error returned from database: deadlock detected".into());
+            record_request(&store, &payload).await.unwrap();
+            let errors = HistoryFilter {
+                errors_only: true,
+                ..filter
+            };
+            let page = search(&store, &errors, 0, 10).await.unwrap();
+            assert_eq!(page.total, 2);
+            assert!(page.entries.iter().any(|e| e.error_code.is_none()));
+
+            payload.sql = "  ".into();
+            assert!(record_request(&store, &payload).await.is_err());
+        });
+    }
+}
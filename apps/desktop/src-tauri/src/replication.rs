@@ -0,0 +1,587 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::cmp::Reverse;
+use tauri::State;
+
+use crate::local_store::{now_sec, LocalStore};
+use crate::pg::{begin_read_only, fetch_as, fetch_one_as, PgPools};
+
+const STATUS_STATEMENT_TIMEOUT_MS: u64 = 10_000;
+const MIB: i64 = 1024 * 1024;
+const LAG_BYTES_WARNING: i64 = 64 * MIB;
+const LAG_BYTES_CRITICAL: i64 = 1024 * MIB;
+const LAG_SECONDS_WARNING: f64 = 30.0;
+const LAG_SECONDS_CRITICAL: f64 = 300.0;
+/// `wal_receiver_timeout` defaults to 60s; silence longer than that is suspect.
+const RECEIVER_SILENCE_SECS: f64 = 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WarningLevel {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplicationFinding {
+    /// `replica_lag`, `replica_not_streaming`, `slot_inactive`,
+    /// `slot_retaining_wal`, `slot_wal_status`, `standby_replay_delay`,
+    /// `standby_replay_paused`, `wal_receiver_missing`,
+    /// `wal_receiver_not_streaming` or `wal_receiver_silent`.
+    pub kind: String,
+    pub level: WarningLevel,
+    /// Replica application name, slot name or `standby`.
+    pub subject: String,
+    pub message: String,
+    pub lag_bytes: Option<i64>,
+    pub lag_seconds: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ServerWal {
+    pub server_version: i64,
+    pub in_recovery: bool,
+    /// `pg_current_wal_lsn()` on a primary, last replayed LSN on a standby.
+    pub current_lsn: Option<String>,
+    pub receive_lsn: Option<String>,
+    pub replay_lsn: Option<String>,
+    /// Received but not yet replayed (standby only).
+    pub replay_backlog_bytes: Option<i64>,
+    pub last_replay_at: Option<String>,
+    /// Seconds since the last replayed commit; meaningless on an idle primary.
+    pub last_replay_age_seconds: Option<f64>,
+    pub replay_paused: Option<bool>,
+    pub wal_level: String,
+    pub max_wal_senders: i64,
+    pub max_replication_slots: i64,
+    pub max_slot_wal_keep_size: Option<String>,
+}
+
+/// One `pg_stat_replication` row, lag measured from the primary's current LSN.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Replica {
+    pub pid: i64,
+    pub application_name: Option<String>,
+    pub client_addr: Option<String>,
+    pub state: Option<String>,
+    pub sync_state: Option<String>,
+    pub sent_lsn: Option<String>,
+    pub replay_lsn: Option<String>,
+    pub sent_lag_bytes: Option<i64>,
+    pub flush_lag_bytes: Option<i64>,
+    pub replay_lag_bytes: Option<i64>,
+    pub write_lag_seconds: Option<f64>,
+    pub flush_lag_seconds: Option<f64>,
+    pub replay_lag_seconds: Option<f64>,
+    pub slot_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReplicationSlot {
+    pub slot_name: String,
+    pub slot_type: String,
+    pub plugin: Option<String>,
+    pub database: Option<String>,
+    pub active: bool,
+    pub active_pid: Option<i64>,
+    pub temporary: bool,
+    /// `reserved`, `extended`, `unreserved` or `lost` (PostgreSQL 13+).
+    pub wal_status: Option<String>,
+    pub restart_lsn: Option<String>,
+    pub confirmed_flush_lsn: Option<String>,
+    /// WAL kept on disk for this slot.
+    pub retained_bytes: Option<i64>,
+    /// Remaining before the slot is invalidated by `max_slot_wal_keep_size`.
+    pub safe_wal_size: Option<i64>,
+    /// Logical slots: decoded changes not yet confirmed by the consumer.
+    pub confirmed_flush_lag_bytes: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WalReceiver {
+    pub pid: i64,
+    pub status: String,
+    pub sender_host: Option<String>,
+    pub sender_port: Option<i64>,
+    pub slot_name: Option<String>,
+    /// `received_lsn` before PostgreSQL 13.
+    pub flushed_lsn: Option<String>,
+    pub latest_end_lsn: Option<String>,
+    pub latest_end_time: Option<String>,
+    pub last_msg_receipt_time: Option<String>,
+    pub last_msg_age_seconds: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplicationStatus {
+    /// `primary` or `standby`.
+    pub role: String,
+    pub server: ServerWal,
+    pub replicas: Vec<Replica>,
+    pub slots: Vec<ReplicationSlot>,
+    pub wal_receiver: Option<WalReceiver>,
+    /// Standby only: replay delay, zero when everything received is replayed.
+    pub standby_lag_bytes: Option<i64>,
+    pub standby_lag_seconds: Option<f64>,
+    /// Most severe first.
+    pub findings: Vec<ReplicationFinding>,
+    pub level: Option<WarningLevel>,
+    pub generated_at: i64,
+}
+
+const SERVER_SQL: &str = r#"
+SELECT
+  pg_catalog.current_setting('server_version_num')::bigint AS server_version,
+  pg_catalog.pg_is_in_recovery() AS in_recovery,
+  (CASE WHEN pg_catalog.pg_is_in_recovery() THEN pg_catalog.pg_last_wal_replay_lsn()
+        ELSE pg_catalog.pg_current_wal_lsn() END)::text AS current_lsn,
+  pg_catalog.pg_last_wal_receive_lsn()::text AS receive_lsn,
+  pg_catalog.pg_last_wal_replay_lsn()::text AS replay_lsn,
+  pg_catalog.pg_wal_lsn_diff(pg_catalog.pg_last_wal_receive_lsn(), pg_catalog.pg_last_wal_replay_lsn())::bigint AS replay_backlog_bytes,
+  pg_catalog.pg_last_xact_replay_timestamp()::text AS last_replay_at,
+  EXTRACT(EPOCH FROM (pg_catalog.now() - pg_catalog.pg_last_xact_replay_timestamp()))::float8 AS last_replay_age_seconds,
+  CASE WHEN pg_catalog.pg_is_in_recovery() THEN pg_catalog.pg_is_wal_replay_paused() END AS replay_paused,
+  pg_catalog.current_setting('wal_level') AS wal_level,
+  pg_catalog.current_setting('max_wal_senders')::bigint AS max_wal_senders,
+  pg_catalog.current_setting('max_replication_slots')::bigint AS max_replication_slots,
+  pg_catalog.current_setting('max_slot_wal_keep_size', true) AS max_slot_wal_keep_size"#;
+
+/// Sender rows only exist on a server with connected standbys (primaries and
+/// cascading standbys).
+const REPLICAS_SQL: &str = r#"
+WITH cur AS (
+  SELECT CASE WHEN pg_catalog.pg_is_in_recovery() THEN pg_catalog.pg_last_wal_replay_lsn()
+              ELSE pg_catalog.pg_current_wal_lsn() END AS lsn
+)
+SELECT
+  r.pid::bigint AS pid,
+  r.application_name,
+  r.client_addr::text AS client_addr,
+  r.state,
+  r.sync_state,
+  r.sent_lsn::text AS sent_lsn,
+  r.replay_lsn::text AS replay_lsn,
+  pg_catalog.pg_wal_lsn_diff(cur.lsn, r.sent_lsn)::bigint AS sent_lag_bytes,
+  pg_catalog.pg_wal_lsn_diff(cur.lsn, r.flush_lsn)::bigint AS flush_lag_bytes,
+  pg_catalog.pg_wal_lsn_diff(cur.lsn, r.replay_lsn)::bigint AS replay_lag_bytes,
+  EXTRACT(EPOCH FROM r.write_lag)::float8 AS write_lag_seconds,
+  EXTRACT(EPOCH FROM r.flush_lag)::float8 AS flush_lag_seconds,
+  EXTRACT(EPOCH FROM r.replay_lag)::float8 AS replay_lag_seconds,
+  sl.slot_name::text AS slot_name
+FROM pg_catalog.pg_stat_replication r
+CROSS JOIN cur
+LEFT JOIN pg_catalog.pg_replication_slots sl ON sl.active_pid = r.pid
+ORDER BY r.application_name, r.pid"#;
+
+/// `wal_status` and `safe_wal_size` exist from PostgreSQL 13.
+fn slots_sql(server_version: i64) -> String {
+    let wal_columns = if server_version >= 130_000 {
+        "s.wal_status, s.safe_wal_size::bigint AS safe_wal_size"
+    } else {
+        "NULL::text AS wal_status, NULL::bigint AS safe_wal_size"
+    };
+    format!(
+        r#"
+WITH cur AS (
+  SELECT CASE WHEN pg_catalog.pg_is_in_recovery() THEN pg_catalog.pg_last_wal_replay_lsn()
+              ELSE pg_catalog.pg_current_wal_lsn() END AS lsn
+)
+SELECT
+  s.slot_name::text AS slot_name,
+  s.slot_type,
+  s.plugin::text AS plugin,
+  s.database::text AS database,
+  s.active,
+  s.active_pid::bigint AS active_pid,
+  s.temporary,
+  {wal_columns},
+  s.restart_lsn::text AS restart_lsn,
+  s.confirmed_flush_lsn::text AS confirmed_flush_lsn,
+  pg_catalog.pg_wal_lsn_diff(cur.lsn, s.restart_lsn)::bigint AS retained_bytes,
+  pg_catalog.pg_wal_lsn_diff(cur.lsn, s.confirmed_flush_lsn)::bigint AS confirmed_flush_lag_bytes
+FROM pg_catalog.pg_replication_slots s
+CROSS JOIN cur
+ORDER BY s.slot_name"#
+    )
+}
+
+/// PostgreSQL 13 split `received_lsn` into `written_lsn` and `flushed_lsn`.
+fn wal_receiver_sql(server_version: i64) -> String {
+    let flushed = if server_version >= 130_000 {
+        "w.flushed_lsn"
+    } else {
+        "w.received_lsn"
+    };
+    format!(
+        r#"
+SELECT
+  w.pid::bigint AS pid,
+  w.status,
+  w.sender_host,
+  w.sender_port::bigint AS sender_port,
+  w.slot_name,
+  {flushed}::text AS flushed_lsn,
+  w.latest_end_lsn::text AS latest_end_lsn,
+  w.latest_end_time::text AS latest_end_time,
+  w.last_msg_receipt_time::text AS last_msg_receipt_time,
+  EXTRACT(EPOCH FROM (pg_catalog.now() - w.last_msg_receipt_time))::float8 AS last_msg_age_seconds
+FROM pg_catalog.pg_stat_wal_receiver w
+LIMIT 1"#
+    )
+}
+
+fn lag_level(bytes: Option<i64>, seconds: Option<f64>) -> Option<WarningLevel> {
+    let bytes = bytes.unwrap_or(0);
+    let seconds = seconds.unwrap_or(0.0);
+    if bytes >= LAG_BYTES_CRITICAL || seconds >= LAG_SECONDS_CRITICAL {
+        Some(WarningLevel::Critical)
+    } else if bytes >= LAG_BYTES_WARNING || seconds >= LAG_SECONDS_WARNING {
+        Some(WarningLevel::Warning)
+    } else {
+        None
+    }
+}
+
+fn finding(
+    kind: &str,
+    level: WarningLevel,
+    subject: &str,
+    message: String,
+    lag_bytes: Option<i64>,
+    lag_seconds: Option<f64>,
+) -> ReplicationFinding {
+    ReplicationFinding {
+        kind: kind.to_string(),
+        level,
+        subject: subject.to_string(),
+        message,
+        lag_bytes,
+        lag_seconds,
+    }
+}
+
+fn mib(bytes: i64) -> String {
+    format!("{:.1} MiB", bytes as f64 / MIB as f64)
+}
+
+/// Standby lag: nothing is behind when every received record is replayed, even
+/// if the last replayed commit is old (the primary may simply be idle).
+pub fn standby_lag(server: &ServerWal) -> (Option<i64>, Option<f64>) {
+    if !server.in_recovery {
+        return (None, None);
+    }
+    let backlog = server.replay_backlog_bytes.unwrap_or(0).max(0);
+    let seconds = if backlog == 0 {
+        Some(0.0)
+    } else {
+        server.last_replay_age_seconds.map(|s| s.max(0.0))
+    };
+    (Some(backlog), seconds)
+}
+
+pub fn evaluate(
+    server: &ServerWal,
+    replicas: &[Replica],
+    slots: &[ReplicationSlot],
+    receiver: Option<&WalReceiver>,
+) -> Vec<ReplicationFinding> {
+    let mut findings = Vec::new();
+    for replica in replicas {
+        let subject = replica
+            .application_name
+            .clone()
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| format!("pid {}", replica.pid));
+        if let Some(level) = lag_level(replica.replay_lag_bytes, replica.replay_lag_seconds) {
+            findings.push(finding(
+                "replica_lag",
+                level,
+                &subject,
+                format!(
+                    "Replay is {} / {:.0}s behind",
+                    mib(replica.replay_lag_bytes.unwrap_or(0)),
+                    replica.replay_lag_seconds.unwrap_or(0.0)
+                ),
+                replica.replay_lag_bytes,
+                replica.replay_lag_seconds,
+            ));
+        }
+        if let Some(state) = replica.state.as_deref().filter(|s| *s != "streaming") {
+            findings.push(finding(
+                "replica_not_streaming",
+                WarningLevel::Warning,
+                &subject,
+                format!("Replication state is {}", state),
+                replica.replay_lag_bytes,
+                None,
+            ));
+        }
+    }
+
+    for slot in slots {
+        let retained = slot.retained_bytes.unwrap_or(0);
+        match slot.wal_status.as_deref() {
+            Some("lost") => findings.push(finding(
+                "slot_wal_status",
+                WarningLevel::Critical,
+                &slot.slot_name,
+                "Slot has lost required WAL and can no longer be used".to_string(),
+                slot.retained_bytes,
+                None,
+            )),
+            Some("unreserved") => findings.push(finding(
+                "slot_wal_status",
+                WarningLevel::Critical,
+                &slot.slot_name,
+                "Required WAL is past max_slot_wal_keep_size and will be removed at the next checkpoint"
+                    .to_string(),
+                slot.retained_bytes,
+                None,
+            )),
+            Some("extended") => findings.push(finding(
+                "slot_wal_status",
+                WarningLevel::Warning,
+                &slot.slot_name,
+                "Slot retains WAL beyond max_wal_size".to_string(),
+                slot.retained_bytes,
+                None,
+            )),
+            _ => {}
+        }
+        if !slot.active && !slot.temporary {
+            let level = if retained >= LAG_BYTES_CRITICAL {
+                WarningLevel::Critical
+            } else {
+                WarningLevel::Warning
+            };
+            findings.push(finding(
+                "slot_inactive",
+                level,
+                &slot.slot_name,
+                format!(
+                    "Inactive {} slot retaining {} of WAL",
+                    slot.slot_type,
+                    mib(retained)
+                ),
+                slot.retained_bytes,
+                None,
+            ));
+        } else if retained >= LAG_BYTES_WARNING {
+            findings.push(finding(
+                "slot_retaining_wal",
+                if retained >= LAG_BYTES_CRITICAL {
+                    WarningLevel::Warning
+                } else {
+                    WarningLevel::Info
+                },
+                &slot.slot_name,
+                format!("Active slot retaining {} of WAL", mib(retained)),
+                slot.retained_bytes,
+                None,
+            ));
+        }
+    }
+
+    if server.in_recovery {
+        let (lag_bytes, lag_seconds) = standby_lag(server);
+        if let Some(level) = lag_level(lag_bytes, lag_seconds) {
+            findings.push(finding(
+                "standby_replay_delay",
+                level,
+                "standby",
+                format!(
+                    "{} received but not replayed; last replayed commit {:.0}s ago",
+                    mib(lag_bytes.unwrap_or(0)),
+                    lag_seconds.unwrap_or(0.0)
+                ),
+                lag_bytes,
+                lag_seconds,
+            ));
+        }
+        if server.replay_paused == Some(true) {
+            findings.push(finding(
+                "standby_replay_paused",
+                WarningLevel::Warning,
+                "standby",
+                "WAL replay is paused".to_string(),
+                lag_bytes,
+                lag_seconds,
+            ));
+        }
+        match receiver {
+            None => findings.push(finding(
+                "wal_receiver_missing",
+                WarningLevel::Warning,
+                "standby",
+                "No WAL receiver; the standby is not streaming (archive recovery or disconnected)"
+                    .to_string(),
+                None,
+                None,
+            )),
+            Some(receiver) if receiver.status != "streaming" => findings.push(finding(
+                "wal_receiver_not_streaming",
+                WarningLevel::Warning,
+                "standby",
+                format!("WAL receiver status is {}", receiver.status),
+                None,
+                None,
+            )),
+            Some(receiver)
+                if receiver
+                    .last_msg_age_seconds
+                    .is_some_and(|age| age >= RECEIVER_SILENCE_SECS) =>
+            {
+                findings.push(finding(
+                    "wal_receiver_silent",
+                    WarningLevel::Warning,
+                    "standby",
+                    format!(
+                        "No message from the primary for {:.0}s",
+                        receiver.last_msg_age_seconds.unwrap_or(0.0)
+                    ),
+                    None,
+                    receiver.last_msg_age_seconds,
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+
+    findings.sort_by_key(|f| Reverse(f.level));
+    findings
+}
+
+pub async fn collect_status(conn: &mut PgConnection) -> Result<ReplicationStatus, String> {
+    let server: ServerWal = fetch_one_as(conn, SERVER_SQL, &[])
+        .await?
+        .ok_or_else(|| "replication_status_unavailable".to_string())?;
+    let replicas: Vec<Replica> = fetch_as(conn, REPLICAS_SQL, &[]).await?;
+    let slots: Vec<ReplicationSlot> =
+        fetch_as(conn, &slots_sql(server.server_version), &[]).await?;
+    let wal_receiver: Option<WalReceiver> =
+        fetch_one_as(conn, &wal_receiver_sql(server.server_version), &[]).await?;
+    let findings = evaluate(&server, &replicas, &slots, wal_receiver.as_ref());
+    let (standby_lag_bytes, standby_lag_seconds) = standby_lag(&server);
+    Ok(ReplicationStatus {
+        role: if server.in_recovery {
+            "standby"
+        } else {
+            "primary"
+        }
+        .to_string(),
+        level: findings.first().map(|f| f.level),
+        server,
+        replicas,
+        slots,
+        wal_receiver,
+        standby_lag_bytes,
+        standby_lag_seconds,
+        findings,
+        generated_at: now_sec(),
+    })
+}
+
+#[tauri::command]
+pub async fn replication_status(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    conn_id: String,
+) -> Result<ReplicationStatus, String> {
+    let pool = pools.pool_for(&store, &conn_id).await?;
+    let mut tx = begin_read_only(&pool, STATUS_STATEMENT_TIMEOUT_MS).await?;
+    collect_status(&mut tx).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(in_recovery: bool) -> ServerWal {
+        ServerWal {
+            server_version: 150_000,
+            in_recovery,
+            current_lsn: Some("0/5000000".into()),
+            receive_lsn: None,
+            replay_lsn: None,
+            replay_backlog_bytes: None,
+            last_replay_at: None,
+            last_replay_age_seconds: Some(3600.0),
+            replay_paused: None,
+            wal_level: "replica".into(),
+            max_wal_senders: 10,
+            max_replication_slots: 10,
+            max_slot_wal_keep_size: Some("-1".into()),
+        }
+    }
+
+    fn slot(name: &str, active: bool, retained: i64) -> ReplicationSlot {
+        ReplicationSlot {
+            slot_name: name.into(),
+            slot_type: "logical".into(),
+            plugin: Some("pgoutput".into()),
+            database: Some("app".into()),
+            active,
+            active_pid: None,
+            temporary: false,
+            wal_status: Some("reserved".into()),
+            restart_lsn: None,
+            confirmed_flush_lsn: None,
+            retained_bytes: Some(retained),
+            safe_wal_size: None,
+            confirmed_flush_lag_bytes: None,
+        }
+    }
+
+    #[test]
+    fn grades_replica_lag_and_inactive_slots() {
+        let replica = Replica {
+            pid: 7,
+            application_name: Some("replica1".into()),
+            client_addr: None,
+            state: Some("streaming".into()),
+            sync_state: Some("async".into()),
+            sent_lsn: None,
+            replay_lsn: None,
+            sent_lag_bytes: Some(0),
+            flush_lag_bytes: Some(0),
+            replay_lag_bytes: Some(100 * MIB),
+            write_lag_seconds: None,
+            flush_lag_seconds: None,
+            replay_lag_seconds: Some(2.0),
+            slot_name: None,
+        };
+        let slots = vec![
+            slot("cdc", false, 2048 * MIB),
+            slot("live", true, 100 * MIB),
+        ];
+        let findings = evaluate(&server(false), &[replica], &slots, None);
+        let kinds: Vec<(&str, WarningLevel)> = findings
+            .iter()
+            .map(|f| (f.kind.as_str(), f.level))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("slot_inactive", WarningLevel::Critical),
+                ("replica_lag", WarningLevel::Warning),
+                ("slot_retaining_wal", WarningLevel::Info)
+            ]
+        );
+    }
+
+    #[test]
+    fn idle_standby_is_not_lagging() {
+        let mut standby = server(true);
+        standby.replay_backlog_bytes = Some(0);
+        assert_eq!(standby_lag(&standby), (Some(0), Some(0.0)));
+        let findings = evaluate(&standby, &[], &[], None);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, "wal_receiver_missing");
+
+        standby.replay_backlog_bytes = Some(10 * MIB);
+        let findings = evaluate(&standby, &[], &[], None);
+        assert_eq!(findings[0].kind, "standby_replay_delay");
+        assert_eq!(findings[0].level, WarningLevel::Critical);
+    }
+}
@@ -0,0 +1,805 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use tauri::State;
+
+use crate::executor::{self, ExecuteResult};
+use crate::local_store::{db_error, now_ms, row_text, LocalStore};
+use crate::pg::PgPools;
+use crate::sql_guard::is_read_only_sql;
+use crate::sql_template::{self, VarType, VariableDef};
+
+/// A `saved_sql` row as far as the backend needs it.
+#[derive(Debug, Clone)]
+pub struct SavedSql {
+    pub id: String,
+    pub name: String,
+    pub sql: String,
+    pub variables: Vec<VariableDef>,
+}
+
+pub async fn load_saved_sql(store: &LocalStore, id: &str) -> Result<SavedSql, String> {
+    let row = sqlx::query("SELECT id, name, sql, variables FROM saved_sql WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&store.pool()?)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| format!("saved_sql_not_found: {}", id))?;
+    let variables = match row_text(&row, "variables").filter(|raw| !raw.trim().is_empty()) {
+        Some(raw) => serde_json::from_str(&raw)
+            .map_err(|err| format!("saved_sql_invalid_variables: {}", err))?,
+        None => Vec::new(),
+    };
+    Ok(SavedSql {
+        id: row_text(&row, "id").unwrap_or_default(),
+        name: row_text(&row, "name").unwrap_or_default(),
+        sql: row_text(&row, "sql").unwrap_or_default(),
+        variables,
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedSqlExecution {
+    pub saved_id: String,
+    pub name: String,
+    /// Variable per `$n` in `result.sql`; list elements are `name[i]`.
+    pub placeholders: Vec<String>,
+    pub types: Vec<VarType>,
+    /// `sql` and `params` are exactly what was sent to Postgres.
+    #[serde(flatten)]
+    pub result: ExecuteResult,
+}
+
+/// Runs a saved query by id without the webview: values are checked against
+/// the stored variable definitions (type, required, enum options, bounds),
+/// defaults applied, the template compiled, and the statement executed in a
+/// read-only transaction.
+pub async fn run_saved_sql(
+    store: &LocalStore,
+    pools: &PgPools,
+    id: &str,
+    conn_id: &str,
+    values: &Map<String, Value>,
+) -> Result<SavedSqlExecution, String> {
+    let saved = load_saved_sql(store, id).await?;
+    let compiled = sql_template::compile(&saved.sql, &saved.variables, values)
+        .map_err(|err| format!("template_error: {}", err))?;
+    let (sql, params) = sql_template::typed_statement(&compiled);
+    if !is_read_only_sql(&sql) {
+        return Err("saved_sql_not_read_only".to_string());
+    }
+    let result =
+        executor::execute(store, pools, conn_id, &sql, params, false, Some(&saved.id)).await?;
+    Ok(SavedSqlExecution {
+        saved_id: saved.id,
+        name: saved.name,
+        placeholders: compiled.placeholders,
+        types: compiled.types,
+        result,
+    })
+}
+
+/// Runs a saved query by id; see [`run_saved_sql`].
+#[tauri::command]
+pub async fn execute_saved_sql(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    id: String,
+    conn_id: String,
+    values: Option<Map<String, Value>>,
+) -> Result<SavedSqlExecution, String> {
+    run_saved_sql(&store, &pools, &id, &conn_id, &values.unwrap_or_default()).await
+}
+
+/// One `saved_sql_revisions` row; JSON columns stay parsed as plain values
+/// so revisions written by older clients still load.
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedSqlRevision {
+    pub id: String,
+    pub saved_id: String,
+    pub revision: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub sql: String,
+    pub variables: Value,
+    pub dynamic_columns: Value,
+    pub calc_items: Value,
+    pub created_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LineDiff {
+    pub op: DiffOp,
+    pub text: String,
+    /// 1-based line in the older SQL, absent for inserts.
+    pub old_line: Option<usize>,
+    /// 1-based line in the newer SQL, absent for deletes.
+    pub new_line: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JsonChange {
+    /// `variables.limit.default`; arrays of named objects are keyed by name,
+    /// other arrays by index.
+    pub path: String,
+    pub kind: ChangeKind,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RevisionDiff {
+    pub from: SavedSqlRevision,
+    pub to: SavedSqlRevision,
+    pub sql: Vec<LineDiff>,
+    /// Name, description, variables, dynamic columns and calc items.
+    pub changes: Vec<JsonChange>,
+}
+
+/// An empty column reads as `Null`; text that is not JSON is an error rather
+/// than silently dropped, so a restore never overwrites it with a default.
+fn json_column(row: &SqliteRow, column: &str) -> Result<Value, String> {
+    match row_text(row, column).filter(|raw| !raw.trim().is_empty()) {
+        Some(raw) => serde_json::from_str(&raw)
+            .map_err(|err| format!("saved_sql_revision_invalid_json: {}: {}", column, err)),
+        None => Ok(Value::Null),
+    }
+}
+
+fn revision_from_row(row: &SqliteRow) -> Result<SavedSqlRevision, String> {
+    Ok(SavedSqlRevision {
+        id: row_text(row, "id").unwrap_or_default(),
+        saved_id: row_text(row, "saved_id").unwrap_or_default(),
+        revision: row.try_get("revision").unwrap_or_default(),
+        name: row_text(row, "name").unwrap_or_default(),
+        description: row_text(row, "description"),
+        sql: row_text(row, "sql").unwrap_or_default(),
+        variables: json_column(row, "variables")?,
+        dynamic_columns: json_column(row, "dynamic_columns")?,
+        calc_items: json_column(row, "calc_items")?,
+        created_at: row.try_get("created_at").ok().flatten(),
+    })
+}
+
+const REVISION_COLUMNS: &str =
+    "id, saved_id, revision, name, description, sql, variables, dynamic_columns, calc_items, created_at";
+
+async fn load_revision(store: &LocalStore, id: &str) -> Result<SavedSqlRevision, String> {
+    let sql = format!(
+        "SELECT {} FROM saved_sql_revisions WHERE id = ?",
+        REVISION_COLUMNS
+    );
+    sqlx::query(&sql)
+        .bind(id)
+        .fetch_optional(&store.pool()?)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| format!("saved_sql_revision_not_found: {}", id))
+        .and_then(|row| revision_from_row(&row))
+}
+
+/// Above this many LCS table cells (16 MB of `u32`) the changed middle of a
+/// diff is reported as one delete-then-insert block.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+/// Line diff: the common prefix and suffix match directly, the lines between
+/// them by longest common subsequence while that stays under `MAX_LCS_CELLS`.
+pub fn diff_lines(old: &str, new: &str) -> Vec<LineDiff> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (n, m) = (old.len() - prefix - suffix, new.len() - prefix - suffix);
+    let line = |op, text: &str, old_line, new_line| LineDiff {
+        op,
+        text: text.to_string(),
+        old_line,
+        new_line,
+    };
+    let equal = |i: usize, j: usize| line(DiffOp::Equal, old[i], Some(i + 1), Some(j + 1));
+    let delete = |i: usize| line(DiffOp::Delete, old[i], Some(i + 1), None);
+    let insert = |j: usize| line(DiffOp::Insert, new[j], None, Some(j + 1));
+
+    let mut out = Vec::with_capacity(old.len().max(new.len()));
+    out.extend((0..prefix).map(|i| equal(i, i)));
+    if (n + 1).saturating_mul(m + 1) <= MAX_LCS_CELLS {
+        let (a, b) = (&old[prefix..prefix + n], &new[prefix..prefix + m]);
+        // lcs[i * (m + 1) + j]: common lines between a[i..] and b[j..].
+        let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+        let at = |i: usize, j: usize| i * (m + 1) + j;
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[at(i, j)] = if a[i] == b[j] {
+                    lcs[at(i + 1, j + 1)] + 1
+                } else {
+                    lcs[at(i + 1, j)].max(lcs[at(i, j + 1)])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && a[i] == b[j] {
+                out.push(equal(prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if i < n && (j == m || lcs[at(i + 1, j)] >= lcs[at(i, j + 1)]) {
+                out.push(delete(prefix + i));
+                i += 1;
+            } else {
+                out.push(insert(prefix + j));
+                j += 1;
+            }
+        }
+    } else {
+        out.extend((prefix..prefix + n).map(delete));
+        out.extend((prefix..prefix + m).map(insert));
+    }
+    out.extend((0..suffix).map(|k| equal(prefix + n + k, prefix + m + k)));
+    out
+}
+
+fn named_entries(items: &[Value]) -> Option<Vec<(String, &Value)>> {
+    items
+        .iter()
+        .map(|item| {
+            item.get("name")
+                .and_then(Value::as_str)
+                .map(|name| (name.to_string(), item))
+        })
+        .collect()
+}
+
+/// Structural diff of two JSON values. Objects compare by key; arrays whose
+/// elements all carry a `name` compare by that name so reordering variables
+/// is not reported as a change of every entry.
+pub fn diff_json(path: &str, old: &Value, new: &Value, out: &mut Vec<JsonChange>) {
+    if old == new {
+        return;
+    }
+    let child = |key: &str| format!("{}.{}", path, key);
+    let mut walk = |old: Vec<(String, &Value)>, new: Vec<(String, &Value)>| {
+        for (key, old_value) in &old {
+            match new.iter().find(|(k, _)| k == key) {
+                Some((_, new_value)) => diff_json(&child(key), old_value, new_value, out),
+                None => out.push(JsonChange {
+                    path: child(key),
+                    kind: ChangeKind::Removed,
+                    old: Some((*old_value).clone()),
+                    new: None,
+                }),
+            }
+        }
+        for (key, new_value) in &new {
+            if !old.iter().any(|(k, _)| k == key) {
+                out.push(JsonChange {
+                    path: child(key),
+                    kind: ChangeKind::Added,
+                    old: None,
+                    new: Some((*new_value).clone()),
+                });
+            }
+        }
+    };
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => walk(
+            old.iter().map(|(k, v)| (k.clone(), v)).collect(),
+            new.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        ),
+        (Value::Array(old_items), Value::Array(new_items)) => {
+            match (named_entries(old_items), named_entries(new_items)) {
+                (Some(old), Some(new)) => walk(old, new),
+                _ => walk(
+                    old_items
+                        .iter()
+                        .enumerate()
+                        .map(|(i, v)| (i.to_string(), v))
+                        .collect(),
+                    new_items
+                        .iter()
+                        .enumerate()
+                        .map(|(i, v)| (i.to_string(), v))
+                        .collect(),
+                ),
+            }
+        }
+        _ => out.push(JsonChange {
+            path: path.to_string(),
+            kind: ChangeKind::Changed,
+            old: Some(old.clone()),
+            new: Some(new.clone()),
+        }),
+    }
+}
+
+#[tauri::command]
+pub async fn list_saved_sql_revisions(
+    store: State<'_, LocalStore>,
+    saved_id: String,
+) -> Result<Vec<SavedSqlRevision>, String> {
+    let sql = format!(
+        "SELECT {} FROM saved_sql_revisions WHERE saved_id = ? ORDER BY revision DESC",
+        REVISION_COLUMNS
+    );
+    let rows = sqlx::query(&sql)
+        .bind(&saved_id)
+        .fetch_all(&store.pool()?)
+        .await
+        .map_err(db_error)?;
+    rows.iter().map(revision_from_row).collect()
+}
+
+#[tauri::command]
+pub async fn diff_saved_sql_revisions(
+    store: State<'_, LocalStore>,
+    from_id: String,
+    to_id: String,
+) -> Result<RevisionDiff, String> {
+    let from = load_revision(&store, &from_id).await?;
+    let to = load_revision(&store, &to_id).await?;
+    if from.saved_id != to.saved_id {
+        return Err("saved_sql_revision_mismatch".to_string());
+    }
+    let mut changes = Vec::new();
+    diff_json(
+        "name",
+        &Value::String(from.name.clone()),
+        &Value::String(to.name.clone()),
+        &mut changes,
+    );
+    diff_json(
+        "description",
+        &from
+            .description
+            .clone()
+            .map(Value::String)
+            .unwrap_or(Value::Null),
+        &to.description
+            .clone()
+            .map(Value::String)
+            .unwrap_or(Value::Null),
+        &mut changes,
+    );
+    diff_json("variables", &from.variables, &to.variables, &mut changes);
+    diff_json(
+        "dynamicColumns",
+        &from.dynamic_columns,
+        &to.dynamic_columns,
+        &mut changes,
+    );
+    diff_json("calcItems", &from.calc_items, &to.calc_items, &mut changes);
+    Ok(RevisionDiff {
+        sql: diff_lines(&from.sql, &to.sql),
+        from,
+        to,
+        changes,
+    })
+}
+
+/// Copies a revision back onto its saved query, re-creating the query if it
+/// was deleted. The triggers record the result as a new revision, so
+/// restoring never discards history.
+pub async fn restore_revision(
+    store: &LocalStore,
+    revision_id: &str,
+) -> Result<SavedSqlRevision, String> {
+    let revision = load_revision(store, revision_id).await?;
+    let text = |value: &Value| (!value.is_null()).then(|| value.to_string());
+    // `saved_sql` timestamps are milliseconds, as the webview writes them.
+    let now = now_ms();
+    sqlx::query(
+        "INSERT INTO saved_sql (id, name, description, sql, variables, dynamic_columns, calc_items, is_archived, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, ?8, ?8)
+         ON CONFLICT(id) DO UPDATE SET name = excluded.name, description = excluded.description, sql = excluded.sql,
+           variables = excluded.variables, dynamic_columns = excluded.dynamic_columns, calc_items = excluded.calc_items,
+           updated_at = excluded.updated_at",
+    )
+    .bind(&revision.saved_id)
+    .bind(&revision.name)
+    .bind(&revision.description)
+    .bind(&revision.sql)
+    .bind(text(&revision.variables).unwrap_or_else(|| "[]".to_string()))
+    .bind(text(&revision.dynamic_columns))
+    .bind(text(&revision.calc_items))
+    .bind(now)
+    .execute(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    let sql = format!(
+        "SELECT {} FROM saved_sql_revisions WHERE saved_id = ? ORDER BY revision DESC LIMIT 1",
+        REVISION_COLUMNS
+    );
+    let row = sqlx::query(&sql)
+        .bind(&revision.saved_id)
+        .fetch_one(&store.pool()?)
+        .await
+        .map_err(db_error)?;
+    revision_from_row(&row)
+}
+
+#[tauri::command]
+pub async fn restore_saved_sql_revision(
+    store: State<'_, LocalStore>,
+    revision_id: String,
+) -> Result<SavedSqlRevision, String> {
+    restore_revision(&store, &revision_id).await
+}
+
+/// Highlight markers passed to FTS5 `highlight()`/`snippet()`; control
+/// characters cannot collide with saved SQL text.
+const HIT_OPEN: char = '\u{2}';
+const HIT_CLOSE: char = '\u{3}';
+const SEARCH_LIMIT: i64 = 50;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SnippetPart {
+    pub text: String,
+    pub hit: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedSqlSearchHit {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_archived: bool,
+    pub updated_at: Option<i64>,
+    pub name_highlight: Vec<SnippetPart>,
+    /// Best matching fragment from any column.
+    pub snippet: Vec<SnippetPart>,
+    /// Negated bm25; higher is better.
+    pub score: f64,
+}
+
+/// Turns search input into an FTS5 query. Words are ANDed, `"..."` is a
+/// phrase and a trailing `*` makes a prefix query; every term is quoted so
+/// FTS5 operators and punctuation in the input are matched literally.
+pub fn fts_query(input: &str) -> Option<String> {
+    let mut terms = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&ch) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut term = String::new();
+        if ch == '"' {
+            chars.next();
+            for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+                term.push(c);
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                term.push(c);
+                chars.next();
+            }
+        }
+        let mut prefix = false;
+        if chars.peek() == Some(&'*') {
+            chars.next();
+            prefix = true;
+        }
+        while let Some(stripped) = term.strip_suffix('*') {
+            term = stripped.to_string();
+            prefix = true;
+        }
+        let term = term.trim();
+        if term.is_empty() {
+            continue;
+        }
+        terms.push(format!(
+            "\"{}\"{}",
+            term.replace('"', "\"\""),
+            if prefix { "*" } else { "" }
+        ));
+    }
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+fn snippet_parts(marked: &str) -> Vec<SnippetPart> {
+    let mut parts: Vec<SnippetPart> = Vec::new();
+    let mut hit = false;
+    let mut text = String::new();
+    for ch in marked.chars() {
+        if ch == HIT_OPEN || ch == HIT_CLOSE {
+            if !text.is_empty() {
+                parts.push(SnippetPart {
+                    text: std::mem::take(&mut text),
+                    hit,
+                });
+            }
+            hit = ch == HIT_OPEN;
+            continue;
+        }
+        text.push(ch);
+    }
+    if !text.is_empty() {
+        parts.push(SnippetPart { text, hit });
+    }
+    parts
+}
+
+/// Ranked search over saved SQL names, descriptions, bodies and variable
+/// names. Name hits weigh most, then variable names, descriptions and SQL.
+pub async fn search(
+    store: &LocalStore,
+    query: &str,
+    include_archived: bool,
+    limit: i64,
+) -> Result<Vec<SavedSqlSearchHit>, String> {
+    let Some(matcher) = fts_query(query) else {
+        return Ok(Vec::new());
+    };
+    let sql = r#"
+        SELECT saved_sql_fts.saved_id AS id, s.name, s.description, s.is_archived, s.updated_at,
+               highlight(saved_sql_fts, 1, char(2), char(3)) AS name_highlight,
+               snippet(saved_sql_fts, -1, char(2), char(3), '…', 16) AS snippet,
+               bm25(saved_sql_fts, 0.0, 10.0, 3.0, 1.0, 5.0) AS rank
+        FROM saved_sql_fts
+        JOIN saved_sql s ON s.id = saved_sql_fts.saved_id
+        WHERE saved_sql_fts MATCH ?1 AND (?2 OR COALESCE(s.is_archived, 0) = 0)
+        ORDER BY rank
+        LIMIT ?3
+    "#;
+    let rows = sqlx::query(sql)
+        .bind(&matcher)
+        .bind(include_archived)
+        .bind(limit.clamp(1, 500))
+        .fetch_all(&store.pool()?)
+        .await
+        .map_err(db_error)?;
+    Ok(rows
+        .iter()
+        .map(|row| SavedSqlSearchHit {
+            id: row_text(row, "id").unwrap_or_default(),
+            name: row_text(row, "name").unwrap_or_default(),
+            description: row_text(row, "description"),
+            is_archived: row
+                .try_get::<Option<i64>, _>("is_archived")
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+                != 0,
+            updated_at: row.try_get("updated_at").ok().flatten(),
+            name_highlight: snippet_parts(&row_text(row, "name_highlight").unwrap_or_default()),
+            snippet: snippet_parts(&row_text(row, "snippet").unwrap_or_default()),
+            score: -row.try_get::<f64, _>("rank").unwrap_or_default(),
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn search_saved_sql(
+    store: State<'_, LocalStore>,
+    query: String,
+    include_archived: Option<bool>,
+    limit: Option<i64>,
+) -> Result<Vec<SavedSqlSearchHit>, String> {
+    search(
+        &store,
+        &query,
+        include_archived.unwrap_or(false),
+        limit.unwrap_or(SEARCH_LIMIT),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn diffs_sql_lines() {
+        let diff = diff_lines(
+            "select a\nfrom t\nwhere x = 1",
+            "select a, b\nfrom t\nwhere x = 1\nlimit 10",
+        );
+        let ops: Vec<(DiffOp, &str)> = diff
+            .iter()
+            .map(|d| (d.op.clone(), d.text.as_str()))
+            .collect();
+        assert_eq!(
+            ops,
+            vec![
+                (DiffOp::Delete, "select a"),
+                (DiffOp::Insert, "select a, b"),
+                (DiffOp::Equal, "from t"),
+                (DiffOp::Equal, "where x = 1"),
+                (DiffOp::Insert, "limit 10"),
+            ]
+        );
+        assert_eq!((diff[2].old_line, diff[2].new_line), (Some(2), Some(2)));
+        assert_eq!(diff[4].new_line, Some(4));
+
+        // Too large for the LCS table: the changed middle becomes one block.
+        let old: String = (0..3000).map(|i| format!("old {}\n", i)).collect();
+        let new: String = (0..3000).map(|i| format!("new {}\n", i)).collect();
+        let diff = diff_lines(&format!("head\n{}tail", old), &format!("head\n{}tail", new));
+        assert_eq!(diff.len(), 6002);
+        assert_eq!(
+            (diff[0].op.clone(), diff[0].new_line),
+            (DiffOp::Equal, Some(1))
+        );
+        assert_eq!(
+            (diff[1].op.clone(), diff[1].old_line),
+            (DiffOp::Delete, Some(2))
+        );
+        assert_eq!(
+            (diff[3001].op.clone(), diff[3001].new_line),
+            (DiffOp::Insert, Some(2))
+        );
+        assert_eq!(
+            (
+                diff[6001].op.clone(),
+                diff[6001].old_line,
+                diff[6001].new_line
+            ),
+            (DiffOp::Equal, Some(3002), Some(3002))
+        );
+    }
+
+    #[test]
+    fn diffs_variables_by_name() {
+        let old = json!([
+            { "name": "limit", "type": "number", "default": 100 },
+            { "name": "status", "type": "enum", "options": ["open"] }
+        ]);
+        let new = json!([
+            { "name": "status", "type": "enum", "options": ["open", "closed"] },
+            { "name": "limit", "type": "number", "default": 50 },
+            { "name": "since", "type": "date" }
+        ]);
+        let mut changes = Vec::new();
+        diff_json("variables", &old, &new, &mut changes);
+        let summary: Vec<(&str, ChangeKind)> = changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.kind.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("variables.limit.default", ChangeKind::Changed),
+                ("variables.status.options.1", ChangeKind::Added),
+                ("variables.since", ChangeKind::Added),
+            ]
+        );
+        assert_eq!(changes[0].old, Some(json!(100)));
+    }
+
+    #[test]
+    fn builds_fts_queries() {
+        assert_eq!(fts_query("  "), None);
+        assert_eq!(
+            fts_query("orders cust*"),
+            Some("\"orders\" \"cust\"*".to_string())
+        );
+        assert_eq!(
+            fts_query("\"group by\" status NEAR(a) o\"x"),
+            Some("\"group by\" \"status\" \"NEAR(a)\" \"o\" \"x\"".to_string())
+        );
+        assert_eq!(
+            fts_query("\"left join\"* -"),
+            Some("\"left join\"* \"-\"".to_string())
+        );
+    }
+
+    #[test]
+    fn revisions_survive_delete_and_reject_invalid_json() {
+        crate::test_support::with_store(|store| async move {
+            let pool = store.pool().unwrap();
+            let insert = |variables: &'static str| {
+                sqlx::query(
+                    "INSERT INTO saved_sql (id, name, sql, variables) VALUES ('s1', 'q', 'select 1', ?)",
+                )
+                .bind(variables)
+                .execute(&pool)
+            };
+            insert("[]").await.unwrap();
+            sqlx::query("DELETE FROM saved_sql WHERE id = 's1'")
+                .execute(&pool)
+                .await
+                .unwrap();
+            insert("not json").await.unwrap();
+
+            let rows: Vec<(String, i64)> = sqlx::query_as(
+                "SELECT id, revision FROM saved_sql_revisions WHERE saved_id = 's1' ORDER BY revision",
+            )
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+            assert_eq!(
+                rows.iter().map(|(_, rev)| *rev).collect::<Vec<_>>(),
+                vec![1, 2]
+            );
+            assert_eq!(
+                load_revision(&store, &rows[0].0).await.unwrap().variables,
+                json!([])
+            );
+            let err = load_revision(&store, &rows[1].0).await.unwrap_err();
+            assert!(
+                err.starts_with("saved_sql_revision_invalid_json: variables"),
+                "{}",
+                err
+            );
+        });
+    }
+
+    #[test]
+    fn restores_revisions_with_millisecond_timestamps() {
+        crate::test_support::with_store(|store| async move {
+            let pool = store.pool().unwrap();
+            // The webview stamps `saved_sql` with `Date.now()`.
+            let written = now_ms() - 60_000;
+            for (id, sql) in [("s1", "select 1"), ("s2", "select 2")] {
+                sqlx::query(
+                    "INSERT INTO saved_sql (id, name, sql, variables, created_at, updated_at) VALUES (?1, ?1, ?2, '[]', ?3, ?3)",
+                )
+                .bind(id)
+                .bind(sql)
+                .bind(written)
+                .execute(&pool)
+                .await
+                .unwrap();
+            }
+            sqlx::query(
+                "UPDATE saved_sql SET sql = 'select 10', updated_at = NULL WHERE id = 's1'",
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+            let revisions: Vec<(String, i64)> = sqlx::query_as(
+                "SELECT id, created_at FROM saved_sql_revisions WHERE saved_id = 's1' ORDER BY revision",
+            )
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+            // Trigger fallback when the row carries no timestamp.
+            assert!(revisions[1].1 >= written, "{:?}", revisions);
+
+            let restored = restore_revision(&store, &revisions[0].0).await.unwrap();
+            assert_eq!((restored.revision, restored.sql.as_str()), (3, "select 1"));
+            let newest: (String, i64) = sqlx::query_as(
+                "SELECT id, updated_at FROM saved_sql ORDER BY updated_at DESC LIMIT 1",
+            )
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            assert_eq!(newest.0, "s1");
+            assert!(
+                newest.1 > written && newest.1 <= now_ms(),
+                "{} vs {}",
+                newest.1,
+                written
+            );
+            assert_eq!(restored.created_at, Some(newest.1));
+        });
+    }
+}
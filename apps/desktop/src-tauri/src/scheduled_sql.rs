@@ -0,0 +1,966 @@
+//! Saved SQL run on cron schedules while the app is open. Each run is kept
+//! as a snapshot in `saved_sql_snapshots` (pruned by age and count), an
+//! optional condition on the result raises a desktop notification, and two
+//! snapshots of a schedule can be diffed row by row.
+
+use chrono::{Local, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::cron::CronSchedule;
+use crate::local_store::{db_error, new_id, now_sec, row_text, LocalStore};
+use crate::pg::PgPools;
+use crate::saved_sql::{self, SavedSqlExecution};
+use crate::sql_guard::is_read_only_sql;
+use crate::sql_template;
+
+pub const RUN_EVENT: &str = "saved-sql-schedule://run";
+
+/// Rows stored per snapshot; `row_count` still reports the full result.
+const MAX_SNAPSHOT_ROWS: usize = 1_000;
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+const MAX_RETENTION_DAYS: i64 = 3_650;
+const DEFAULT_MAX_SNAPSHOTS: i64 = 100;
+const MAX_SNAPSHOTS: i64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Eq,
+    Ne,
+}
+
+impl Comparison {
+    fn holds(self, ordering: Option<Ordering>) -> bool {
+        match (self, ordering) {
+            (Comparison::Ne, None) => true,
+            (_, None) => false,
+            (Comparison::Gt, Some(o)) => o == Ordering::Greater,
+            (Comparison::Gte, Some(o)) => o != Ordering::Less,
+            (Comparison::Lt, Some(o)) => o == Ordering::Less,
+            (Comparison::Lte, Some(o)) => o != Ordering::Greater,
+            (Comparison::Eq, Some(o)) => o == Ordering::Equal,
+            (Comparison::Ne, Some(o)) => o != Ordering::Equal,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Comparison::Gt => ">",
+            Comparison::Gte => ">=",
+            Comparison::Lt => "<",
+            Comparison::Lte => "<=",
+            Comparison::Eq => "=",
+            Comparison::Ne => "!=",
+        }
+    }
+}
+
+/// When a run should notify.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScheduleCondition {
+    /// Number of rows the query returned.
+    RowCount { op: Comparison, value: i64 },
+    /// `column` of the first row. Numbers (and numeric text) compare
+    /// numerically, anything else as text; a missing row reads as null.
+    Value {
+        column: String,
+        op: Comparison,
+        value: Value,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SqlSchedule {
+    pub id: String,
+    pub saved_id: String,
+    pub saved_name: String,
+    pub conn_id: String,
+    pub cron: String,
+    pub values: Map<String, Value>,
+    pub condition: Option<ScheduleCondition>,
+    pub notify: bool,
+    pub retention_days: i64,
+    pub max_snapshots: i64,
+    pub enabled: bool,
+    /// Last cron run; manual runs only show up as snapshots.
+    pub last_run_at: Option<i64>,
+    /// Next time the cron expression fires, if enabled.
+    pub next_run_at: Option<i64>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScheduleRequest {
+    /// Updates this schedule; a new one is created without it.
+    #[serde(default)]
+    pub id: Option<String>,
+    pub saved_id: String,
+    pub conn_id: String,
+    pub cron: String,
+    #[serde(default)]
+    pub values: Map<String, Value>,
+    #[serde(default)]
+    pub condition: Option<ScheduleCondition>,
+    #[serde(default)]
+    pub notify: Option<bool>,
+    #[serde(default)]
+    pub retention_days: Option<i64>,
+    #[serde(default)]
+    pub max_snapshots: Option<i64>,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotSummary {
+    pub id: String,
+    pub schedule_id: String,
+    pub saved_id: String,
+    pub conn_id: String,
+    pub run_at: i64,
+    /// `ok` or `error`.
+    pub status: String,
+    pub duration_ms: i64,
+    pub row_count: Option<i64>,
+    /// Only the first rows were stored.
+    pub truncated: bool,
+    /// The schedule's condition matched this result.
+    pub triggered: bool,
+    /// Condition message, or the error of a failed run.
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    #[serde(flatten)]
+    pub summary: SnapshotSummary,
+    pub columns: Vec<String>,
+    pub rows: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RowChange {
+    /// Values of the key columns, in order.
+    pub key: Value,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotDiff {
+    pub base: SnapshotSummary,
+    pub target: SnapshotSummary,
+    /// Rows are matched on these columns; empty compares whole rows.
+    pub key_columns: Vec<String>,
+    pub row_count_delta: Option<i64>,
+    pub added: Vec<Value>,
+    pub removed: Vec<Value>,
+    pub changed: Vec<RowChange>,
+    pub unchanged: usize,
+    /// A snapshot was capped, so only the stored rows were compared.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SnapshotDiffRequest {
+    pub target_id: String,
+    /// Defaults to the schedule's last successful run before the target's
+    /// day ("yesterday"), else the run just before it.
+    #[serde(default)]
+    pub base_id: Option<String>,
+    #[serde(default)]
+    pub key_columns: Vec<String>,
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    if let (Some(a), Some(b)) = (as_number(left), as_number(right)) {
+        return a.partial_cmp(&b);
+    }
+    let text = |value: &Value| match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    match (left, right) {
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        (Value::Null, _) | (_, Value::Null) => None,
+        _ => Some(text(left).cmp(&text(right))),
+    }
+}
+
+/// Message for a result that matches `condition`, `None` otherwise.
+pub fn evaluate(condition: &ScheduleCondition, row_count: i64, rows: &[Value]) -> Option<String> {
+    match condition {
+        ScheduleCondition::RowCount { op, value } => op
+            .holds(Some(row_count.cmp(value)))
+            .then(|| format!("{} row(s) returned ({} {})", row_count, op.symbol(), value)),
+        ScheduleCondition::Value { column, op, value } => {
+            let actual = rows
+                .first()
+                .and_then(|row| row.get(column))
+                .cloned()
+                .unwrap_or(Value::Null);
+            op.holds(compare(&actual, value))
+                .then(|| format!("{} is {} ({} {})", column, actual, op.symbol(), value))
+        }
+    }
+}
+
+fn row_key(row: &Value, key_columns: &[String]) -> Value {
+    Value::Array(
+        key_columns
+            .iter()
+            .map(|column| row.get(column).cloned().unwrap_or(Value::Null))
+            .collect(),
+    )
+}
+
+/// Rows added, removed and changed from `base` to `target`, plus the number
+/// left unchanged. Without key columns rows only match identical rows, so a
+/// change shows up as one removal and one addition.
+pub fn diff_rows(
+    base: &[Value],
+    target: &[Value],
+    key_columns: &[String],
+) -> (Vec<Value>, Vec<Value>, Vec<RowChange>, usize) {
+    let (mut added, mut removed, mut changed, mut unchanged) =
+        (Vec::new(), Vec::new(), Vec::new(), 0);
+    if key_columns.is_empty() {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for row in base {
+            *counts.entry(row.to_string()).or_default() += 1;
+        }
+        for row in target {
+            match counts.get_mut(&row.to_string()).filter(|n| **n > 0) {
+                Some(n) => {
+                    *n -= 1;
+                    unchanged += 1;
+                }
+                None => added.push(row.clone()),
+            }
+        }
+        for row in base {
+            if let Some(n) = counts.get_mut(&row.to_string()).filter(|n| **n > 0) {
+                *n -= 1;
+                removed.push(row.clone());
+            }
+        }
+        return (added, removed, changed, unchanged);
+    }
+    let mut by_key: HashMap<String, (&Value, bool)> = base
+        .iter()
+        .map(|row| (row_key(row, key_columns).to_string(), (row, false)))
+        .collect();
+    for row in target {
+        let key = row_key(row, key_columns);
+        match by_key.get_mut(&key.to_string()) {
+            Some((before, seen)) => {
+                *seen = true;
+                if *before == row {
+                    unchanged += 1;
+                } else {
+                    changed.push(RowChange {
+                        key,
+                        before: (*before).clone(),
+                        after: row.clone(),
+                    });
+                }
+            }
+            None => added.push(row.clone()),
+        }
+    }
+    for row in base {
+        if let Some((_, seen)) = by_key.get_mut(&row_key(row, key_columns).to_string()) {
+            if !*seen {
+                *seen = true;
+                removed.push(row.clone());
+            }
+        }
+    }
+    (added, removed, changed, unchanged)
+}
+
+fn next_run(cron: &str, enabled: bool) -> Option<i64> {
+    let schedule = CronSchedule::parse(cron).ok().filter(|_| enabled)?;
+    schedule.next_after(Local::now()).map(|at| at.timestamp())
+}
+
+fn schedule_from_row(row: &SqliteRow) -> SqlSchedule {
+    let cron = row_text(row, "cron").unwrap_or_default();
+    let enabled = row.try_get::<bool, _>("enabled").unwrap_or_default();
+    SqlSchedule {
+        id: row_text(row, "id").unwrap_or_default(),
+        saved_id: row_text(row, "saved_id").unwrap_or_default(),
+        saved_name: row_text(row, "saved_name").unwrap_or_default(),
+        conn_id: row_text(row, "conn_id").unwrap_or_default(),
+        values: row_text(row, "vars")
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default(),
+        condition: row_text(row, "condition").and_then(|raw| serde_json::from_str(&raw).ok()),
+        notify: row.try_get::<bool, _>("notify").unwrap_or_default(),
+        retention_days: row
+            .try_get("retention_days")
+            .unwrap_or(DEFAULT_RETENTION_DAYS),
+        max_snapshots: row
+            .try_get("max_snapshots")
+            .unwrap_or(DEFAULT_MAX_SNAPSHOTS),
+        enabled,
+        last_run_at: row.try_get("last_run_at").ok().flatten(),
+        next_run_at: next_run(&cron, enabled),
+        created_at: row.try_get("created_at").unwrap_or_default(),
+        updated_at: row.try_get("updated_at").unwrap_or_default(),
+        cron,
+    }
+}
+
+fn summary_from_row(row: &SqliteRow) -> SnapshotSummary {
+    SnapshotSummary {
+        id: row_text(row, "id").unwrap_or_default(),
+        schedule_id: row_text(row, "schedule_id").unwrap_or_default(),
+        saved_id: row_text(row, "saved_id").unwrap_or_default(),
+        conn_id: row_text(row, "conn_id").unwrap_or_default(),
+        run_at: row.try_get("run_at").unwrap_or_default(),
+        status: row_text(row, "status").unwrap_or_default(),
+        duration_ms: row.try_get("duration_ms").unwrap_or_default(),
+        row_count: row.try_get("row_count").ok().flatten(),
+        truncated: row.try_get::<bool, _>("truncated").unwrap_or_default(),
+        triggered: row.try_get::<bool, _>("triggered").unwrap_or_default(),
+        message: row_text(row, "message"),
+    }
+}
+
+const SCHEDULE_SELECT: &str = "SELECT s.*, q.name AS saved_name
+     FROM saved_sql_schedules s JOIN saved_sql q ON q.id = s.saved_id";
+
+pub async fn load_schedule(store: &LocalStore, id: &str) -> Result<SqlSchedule, String> {
+    let row = sqlx::query(&format!("{} WHERE s.id = ?1", SCHEDULE_SELECT))
+        .bind(id)
+        .fetch_optional(&store.pool()?)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| format!("schedule_not_found: {}", id))?;
+    Ok(schedule_from_row(&row))
+}
+
+pub async fn list_schedules(
+    store: &LocalStore,
+    saved_id: Option<&str>,
+) -> Result<Vec<SqlSchedule>, String> {
+    let rows = sqlx::query(&format!(
+        "{} WHERE ?1 IS NULL OR s.saved_id = ?1 ORDER BY q.name, s.created_at",
+        SCHEDULE_SELECT
+    ))
+    .bind(saved_id)
+    .fetch_all(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    Ok(rows.iter().map(schedule_from_row).collect())
+}
+
+/// Validates the cron expression, the variable values against the saved
+/// query, and that the compiled statement is read-only, then upserts.
+pub async fn save_schedule(
+    store: &LocalStore,
+    request: ScheduleRequest,
+) -> Result<SqlSchedule, String> {
+    let cron = request.cron.trim().to_string();
+    CronSchedule::parse(&cron)?;
+    let saved = saved_sql::load_saved_sql(store, &request.saved_id).await?;
+    let compiled = sql_template::compile(&saved.sql, &saved.variables, &request.values)
+        .map_err(|err| format!("template_error: {}", err))?;
+    if !is_read_only_sql(&sql_template::typed_statement(&compiled).0) {
+        return Err("saved_sql_not_read_only".to_string());
+    }
+    if let Some(ScheduleCondition::Value { column, .. }) = &request.condition {
+        if column.trim().is_empty() {
+            return Err("schedule_condition_invalid: column is required".to_string());
+        }
+    }
+    let values = serde_json::to_string(&request.values).map_err(|err| err.to_string())?;
+    let condition = request
+        .condition
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|err| err.to_string())?;
+    let retention_days = request
+        .retention_days
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+        .clamp(1, MAX_RETENTION_DAYS);
+    let max_snapshots = request
+        .max_snapshots
+        .unwrap_or(DEFAULT_MAX_SNAPSHOTS)
+        .clamp(1, MAX_SNAPSHOTS);
+    let now = now_sec();
+    let id = match request.id {
+        Some(id) => {
+            let done = sqlx::query(
+                "UPDATE saved_sql_schedules SET saved_id = ?2, conn_id = ?3, cron = ?4, vars = ?5,
+                   condition = ?6, notify = ?7, retention_days = ?8, max_snapshots = ?9,
+                   enabled = ?10, updated_at = ?11
+                 WHERE id = ?1",
+            )
+            .bind(&id)
+            .bind(&saved.id)
+            .bind(&request.conn_id)
+            .bind(&cron)
+            .bind(&values)
+            .bind(&condition)
+            .bind(request.notify.unwrap_or(true))
+            .bind(retention_days)
+            .bind(max_snapshots)
+            .bind(request.enabled.unwrap_or(true))
+            .bind(now)
+            .execute(&store.pool()?)
+            .await
+            .map_err(db_error)?;
+            if done.rows_affected() == 0 {
+                return Err(format!("schedule_not_found: {}", id));
+            }
+            id
+        }
+        None => {
+            let id = new_id("sched");
+            sqlx::query(
+                "INSERT INTO saved_sql_schedules (id, saved_id, conn_id, cron, vars, condition, notify,
+                   retention_days, max_snapshots, enabled, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?11)",
+            )
+            .bind(&id)
+            .bind(&saved.id)
+            .bind(&request.conn_id)
+            .bind(&cron)
+            .bind(&values)
+            .bind(&condition)
+            .bind(request.notify.unwrap_or(true))
+            .bind(retention_days)
+            .bind(max_snapshots)
+            .bind(request.enabled.unwrap_or(true))
+            .bind(now)
+            .execute(&store.pool()?)
+            .await
+            .map_err(db_error)?;
+            id
+        }
+    };
+    load_schedule(store, &id).await
+}
+
+/// Snapshot of one run; rows past [`MAX_SNAPSHOT_ROWS`] are dropped after
+/// the condition has seen them.
+fn build_snapshot(
+    schedule: &SqlSchedule,
+    run_at: i64,
+    duration_ms: i64,
+    outcome: Result<SavedSqlExecution, String>,
+) -> Snapshot {
+    let mut summary = SnapshotSummary {
+        id: new_id("snap"),
+        schedule_id: schedule.id.clone(),
+        saved_id: schedule.saved_id.clone(),
+        conn_id: schedule.conn_id.clone(),
+        run_at,
+        status: "ok".to_string(),
+        duration_ms,
+        row_count: None,
+        truncated: false,
+        triggered: false,
+        message: None,
+    };
+    match outcome {
+        Ok(execution) => {
+            let mut rows = execution.result.rows;
+            let row_count = rows.len() as i64;
+            summary.row_count = Some(row_count);
+            summary.message = schedule
+                .condition
+                .as_ref()
+                .and_then(|condition| evaluate(condition, row_count, &rows));
+            summary.triggered = summary.message.is_some();
+            summary.truncated = rows.len() > MAX_SNAPSHOT_ROWS;
+            rows.truncate(MAX_SNAPSHOT_ROWS);
+            Snapshot {
+                summary,
+                columns: execution.result.columns,
+                rows,
+            }
+        }
+        Err(err) => {
+            summary.status = "error".to_string();
+            summary.message = Some(err);
+            Snapshot {
+                summary,
+                columns: Vec::new(),
+                rows: Vec::new(),
+            }
+        }
+    }
+}
+
+/// Inserts the snapshot, then prunes the schedule's snapshots past its
+/// retention age or count.
+async fn store_snapshot(
+    store: &LocalStore,
+    schedule: &SqlSchedule,
+    snapshot: &Snapshot,
+) -> Result<(), String> {
+    let summary = &snapshot.summary;
+    let columns = serde_json::to_string(&snapshot.columns).map_err(|err| err.to_string())?;
+    let rows = serde_json::to_string(&snapshot.rows).map_err(|err| err.to_string())?;
+    let mut tx = store.pool()?.begin().await.map_err(db_error)?;
+    sqlx::query(
+        "INSERT INTO saved_sql_snapshots (id, schedule_id, saved_id, conn_id, run_at, status,
+           duration_ms, row_count, columns, rows, truncated, triggered, message)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+    )
+    .bind(&summary.id)
+    .bind(&summary.schedule_id)
+    .bind(&summary.saved_id)
+    .bind(&summary.conn_id)
+    .bind(summary.run_at)
+    .bind(&summary.status)
+    .bind(summary.duration_ms)
+    .bind(summary.row_count)
+    .bind(columns)
+    .bind(rows)
+    .bind(summary.truncated)
+    .bind(summary.triggered)
+    .bind(&summary.message)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    sqlx::query(
+        "DELETE FROM saved_sql_snapshots WHERE schedule_id = ?1 AND (run_at < ?2 OR id NOT IN (
+           SELECT id FROM saved_sql_snapshots WHERE schedule_id = ?1 ORDER BY run_at DESC, id DESC LIMIT ?3
+         ))",
+    )
+    .bind(&schedule.id)
+    .bind(summary.run_at - schedule.retention_days * 86_400)
+    .bind(schedule.max_snapshots)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)
+}
+
+fn notify(app: &AppHandle, schedule: &SqlSchedule, summary: &SnapshotSummary) {
+    let body = match summary.status.as_str() {
+        "error" => format!(
+            "{} failed: {}",
+            schedule.saved_name,
+            summary.message.as_deref().unwrap_or_default()
+        ),
+        _ => format!(
+            "{}: {}",
+            schedule.saved_name,
+            summary.message.as_deref().unwrap_or_default()
+        ),
+    };
+    let _ = app
+        .notification()
+        .builder()
+        .title("reiDbView scheduled SQL")
+        .body(&body)
+        .show();
+}
+
+/// Schedules whose query is in flight, whether started by the cron loop or
+/// by hand, so one schedule never runs twice at once.
+#[derive(Default)]
+pub struct ScheduleRuns {
+    running: Mutex<HashSet<String>>,
+}
+
+impl ScheduleRuns {
+    /// `None` while a run of the schedule is already in flight.
+    fn begin(&self, id: &str) -> Result<Option<RunGuard<'_>>, String> {
+        let mut running = self
+            .running
+            .lock()
+            .map_err(|_| "schedule_runs_poisoned".to_string())?;
+        Ok(running.insert(id.to_string()).then(|| RunGuard {
+            runs: self,
+            id: id.to_string(),
+        }))
+    }
+}
+
+/// Clears the schedule's in-flight mark when the run ends, however it ends.
+struct RunGuard<'a> {
+    runs: &'a ScheduleRuns,
+    id: String,
+}
+
+impl Drop for RunGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut running) = self.runs.running.lock() {
+            running.remove(&self.id);
+        }
+    }
+}
+
+/// Runs the schedule's query once, stores the snapshot, emits [`RUN_EVENT`]
+/// and notifies when the condition matched or the run failed. Fails with
+/// `schedule_running` while another run of the schedule is in flight.
+pub async fn run_schedule(
+    app: &AppHandle,
+    schedule: &SqlSchedule,
+) -> Result<SnapshotSummary, String> {
+    let runs = app.state::<ScheduleRuns>();
+    let Some(_run) = runs.begin(&schedule.id)? else {
+        return Err("schedule_running".to_string());
+    };
+    let store = app.state::<LocalStore>();
+    let pools = app.state::<PgPools>();
+    let run_at = now_sec();
+    let started = Instant::now();
+    let outcome = saved_sql::run_saved_sql(
+        &store,
+        &pools,
+        &schedule.saved_id,
+        &schedule.conn_id,
+        &schedule.values,
+    )
+    .await;
+    let snapshot = build_snapshot(
+        schedule,
+        run_at,
+        started.elapsed().as_millis() as i64,
+        outcome,
+    );
+    store_snapshot(&store, schedule, &snapshot).await?;
+    let summary = snapshot.summary;
+    let _ = app.emit(RUN_EVENT, &summary);
+    if schedule.notify && (summary.triggered || summary.status == "error") {
+        notify(app, schedule, &summary);
+    }
+    Ok(summary)
+}
+
+/// Moves `last_run_at`, the anchor `run_due` computes the next fire time
+/// from, to `now`. Compare-and-set, so each cron occurrence is claimed once;
+/// a claimed occurrence that finds a manual run in flight is skipped.
+async fn claim(store: &LocalStore, schedule: &SqlSchedule, now: i64) -> Result<bool, String> {
+    let done = sqlx::query(
+        "UPDATE saved_sql_schedules SET last_run_at = ?2 WHERE id = ?1 AND last_run_at IS ?3",
+    )
+    .bind(&schedule.id)
+    .bind(now)
+    .bind(schedule.last_run_at)
+    .execute(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    Ok(done.rows_affected() == 1)
+}
+
+/// Starts every enabled schedule whose next fire time since its last run,
+/// its last edit or `since` has passed.
+async fn run_due(app: &AppHandle, since: i64) -> Result<(), String> {
+    let store = app.state::<LocalStore>();
+    let now = now_sec();
+    for schedule in list_schedules(&store, None).await? {
+        if !schedule.enabled {
+            continue;
+        }
+        let Ok(cron) = CronSchedule::parse(&schedule.cron) else {
+            continue;
+        };
+        let anchor = schedule
+            .last_run_at
+            .unwrap_or_default()
+            .max(schedule.updated_at)
+            .max(since);
+        let due = Local
+            .timestamp_opt(anchor, 0)
+            .single()
+            .and_then(|anchor| cron.next_after(anchor));
+        let Some(due) = due else {
+            continue;
+        };
+        if due.timestamp() > now || !claim(&store, &schedule, now).await? {
+            continue;
+        }
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let _ = run_schedule(&app, &schedule).await;
+        });
+    }
+    Ok(())
+}
+
+/// Checks schedules at the start of every minute. Runs missed while the app
+/// was closed or the store locked are not made up.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut available_since: Option<i64> = None;
+        loop {
+            if app.state::<LocalStore>().pool().is_ok() {
+                let since = *available_since.get_or_insert_with(now_sec);
+                // A store that is still migrating has no schedule table yet.
+                let _ = run_due(&app, since).await;
+            } else {
+                available_since = None;
+            }
+            let second = u64::from(Local::now().second());
+            tokio::time::sleep(Duration::from_secs(60 - second.min(59) + 1)).await;
+        }
+    });
+}
+
+async fn load_snapshot(store: &LocalStore, id: &str) -> Result<Snapshot, String> {
+    let row = sqlx::query("SELECT * FROM saved_sql_snapshots WHERE id = ?1")
+        .bind(id)
+        .fetch_optional(&store.pool()?)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| format!("snapshot_not_found: {}", id))?;
+    let parse = |column: &str| {
+        row_text(&row, column)
+            .and_then(|raw| serde_json::from_str::<Vec<Value>>(&raw).ok())
+            .unwrap_or_default()
+    };
+    Ok(Snapshot {
+        summary: summary_from_row(&row),
+        columns: parse("columns")
+            .into_iter()
+            .filter_map(|c| c.as_str().map(str::to_string))
+            .collect(),
+        rows: parse("rows"),
+    })
+}
+
+/// The run to compare `target` with: the last successful one before the
+/// target's local day, else the one just before it.
+async fn baseline_id(store: &LocalStore, target: &SnapshotSummary) -> Result<String, String> {
+    let day_start = Local
+        .timestamp_opt(target.run_at, 0)
+        .single()
+        .and_then(|at| at.date_naive().and_hms_opt(0, 0, 0))
+        .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+        .map(|midnight| midnight.timestamp())
+        .unwrap_or(target.run_at - 86_400);
+    let pool = store.pool()?;
+    let yesterday: Option<String> = sqlx::query_scalar(
+        "SELECT id FROM saved_sql_snapshots
+         WHERE schedule_id = ?1 AND status = 'ok' AND run_at < ?2
+         ORDER BY run_at DESC, id DESC LIMIT 1",
+    )
+    .bind(&target.schedule_id)
+    .bind(day_start)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_error)?;
+    if let Some(id) = yesterday {
+        return Ok(id);
+    }
+    let previous: Option<String> = sqlx::query_scalar(
+        "SELECT id FROM saved_sql_snapshots
+         WHERE schedule_id = ?1 AND (run_at < ?2 OR (run_at = ?2 AND id < ?3))
+         ORDER BY run_at DESC, id DESC LIMIT 1",
+    )
+    .bind(&target.schedule_id)
+    .bind(target.run_at)
+    .bind(&target.id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_error)?;
+    previous.ok_or_else(|| "snapshot_no_baseline".to_string())
+}
+
+pub async fn diff_snapshots(
+    store: &LocalStore,
+    request: &SnapshotDiffRequest,
+) -> Result<SnapshotDiff, String> {
+    let target = load_snapshot(store, &request.target_id).await?;
+    let base_id = match &request.base_id {
+        Some(id) => id.clone(),
+        None => baseline_id(store, &target.summary).await?,
+    };
+    let base = load_snapshot(store, &base_id).await?;
+    let key_columns: Vec<String> = request
+        .key_columns
+        .iter()
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .collect();
+    let (added, removed, changed, unchanged) = diff_rows(&base.rows, &target.rows, &key_columns);
+    Ok(SnapshotDiff {
+        row_count_delta: target
+            .summary
+            .row_count
+            .zip(base.summary.row_count)
+            .map(|(after, before)| after - before),
+        truncated: base.summary.truncated || target.summary.truncated,
+        base: base.summary,
+        target: target.summary,
+        key_columns,
+        added,
+        removed,
+        changed,
+        unchanged,
+    })
+}
+
+#[tauri::command]
+pub async fn save_sql_schedule(
+    store: State<'_, LocalStore>,
+    payload: ScheduleRequest,
+) -> Result<SqlSchedule, String> {
+    save_schedule(&store, payload).await
+}
+
+#[tauri::command]
+pub async fn list_sql_schedules(
+    store: State<'_, LocalStore>,
+    saved_id: Option<String>,
+) -> Result<Vec<SqlSchedule>, String> {
+    list_schedules(&store, saved_id.as_deref()).await
+}
+
+/// Deletes the schedule and its snapshots.
+#[tauri::command]
+pub async fn delete_sql_schedule(store: State<'_, LocalStore>, id: String) -> Result<bool, String> {
+    let done = sqlx::query("DELETE FROM saved_sql_schedules WHERE id = ?1")
+        .bind(&id)
+        .execute(&store.pool()?)
+        .await
+        .map_err(db_error)?;
+    Ok(done.rows_affected() > 0)
+}
+
+/// Runs a schedule immediately. `last_run_at` only records cron runs, so its
+/// cron timing is unaffected; fails with `schedule_running` while a run of
+/// the schedule is in flight.
+#[tauri::command]
+pub async fn run_sql_schedule_now(
+    app: AppHandle,
+    store: State<'_, LocalStore>,
+    id: String,
+) -> Result<SnapshotSummary, String> {
+    let schedule = load_schedule(&store, &id).await?;
+    run_schedule(&app, &schedule).await
+}
+
+/// Newest first, without rows.
+#[tauri::command]
+pub async fn list_sql_snapshots(
+    store: State<'_, LocalStore>,
+    schedule_id: String,
+    limit: Option<i64>,
+) -> Result<Vec<SnapshotSummary>, String> {
+    let rows = sqlx::query(
+        "SELECT id, schedule_id, saved_id, conn_id, run_at, status, duration_ms, row_count,
+           truncated, triggered, message
+         FROM saved_sql_snapshots WHERE schedule_id = ?1 ORDER BY run_at DESC, id DESC LIMIT ?2",
+    )
+    .bind(&schedule_id)
+    .bind(limit.unwrap_or(50).clamp(1, MAX_SNAPSHOTS))
+    .fetch_all(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    Ok(rows.iter().map(summary_from_row).collect())
+}
+
+#[tauri::command]
+pub async fn get_sql_snapshot(
+    store: State<'_, LocalStore>,
+    id: String,
+) -> Result<Snapshot, String> {
+    load_snapshot(&store, &id).await
+}
+
+#[tauri::command]
+pub async fn diff_sql_snapshots(
+    store: State<'_, LocalStore>,
+    payload: SnapshotDiffRequest,
+) -> Result<SnapshotDiff, String> {
+    diff_snapshots(&store, &payload).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn evaluates_conditions() {
+        let rows = vec![json!({ "orphans": "12", "oldest": null })];
+        let row_count: ScheduleCondition =
+            serde_json::from_value(json!({ "kind": "row_count", "op": "gt", "value": 0 })).unwrap();
+        assert_eq!(
+            evaluate(&row_count, 1, &rows).as_deref(),
+            Some("1 row(s) returned (> 0)")
+        );
+        assert_eq!(evaluate(&row_count, 0, &[]), None);
+
+        let value = |op: &str, value: Value, column: &str| ScheduleCondition::Value {
+            column: column.to_string(),
+            op: serde_json::from_value(json!(op)).unwrap(),
+            value,
+        };
+        // Numeric text from Postgres compares as a number, not "12" < "9".
+        assert!(evaluate(&value("gte", json!(9), "orphans"), 1, &rows).is_some());
+        assert!(evaluate(&value("lt", json!(9), "orphans"), 1, &rows).is_none());
+        assert!(evaluate(&value("eq", Value::Null, "oldest"), 1, &rows).is_some());
+        assert!(evaluate(&value("gt", json!(0), "oldest"), 1, &rows).is_none());
+        assert!(evaluate(&value("ne", json!("x"), "missing"), 0, &[]).is_some());
+    }
+
+    #[test]
+    fn diffs_rows_with_and_without_keys() {
+        let base = vec![
+            json!({ "id": 1, "state": "stuck" }),
+            json!({ "id": 2, "state": "stuck" }),
+        ];
+        let target = vec![
+            json!({ "id": 2, "state": "failed" }),
+            json!({ "id": 3, "state": "stuck" }),
+        ];
+        let (added, removed, changed, unchanged) = diff_rows(&base, &target, &["id".to_string()]);
+        assert_eq!(added, vec![json!({ "id": 3, "state": "stuck" })]);
+        assert_eq!(removed, vec![json!({ "id": 1, "state": "stuck" })]);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].key, json!([2]));
+        assert_eq!(unchanged, 0);
+
+        let (added, removed, changed, unchanged) = diff_rows(&base, &target, &[]);
+        assert_eq!((added.len(), removed.len(), changed.len()), (2, 2, 0));
+        assert_eq!(unchanged, 0);
+        let (added, removed, _, unchanged) = diff_rows(&base, &base, &[]);
+        assert!(added.is_empty() && removed.is_empty());
+        assert_eq!(unchanged, 2);
+    }
+
+    #[test]
+    fn tracks_in_flight_runs_per_schedule() {
+        let runs = ScheduleRuns::default();
+        let first = runs.begin("sch_a").unwrap();
+        assert!(first.is_some());
+        assert!(runs.begin("sch_a").unwrap().is_none());
+        assert!(runs.begin("sch_b").unwrap().is_some());
+        drop(first);
+        assert!(runs.begin("sch_a").unwrap().is_some());
+    }
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::local_store::{db_error, row_text};
+
+/// Mirror of `SchemaCachePayload` in `lib/schema-cache.ts`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SchemaCachePayload {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(default)]
+    pub tables: Vec<CachedTable>,
+    #[serde(default)]
+    pub ddls: Vec<CachedDdl>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CachedTable {
+    pub schema: String,
+    pub name: String,
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub columns: Vec<CachedColumn>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CachedColumn {
+    pub name: String,
+    #[serde(default, rename = "dataType")]
+    pub data_type: String,
+    #[serde(default)]
+    pub nullable: Option<bool>,
+    #[serde(default, rename = "isPrimaryKey")]
+    pub is_primary_key: Option<bool>,
+    #[serde(default, rename = "isForeignKey")]
+    pub is_foreign_key: Option<bool>,
+    #[serde(default)]
+    pub references: Option<ColumnReference>,
+    /// Every foreign key the column takes part in; `references` only holds
+    /// the first. Missing in caches written before it was recorded.
+    #[serde(default, rename = "foreignKeys", skip_serializing_if = "Vec::is_empty")]
+    pub foreign_keys: Vec<ColumnReference>,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+impl CachedColumn {
+    /// The column's foreign key references, falling back to `references` for
+    /// caches that predate `foreignKeys`.
+    pub fn all_references(&self) -> impl Iterator<Item = &ColumnReference> {
+        let fallback = if self.foreign_keys.is_empty() {
+            self.references.as_ref()
+        } else {
+            None
+        };
+        self.foreign_keys.iter().chain(fallback)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ColumnReference {
+    pub schema: String,
+    pub table: String,
+    pub column: String,
+    /// Foreign key constraint name; columns of a composite key share it.
+    /// Missing in caches written before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constraint: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CachedDdl {
+    pub schema: String,
+    pub name: String,
+    pub ddl: String,
+}
+
+pub async fn load_schema_cache(
+    pool: &SqlitePool,
+    conn_id: &str,
+) -> Result<Option<SchemaCachePayload>, String> {
+    let row = sqlx::query("SELECT content FROM schema_cache WHERE conn_id = ?1 LIMIT 1")
+        .bind(conn_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let Some(content) = row_text(&row, "content") else {
+        return Ok(None);
+    };
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|err| format!("schema_cache_invalid: {}", err))
+}
+
+/// Resolves `schema.table` or a bare table name against the cached tables.
+/// Bare names prefer `public` when several schemas contain the same table.
+pub fn resolve_table<'a>(
+    payload: &'a SchemaCachePayload,
+    name: &str,
+) -> Result<&'a CachedTable, String> {
+    let trimmed = name.trim().trim_matches('"');
+    if let Some((schema, table)) = trimmed.split_once('.') {
+        let schema = schema.trim_matches('"');
+        let table = table.trim_matches('"');
+        return payload
+            .tables
+            .iter()
+            .find(|t| t.schema == schema && t.name == table)
+            .ok_or_else(|| format!("table_not_found:{}", trimmed));
+    }
+    let matches: Vec<&CachedTable> = payload
+        .tables
+        .iter()
+        .filter(|t| t.name == trimmed)
+        .collect();
+    match matches.len() {
+        0 => Err(format!("table_not_found:{}", trimmed)),
+        1 => Ok(matches[0]),
+        _ => matches
+            .iter()
+            .find(|t| t.schema == "public")
+            .copied()
+            .ok_or_else(|| format!("ambiguous_table:{}", trimmed)),
+    }
+}
+
+pub fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+pub fn quote_qualified(schema: &str, name: &str) -> String {
+    format!("{}.{}", quote_ident(schema), quote_ident(name))
+}
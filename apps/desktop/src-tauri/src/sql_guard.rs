@@ -0,0 +1,295 @@
+/// What a stretch of SQL text is, as far as statement splitting and keyword
+/// screens care.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlSpan {
+    Code,
+    /// `'...'` (including `E'...'`), `"..."` or a `$tag$...$tag$` body.
+    Quoted,
+    /// `-- ...` up to the newline, or a (nested) `/* ... */`.
+    Comment,
+}
+
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte >= 0x80
+}
+
+fn quote_end(bytes: &[u8], start: usize, quote: u8, backslash_escapes: bool) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if backslash_escapes && bytes[i] == b'\\' {
+            i += 2;
+        } else if bytes[i] == quote {
+            if bytes.get(i + 1) != Some(&quote) {
+                return i + 1;
+            }
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+fn block_comment_end(bytes: &[u8], start: usize) -> usize {
+    let (mut depth, mut i) = (1, start + 2);
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'/', Some(b'*')) => {
+                depth += 1;
+                i += 2;
+            }
+            (b'*', Some(b'/')) => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Length of the `$tag$` opening at `start`; `$1` parameters are not tags.
+fn dollar_tag_len(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    match bytes.get(i) {
+        Some(b'$') => return Some(2),
+        Some(&byte) if byte.is_ascii_alphabetic() || byte == b'_' || byte >= 0x80 => {}
+        _ => return None,
+    }
+    while bytes.get(i).is_some_and(|&byte| is_ident_byte(byte)) {
+        i += 1;
+    }
+    (bytes.get(i) == Some(&b'$')).then_some(i + 1 - start)
+}
+
+/// Splits `sql` into code, quoted and comment spans the way the Postgres
+/// lexer would, so `;`, `--` and keywords inside literals are left alone.
+/// Unterminated quotes and comments run to the end of the text.
+pub fn lex_sql(sql: &str) -> Vec<(SqlSpan, &str)> {
+    let bytes = sql.as_bytes();
+    let mut spans = Vec::new();
+    let (mut code_start, mut i) = (0, 0);
+    while i < bytes.len() {
+        let prev_is_ident = i > 0 && is_ident_byte(bytes[i - 1]);
+        let (kind, end) = match bytes[i] {
+            b'-' if bytes.get(i + 1) == Some(&b'-') => (
+                SqlSpan::Comment,
+                sql[i..].find('\n').map_or(bytes.len(), |n| i + n),
+            ),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                (SqlSpan::Comment, block_comment_end(bytes, i))
+            }
+            b'\'' => {
+                // `E'...'` strings take backslash escapes.
+                let escapes = prev_is_ident
+                    && matches!(bytes[i - 1], b'e' | b'E')
+                    && (i < 2 || !is_ident_byte(bytes[i - 2]));
+                (SqlSpan::Quoted, quote_end(bytes, i, b'\'', escapes))
+            }
+            b'"' => (SqlSpan::Quoted, quote_end(bytes, i, b'"', false)),
+            b'$' if !prev_is_ident => match dollar_tag_len(bytes, i) {
+                Some(len) => {
+                    let tag = &sql[i..i + len];
+                    let end = sql[i + len..]
+                        .find(tag)
+                        .map_or(bytes.len(), |n| i + len + n + len);
+                    (SqlSpan::Quoted, end)
+                }
+                None => {
+                    i += 1;
+                    continue;
+                }
+            },
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        if code_start < i {
+            spans.push((SqlSpan::Code, &sql[code_start..i]));
+        }
+        let end = end.min(bytes.len());
+        spans.push((kind, &sql[i..end]));
+        code_start = end;
+        i = end;
+    }
+    if code_start < bytes.len() {
+        spans.push((SqlSpan::Code, &sql[code_start..]));
+    }
+    spans
+}
+
+/// `sql` with each comment replaced by a space.
+pub fn strip_sql_comments(sql: &str) -> String {
+    lex_sql(sql)
+        .into_iter()
+        .map(|(kind, text)| if kind == SqlSpan::Comment { " " } else { text })
+        .collect()
+}
+
+/// Statements separated by `;` outside literals and comments, with comments
+/// removed and surrounding whitespace trimmed; empty statements are dropped.
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    for (kind, text) in lex_sql(sql) {
+        match kind {
+            SqlSpan::Comment => current.push(' '),
+            SqlSpan::Quoted => current.push_str(text),
+            SqlSpan::Code => {
+                let mut parts = text.split(';');
+                current.push_str(parts.next().unwrap_or_default());
+                for part in parts {
+                    statements.push(std::mem::take(&mut current));
+                    current.push_str(part);
+                }
+            }
+        }
+    }
+    statements.push(current);
+    statements
+        .into_iter()
+        .map(|statement| statement.trim().to_string())
+        .filter(|statement| !statement.is_empty())
+        .collect()
+}
+
+/// Keyword screen used before SQL reaches a read-only transaction: a
+/// `SELECT`/`WITH` with no write or transaction-control keywords. Whitespace
+/// is collapsed first so keywords on their own line are still seen.
+pub fn is_read_only_sql(sql: &str) -> bool {
+    // Literals are masked so their contents never read as keywords.
+    let masked: String = lex_sql(sql)
+        .into_iter()
+        .map(|(kind, text)| match kind {
+            SqlSpan::Code => text.replace(';', " ; "),
+            SqlSpan::Quoted => " '' ".to_string(),
+            SqlSpan::Comment => " ".to_string(),
+        })
+        .collect();
+    let normalized = masked.split_whitespace().collect::<Vec<_>>().join(" ");
+    if normalized.is_empty() {
+        return false;
+    }
+    let lowered = format!("{} ", normalized.to_lowercase());
+    let disallowed = [
+        " insert ",
+        " update ",
+        " delete ",
+        " drop ",
+        " truncate ",
+        " alter ",
+        " create ",
+        " grant ",
+        " revoke ",
+        " comment ",
+        " merge ",
+        " call ",
+        " do ",
+        " begin ",
+        " commit ",
+        " rollback ",
+    ];
+    for keyword in disallowed.iter() {
+        if lowered.contains(keyword) {
+            return false;
+        }
+    }
+    lowered.starts_with("select ") || lowered.starts_with("with ")
+}
+
+/// Screen for strict read-only sessions: returns the first statement that
+/// would lift the session's read-only default. That covers `SET`/`RESET` of
+/// `default_transaction_read_only` or `transaction_read_only`, `READ WRITE`
+/// transaction modes, `RESET ALL`, `DISCARD ALL` and `set_config()` calls
+/// on either setting.
+pub fn read_only_override(sql: &str) -> Option<String> {
+    split_statements(sql).into_iter().find_map(|statement| {
+        let normalized = statement.split_whitespace().collect::<Vec<_>>().join(" ");
+        let lowered = normalized.to_lowercase();
+        let touches_setting = lowered.contains("transaction_read_only");
+        let read_write = lowered.contains("read write");
+        let overrides = match lowered.split(' ').next().unwrap_or("") {
+            "set" => touches_setting || read_write,
+            "reset" => touches_setting || lowered == "reset all",
+            "discard" => lowered == "discard all",
+            "begin" | "start" => read_write,
+            _ => false,
+        } || (touches_setting && lowered.contains("set_config"));
+        overrides.then_some(normalized)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screens_multiline_sql() {
+        assert!(is_read_only_sql("SELECT\n  pid\nFROM pg_stat_activity"));
+        assert!(is_read_only_sql(
+            "with x as (select 1) select * from x -- delete "
+        ));
+        assert!(!is_read_only_sql("select 1;\ndelete from t"));
+        assert!(!is_read_only_sql("select 1; commit"));
+        assert!(!is_read_only_sql("update t set a = 1"));
+    }
+
+    #[test]
+    fn finds_read_only_overrides() {
+        for sql in [
+            "SET default_transaction_read_only = off",
+            "set local transaction_read_only to false",
+            "SET SESSION CHARACTERISTICS AS TRANSACTION\n  READ WRITE",
+            "select 1; RESET ALL",
+            "DISCARD ALL",
+            "BEGIN ISOLATION LEVEL SERIALIZABLE, READ WRITE",
+            "select set_config('default_transaction_read_only', 'off', false)",
+        ] {
+            assert!(read_only_override(sql).is_some(), "{}", sql);
+        }
+        assert_eq!(
+            read_only_override("select 1;\nreset   default_transaction_read_only").as_deref(),
+            Some("reset default_transaction_read_only")
+        );
+        assert!(read_only_override("SET search_path = public").is_none());
+        assert!(read_only_override("select * from t -- read write").is_none());
+        assert!(read_only_override("RESET search_path").is_none());
+        assert!(read_only_override("select 'a; reset all' as note").is_none());
+        assert_eq!(
+            read_only_override("select ';'; set transaction read write").as_deref(),
+            Some("set transaction read write")
+        );
+    }
+
+    #[test]
+    fn lexes_literals_and_comments() {
+        let statements = split_statements(
+            "select 'a;b' as note, \"x;--y\" from t -- tail; comment\n\
+             ; select $fn$ begin; return 1; end $fn$, $1::int /* a /* nested; */ b */;",
+        );
+        assert_eq!(
+            statements,
+            vec![
+                "select 'a;b' as note, \"x;--y\" from t",
+                "select $fn$ begin; return 1; end $fn$, $1::int",
+            ]
+        );
+        assert_eq!(
+            split_statements("select E'it\\'s; fine', 'it''s; fine';;").len(),
+            1
+        );
+        assert_eq!(
+            strip_sql_comments("select '--not a comment' -- a comment"),
+            "select '--not a comment'  "
+        );
+        assert!(is_read_only_sql(
+            "select * from jobs where note = 'a;b -- delete'"
+        ));
+        assert!(is_read_only_sql("select $$; drop table t$$ as body"));
+        assert!(!is_read_only_sql("select 'x'; drop table t"));
+    }
+}
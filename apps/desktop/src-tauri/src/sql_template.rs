@@ -0,0 +1,1380 @@
+//! Saved SQL templates, the Rust side of `lib/sql-template.ts`: `{{var}}`,
+//! `{{#when}}`, `{{#if}}` and `{{#each list as item}}` with `@index`,
+//! `@first` and `@last`, compiled to `$n` placeholders and typed values.
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VarType {
+    Text,
+    Number,
+    Boolean,
+    Date,
+    Timestamp,
+    Json,
+    Uuid,
+    Raw,
+    Enum,
+}
+
+/// `SavedQueryVariableDef`. A definition with `itemType` is a list variable:
+/// `{{name}}` expands to one placeholder per element and `{{#each}}` walks it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariableDef {
+    pub name: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(rename = "type")]
+    pub var_type: VarType,
+    #[serde(default)]
+    pub required: bool,
+    /// `Some(Value::Null)` is an explicit null default, unlike a missing one.
+    #[serde(default, deserialize_with = "present")]
+    pub default: Option<Value>,
+    #[serde(default)]
+    pub options: Option<Vec<String>>,
+    #[serde(default)]
+    pub item_type: Option<VarType>,
+    /// Inclusive bounds: numeric for numbers, ISO order for dates and
+    /// timestamps, character count for text.
+    #[serde(default)]
+    pub min: Option<Value>,
+    #[serde(default)]
+    pub max: Option<Value>,
+}
+
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompiledSql {
+    pub text: String,
+    pub values: Vec<Value>,
+    /// Variable per placeholder; list elements are `name[i]`.
+    pub placeholders: Vec<String>,
+    pub types: Vec<VarType>,
+    /// `text` with values inlined, for display only.
+    pub preview: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TemplateError {
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl TemplateError {
+    fn at(template: &str, offset: usize, message: String) -> Self {
+        let before = &template[..offset.min(template.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rsplit('\n')
+            .next()
+            .map(|tail| tail.chars().count() + 1)
+            .unwrap_or(1);
+        TemplateError {
+            message,
+            line: Some(line),
+            column: Some(column),
+        }
+    }
+
+    fn value(message: String) -> Self {
+        TemplateError {
+            message,
+            line: None,
+            column: None,
+        }
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "{} (line {}, column {})", self.message, line, column)
+            }
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Parse and render failures carry a byte offset into the template; it is
+/// turned into line and column once, at the public boundary.
+type Fail = (usize, String);
+
+fn fail<T>(offset: usize, message: impl Into<String>) -> Result<T, Fail> {
+    Err((offset, message.into()))
+}
+
+// --- Template AST -------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Var {
+        name: String,
+        pos: usize,
+    },
+    When {
+        vars: Vec<String>,
+        pos: usize,
+        body: Vec<Node>,
+        else_body: Option<Vec<Node>>,
+    },
+    If {
+        expr: Expr,
+        body: Vec<Node>,
+        else_body: Option<Vec<Node>>,
+    },
+    Each {
+        list: String,
+        alias: String,
+        pos: usize,
+        body: Vec<Node>,
+        else_body: Option<Vec<Node>>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    And,
+    Or,
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Value),
+    Variable { name: String, pos: usize },
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    In(Box<Expr>, Vec<Value>),
+    Presence { name: String, pos: usize },
+}
+
+enum Block {
+    When(Vec<String>),
+    If(Expr),
+    Each { list: String, alias: String },
+}
+
+impl Block {
+    fn name(&self) -> &'static str {
+        match self {
+            Block::When(_) => "when",
+            Block::If(_) => "if",
+            Block::Each { .. } => "each",
+        }
+    }
+}
+
+struct Frame {
+    block: Block,
+    pos: usize,
+    body: Vec<Node>,
+    else_body: Option<Vec<Node>>,
+}
+
+fn ident_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap())
+}
+
+fn is_loop_var(name: &str) -> bool {
+    matches!(name, "@index" | "@first" | "@last")
+}
+
+fn parse_template(template: &str) -> Result<Vec<Node>, Fail> {
+    let mut root: Vec<Node> = Vec::new();
+    let mut stack: Vec<Frame> = Vec::new();
+
+    fn bucket<'a>(root: &'a mut Vec<Node>, stack: &'a mut [Frame]) -> &'a mut Vec<Node> {
+        match stack.last_mut() {
+            None => root,
+            Some(frame) => match frame.else_body.as_mut() {
+                Some(else_body) => else_body,
+                None => &mut frame.body,
+            },
+        }
+    }
+
+    let mut cursor = 0;
+    while cursor < template.len() {
+        let Some(open) = template[cursor..].find("{{").map(|i| cursor + i) else {
+            bucket(&mut root, &mut stack).push(Node::Text(template[cursor..].to_string()));
+            break;
+        };
+        if open > cursor {
+            bucket(&mut root, &mut stack).push(Node::Text(template[cursor..open].to_string()));
+        }
+        let Some(close) = template[open + 2..].find("}}").map(|i| open + 2 + i) else {
+            return fail(open, "Unclosed tag");
+        };
+        cursor = close + 2;
+        let raw = &template[open + 2..close];
+        let content = raw.trim();
+        if content.is_empty() {
+            continue;
+        }
+        let content_pos = open + 2 + (raw.len() - raw.trim_start().len());
+
+        if let Some(directive) = content.strip_prefix('#') {
+            let (name, rest) = match directive.find(char::is_whitespace) {
+                Some(i) => (&directive[..i], directive[i..].trim()),
+                None => (directive, ""),
+            };
+            let rest_pos = content_pos + content.len() - rest.len();
+            let block = match name {
+                "when" => {
+                    let vars: Vec<String> = rest
+                        .split(|c: char| c.is_whitespace() || c == ',')
+                        .filter(|s| !s.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                    if vars.is_empty() {
+                        return fail(open, "`#when` requires at least one variable name");
+                    }
+                    Block::When(vars)
+                }
+                "if" => {
+                    if rest.is_empty() {
+                        return fail(open, "`#if` requires a condition expression");
+                    }
+                    Block::If(parse_expression(rest, rest_pos)?)
+                }
+                "each" => {
+                    let parts: Vec<&str> = rest.split_whitespace().collect();
+                    match parts.as_slice() {
+                        [list, "as", alias]
+                            if ident_regex().is_match(list) && ident_regex().is_match(alias) =>
+                        {
+                            Block::Each {
+                                list: list.to_string(),
+                                alias: alias.to_string(),
+                            }
+                        }
+                        _ => return fail(open, "`#each` expects `{{#each list as item}}`"),
+                    }
+                }
+                _ => return fail(open, format!("Unknown block directive: #{}", name)),
+            };
+            stack.push(Frame {
+                block,
+                pos: open,
+                body: Vec::new(),
+                else_body: None,
+            });
+            continue;
+        }
+
+        if content == "else" {
+            let Some(frame) = stack.last_mut() else {
+                return fail(open, "`else` found without an open block");
+            };
+            if frame.else_body.is_some() {
+                return fail(open, "Multiple `else` clauses are not allowed");
+            }
+            frame.else_body = Some(Vec::new());
+            continue;
+        }
+
+        if let Some(name) = content.strip_prefix('/') {
+            let name = name.trim();
+            let Some(frame) = stack.pop() else {
+                return fail(open, format!("Unmatched closing block: /{}", name));
+            };
+            if frame.block.name() != name {
+                return fail(
+                    open,
+                    format!(
+                        "Closing block /{} does not match opening {}",
+                        name,
+                        frame.block.name()
+                    ),
+                );
+            }
+            let node = match frame.block {
+                Block::When(vars) => Node::When {
+                    vars,
+                    pos: frame.pos,
+                    body: frame.body,
+                    else_body: frame.else_body,
+                },
+                Block::If(expr) => Node::If {
+                    expr,
+                    body: frame.body,
+                    else_body: frame.else_body,
+                },
+                Block::Each { list, alias } => Node::Each {
+                    list,
+                    alias,
+                    pos: frame.pos,
+                    body: frame.body,
+                    else_body: frame.else_body,
+                },
+            };
+            bucket(&mut root, &mut stack).push(node);
+            continue;
+        }
+
+        if is_loop_var(content) {
+            if !stack
+                .iter()
+                .any(|frame| matches!(frame.block, Block::Each { .. }))
+            {
+                return fail(open, format!("{} is only available inside #each", content));
+            }
+        } else if !ident_regex().is_match(content) {
+            return fail(open, format!("Unsupported tag: {{{{{}}}}}", content));
+        }
+        bucket(&mut root, &mut stack).push(Node::Var {
+            name: content.to_string(),
+            pos: open,
+        });
+    }
+
+    if let Some(frame) = stack.last() {
+        return fail(frame.pos, format!("Unclosed block: {}", frame.block.name()));
+    }
+    Ok(root)
+}
+
+// --- Expressions --------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(Value),
+    Op(&'static str),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    In,
+}
+
+fn tokenize_expression(input: &str, base: usize) -> Result<Vec<(Token, usize)>, Fail> {
+    let chars: Vec<(usize, char)> = input.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let (offset, ch) = chars[i];
+        let pos = base + offset;
+        let next = chars.get(i + 1).map(|(_, c)| *c);
+        if ch.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if ch == '"' || ch == '\'' {
+            let mut value = String::new();
+            let mut closed = false;
+            i += 1;
+            while i < chars.len() {
+                let c = chars[i].1;
+                if c == '\\' {
+                    let Some((_, escaped)) = chars.get(i + 1) else {
+                        return fail(
+                            base + chars[i].0,
+                            "Invalid escape sequence in string literal",
+                        );
+                    };
+                    value.push(*escaped);
+                    i += 2;
+                    continue;
+                }
+                i += 1;
+                if c == ch {
+                    closed = true;
+                    break;
+                }
+                value.push(c);
+            }
+            if !closed {
+                return fail(pos, "Unterminated string literal");
+            }
+            tokens.push((Token::Literal(Value::String(value)), pos));
+            continue;
+        }
+        if ch.is_ascii_digit() || (ch == '-' && next.is_some_and(|c| c.is_ascii_digit())) {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i].1.is_ascii_digit() {
+                i += 1;
+            }
+            if i < chars.len() && chars[i].1 == '.' {
+                i += 1;
+                while i < chars.len() && chars[i].1.is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let end = chars.get(i).map(|(o, _)| *o).unwrap_or(input.len());
+            let raw = &input[chars[start].0..end];
+            let number = match raw.parse::<i64>() {
+                Ok(int) => Value::from(int),
+                Err(_) => match raw.parse::<f64>() {
+                    Ok(float) => Value::from(float),
+                    Err(_) => return fail(pos, format!("Invalid number literal: {}", raw)),
+                },
+            };
+            tokens.push((Token::Literal(number), pos));
+            continue;
+        }
+        let two: Option<&'static str> = match (ch, next) {
+            ('&', Some('&')) => Some("&&"),
+            ('|', Some('|')) => Some("||"),
+            ('=', Some('=')) => Some("=="),
+            ('!', Some('=')) => Some("!="),
+            ('>', Some('=')) => Some(">="),
+            ('<', Some('=')) => Some("<="),
+            _ => None,
+        };
+        if let Some(op) = two {
+            tokens.push((Token::Op(op), pos));
+            i += 2;
+            continue;
+        }
+        let single = match ch {
+            '!' => Some(Token::Op("!")),
+            '>' => Some(Token::Op(">")),
+            '<' => Some(Token::Op("<")),
+            '(' => Some(Token::LParen),
+            ')' => Some(Token::RParen),
+            '[' => Some(Token::LBracket),
+            ']' => Some(Token::RBracket),
+            ',' => Some(Token::Comma),
+            _ => None,
+        };
+        if let Some(token) = single {
+            tokens.push((token, pos));
+            i += 1;
+            continue;
+        }
+        if ch.is_ascii_alphabetic() || ch == '_' || ch == '@' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].1.is_ascii_alphanumeric() || chars[i].1 == '_') {
+                i += 1;
+            }
+            let end = chars.get(i).map(|(o, _)| *o).unwrap_or(input.len());
+            let ident = &input[chars[start].0..end];
+            let token = match ident {
+                "true" => Token::Literal(Value::Bool(true)),
+                "false" => Token::Literal(Value::Bool(false)),
+                "null" => Token::Literal(Value::Null),
+                "in" => Token::In,
+                _ if ch == '@' && !is_loop_var(ident) => {
+                    return fail(pos, format!("Unknown loop variable: {}", ident))
+                }
+                _ => Token::Ident(ident.to_string()),
+            };
+            tokens.push((token, pos));
+            continue;
+        }
+        return fail(pos, format!("Unexpected character in expression: {}", ch));
+    }
+    Ok(tokens)
+}
+
+struct ExprParser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+}
+
+impl ExprParser {
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|(_, offset)| *offset)
+            .unwrap_or(self.end)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.tokens.get(self.pos).map(|(t, _)| t) == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), Fail> {
+        if self.eat(token) {
+            Ok(())
+        } else if self.pos >= self.tokens.len() {
+            fail(self.end, "Unexpected end of expression")
+        } else {
+            fail(self.offset(), "Unexpected token in expression")
+        }
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[(&'static str, BinaryOp)],
+        next: fn(&mut Self) -> Result<Expr, Fail>,
+    ) -> Result<Expr, Fail> {
+        let mut node = next(self)?;
+        'outer: loop {
+            for (symbol, op) in ops {
+                if self.eat(&Token::Op(symbol)) {
+                    node = Expr::Binary(*op, Box::new(node), Box::new(next(self)?));
+                    continue 'outer;
+                }
+            }
+            return Ok(node);
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, Fail> {
+        self.binary(&[("||", BinaryOp::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, Fail> {
+        self.binary(&[("&&", BinaryOp::And)], Self::equality)
+    }
+
+    fn equality(&mut self) -> Result<Expr, Fail> {
+        self.binary(
+            &[("==", BinaryOp::Eq), ("!=", BinaryOp::Ne)],
+            Self::relational,
+        )
+    }
+
+    fn relational(&mut self) -> Result<Expr, Fail> {
+        self.binary(
+            &[
+                (">", BinaryOp::Gt),
+                (">=", BinaryOp::Ge),
+                ("<", BinaryOp::Lt),
+                ("<=", BinaryOp::Le),
+            ],
+            Self::membership,
+        )
+    }
+
+    fn membership(&mut self) -> Result<Expr, Fail> {
+        let node = self.unary()?;
+        if !self.eat(&Token::In) {
+            return Ok(node);
+        }
+        self.expect(&Token::LBracket)?;
+        let mut options = Vec::new();
+        if !self.eat(&Token::RBracket) {
+            loop {
+                match self.tokens.get(self.pos).cloned() {
+                    Some((Token::Literal(value), _)) => {
+                        self.pos += 1;
+                        options.push(value);
+                    }
+                    Some((_, offset)) => {
+                        return fail(offset, "Array literals only support primitive constants")
+                    }
+                    None => return fail(self.end, "Unexpected end in array literal"),
+                }
+                if self.eat(&Token::Comma) {
+                    continue;
+                }
+                self.expect(&Token::RBracket)?;
+                break;
+            }
+        }
+        Ok(Expr::In(Box::new(node), options))
+    }
+
+    fn unary(&mut self) -> Result<Expr, Fail> {
+        if self.eat(&Token::Op("!")) {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, Fail> {
+        let Some((token, offset)) = self.tokens.get(self.pos).cloned() else {
+            return fail(self.end, "Unexpected end of expression");
+        };
+        self.pos += 1;
+        match token {
+            Token::Literal(value) => Ok(Expr::Literal(value)),
+            Token::LParen => {
+                let expr = self.or()?;
+                self.expect(&Token::RParen)?;
+                Ok(expr)
+            }
+            Token::Ident(name) if self.eat(&Token::LParen) => {
+                if name != "presence" {
+                    return fail(offset, format!("Unsupported function: {}", name));
+                }
+                let arg = match self.tokens.get(self.pos).cloned() {
+                    Some((Token::Ident(arg), arg_pos)) => {
+                        self.pos += 1;
+                        Expr::Presence {
+                            name: arg,
+                            pos: arg_pos,
+                        }
+                    }
+                    _ => return fail(offset, "presence() argument must be a variable name"),
+                };
+                self.expect(&Token::RParen)?;
+                Ok(arg)
+            }
+            Token::Ident(name) => Ok(Expr::Variable { name, pos: offset }),
+            _ => fail(offset, "Unexpected token in expression"),
+        }
+    }
+}
+
+fn parse_expression(input: &str, base: usize) -> Result<Expr, Fail> {
+    let mut parser = ExprParser {
+        tokens: tokenize_expression(input, base)?,
+        pos: 0,
+        end: base + input.len(),
+    };
+    let expr = parser.or()?;
+    if parser.pos < parser.tokens.len() {
+        return fail(parser.offset(), "Unexpected trailing tokens in expression");
+    }
+    Ok(expr)
+}
+
+/// JavaScript truthiness, as the webview evaluates `{{#if}}`.
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(flag) => *flag,
+        Value::Number(number) => number.as_f64().is_some_and(|n| n != 0.0 && !n.is_nan()),
+        Value::String(text) => !text.is_empty(),
+        Value::Array(_) | Value::Object(_) => true,
+    }
+}
+
+fn presence(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Array(items) => !items.is_empty(),
+        Value::String(text) => !text.trim().is_empty(),
+        _ => true,
+    }
+}
+
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) if a.is_number() && b.is_number() => x == y,
+        _ => a == b,
+    }
+}
+
+fn compare(op: BinaryOp, a: &Value, b: &Value) -> bool {
+    let ordering = match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64().partial_cmp(&y.as_f64()),
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        _ => None,
+    };
+    let Some(ordering) = ordering else {
+        return false;
+    };
+    match op {
+        BinaryOp::Gt => ordering.is_gt(),
+        BinaryOp::Ge => ordering.is_ge(),
+        BinaryOp::Lt => ordering.is_lt(),
+        BinaryOp::Le => ordering.is_le(),
+        _ => false,
+    }
+}
+
+// --- Values -------------------------------------------------------------------
+
+fn date_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"^\d{4}-\d{2}-\d{2}([ T]\d{2}:\d{2}(:\d{2}(\.\d+)?)?)?(Z|[+-]\d{2}(:?\d{2})?)?$",
+        )
+        .unwrap()
+    })
+}
+
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// `normalizeValue` for one non-null value, then the definition's bounds.
+fn coerce(name: &str, var_type: VarType, def: &VariableDef, raw: &Value) -> Result<Value, String> {
+    let options = def.options.as_deref().unwrap_or_default();
+    let value = coerce_type(name, var_type, options, raw)?;
+    for (bound, is_min) in [(&def.min, true), (&def.max, false)] {
+        let Some(bound) = bound.as_ref().filter(|bound| !bound.is_null()) else {
+            continue;
+        };
+        let ordering = match var_type {
+            VarType::Number => value
+                .as_f64()
+                .zip(
+                    coerce_type(name, var_type, &[], bound)
+                        .ok()
+                        .and_then(|b| b.as_f64()),
+                )
+                .and_then(|(value, bound)| value.partial_cmp(&bound)),
+            VarType::Date | VarType::Timestamp => {
+                value.as_str().map(|v| v.cmp(as_text(bound).trim()))
+            }
+            VarType::Text | VarType::Uuid | VarType::Enum => value
+                .as_str()
+                .zip(bound.as_u64())
+                .map(|(v, bound)| (v.chars().count() as u64).cmp(&bound)),
+            _ => None,
+        };
+        let within = match ordering {
+            Some(ordering) if is_min => ordering.is_ge(),
+            Some(ordering) => ordering.is_le(),
+            None => true,
+        };
+        if !within {
+            let (relation, bound) = (if is_min { ">=" } else { "<=" }, as_text(bound));
+            return Err(match var_type {
+                VarType::Text | VarType::Uuid | VarType::Enum => {
+                    format!("Variable {} length must be {} {}", name, relation, bound)
+                }
+                _ => format!("Variable {} must be {} {}", name, relation, bound),
+            });
+        }
+    }
+    Ok(value)
+}
+
+fn coerce_type(
+    name: &str,
+    var_type: VarType,
+    options: &[String],
+    raw: &Value,
+) -> Result<Value, String> {
+    match var_type {
+        VarType::Text | VarType::Uuid | VarType::Raw => Ok(Value::String(as_text(raw))),
+        VarType::Enum => {
+            let text = as_text(raw);
+            if !options.is_empty() && !options.contains(&text) {
+                return Err(format!(
+                    "Variable {} must be one of: {}",
+                    name,
+                    options.join(", ")
+                ));
+            }
+            Ok(Value::String(text))
+        }
+        VarType::Number => {
+            let parsed = match raw {
+                Value::Number(number) => Some(number.clone()),
+                Value::String(text) => {
+                    let text = text.trim();
+                    match text.parse::<i64>() {
+                        Ok(int) => Some(int.into()),
+                        Err(_) => text
+                            .parse::<f64>()
+                            .ok()
+                            .and_then(serde_json::Number::from_f64),
+                    }
+                }
+                _ => None,
+            };
+            parsed
+                .map(Value::Number)
+                .ok_or_else(|| format!("Variable {} must be a number", name))
+        }
+        VarType::Boolean => match raw {
+            Value::Bool(flag) => Ok(Value::Bool(*flag)),
+            Value::String(text) if text == "true" || text == "1" => Ok(Value::Bool(true)),
+            Value::String(text) if text == "false" || text == "0" => Ok(Value::Bool(false)),
+            Value::Number(number) if number.as_f64() == Some(1.0) => Ok(Value::Bool(true)),
+            Value::Number(number) if number.as_f64() == Some(0.0) => Ok(Value::Bool(false)),
+            _ => Err(format!("Variable {} must be a boolean", name)),
+        },
+        VarType::Date | VarType::Timestamp => {
+            let text = as_text(raw).trim().to_string();
+            if !date_regex().is_match(&text) {
+                let kind = if var_type == VarType::Date {
+                    "date"
+                } else {
+                    "timestamp"
+                };
+                return Err(format!("Variable {} must be a {}", name, kind));
+            }
+            if var_type == VarType::Date {
+                Ok(Value::String(text[..10].to_string()))
+            } else {
+                Ok(Value::String(text))
+            }
+        }
+        VarType::Json => match raw {
+            Value::String(text) => serde_json::from_str(text)
+                .map_err(|_| format!("Variable {} must be valid JSON", name)),
+            other => Ok(other.clone()),
+        },
+    }
+}
+
+/// Resolves one variable: defaults apply only when the key is absent; an
+/// explicit `null` or `''` clears the value.
+fn resolve_value(def: &VariableDef, input: &Map<String, Value>) -> Result<Value, String> {
+    // Defaults come from the saved definition, not from a form, but they get
+    // the same coercion and bounds as user input.
+    let Some(raw) = input.get(&def.name).or(def.default.as_ref()) else {
+        if def.required {
+            return Err(format!("Variable {} is required", def.name));
+        }
+        return Ok(Value::Null);
+    };
+    if raw.is_null() || raw.as_str() == Some("") {
+        if def.required {
+            return Err(format!("Variable {} is required", def.name));
+        }
+        return Ok(Value::Null);
+    }
+    let Some(item_type) = def.item_type else {
+        return coerce(&def.name, def.var_type, def, raw);
+    };
+    let items = match raw {
+        Value::Array(items) => items,
+        _ => return Err(format!("Variable {} must be a list", def.name)),
+    };
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            if item.is_null() {
+                Ok(Value::Null)
+            } else {
+                coerce(&format!("{}[{}]", def.name, index), item_type, def, item)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Value::Array)
+}
+
+fn infer_type(value: &Value) -> VarType {
+    match value {
+        Value::Number(_) => VarType::Number,
+        Value::Bool(_) => VarType::Boolean,
+        Value::Array(_) | Value::Object(_) => VarType::Json,
+        _ => VarType::Text,
+    }
+}
+
+// --- Rendering ----------------------------------------------------------------
+
+/// The `{{#each}}` iteration a tag is rendered in.
+struct Scope<'a> {
+    alias: &'a str,
+    /// Placeholder name prefix: `ids[2]`.
+    key: String,
+    value: Value,
+    item_type: Option<VarType>,
+    index: usize,
+    len: usize,
+}
+
+/// What a name resolves to while rendering.
+struct Binding {
+    key: String,
+    value: Value,
+    var_type: VarType,
+    /// Set for list variables and `#each` over them.
+    item_type: Option<VarType>,
+}
+
+struct Renderer<'a> {
+    defs: HashMap<&'a str, &'a VariableDef>,
+    values: HashMap<&'a str, Value>,
+    scopes: Vec<Scope<'a>>,
+    out: String,
+    params: Vec<(String, Value, VarType)>,
+}
+
+/// Marks a parameter in the rendered text; replaced by `$n` after
+/// whitespace is tidied so raw values are inserted untouched.
+const PARAM_MARK: char = '\u{0}';
+
+impl<'a> Renderer<'a> {
+    fn lookup(&self, name: &str, pos: usize) -> Result<Binding, Fail> {
+        if let Some(scope) = self.scopes.last() {
+            let value = match name {
+                "@index" => Some(Value::from(scope.index as u64)),
+                "@first" => Some(Value::Bool(scope.index == 0)),
+                "@last" => Some(Value::Bool(scope.index + 1 == scope.len)),
+                _ => None,
+            };
+            if let Some(value) = value {
+                return Ok(Binding {
+                    key: name.to_string(),
+                    var_type: infer_type(&value),
+                    value,
+                    item_type: None,
+                });
+            }
+        }
+        if let Some(scope) = self.scopes.iter().rev().find(|scope| scope.alias == name) {
+            let var_type = scope.item_type.unwrap_or_else(|| infer_type(&scope.value));
+            return Ok(Binding {
+                key: scope.key.clone(),
+                value: scope.value.clone(),
+                var_type,
+                item_type: None,
+            });
+        }
+        match (self.defs.get(name), self.values.get(name)) {
+            (Some(def), Some(value)) => Ok(Binding {
+                key: def.name.clone(),
+                value: value.clone(),
+                var_type: def.var_type,
+                item_type: def.item_type,
+            }),
+            _ => fail(pos, format!("Undefined variable: {}", name)),
+        }
+    }
+
+    fn push_param(&mut self, key: String, value: Value, var_type: VarType) {
+        if var_type == VarType::Raw {
+            self.out.push_str(&as_text(&value));
+            return;
+        }
+        let index = match self
+            .params
+            .iter()
+            .position(|(existing, _, _)| *existing == key)
+        {
+            Some(index) => index,
+            None => {
+                self.params.push((key, value, var_type));
+                self.params.len() - 1
+            }
+        };
+        self.out.push(PARAM_MARK);
+        self.out.push_str(&index.to_string());
+        self.out.push(PARAM_MARK);
+    }
+
+    fn eval(&self, expr: &Expr) -> Result<Value, Fail> {
+        Ok(match expr {
+            Expr::Literal(value) => value.clone(),
+            Expr::Variable { name, pos } => self.lookup(name, *pos)?.value,
+            Expr::Not(inner) => Value::Bool(!truthy(&self.eval(inner)?)),
+            Expr::Presence { name, pos } => Value::Bool(presence(&self.lookup(name, *pos)?.value)),
+            Expr::In(value, options) => {
+                let value = self.eval(value)?;
+                Value::Bool(options.iter().any(|option| values_equal(option, &value)))
+            }
+            Expr::Binary(op, left, right) => {
+                let left = self.eval(left)?;
+                Value::Bool(match op {
+                    BinaryOp::And => truthy(&left) && truthy(&self.eval(right)?),
+                    BinaryOp::Or => truthy(&left) || truthy(&self.eval(right)?),
+                    BinaryOp::Eq => values_equal(&left, &self.eval(right)?),
+                    BinaryOp::Ne => !values_equal(&left, &self.eval(right)?),
+                    _ => compare(*op, &left, &self.eval(right)?),
+                })
+            }
+        })
+    }
+
+    fn render(&mut self, nodes: &'a [Node]) -> Result<(), Fail> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => self.out.push_str(text),
+                Node::Var { name, pos } => {
+                    let binding = self.lookup(name, *pos)?;
+                    if is_loop_var(name) {
+                        self.out.push_str(&binding.value.to_string());
+                    } else if let Some(item_type) = binding.item_type {
+                        // List variable used directly: `IN ({{ids}})`.
+                        let items = binding.value.as_array().cloned().unwrap_or_default();
+                        if items.is_empty() {
+                            self.out.push_str("NULL");
+                        }
+                        for (index, item) in items.into_iter().enumerate() {
+                            if index > 0 {
+                                self.out.push_str(", ");
+                            }
+                            self.push_param(format!("{}[{}]", binding.key, index), item, item_type);
+                        }
+                    } else {
+                        self.push_param(binding.key, binding.value, binding.var_type);
+                    }
+                }
+                Node::When {
+                    vars,
+                    pos,
+                    body,
+                    else_body,
+                } => {
+                    let mut all_present = true;
+                    for var in vars {
+                        all_present &= presence(&self.lookup(var, *pos)?.value);
+                    }
+                    let branch = if all_present {
+                        Some(body)
+                    } else {
+                        else_body.as_ref()
+                    };
+                    if let Some(branch) = branch {
+                        self.render(branch)?;
+                    }
+                }
+                Node::If {
+                    expr,
+                    body,
+                    else_body,
+                } => {
+                    let branch = if truthy(&self.eval(expr)?) {
+                        Some(body)
+                    } else {
+                        else_body.as_ref()
+                    };
+                    if let Some(branch) = branch {
+                        self.render(branch)?;
+                    }
+                }
+                Node::Each {
+                    list,
+                    alias,
+                    pos,
+                    body,
+                    else_body,
+                } => {
+                    let binding = self.lookup(list, *pos)?;
+                    let items = match &binding.value {
+                        Value::Null => Vec::new(),
+                        Value::Array(items) => items.clone(),
+                        _ => return fail(*pos, format!("`#each` expects a list: {}", list)),
+                    };
+                    if items.is_empty() {
+                        if let Some(else_body) = else_body {
+                            self.render(else_body)?;
+                        }
+                        continue;
+                    }
+                    let len = items.len();
+                    for (index, value) in items.into_iter().enumerate() {
+                        self.scopes.push(Scope {
+                            alias,
+                            key: format!("{}[{}]", binding.key, index),
+                            value,
+                            item_type: binding.item_type,
+                            index,
+                            len,
+                        });
+                        let rendered = self.render(body);
+                        self.scopes.pop();
+                        rendered?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn collapse_whitespace(sql: &str) -> String {
+    static TRAILING: OnceLock<Regex> = OnceLock::new();
+    static BLANK_LINES: OnceLock<Regex> = OnceLock::new();
+    let trailing = TRAILING.get_or_init(|| Regex::new(r"[ \t]+\n").unwrap());
+    let blank_lines = BLANK_LINES.get_or_init(|| Regex::new(r"\n{3,}").unwrap());
+    let tidied = trailing.replace_all(sql, "\n");
+    blank_lines.replace_all(&tidied, "\n\n").to_string()
+}
+
+/// `renderSqlPreview`: inlines values as literals. Display only.
+pub fn render_preview(text: &str, values: &[Value], types: &[VarType]) -> String {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    let placeholder = PLACEHOLDER.get_or_init(|| Regex::new(r"\$(\d+)").unwrap());
+    let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+    placeholder
+        .replace_all(text, |caps: &regex::Captures<'_>| {
+            let index = caps[1].parse::<usize>().unwrap_or(0).wrapping_sub(1);
+            let (Some(value), Some(var_type)) = (values.get(index), types.get(index)) else {
+                return caps[0].to_string();
+            };
+            if value.is_null() {
+                return "NULL".to_string();
+            }
+            match var_type {
+                VarType::Number => as_text(value),
+                VarType::Boolean => if truthy(value) { "TRUE" } else { "FALSE" }.to_string(),
+                VarType::Date => format!("{}::date", quote(&as_text(value))),
+                VarType::Timestamp => format!("{}::timestamptz", quote(&as_text(value))),
+                VarType::Json => format!("{}::jsonb", quote(&value.to_string())),
+                _ => quote(&as_text(value)),
+            }
+        })
+        .to_string()
+}
+
+/// SQL and parameters as bound by the backend. `pg_arguments` sends strings
+/// as `text`, so date, timestamp, uuid and json placeholders get an explicit
+/// cast; json values travel as their serialised text.
+pub fn typed_statement(compiled: &CompiledSql) -> (String, Vec<Value>) {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    let placeholder = PLACEHOLDER.get_or_init(|| Regex::new(r"\$(\d+)\b").unwrap());
+    let text = placeholder
+        .replace_all(&compiled.text, |caps: &regex::Captures<'_>| {
+            let index = caps[1].parse::<usize>().unwrap_or(0).wrapping_sub(1);
+            let cast = match compiled.types.get(index) {
+                Some(VarType::Date) => "::date",
+                Some(VarType::Timestamp) => "::timestamptz",
+                Some(VarType::Uuid) => "::uuid",
+                Some(VarType::Json) => "::jsonb",
+                _ => "",
+            };
+            format!("{}{}", &caps[0], cast)
+        })
+        .to_string();
+    let values = compiled
+        .values
+        .iter()
+        .zip(&compiled.types)
+        .map(|(value, var_type)| match (var_type, value) {
+            (_, Value::Null) => Value::Null,
+            (VarType::Json, value) => Value::String(value.to_string()),
+            (_, value) => value.clone(),
+        })
+        .collect();
+    (text, values)
+}
+
+/// `compileSql` with `{{#each}}`: renders the template against `input` and
+/// numbers each distinct parameter `$1..$n` in order of first use.
+pub fn compile(
+    sql: &str,
+    vars: &[VariableDef],
+    input: &Map<String, Value>,
+) -> Result<CompiledSql, TemplateError> {
+    let ast =
+        parse_template(sql).map_err(|(offset, message)| TemplateError::at(sql, offset, message))?;
+    let mut values = HashMap::new();
+    for def in vars {
+        values.insert(
+            def.name.as_str(),
+            resolve_value(def, input).map_err(TemplateError::value)?,
+        );
+    }
+    let mut renderer = Renderer {
+        defs: vars.iter().map(|def| (def.name.as_str(), def)).collect(),
+        values,
+        scopes: Vec::new(),
+        out: String::new(),
+        params: Vec::new(),
+    };
+    renderer
+        .render(&ast)
+        .map_err(|(offset, message)| TemplateError::at(sql, offset, message))?;
+
+    let collapsed = collapse_whitespace(&renderer.out);
+    let mut text = String::with_capacity(collapsed.len());
+    let mut parts = collapsed.split(PARAM_MARK);
+    text.push_str(parts.next().unwrap_or_default());
+    while let (Some(index), Some(rest)) = (parts.next(), parts.next()) {
+        let index: usize = index.parse().unwrap_or_default();
+        text.push('$');
+        text.push_str(&(index + 1).to_string());
+        text.push_str(rest);
+    }
+    let (placeholders, (values, types)): (Vec<_>, (Vec<_>, Vec<_>)) = renderer
+        .params
+        .into_iter()
+        .map(|(key, value, var_type)| (key, (value, var_type)))
+        .unzip();
+    let preview = render_preview(&text, &values, &types);
+    Ok(CompiledSql {
+        text,
+        values,
+        placeholders,
+        types,
+        preview,
+    })
+}
+
+#[tauri::command]
+pub fn compile_sql_template(
+    sql: String,
+    variables: Vec<VariableDef>,
+    values: Option<Map<String, Value>>,
+) -> Result<CompiledSql, String> {
+    compile(&sql, &variables, &values.unwrap_or_default())
+        .map_err(|err| format!("template_error: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn defs(value: Value) -> Vec<VariableDef> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn input(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn expands_each_and_list_variables() {
+        let vars = defs(json!([
+            { "name": "status", "type": "enum", "options": ["open", "closed"] },
+            { "name": "ids", "type": "text", "itemType": "number" },
+            { "name": "mode", "type": "text", "default": "whitelist" }
+        ]));
+        let sql = "select * from orders\nwhere status = {{status}}\n\
+            {{#if presence(ids) && mode == 'whitelist'}}\n  and id in (\
+            {{#each ids as id}}{{id}}{{#if !@last}}, {{/if}}{{/each}})\n{{/if}}\n\
+            {{#each ids as id}}{{#if @first}}  -- first {{@index}}{{/if}}{{else}}  -- none{{/each}}\n\
+            and owner_id in ({{ids}}) and status = {{status}}";
+
+        let compiled = compile(
+            sql,
+            &vars,
+            &input(json!({ "status": "open", "ids": [4, "5", 6] })),
+        )
+        .unwrap();
+        assert_eq!(
+            compiled.text,
+            "select * from orders\nwhere status = $1\n\n  and id in ($2, $3, $4)\n\n  -- first 0\n\
+             and owner_id in ($2, $3, $4) and status = $1"
+        );
+        assert_eq!(
+            compiled.values,
+            vec![json!("open"), json!(4), json!(5), json!(6)]
+        );
+        assert_eq!(
+            compiled.placeholders,
+            vec!["status", "ids[0]", "ids[1]", "ids[2]"]
+        );
+        assert_eq!(compiled.types[1], VarType::Number);
+        assert!(compiled.preview.contains("id in (4, 5, 6)"));
+        assert!(compiled.preview.contains("status = 'open'"));
+
+        let empty = compile(sql, &vars, &input(json!({ "status": "open", "ids": [] }))).unwrap();
+        assert!(!empty.text.contains("and id in"));
+        assert!(empty.text.contains("-- none"));
+        assert!(empty.text.contains("owner_id in (NULL)"));
+        assert_eq!(empty.values, vec![json!("open")]);
+    }
+
+    #[test]
+    fn reports_errors_with_line_and_column() {
+        let vars = defs(json!([{ "name": "ids", "type": "text", "itemType": "text" }]));
+        let no_input = Map::new();
+        let err = |sql: &str| compile(sql, &vars, &no_input).unwrap_err().to_string();
+
+        assert_eq!(
+            err("select 1\nwhere {{#each ids as id}}\n  {{id}}"),
+            "Unclosed block: each (line 2, column 7)"
+        );
+        assert_eq!(
+            err("select 1\n  and {{@index}}"),
+            "@index is only available inside #each (line 2, column 7)"
+        );
+        assert_eq!(
+            err("select 1\n{{#if ids == }}x{{/if}}"),
+            "Unexpected end of expression (line 2, column 13)"
+        );
+        let values = input(json!({ "ids": ["a"] }));
+        assert_eq!(
+            compile(
+                "select {{#each ids as id}}{{missing}}{{/each}}",
+                &vars,
+                &values
+            )
+            .unwrap_err()
+            .to_string(),
+            "Undefined variable: missing (line 1, column 27)"
+        );
+    }
+
+    #[test]
+    fn validates_bounds_and_types_bound_values() {
+        let vars = defs(json!([
+            { "name": "limit", "type": "number", "required": true, "min": 1, "max": "500" },
+            { "name": "since", "type": "date", "min": "2020-01-01" },
+            { "name": "owner", "type": "uuid", "default": null },
+            { "name": "filter", "type": "json", "default": { "a": 1 } }
+        ]));
+        let sql = "select * from t where created_at >= {{since}} and owner = {{owner}}\n\
+            and payload @> {{filter}} limit {{limit}}";
+
+        let compiled = compile(
+            sql,
+            &vars,
+            &input(json!({ "limit": "50", "since": "2024-03-01T10:00:00Z" })),
+        )
+        .unwrap();
+        let (text, params) = typed_statement(&compiled);
+        assert_eq!(
+            text,
+            "select * from t where created_at >= $1::date and owner = $2::uuid\n\
+             and payload @> $3::jsonb limit $4"
+        );
+        assert_eq!(
+            params,
+            vec![
+                json!("2024-03-01"),
+                Value::Null,
+                json!("{\"a\":1}"),
+                json!(50)
+            ]
+        );
+
+        let err = |values: Value| compile(sql, &vars, &input(values)).unwrap_err().to_string();
+        assert_eq!(err(json!({})), "Variable limit is required");
+        assert_eq!(err(json!({ "limit": 0 })), "Variable limit must be >= 1");
+        assert_eq!(
+            err(json!({ "limit": 501 })),
+            "Variable limit must be <= 500"
+        );
+        assert_eq!(
+            err(json!({ "limit": 5, "since": "2019-12-31" })),
+            "Variable since must be >= 2020-01-01"
+        );
+        assert_eq!(
+            err(json!({ "limit": "ten" })),
+            "Variable limit must be a number"
+        );
+    }
+
+    #[test]
+    fn coerces_defaults_like_user_input() {
+        let vars = defs(json!([
+            { "name": "limit", "type": "number", "default": "50", "max": 500 },
+            { "name": "ids", "type": "text", "itemType": "number", "default": ["1", 2] }
+        ]));
+        let sql = "select * from t where id in ({{ids}}) limit {{limit}}";
+
+        let compiled = compile(sql, &vars, &Map::new()).unwrap();
+        assert_eq!(compiled.values, vec![json!(1), json!(2), json!(50)]);
+        assert_eq!(compiled.types[2], VarType::Number);
+
+        let bad =
+            defs(json!([{ "name": "limit", "type": "number", "default": "900", "max": 500 }]));
+        assert_eq!(
+            compile("select {{limit}}", &bad, &Map::new())
+                .unwrap_err()
+                .to_string(),
+            "Variable limit must be <= 500"
+        );
+    }
+}
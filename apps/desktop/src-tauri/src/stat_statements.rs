@@ -0,0 +1,615 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{PgConnection, Row};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tauri::State;
+
+use crate::crypto::random_token;
+use crate::local_store::{db_error, new_id, now_sec, row_text, LocalStore};
+use crate::pg::{
+    begin_read_only, fetch_as, fetch_one_as, pg_error, strip_trailing_semicolons, PgPools,
+    ANALYSIS_STATEMENT_TIMEOUT_MS,
+};
+use crate::schema_cache::quote_ident;
+
+const DEFAULT_LIMIT: usize = 50;
+/// Snapshots kept per connection; older ones are pruned on insert.
+const MAX_SNAPSHOTS: i64 = 50;
+
+#[derive(Debug, Deserialize)]
+struct ExtensionInfo {
+    schema: String,
+    version: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SnapshotEntry {
+    pub queryid: String,
+    pub userid: i64,
+    pub dbid: i64,
+    pub username: Option<String>,
+    pub query: String,
+    pub calls: i64,
+    pub total_ms: f64,
+    pub rows: i64,
+    pub shared_blks_hit: i64,
+    pub shared_blks_read: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatementSnapshot {
+    pub id: String,
+    pub conn_id: String,
+    pub taken_at: i64,
+    pub stats_reset: Option<String>,
+    pub entry_count: i64,
+}
+
+/// What the UI needs to call `explain_statement` for an entry.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExplainLink {
+    pub queryid: String,
+    pub query: String,
+    pub param_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatementDelta {
+    pub queryid: String,
+    pub username: Option<String>,
+    pub query: String,
+    pub calls: i64,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub rows: i64,
+    pub shared_blks_hit: i64,
+    pub shared_blks_read: i64,
+    pub cache_hit_ratio: Option<f64>,
+    /// Share of all statement time in the window.
+    pub time_percent: f64,
+    /// Counters went backwards (reset or eviction); the later values are used.
+    pub reset: bool,
+    pub explain: ExplainLink,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatementsDelta {
+    pub from: StatementSnapshot,
+    pub to: StatementSnapshot,
+    pub window_secs: i64,
+    pub total_ms: f64,
+    pub total_calls: i64,
+    pub entries: Vec<StatementDelta>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SampleParam {
+    pub position: usize,
+    pub data_type: String,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExplainedStatement {
+    pub sql: String,
+    pub params: Vec<SampleParam>,
+    pub plan: Value,
+}
+
+const EXTENSION_SQL: &str = r#"
+SELECT n.nspname AS schema, e.extversion AS version
+FROM pg_catalog.pg_extension e
+JOIN pg_catalog.pg_namespace n ON n.oid = e.extnamespace
+WHERE e.extname = 'pg_stat_statements'"#;
+
+fn param_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\$(\d+)").expect("valid regex"))
+}
+
+/// Highest `$n` placeholder in a normalised statement.
+pub fn param_count(query: &str) -> usize {
+    param_regex()
+        .captures_iter(query)
+        .filter_map(|caps| caps[1].parse::<usize>().ok())
+        .max()
+        .unwrap_or(0)
+}
+
+fn version_at_least(version: &str, wanted: (u32, u32)) -> bool {
+    let mut parts = version.split('.').filter_map(|p| p.parse::<u32>().ok());
+    let major = parts.next().unwrap_or(0);
+    let minor = parts.next().unwrap_or(0);
+    (major, minor) >= wanted
+}
+
+/// 1.8 (PostgreSQL 13) split `total_time` into planning and execution time.
+fn total_time_column(version: &str) -> &'static str {
+    if version_at_least(version, (1, 8)) {
+        "total_exec_time"
+    } else {
+        "total_time"
+    }
+}
+
+async fn extension(conn: &mut PgConnection) -> Result<ExtensionInfo, String> {
+    fetch_one_as(conn, EXTENSION_SQL, &[])
+        .await?
+        .ok_or_else(|| "pg_stat_statements_missing".to_string())
+}
+
+pub async fn read_entries(
+    conn: &mut PgConnection,
+) -> Result<(Vec<SnapshotEntry>, Option<String>), String> {
+    let ext = extension(conn).await?;
+    let schema = quote_ident(&ext.schema);
+    let sql = format!(
+        r#"
+SELECT
+  s.queryid::text AS queryid,
+  s.userid::bigint AS userid,
+  s.dbid::bigint AS dbid,
+  pg_catalog.pg_get_userbyid(s.userid)::text AS username,
+  s.query,
+  s.calls::bigint AS calls,
+  s.{total}::float8 AS total_ms,
+  s.rows::bigint AS rows,
+  s.shared_blks_hit::bigint AS shared_blks_hit,
+  s.shared_blks_read::bigint AS shared_blks_read
+FROM {schema}.pg_stat_statements s
+WHERE s.dbid = (SELECT oid FROM pg_catalog.pg_database WHERE datname = current_database())
+  AND s.queryid IS NOT NULL"#,
+        total = total_time_column(&ext.version),
+    );
+    let entries: Vec<SnapshotEntry> = fetch_as(conn, &sql, &[]).await?;
+    // `pg_stat_statements_info` arrived in 1.9.
+    let stats_reset = if version_at_least(&ext.version, (1, 9)) {
+        let info: Option<Value> = fetch_one_as(
+            conn,
+            &format!(
+                "SELECT stats_reset::text AS stats_reset FROM {schema}.pg_stat_statements_info"
+            ),
+            &[],
+        )
+        .await?;
+        info.and_then(|row| {
+            row.get("stats_reset")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+    } else {
+        None
+    };
+    Ok((entries, stats_reset))
+}
+
+fn cache_ratio(hit: i64, read: i64) -> Option<f64> {
+    let total = hit + read;
+    (total > 0).then(|| hit as f64 / total as f64)
+}
+
+/// Subtracts `from` counters from `to`, per (queryid, userid, dbid), and
+/// ranks statements by time spent in the window.
+pub fn compute_deltas(from: &[SnapshotEntry], to: &[SnapshotEntry]) -> Vec<StatementDelta> {
+    let before: HashMap<(&str, i64, i64), &SnapshotEntry> = from
+        .iter()
+        .map(|e| ((e.queryid.as_str(), e.userid, e.dbid), e))
+        .collect();
+    let mut deltas: Vec<StatementDelta> = to
+        .iter()
+        .filter_map(|after| {
+            let prior = before.get(&(after.queryid.as_str(), after.userid, after.dbid));
+            let reset = prior.is_some_and(|p| after.calls < p.calls);
+            let base = prior.filter(|_| !reset);
+            let calls = after.calls - base.map_or(0, |p| p.calls);
+            if calls <= 0 {
+                return None;
+            }
+            let total_ms = after.total_ms - base.map_or(0.0, |p| p.total_ms);
+            let shared_blks_hit = after.shared_blks_hit - base.map_or(0, |p| p.shared_blks_hit);
+            let shared_blks_read = after.shared_blks_read - base.map_or(0, |p| p.shared_blks_read);
+            Some(StatementDelta {
+                queryid: after.queryid.clone(),
+                username: after.username.clone(),
+                query: after.query.clone(),
+                calls,
+                total_ms,
+                mean_ms: total_ms / calls as f64,
+                rows: after.rows - base.map_or(0, |p| p.rows),
+                shared_blks_hit,
+                shared_blks_read,
+                cache_hit_ratio: cache_ratio(shared_blks_hit, shared_blks_read),
+                time_percent: 0.0,
+                reset,
+                explain: ExplainLink {
+                    queryid: after.queryid.clone(),
+                    query: after.query.clone(),
+                    param_count: param_count(&after.query),
+                },
+            })
+        })
+        .collect();
+    let total: f64 = deltas.iter().map(|d| d.total_ms).sum();
+    for delta in &mut deltas {
+        delta.time_percent = if total > 0.0 {
+            delta.total_ms / total * 100.0
+        } else {
+            0.0
+        };
+    }
+    deltas.sort_by(|a, b| {
+        b.total_ms
+            .total_cmp(&a.total_ms)
+            .then_with(|| b.calls.cmp(&a.calls))
+    });
+    deltas
+}
+
+async fn store_snapshot(
+    store: &LocalStore,
+    conn_id: &str,
+    entries: &[SnapshotEntry],
+    stats_reset: Option<String>,
+) -> Result<StatementSnapshot, String> {
+    let snapshot = StatementSnapshot {
+        id: new_id("pgss"),
+        conn_id: conn_id.to_string(),
+        taken_at: now_sec(),
+        stats_reset,
+        entry_count: entries.len() as i64,
+    };
+    let mut tx = store.pool()?.begin().await.map_err(db_error)?;
+    sqlx::query(
+        "INSERT INTO stat_statements_snapshots (id, conn_id, taken_at, stats_reset, entry_count)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )
+    .bind(&snapshot.id)
+    .bind(conn_id)
+    .bind(snapshot.taken_at)
+    .bind(&snapshot.stats_reset)
+    .bind(snapshot.entry_count)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    for entry in entries {
+        sqlx::query(
+            "INSERT OR REPLACE INTO stat_statements_entries
+               (snapshot_id, queryid, userid, dbid, username, query, calls, total_ms, rows, shared_blks_hit, shared_blks_read)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        )
+        .bind(&snapshot.id)
+        .bind(&entry.queryid)
+        .bind(entry.userid)
+        .bind(entry.dbid)
+        .bind(&entry.username)
+        .bind(&entry.query)
+        .bind(entry.calls)
+        .bind(entry.total_ms)
+        .bind(entry.rows)
+        .bind(entry.shared_blks_hit)
+        .bind(entry.shared_blks_read)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    }
+    sqlx::query(
+        "DELETE FROM stat_statements_snapshots WHERE conn_id = ?1 AND id NOT IN (
+           SELECT id FROM stat_statements_snapshots WHERE conn_id = ?1 ORDER BY taken_at DESC, id DESC LIMIT ?2
+         )",
+    )
+    .bind(conn_id)
+    .bind(MAX_SNAPSHOTS)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+    Ok(snapshot)
+}
+
+fn snapshot_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<StatementSnapshot, String> {
+    Ok(StatementSnapshot {
+        id: row_text(row, "id").unwrap_or_default(),
+        conn_id: row_text(row, "conn_id").unwrap_or_default(),
+        taken_at: row.try_get("taken_at").map_err(db_error)?,
+        stats_reset: row_text(row, "stats_reset"),
+        entry_count: row.try_get("entry_count").map_err(db_error)?,
+    })
+}
+
+async fn load_snapshot(
+    store: &LocalStore,
+    conn_id: &str,
+    id: &str,
+) -> Result<(StatementSnapshot, Vec<SnapshotEntry>), String> {
+    let row = sqlx::query(
+        "SELECT id, conn_id, taken_at, stats_reset, entry_count
+         FROM stat_statements_snapshots WHERE id = ?1 AND conn_id = ?2",
+    )
+    .bind(id)
+    .bind(conn_id)
+    .fetch_optional(&store.pool()?)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| format!("snapshot_not_found:{}", id))?;
+    let snapshot = snapshot_from_row(&row)?;
+    let rows = sqlx::query(
+        "SELECT queryid, userid, dbid, username, query, calls, total_ms, rows, shared_blks_hit, shared_blks_read
+         FROM stat_statements_entries WHERE snapshot_id = ?1",
+    )
+    .bind(id)
+    .fetch_all(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    let entries = rows
+        .iter()
+        .map(|row| {
+            Ok(SnapshotEntry {
+                queryid: row_text(row, "queryid").unwrap_or_default(),
+                userid: row.try_get("userid").map_err(db_error)?,
+                dbid: row.try_get("dbid").map_err(db_error)?,
+                username: row_text(row, "username"),
+                query: row_text(row, "query").unwrap_or_default(),
+                calls: row.try_get("calls").map_err(db_error)?,
+                total_ms: row.try_get("total_ms").map_err(db_error)?,
+                rows: row.try_get("rows").map_err(db_error)?,
+                shared_blks_hit: row.try_get("shared_blks_hit").map_err(db_error)?,
+                shared_blks_read: row.try_get("shared_blks_read").map_err(db_error)?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok((snapshot, entries))
+}
+
+async fn take_snapshot(
+    store: &LocalStore,
+    pools: &PgPools,
+    conn_id: &str,
+) -> Result<StatementSnapshot, String> {
+    let pool = pools.pool_for(store, conn_id).await?;
+    let mut tx = begin_read_only(&pool, ANALYSIS_STATEMENT_TIMEOUT_MS).await?;
+    let (entries, stats_reset) = read_entries(&mut tx).await?;
+    drop(tx);
+    store_snapshot(store, conn_id, &entries, stats_reset).await
+}
+
+/// Literal used for a parameter of `data_type` when explaining a normalised
+/// statement. `None` binds SQL NULL.
+fn sample_value(data_type: &str) -> Option<&'static str> {
+    if data_type.ends_with("[]") {
+        return Some("{}");
+    }
+    match data_type {
+        "smallint" | "integer" | "bigint" | "numeric" | "real" | "double precision" | "oid" => {
+            Some("1")
+        }
+        "text" | "character varying" | "character" | "name" | "citext" => Some("sample"),
+        "boolean" => Some("true"),
+        "date" => Some("2000-01-01"),
+        "timestamp without time zone" | "timestamp with time zone" => Some("2000-01-01 00:00:00"),
+        "time without time zone" | "time with time zone" => Some("00:00:00"),
+        "interval" => Some("1 day"),
+        "uuid" => Some("00000000-0000-0000-0000-000000000000"),
+        "json" | "jsonb" => Some("{}"),
+        _ => None,
+    }
+}
+
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+async fn explain_prepared(
+    conn: &mut PgConnection,
+    name: &str,
+    query: &str,
+) -> Result<ExplainedStatement, String> {
+    sqlx::query(&format!("PREPARE {} AS {}", name, query))
+        .execute(&mut *conn)
+        .await
+        .map_err(pg_error)?;
+    let types: Option<Value> = fetch_one_as(
+        conn,
+        "SELECT parameter_types::text[] AS types FROM pg_catalog.pg_prepared_statements WHERE name = $1",
+        &[json!(name)],
+    )
+    .await?;
+    let types: Vec<String> = types
+        .and_then(|row| row.get("types").cloned())
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+    let params: Vec<SampleParam> = types
+        .iter()
+        .enumerate()
+        .map(|(idx, data_type)| SampleParam {
+            position: idx + 1,
+            data_type: data_type.clone(),
+            value: sample_value(data_type).map(str::to_string),
+        })
+        .collect();
+    let args: Vec<String> = params
+        .iter()
+        .map(|param| match &param.value {
+            Some(value) => format!("{}::{}", quote_literal(value), param.data_type),
+            None => format!("NULL::{}", param.data_type),
+        })
+        .collect();
+    let execute = if args.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}({})", name, args.join(", "))
+    };
+    let explain_sql = format!("EXPLAIN (FORMAT JSON) EXECUTE {}", execute);
+    sqlx::query("SAVEPOINT rdv_explain")
+        .execute(&mut *conn)
+        .await
+        .map_err(pg_error)?;
+    let plan = sqlx::query_scalar::<_, sqlx::types::Json<Value>>(&explain_sql)
+        .fetch_one(&mut *conn)
+        .await;
+    if plan.is_err() {
+        let _ = sqlx::query("ROLLBACK TO SAVEPOINT rdv_explain")
+            .execute(&mut *conn)
+            .await;
+    }
+    let plan = plan.map_err(pg_error)?;
+    // Show the user a self-contained statement rather than the prepared name.
+    let sql = format!(
+        "PREPARE rdv_explain AS\n{};\nEXPLAIN (FORMAT JSON) EXECUTE rdv_explain{};\nDEALLOCATE rdv_explain;",
+        query,
+        if args.is_empty() {
+            String::new()
+        } else {
+            format!("({})", args.join(", "))
+        }
+    );
+    Ok(ExplainedStatement {
+        sql,
+        params,
+        plan: plan.0,
+    })
+}
+
+/// Plans a normalised statement (`$1`, `$2`, ...) with sample parameters.
+/// Only `EXPLAIN` without `ANALYZE` runs, inside a read-only transaction.
+pub async fn explain_with_samples(
+    conn: &mut PgConnection,
+    query: &str,
+) -> Result<ExplainedStatement, String> {
+    let query = strip_trailing_semicolons(query);
+    if query.trim().is_empty() {
+        return Err("empty_query".to_string());
+    }
+    let name = format!("rdv_explain_{}", random_token(6));
+    let result = explain_prepared(conn, &name, query).await;
+    let _ = sqlx::query(&format!("DEALLOCATE {}", name))
+        .execute(&mut *conn)
+        .await;
+    result
+}
+
+#[tauri::command]
+pub async fn snapshot_stat_statements(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    conn_id: String,
+) -> Result<StatementSnapshot, String> {
+    take_snapshot(&store, &pools, &conn_id).await
+}
+
+#[tauri::command]
+pub async fn list_stat_statements_snapshots(
+    store: State<'_, LocalStore>,
+    conn_id: String,
+) -> Result<Vec<StatementSnapshot>, String> {
+    let rows = sqlx::query(
+        "SELECT id, conn_id, taken_at, stats_reset, entry_count
+         FROM stat_statements_snapshots WHERE conn_id = ?1 ORDER BY taken_at DESC, id DESC",
+    )
+    .bind(&conn_id)
+    .fetch_all(&store.pool()?)
+    .await
+    .map_err(db_error)?;
+    rows.iter().map(snapshot_from_row).collect()
+}
+
+/// Ranks statements between two snapshots. Without `to_id` a fresh snapshot
+/// is taken and used as the end of the window.
+#[tauri::command]
+pub async fn stat_statements_delta(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    conn_id: String,
+    from_id: String,
+    to_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<StatementsDelta, String> {
+    let to_id = match to_id {
+        Some(id) => id,
+        None => take_snapshot(&store, &pools, &conn_id).await?.id,
+    };
+    let (from, from_entries) = load_snapshot(&store, &conn_id, &from_id).await?;
+    let (to, to_entries) = load_snapshot(&store, &conn_id, &to_id).await?;
+    if to.taken_at < from.taken_at {
+        return Err("snapshot_order_invalid".to_string());
+    }
+    let mut entries = compute_deltas(&from_entries, &to_entries);
+    let total_ms = entries.iter().map(|e| e.total_ms).sum();
+    let total_calls = entries.iter().map(|e| e.calls).sum();
+    entries.truncate(limit.unwrap_or(DEFAULT_LIMIT).max(1));
+    Ok(StatementsDelta {
+        window_secs: to.taken_at - from.taken_at,
+        from,
+        to,
+        total_ms,
+        total_calls,
+        entries,
+    })
+}
+
+#[tauri::command]
+pub async fn explain_statement(
+    store: State<'_, LocalStore>,
+    pools: State<'_, PgPools>,
+    conn_id: String,
+    query: String,
+) -> Result<ExplainedStatement, String> {
+    let pool = pools.pool_for(&store, &conn_id).await?;
+    let mut tx = begin_read_only(&pool, ANALYSIS_STATEMENT_TIMEOUT_MS).await?;
+    explain_with_samples(&mut tx, &query).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(queryid: &str, calls: i64, total_ms: f64) -> SnapshotEntry {
+        SnapshotEntry {
+            queryid: queryid.into(),
+            userid: 10,
+            dbid: 5,
+            username: Some("app".into()),
+            query: "SELECT * FROM orders WHERE id = $1 AND status = $2".into(),
+            calls,
+            total_ms,
+            rows: calls,
+            shared_blks_hit: calls * 9,
+            shared_blks_read: calls,
+        }
+    }
+
+    #[test]
+    fn ranks_window_deltas_and_handles_resets() {
+        let from = vec![
+            entry("1", 100, 50.0),
+            entry("2", 10, 900.0),
+            entry("3", 500, 10.0),
+        ];
+        let to = vec![
+            entry("1", 150, 80.0),
+            entry("2", 10, 900.0),
+            entry("3", 20, 400.0),
+            entry("4", 5, 100.0),
+        ];
+        let deltas = compute_deltas(&from, &to);
+        let ids: Vec<&str> = deltas.iter().map(|d| d.queryid.as_str()).collect();
+        assert_eq!(ids, vec!["3", "4", "1"]);
+        assert!(deltas[0].reset);
+        assert_eq!(deltas[2].calls, 50);
+        assert_eq!(deltas[2].total_ms, 30.0);
+        assert_eq!(deltas[2].mean_ms, 0.6);
+        assert_eq!(deltas[2].cache_hit_ratio, Some(0.9));
+        let share: f64 = deltas.iter().map(|d| d.time_percent).sum();
+        assert!((share - 100.0).abs() < 1e-9);
+        assert_eq!(deltas[0].explain.param_count, 2);
+    }
+
+    #[test]
+    fn picks_time_column_by_extension_version() {
+        assert_eq!(total_time_column("1.7"), "total_time");
+        assert_eq!(total_time_column("1.8"), "total_exec_time");
+        assert_eq!(total_time_column("1.10"), "total_exec_time");
+        assert_eq!(param_count("SELECT $1, $12, $3"), 12);
+    }
+}
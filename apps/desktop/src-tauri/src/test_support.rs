@@ -0,0 +1,29 @@
+//! Fixtures shared by unit tests.
+
+use std::future::Future;
+use tempfile::TempDir;
+
+use crate::local_store::LocalStore;
+
+/// Fresh directory, removed when dropped, including when the test panics.
+pub fn temp_dir() -> TempDir {
+    tempfile::Builder::new()
+        .prefix("rdv_test_")
+        .tempdir()
+        .unwrap()
+}
+
+/// Runs `body` on a migrated store in a fresh [`temp_dir`].
+pub fn with_store<F, Fut>(body: F)
+where
+    F: FnOnce(LocalStore) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let dir = temp_dir();
+    tauri::async_runtime::block_on(async {
+        // The lazy pool needs a runtime to be created in.
+        let store = LocalStore::open(dir.path()).unwrap();
+        store.migrate().await.unwrap();
+        body(store).await;
+    });
+}
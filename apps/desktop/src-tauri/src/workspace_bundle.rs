@@ -0,0 +1,1336 @@
+//! Workspace bundles: a zip with `manifest.json` plus one file per section
+//! (saved SQL, connections, app prefs, assistant profiles) and the user's
+//! ops catalog files, for sharing a standard toolkit between machines.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{Row, SqliteConnection};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use tauri::State;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::crypto::{
+    aes_decrypt_to_string, aes_encrypt_string, passphrase_key, random_base64, AesCipher,
+};
+use crate::local_store::{db_error, new_id, now_ms, now_sec, row_text, LocalStore};
+use crate::ops_catalog::{catalog_files, OpsCatalog};
+use crate::pg::DEVICE_KEY_PREF;
+
+pub const BUNDLE_FORMAT: &str = "rdv-workspace-bundle";
+pub const BUNDLE_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+const CATALOG_PREFIX: &str = "ops_catalog/";
+/// Same key as `lib/assistant/provider-settings.ts`.
+const PROFILES_PREF: &str = "assistant.providerProfiles.v1";
+const API_KEY_PREF_PREFIX: &str = "assistant.apiKey.";
+const KDF_ALG: &str = "PBKDF2-SHA256";
+const KDF_ITERATIONS: u32 = 310_000;
+/// Encrypted under the passphrase key so a wrong passphrase fails up front.
+const PASSPHRASE_CHECK: &str = "rdv-workspace-bundle";
+const MAX_ENTRY_BYTES: u64 = 32 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Section {
+    SavedSql,
+    Connections,
+    AppPrefs,
+    OpsCatalog,
+    AssistantProfiles,
+}
+
+impl Section {
+    pub const ALL: [Section; 5] = [
+        Section::SavedSql,
+        Section::Connections,
+        Section::AppPrefs,
+        Section::OpsCatalog,
+        Section::AssistantProfiles,
+    ];
+
+    fn file(self) -> &'static str {
+        match self {
+            Section::SavedSql => "saved_sql.json",
+            Section::Connections => "connections.json",
+            Section::AppPrefs => "app_prefs.json",
+            Section::OpsCatalog => CATALOG_PREFIX,
+            Section::AssistantProfiles => "assistant_profiles.json",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BundleKdf {
+    pub alg: String,
+    pub iterations: u32,
+    pub salt: String,
+    pub check: AesCipher,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BundleManifest {
+    pub format: String,
+    pub version: u32,
+    pub app_version: String,
+    pub created_at: i64,
+    /// Entry count per included section.
+    pub sections: BTreeMap<Section, usize>,
+    /// Present when connection DSNs are included, encrypted under a key
+    /// derived from the export passphrase.
+    pub kdf: Option<BundleKdf>,
+    /// sha256 of every other file in the archive.
+    pub files: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SavedSqlEntry {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub sql: String,
+    pub variables: Value,
+    pub dynamic_columns: Value,
+    pub calc_items: Value,
+    pub is_archived: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConnectionEntry {
+    pub id: String,
+    pub alias: String,
+    pub driver: String,
+    pub host: Option<String>,
+    pub port: Option<i64>,
+    pub database: Option<String>,
+    pub username: Option<String>,
+    pub strict_read_only: bool,
+    /// DSN under the passphrase key; absent in secret-free bundles.
+    pub dsn: Option<AesCipher>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PrefEntry {
+    pub k: String,
+    pub v: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BundleContents {
+    pub saved_sql: Vec<SavedSqlEntry>,
+    pub connections: Vec<ConnectionEntry>,
+    pub prefs: Vec<PrefEntry>,
+    /// `(file name, text)` of user catalog files.
+    pub catalog: Vec<(String, String)>,
+    pub profiles: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    #[default]
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImportPolicy {
+    pub conflict: ConflictStrategy,
+    /// Lets an overwrite turn strict read-only off on an existing connection;
+    /// otherwise the stricter of the two settings is kept.
+    pub allow_read_only_downgrade: bool,
+}
+
+impl From<ConflictStrategy> for ImportPolicy {
+    fn from(conflict: ConflictStrategy) -> Self {
+        Self {
+            conflict,
+            allow_read_only_downgrade: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportAction {
+    Add,
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportItem {
+    pub section: Section,
+    /// Name, alias, pref key or file name as found in the bundle.
+    pub key: String,
+    pub action: ImportAction,
+    /// New name for renamed entries.
+    pub target: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    pub manifest: BundleManifest,
+    pub dry_run: bool,
+    pub items: Vec<ImportItem>,
+}
+
+/// Secrets never leave the device through `app_prefs`: the device key, API
+/// keys and anything stored as an encrypted envelope.
+fn is_secret_pref(key: &str, value: &str) -> bool {
+    key == DEVICE_KEY_PREF
+        || key.starts_with(API_KEY_PREF_PREFIX)
+        || serde_json::from_str::<Value>(value)
+            .ok()
+            .is_some_and(|v| v.get("ct").is_some() && v.get("iv").is_some())
+}
+
+/// `base (imported)`, then `base (imported 2)`, ... until unused.
+pub fn unique_name(base: &str, taken: &HashSet<String>) -> String {
+    (1..)
+        .map(|n| match n {
+            1 => format!("{} (imported)", base),
+            n => format!("{} (imported {})", base, n),
+        })
+        .find(|name| !taken.contains(name))
+        .unwrap_or_default()
+}
+
+/// `file-imported.toml`, `file-imported-2.toml`, ...
+fn unique_file_name(name: &str, taken: &HashSet<String>) -> String {
+    let (stem, ext) = name.rsplit_once('.').unwrap_or((name, ""));
+    (1..)
+        .map(|n| match n {
+            1 => format!("{}-imported.{}", stem, ext),
+            n => format!("{}-imported-{}.{}", stem, n, ext),
+        })
+        .find(|name| !taken.contains(name))
+        .unwrap_or_default()
+}
+
+fn json_text(value: &Value) -> Option<String> {
+    (!value.is_null()).then(|| value.to_string())
+}
+
+fn parse_json_column(raw: Option<String>) -> Value {
+    raw.and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or(Value::Null)
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// The DSN device key, if this device has one.
+async fn existing_device_key(conn: &mut SqliteConnection) -> Result<Option<String>, String> {
+    let existing: Option<String> = sqlx::query_scalar("SELECT v FROM app_prefs WHERE k = ?1")
+        .bind(DEVICE_KEY_PREF)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(db_error)?;
+    Ok(existing.filter(|key| !key.trim().is_empty()))
+}
+
+/// The DSN device key, created the way `getOrInitDeviceKeyBase64` does when
+/// this device has none yet.
+async fn device_key(conn: &mut SqliteConnection) -> Result<String, String> {
+    if let Some(key) = existing_device_key(conn).await? {
+        return Ok(key);
+    }
+    let key = random_base64(32);
+    sqlx::query(
+        "INSERT INTO app_prefs (k, v) VALUES (?1, ?2) ON CONFLICT(k) DO UPDATE SET v = excluded.v",
+    )
+    .bind(DEVICE_KEY_PREF)
+    .bind(&key)
+    .execute(&mut *conn)
+    .await
+    .map_err(db_error)?;
+    Ok(key)
+}
+
+async fn load_profiles(conn: &mut SqliteConnection) -> Result<Vec<Value>, String> {
+    let raw: Option<String> = sqlx::query_scalar("SELECT v FROM app_prefs WHERE k = ?1")
+        .bind(PROFILES_PREF)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(db_error)?;
+    Ok(raw
+        .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+        .and_then(|payload| payload.get("profiles").and_then(Value::as_array).cloned())
+        .unwrap_or_default())
+}
+
+// --- Export -------------------------------------------------------------------
+
+async fn collect(
+    conn: &mut SqliteConnection,
+    catalog_dir: Option<&Path>,
+    sections: &HashSet<Section>,
+    passphrase_key: Option<&str>,
+) -> Result<BundleContents, String> {
+    let mut contents = BundleContents::default();
+    if sections.contains(&Section::SavedSql) {
+        let rows = sqlx::query(
+            "SELECT id, name, description, sql, variables, dynamic_columns, calc_items, is_archived FROM saved_sql ORDER BY name",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(db_error)?;
+        contents.saved_sql = rows
+            .iter()
+            .map(|row| SavedSqlEntry {
+                id: row_text(row, "id").unwrap_or_default(),
+                name: row_text(row, "name").unwrap_or_default(),
+                description: row_text(row, "description"),
+                sql: row_text(row, "sql").unwrap_or_default(),
+                variables: parse_json_column(row_text(row, "variables")),
+                dynamic_columns: parse_json_column(row_text(row, "dynamic_columns")),
+                calc_items: parse_json_column(row_text(row, "calc_items")),
+                is_archived: row
+                    .try_get::<Option<i64>, _>("is_archived")
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default()
+                    != 0,
+            })
+            .collect();
+    }
+    if sections.contains(&Section::Connections) {
+        // Without a device key no stored DSN can be decrypted; export never
+        // creates one.
+        let device = match passphrase_key {
+            Some(_) => existing_device_key(conn).await?,
+            None => None,
+        };
+        let rows = sqlx::query(
+            "SELECT id, alias, driver, host, port, database, username, dsn_cipher, strict_read_only FROM user_connections ORDER BY alias",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(db_error)?;
+        for row in rows {
+            let alias = row_text(&row, "alias").unwrap_or_default();
+            let dsn = match (passphrase_key, device.as_deref()) {
+                (Some(bundle_key), Some(device)) => row_text(&row, "dsn_cipher")
+                    .and_then(|text| serde_json::from_str::<AesCipher>(&text).ok())
+                    .map(|cipher| {
+                        let dsn = aes_decrypt_to_string(device, &cipher)
+                            .map_err(|err| format!("{} ({})", err, alias))?;
+                        aes_encrypt_string(bundle_key, &dsn)
+                    })
+                    .transpose()?,
+                _ => None,
+            };
+            contents.connections.push(ConnectionEntry {
+                id: row_text(&row, "id").unwrap_or_default(),
+                alias,
+                driver: row_text(&row, "driver").unwrap_or_else(|| "postgres".to_string()),
+                host: row_text(&row, "host"),
+                port: row.try_get("port").ok().flatten(),
+                database: row_text(&row, "database"),
+                username: row_text(&row, "username"),
+                strict_read_only: row
+                    .try_get::<i64, _>("strict_read_only")
+                    .unwrap_or_default()
+                    != 0,
+                dsn,
+            });
+        }
+    }
+    if sections.contains(&Section::AppPrefs) {
+        let rows = sqlx::query("SELECT k, v FROM app_prefs ORDER BY k")
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(db_error)?;
+        contents.prefs = rows
+            .iter()
+            .filter_map(|row| {
+                let k = row_text(row, "k")?;
+                let v = row_text(row, "v").unwrap_or_default();
+                (k != PROFILES_PREF && !is_secret_pref(&k, &v)).then_some(PrefEntry { k, v })
+            })
+            .collect();
+    }
+    if sections.contains(&Section::OpsCatalog) {
+        for path in catalog_dir.map(catalog_files).unwrap_or_default() {
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let text = std::fs::read_to_string(&path)
+                .map_err(|err| format!("bundle_read_failed: {}: {}", name, err))?;
+            contents.catalog.push((name.to_string(), text));
+        }
+    }
+    if sections.contains(&Section::AssistantProfiles) {
+        contents.profiles = load_profiles(conn).await?;
+    }
+    Ok(contents)
+}
+
+fn write_zip(manifest: &BundleManifest, files: &[(String, Vec<u8>)]) -> Result<Vec<u8>, String> {
+    let zip_err = |err: zip::result::ZipError| format!("bundle_write_failed: {}", err);
+    let io_err = |err: std::io::Error| format!("bundle_write_failed: {}", err);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let manifest_json = serde_json::to_vec_pretty(manifest)
+        .map_err(|err| format!("bundle_write_failed: {}", err))?;
+    zip.start_file(MANIFEST_FILE, options).map_err(zip_err)?;
+    zip.write_all(&manifest_json).map_err(io_err)?;
+    for (name, bytes) in files {
+        zip.start_file(name.as_str(), options).map_err(zip_err)?;
+        zip.write_all(bytes).map_err(io_err)?;
+    }
+    Ok(zip.finish().map_err(zip_err)?.into_inner())
+}
+
+/// Builds a bundle of the chosen sections. Without a passphrase connection
+/// DSNs are left out; with one they are re-encrypted under a key derived from
+/// it instead of this device's key.
+pub async fn export_bundle(
+    store: &LocalStore,
+    catalog_dir: Option<&Path>,
+    sections: &[Section],
+    passphrase: Option<&str>,
+) -> Result<(Vec<u8>, BundleManifest), String> {
+    let sections: HashSet<Section> = sections.iter().copied().collect();
+    let passphrase = passphrase.filter(|p| !p.is_empty());
+    let kdf = match passphrase {
+        Some(passphrase) => {
+            let salt = random_base64(16);
+            let key = passphrase_key(passphrase, salt.as_bytes(), KDF_ITERATIONS);
+            Some((
+                key.clone(),
+                BundleKdf {
+                    alg: KDF_ALG.to_string(),
+                    iterations: KDF_ITERATIONS,
+                    salt,
+                    check: aes_encrypt_string(&key, PASSPHRASE_CHECK)?,
+                },
+            ))
+        }
+        None => None,
+    };
+    let mut conn = store.pool()?.acquire().await.map_err(db_error)?;
+    let contents = collect(
+        &mut conn,
+        catalog_dir,
+        &sections,
+        kdf.as_ref().map(|(key, _)| key.as_str()),
+    )
+    .await?;
+
+    let to_json = |value: Value| serde_json::to_vec_pretty(&value).unwrap_or_default();
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut counts = BTreeMap::new();
+    for section in Section::ALL {
+        if !sections.contains(&section) {
+            continue;
+        }
+        let (count, entries) = match section {
+            Section::SavedSql => (
+                contents.saved_sql.len(),
+                vec![(
+                    section.file().to_string(),
+                    to_json(json!(contents.saved_sql)),
+                )],
+            ),
+            Section::Connections => (
+                contents.connections.len(),
+                vec![(
+                    section.file().to_string(),
+                    to_json(json!(contents.connections)),
+                )],
+            ),
+            Section::AppPrefs => (
+                contents.prefs.len(),
+                vec![(section.file().to_string(), to_json(json!(contents.prefs)))],
+            ),
+            Section::AssistantProfiles => (
+                contents.profiles.len(),
+                vec![(
+                    section.file().to_string(),
+                    to_json(json!(contents.profiles)),
+                )],
+            ),
+            Section::OpsCatalog => (
+                contents.catalog.len(),
+                contents
+                    .catalog
+                    .iter()
+                    .map(|(name, text)| {
+                        (
+                            format!("{}{}", CATALOG_PREFIX, name),
+                            text.clone().into_bytes(),
+                        )
+                    })
+                    .collect(),
+            ),
+        };
+        counts.insert(section, count);
+        files.extend(entries);
+    }
+    let manifest = BundleManifest {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: now_sec(),
+        sections: counts,
+        kdf: kdf.map(|(_, kdf)| kdf),
+        files: files
+            .iter()
+            .map(|(name, bytes)| (name.clone(), sha256_hex(bytes)))
+            .collect(),
+    };
+    Ok((write_zip(&manifest, &files)?, manifest))
+}
+
+// --- Import -------------------------------------------------------------------
+
+/// Reads and verifies a bundle: format, version and every checksum.
+pub fn read_bundle(bytes: &[u8]) -> Result<(BundleManifest, BTreeMap<String, Vec<u8>>), String> {
+    let invalid = |reason: String| format!("bundle_invalid: {}", reason);
+    let mut archive =
+        ZipArchive::new(Cursor::new(bytes)).map_err(|err| invalid(err.to_string()))?;
+    let mut files = BTreeMap::new();
+    for index in 0..archive.len() {
+        let entry = archive
+            .by_index(index)
+            .map_err(|err| invalid(err.to_string()))?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        let mut data = Vec::new();
+        entry
+            .take(MAX_ENTRY_BYTES + 1)
+            .read_to_end(&mut data)
+            .map_err(|err| invalid(err.to_string()))?;
+        if data.len() as u64 > MAX_ENTRY_BYTES {
+            return Err(invalid(format!("{} is too large", name)));
+        }
+        files.insert(name, data);
+    }
+    let manifest: BundleManifest = files
+        .remove(MANIFEST_FILE)
+        .ok_or_else(|| invalid("manifest.json missing".to_string()))
+        .and_then(|raw| serde_json::from_slice(&raw).map_err(|err| invalid(err.to_string())))?;
+    if manifest.format != BUNDLE_FORMAT {
+        return Err(invalid(format!("unknown format {}", manifest.format)));
+    }
+    if manifest.version > BUNDLE_VERSION {
+        return Err(format!("bundle_version_unsupported: {}", manifest.version));
+    }
+    for (name, expected) in &manifest.files {
+        match files.get(name) {
+            Some(data) if sha256_hex(data) == *expected => {}
+            Some(_) => return Err(invalid(format!("checksum mismatch for {}", name))),
+            None => return Err(invalid(format!("{} missing", name))),
+        }
+    }
+    files.retain(|name, _| manifest.files.contains_key(name));
+    Ok((manifest, files))
+}
+
+fn parse_section<T: for<'de> Deserialize<'de>>(
+    files: &BTreeMap<String, Vec<u8>>,
+    section: Section,
+) -> Result<Vec<T>, String> {
+    match files.get(section.file()) {
+        Some(raw) => serde_json::from_slice(raw)
+            .map_err(|err| format!("bundle_invalid: {}: {}", section.file(), err)),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn contents_of(files: &BTreeMap<String, Vec<u8>>) -> Result<BundleContents, String> {
+    Ok(BundleContents {
+        saved_sql: parse_section(files, Section::SavedSql)?,
+        connections: parse_section(files, Section::Connections)?,
+        prefs: parse_section(files, Section::AppPrefs)?,
+        profiles: parse_section(files, Section::AssistantProfiles)?,
+        catalog: files
+            .iter()
+            .filter_map(|(name, data)| {
+                let file = name.strip_prefix(CATALOG_PREFIX)?;
+                let valid = !file.is_empty()
+                    && !file.contains(['/', '\\'])
+                    && !file.starts_with('.')
+                    && [".toml", ".yaml", ".yml"]
+                        .iter()
+                        .any(|ext| file.ends_with(ext));
+                valid.then(|| (file.to_string(), String::from_utf8_lossy(data).into_owned()))
+            })
+            .collect(),
+    })
+}
+
+/// The passphrase key, checked against the manifest's verifier.
+fn unlock(manifest: &BundleManifest, passphrase: Option<&str>) -> Result<Option<String>, String> {
+    let Some(kdf) = &manifest.kdf else {
+        return Ok(None);
+    };
+    if kdf.alg != KDF_ALG {
+        return Err(format!("bundle_kdf_unsupported: {}", kdf.alg));
+    }
+    let passphrase = passphrase
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| "bundle_passphrase_required".to_string())?;
+    let key = passphrase_key(passphrase, kdf.salt.as_bytes(), kdf.iterations);
+    match aes_decrypt_to_string(&key, &kdf.check) {
+        Ok(check) if check == PASSPHRASE_CHECK => Ok(Some(key)),
+        _ => Err("bundle_passphrase_invalid".to_string()),
+    }
+}
+
+struct Importer<'a> {
+    conn: &'a mut SqliteConnection,
+    strategy: ConflictStrategy,
+    allow_read_only_downgrade: bool,
+    items: Vec<ImportItem>,
+}
+
+impl Importer<'_> {
+    fn note(
+        &mut self,
+        section: Section,
+        key: &str,
+        action: ImportAction,
+        target: Option<String>,
+        note: Option<&str>,
+    ) {
+        self.items.push(ImportItem {
+            section,
+            key: key.to_string(),
+            action,
+            target,
+            note: note.map(str::to_string),
+        });
+    }
+
+    async fn saved_sql(&mut self, entries: &[SavedSqlEntry]) -> Result<(), String> {
+        let rows = sqlx::query("SELECT id, name FROM saved_sql")
+            .fetch_all(&mut *self.conn)
+            .await
+            .map_err(db_error)?;
+        let mut ids: HashSet<String> = rows.iter().filter_map(|r| row_text(r, "id")).collect();
+        let mut by_name: BTreeMap<String, String> = rows
+            .iter()
+            .filter_map(|r| Some((row_text(r, "name")?, row_text(r, "id")?)))
+            .collect();
+        // `saved_sql` timestamps are milliseconds, as the webview writes them.
+        let now = now_ms();
+        for entry in entries {
+            let existing = by_name.get(&entry.name).cloned();
+            let (action, name) = match (&existing, self.strategy) {
+                (None, _) => (ImportAction::Add, entry.name.clone()),
+                (Some(_), ConflictStrategy::Skip) => {
+                    self.note(
+                        Section::SavedSql,
+                        &entry.name,
+                        ImportAction::Skip,
+                        None,
+                        Some("name exists"),
+                    );
+                    continue;
+                }
+                (Some(_), ConflictStrategy::Overwrite) => {
+                    (ImportAction::Overwrite, entry.name.clone())
+                }
+                (Some(_), ConflictStrategy::Rename) => {
+                    let taken = by_name.keys().cloned().collect();
+                    (ImportAction::Rename, unique_name(&entry.name, &taken))
+                }
+            };
+            if let (ImportAction::Overwrite, Some(id)) = (action, &existing) {
+                sqlx::query(
+                    "UPDATE saved_sql SET description = ?1, sql = ?2, variables = ?3, dynamic_columns = ?4, calc_items = ?5, is_archived = ?6, updated_at = ?7 WHERE id = ?8",
+                )
+                .bind(&entry.description)
+                .bind(&entry.sql)
+                .bind(json_text(&entry.variables).unwrap_or_else(|| "[]".to_string()))
+                .bind(json_text(&entry.dynamic_columns))
+                .bind(json_text(&entry.calc_items))
+                .bind(entry.is_archived)
+                .bind(now)
+                .bind(id)
+                .execute(&mut *self.conn)
+                .await
+                .map_err(db_error)?;
+            } else {
+                let id = if entry.id.is_empty() || ids.contains(&entry.id) {
+                    new_id("sq")
+                } else {
+                    entry.id.clone()
+                };
+                sqlx::query(
+                    "INSERT INTO saved_sql (id, name, description, sql, variables, dynamic_columns, calc_items, is_archived, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)",
+                )
+                .bind(&id)
+                .bind(&name)
+                .bind(&entry.description)
+                .bind(&entry.sql)
+                .bind(json_text(&entry.variables).unwrap_or_else(|| "[]".to_string()))
+                .bind(json_text(&entry.dynamic_columns))
+                .bind(json_text(&entry.calc_items))
+                .bind(entry.is_archived)
+                .bind(now)
+                .execute(&mut *self.conn)
+                .await
+                .map_err(db_error)?;
+                ids.insert(id.clone());
+                by_name.insert(name.clone(), id);
+            }
+            let target = (action == ImportAction::Rename).then_some(name);
+            self.note(Section::SavedSql, &entry.name, action, target, None);
+        }
+        Ok(())
+    }
+
+    async fn connections(
+        &mut self,
+        entries: &[ConnectionEntry],
+        bundle_key: Option<&str>,
+    ) -> Result<(), String> {
+        let rows = sqlx::query("SELECT id, alias, strict_read_only FROM user_connections")
+            .fetch_all(&mut *self.conn)
+            .await
+            .map_err(db_error)?;
+        let mut ids: HashSet<String> = rows.iter().filter_map(|r| row_text(r, "id")).collect();
+        let strict_ids: HashSet<String> = rows
+            .iter()
+            .filter(|r| r.try_get::<i64, _>("strict_read_only").unwrap_or_default() != 0)
+            .filter_map(|r| row_text(r, "id"))
+            .collect();
+        let mut by_alias: BTreeMap<String, String> = rows
+            .iter()
+            .filter_map(|r| Some((row_text(r, "alias")?, row_text(r, "id")?)))
+            .collect();
+        let device = if entries.iter().any(|e| e.dsn.is_some()) {
+            Some(device_key(self.conn).await?)
+        } else {
+            None
+        };
+        let now = now_sec();
+        for entry in entries {
+            let existing = by_alias.get(&entry.alias).cloned();
+            let (action, alias) = match (&existing, self.strategy) {
+                (None, _) => (ImportAction::Add, entry.alias.clone()),
+                (Some(_), ConflictStrategy::Skip) => {
+                    self.note(
+                        Section::Connections,
+                        &entry.alias,
+                        ImportAction::Skip,
+                        None,
+                        Some("alias exists"),
+                    );
+                    continue;
+                }
+                (Some(_), ConflictStrategy::Overwrite) => {
+                    (ImportAction::Overwrite, entry.alias.clone())
+                }
+                (Some(_), ConflictStrategy::Rename) => {
+                    let taken = by_alias.keys().cloned().collect();
+                    (ImportAction::Rename, unique_name(&entry.alias, &taken))
+                }
+            };
+            let dsn_cipher = match (&entry.dsn, bundle_key, device.as_deref()) {
+                (Some(cipher), Some(bundle_key), Some(device)) => {
+                    let dsn = aes_decrypt_to_string(bundle_key, cipher)
+                        .map_err(|err| format!("{} ({})", err, entry.alias))?;
+                    Some(json!(aes_encrypt_string(device, &dsn)?).to_string())
+                }
+                _ => None,
+            };
+            let mut notes: Vec<&str> = Vec::new();
+            match (&dsn_cipher, action) {
+                (Some(_), _) => {}
+                (None, ImportAction::Overwrite) => {
+                    notes.push("no DSN in bundle; existing DSN kept")
+                }
+                (None, _) => notes.push("no DSN in bundle; set it before connecting"),
+            }
+            let mut strict_read_only = entry.strict_read_only;
+            if let (ImportAction::Overwrite, Some(id)) = (action, &existing) {
+                match (strict_ids.contains(id), entry.strict_read_only) {
+                    (true, false) if !self.allow_read_only_downgrade => {
+                        strict_read_only = true;
+                        notes.push("strict read-only kept on (bundle has it off)");
+                    }
+                    (true, false) => notes.push("strict read-only turned off"),
+                    (false, true) => notes.push("strict read-only turned on"),
+                    _ => {}
+                }
+            }
+            let note = (!notes.is_empty()).then(|| notes.join("; "));
+            if let (ImportAction::Overwrite, Some(id)) = (action, &existing) {
+                sqlx::query(
+                    "UPDATE user_connections SET driver = ?1, host = ?2, port = ?3, database = ?4, username = ?5,
+                     dsn_cipher = COALESCE(?6, dsn_cipher), strict_read_only = ?7, updated_at = ?8 WHERE id = ?9",
+                )
+                .bind(&entry.driver)
+                .bind(&entry.host)
+                .bind(entry.port)
+                .bind(&entry.database)
+                .bind(&entry.username)
+                .bind(&dsn_cipher)
+                .bind(strict_read_only)
+                .bind(now)
+                .bind(id)
+                .execute(&mut *self.conn)
+                .await
+                .map_err(db_error)?;
+            } else {
+                let id = if entry.id.is_empty() || ids.contains(&entry.id) {
+                    new_id("conn")
+                } else {
+                    entry.id.clone()
+                };
+                sqlx::query(
+                    "INSERT INTO user_connections (id, alias, driver, host, port, database, username, dsn_cipher, dsn_key_ref, strict_read_only, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, NULL, ?9, ?10, ?10)",
+                )
+                .bind(&id)
+                .bind(&alias)
+                .bind(&entry.driver)
+                .bind(&entry.host)
+                .bind(entry.port)
+                .bind(&entry.database)
+                .bind(&entry.username)
+                .bind(&dsn_cipher)
+                .bind(entry.strict_read_only)
+                .bind(now)
+                .execute(&mut *self.conn)
+                .await
+                .map_err(db_error)?;
+                ids.insert(id.clone());
+                by_alias.insert(alias.clone(), id);
+            }
+            let target = (action == ImportAction::Rename).then_some(alias);
+            self.note(
+                Section::Connections,
+                &entry.alias,
+                action,
+                target,
+                note.as_deref(),
+            );
+        }
+        Ok(())
+    }
+
+    async fn prefs(&mut self, entries: &[PrefEntry]) -> Result<(), String> {
+        for entry in entries {
+            if entry.k == PROFILES_PREF || is_secret_pref(&entry.k, &entry.v) {
+                self.note(
+                    Section::AppPrefs,
+                    &entry.k,
+                    ImportAction::Skip,
+                    None,
+                    Some("secret preferences are never imported"),
+                );
+                continue;
+            }
+            let current: Option<String> =
+                sqlx::query_scalar("SELECT v FROM app_prefs WHERE k = ?1")
+                    .bind(&entry.k)
+                    .fetch_optional(&mut *self.conn)
+                    .await
+                    .map_err(db_error)?;
+            let action = match (current, self.strategy) {
+                (None, _) => ImportAction::Add,
+                (Some(v), _) if v == entry.v => {
+                    self.note(
+                        Section::AppPrefs,
+                        &entry.k,
+                        ImportAction::Skip,
+                        None,
+                        Some("unchanged"),
+                    );
+                    continue;
+                }
+                (Some(_), ConflictStrategy::Overwrite) => ImportAction::Overwrite,
+                (Some(_), ConflictStrategy::Skip) => {
+                    self.note(
+                        Section::AppPrefs,
+                        &entry.k,
+                        ImportAction::Skip,
+                        None,
+                        Some("key exists"),
+                    );
+                    continue;
+                }
+                (Some(_), ConflictStrategy::Rename) => {
+                    self.note(
+                        Section::AppPrefs,
+                        &entry.k,
+                        ImportAction::Skip,
+                        None,
+                        Some("preferences cannot be renamed"),
+                    );
+                    continue;
+                }
+            };
+            sqlx::query("INSERT INTO app_prefs (k, v) VALUES (?1, ?2) ON CONFLICT(k) DO UPDATE SET v = excluded.v")
+                .bind(&entry.k)
+                .bind(&entry.v)
+                .execute(&mut *self.conn)
+                .await
+                .map_err(db_error)?;
+            self.note(Section::AppPrefs, &entry.k, action, None, None);
+        }
+        Ok(())
+    }
+
+    /// Merges profiles by id into the stored `{ version, profiles }` payload.
+    async fn profiles(&mut self, entries: &[Value]) -> Result<(), String> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let mut profiles = load_profiles(self.conn).await?;
+        let field = |profile: &Value, key: &str| {
+            profile
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        for entry in entries {
+            let (id, name) = (field(entry, "id"), field(entry, "name"));
+            let position = profiles.iter().position(|p| field(p, "id") == id);
+            match (position, self.strategy) {
+                (None, _) => {
+                    profiles.push(entry.clone());
+                    self.note(
+                        Section::AssistantProfiles,
+                        &name,
+                        ImportAction::Add,
+                        None,
+                        None,
+                    );
+                }
+                (Some(_), ConflictStrategy::Skip) => {
+                    self.note(
+                        Section::AssistantProfiles,
+                        &name,
+                        ImportAction::Skip,
+                        None,
+                        Some("profile exists"),
+                    );
+                }
+                (Some(index), ConflictStrategy::Overwrite) => {
+                    profiles[index] = entry.clone();
+                    self.note(
+                        Section::AssistantProfiles,
+                        &name,
+                        ImportAction::Overwrite,
+                        None,
+                        None,
+                    );
+                }
+                (Some(_), ConflictStrategy::Rename) => {
+                    let taken = profiles.iter().map(|p| field(p, "name")).collect();
+                    let renamed = unique_name(&name, &taken);
+                    let mut copy = entry.clone();
+                    copy["id"] = json!(new_id("profile"));
+                    copy["name"] = json!(renamed);
+                    profiles.push(copy);
+                    self.note(
+                        Section::AssistantProfiles,
+                        &name,
+                        ImportAction::Rename,
+                        Some(renamed),
+                        None,
+                    );
+                }
+            }
+        }
+        sqlx::query("INSERT INTO app_prefs (k, v) VALUES (?1, ?2) ON CONFLICT(k) DO UPDATE SET v = excluded.v")
+            .bind(PROFILES_PREF)
+            .bind(json!({ "version": 1, "profiles": profiles }).to_string())
+            .execute(&mut *self.conn)
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Plans catalog files; returns `(file name, text)` pairs to write.
+    fn catalog(
+        &mut self,
+        entries: &[(String, String)],
+        dir: Option<&Path>,
+    ) -> Vec<(String, String)> {
+        let Some(dir) = dir else {
+            for (name, _) in entries {
+                self.note(
+                    Section::OpsCatalog,
+                    name,
+                    ImportAction::Skip,
+                    None,
+                    Some("ops catalog directory unavailable"),
+                );
+            }
+            return Vec::new();
+        };
+        let mut taken: HashSet<String> = catalog_files(dir)
+            .iter()
+            .filter_map(|p| p.file_name().and_then(|n| n.to_str()).map(str::to_string))
+            .collect();
+        let mut writes = Vec::new();
+        for (name, text) in entries {
+            let (action, target) = match (taken.contains(name), self.strategy) {
+                (false, _) => (ImportAction::Add, name.clone()),
+                (true, ConflictStrategy::Skip) => {
+                    self.note(
+                        Section::OpsCatalog,
+                        name,
+                        ImportAction::Skip,
+                        None,
+                        Some("file exists"),
+                    );
+                    continue;
+                }
+                (true, ConflictStrategy::Overwrite) => (ImportAction::Overwrite, name.clone()),
+                (true, ConflictStrategy::Rename) => {
+                    (ImportAction::Rename, unique_file_name(name, &taken))
+                }
+            };
+            taken.insert(target.clone());
+            let renamed = (action == ImportAction::Rename).then(|| target.clone());
+            writes.push((target, text.clone()));
+            self.note(Section::OpsCatalog, name, action, renamed, None);
+        }
+        writes
+    }
+}
+
+/// Imports a bundle inside one local-store transaction. A dry run performs
+/// the same steps and rolls back, so the preview is exactly what a real
+/// import would do.
+pub async fn import_bundle(
+    store: &LocalStore,
+    catalog_dir: Option<&Path>,
+    bytes: &[u8],
+    sections: &[Section],
+    policy: ImportPolicy,
+    passphrase: Option<&str>,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    let (manifest, files) = read_bundle(bytes)?;
+    let contents = contents_of(&files)?;
+    let sections: HashSet<Section> = sections.iter().copied().collect();
+    let bundle_key = if sections.contains(&Section::Connections) {
+        unlock(&manifest, passphrase)?
+    } else {
+        None
+    };
+
+    let mut tx = store.pool()?.begin().await.map_err(db_error)?;
+    let mut importer = Importer {
+        conn: &mut tx,
+        strategy: policy.conflict,
+        allow_read_only_downgrade: policy.allow_read_only_downgrade,
+        items: Vec::new(),
+    };
+    if sections.contains(&Section::SavedSql) {
+        importer.saved_sql(&contents.saved_sql).await?;
+    }
+    if sections.contains(&Section::Connections) {
+        importer
+            .connections(&contents.connections, bundle_key.as_deref())
+            .await?;
+    }
+    if sections.contains(&Section::AppPrefs) {
+        importer.prefs(&contents.prefs).await?;
+    }
+    if sections.contains(&Section::AssistantProfiles) {
+        importer.profiles(&contents.profiles).await?;
+    }
+    let catalog_writes = if sections.contains(&Section::OpsCatalog) {
+        importer.catalog(&contents.catalog, catalog_dir)
+    } else {
+        Vec::new()
+    };
+    let items = importer.items;
+
+    if dry_run {
+        tx.rollback().await.map_err(db_error)?;
+    } else {
+        tx.commit().await.map_err(db_error)?;
+        if let Some(dir) = catalog_dir.filter(|_| !catalog_writes.is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|err| format!("bundle_write_failed: {}", err))?;
+            for (name, text) in &catalog_writes {
+                std::fs::write(dir.join(name), text)
+                    .map_err(|err| format!("bundle_write_failed: {}: {}", name, err))?;
+            }
+        }
+    }
+    Ok(ImportReport {
+        manifest,
+        dry_run,
+        items,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BundleExportRequest {
+    #[serde(default)]
+    pub sections: Option<Vec<Section>>,
+    #[serde(default)]
+    pub passphrase: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleExport {
+    pub file_name: String,
+    pub data_base64: String,
+    pub manifest: BundleManifest,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BundleImportRequest {
+    pub data_base64: String,
+    #[serde(default)]
+    pub sections: Option<Vec<Section>>,
+    #[serde(default)]
+    pub conflict: ConflictStrategy,
+    /// See [`ImportPolicy::allow_read_only_downgrade`].
+    #[serde(default)]
+    pub allow_read_only_downgrade: bool,
+    #[serde(default)]
+    pub passphrase: Option<String>,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[tauri::command]
+pub async fn export_workspace_bundle(
+    store: State<'_, LocalStore>,
+    catalog: State<'_, OpsCatalog>,
+    payload: BundleExportRequest,
+) -> Result<BundleExport, String> {
+    let sections = payload.sections.unwrap_or_else(|| Section::ALL.to_vec());
+    let (bytes, manifest) = export_bundle(
+        &store,
+        catalog.dir(),
+        &sections,
+        payload.passphrase.as_deref(),
+    )
+    .await?;
+    Ok(BundleExport {
+        file_name: format!("rdv-workspace-{}.zip", manifest.created_at),
+        data_base64: BASE64.encode(bytes),
+        manifest,
+    })
+}
+
+#[tauri::command]
+pub async fn import_workspace_bundle(
+    store: State<'_, LocalStore>,
+    catalog: State<'_, OpsCatalog>,
+    payload: BundleImportRequest,
+) -> Result<ImportReport, String> {
+    let bytes = BASE64
+        .decode(payload.data_base64.trim())
+        .map_err(|_| "bundle_invalid: not base64".to_string())?;
+    let sections = payload.sections.unwrap_or_else(|| Section::ALL.to_vec());
+    let report = import_bundle(
+        &store,
+        catalog.dir(),
+        &bytes,
+        &sections,
+        ImportPolicy {
+            conflict: payload.conflict,
+            allow_read_only_downgrade: payload.allow_read_only_downgrade,
+        },
+        payload.passphrase.as_deref(),
+        payload.dry_run,
+    )
+    .await?;
+    if !report.dry_run && sections.contains(&Section::OpsCatalog) {
+        catalog.reload()?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::with_store;
+
+    #[test]
+    fn renames_without_collisions() {
+        let taken: HashSet<String> = ["Orders", "Orders (imported)"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(unique_name("Orders", &taken), "Orders (imported 2)");
+        assert_eq!(unique_name("Invoices", &taken), "Invoices (imported)");
+        let files: HashSet<String> = ["locks-imported.toml".to_string()].into_iter().collect();
+        assert_eq!(
+            unique_file_name("locks.toml", &files),
+            "locks-imported-2.toml"
+        );
+    }
+
+    #[test]
+    fn skips_secret_prefs() {
+        assert!(is_secret_pref(DEVICE_KEY_PREF, "abc"));
+        assert!(is_secret_pref("assistant.apiKey.openai", "{}"));
+        assert!(is_secret_pref(
+            "other",
+            r#"{"version":1,"alg":"A256GCM","iv":"x","ct":"y"}"#
+        ));
+        assert!(!is_secret_pref("query_history_redact_params", "true"));
+    }
+
+    #[test]
+    fn round_trips_manifest_and_rejects_tampering() {
+        let files = vec![("saved_sql.json".to_string(), b"[]".to_vec())];
+        let mut manifest = BundleManifest {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            app_version: "test".to_string(),
+            created_at: 1,
+            sections: BTreeMap::from([(Section::SavedSql, 0)]),
+            kdf: None,
+            files: BTreeMap::from([("saved_sql.json".to_string(), sha256_hex(b"[]"))]),
+        };
+        let bytes = write_zip(&manifest, &files).unwrap();
+        let (read, contents) = read_bundle(&bytes).unwrap();
+        assert_eq!(read.sections.get(&Section::SavedSql), Some(&0));
+        assert_eq!(
+            contents.get("saved_sql.json").map(Vec::as_slice),
+            Some(&b"[]"[..])
+        );
+
+        manifest
+            .files
+            .insert("saved_sql.json".to_string(), sha256_hex(b"[{}]"));
+        let tampered = write_zip(&manifest, &files).unwrap();
+        assert!(read_bundle(&tampered)
+            .unwrap_err()
+            .contains("checksum mismatch"));
+    }
+
+    #[test]
+    fn keeps_strict_read_only_and_never_creates_a_device_key_on_export() {
+        with_store(|store| async move {
+            let pool = store.pool().unwrap();
+            sqlx::query("INSERT INTO user_connections (id, alias, driver, strict_read_only) VALUES ('c1', 'prod', 'postgres', 0)")
+                .execute(&pool)
+                .await
+                .unwrap();
+            let sections = [Section::Connections];
+            let (bundle, _) = export_bundle(&store, None, &sections, Some("hunter2"))
+                .await
+                .unwrap();
+            let device: Option<String> = sqlx::query_scalar("SELECT v FROM app_prefs WHERE k = ?1")
+                .bind(DEVICE_KEY_PREF)
+                .fetch_optional(&pool)
+                .await
+                .unwrap();
+            assert!(device.is_none());
+
+            sqlx::query("UPDATE user_connections SET strict_read_only = 1")
+                .execute(&pool)
+                .await
+                .unwrap();
+            let import = |policy: ImportPolicy, dry_run| {
+                import_bundle(
+                    &store,
+                    None,
+                    &bundle,
+                    &sections,
+                    policy,
+                    Some("hunter2"),
+                    dry_run,
+                )
+            };
+            let strict = || async {
+                sqlx::query_scalar::<_, i64>("SELECT strict_read_only FROM user_connections")
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap()
+            };
+            let dry = import(ConflictStrategy::Overwrite.into(), true)
+                .await
+                .unwrap();
+            assert_eq!(
+                dry.items[0].note.as_deref(),
+                Some("no DSN in bundle; existing DSN kept; strict read-only kept on (bundle has it off)")
+            );
+            import(ConflictStrategy::Overwrite.into(), false)
+                .await
+                .unwrap();
+            assert_eq!(strict().await, 1);
+            // `user_connections` timestamps stay in seconds.
+            let updated: i64 = sqlx::query_scalar("SELECT updated_at FROM user_connections")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert!(updated <= now_sec() && updated > now_sec() - 60);
+            let downgrade = ImportPolicy {
+                conflict: ConflictStrategy::Overwrite,
+                allow_read_only_downgrade: true,
+            };
+            let report = import(downgrade, false).await.unwrap();
+            assert!(report.items[0]
+                .note
+                .as_deref()
+                .is_some_and(|note| note.ends_with("strict read-only turned off")));
+            assert_eq!(strict().await, 0);
+        });
+    }
+
+    #[test]
+    fn stamps_imported_saved_sql_in_milliseconds() {
+        with_store(|store| async move {
+            let pool = store.pool().unwrap();
+            // The webview stamps `saved_sql` with `Date.now()`.
+            let written = now_ms() - 60_000;
+            sqlx::query(
+                "INSERT INTO saved_sql (id, name, sql, variables, created_at, updated_at) VALUES ('s1', 'Orders', 'select 1', '[]', ?1, ?1)",
+            )
+            .bind(written)
+            .execute(&pool)
+            .await
+            .unwrap();
+            let sections = [Section::SavedSql];
+            let (bundle, _) = export_bundle(&store, None, &sections, None).await.unwrap();
+            for strategy in [ConflictStrategy::Overwrite, ConflictStrategy::Rename] {
+                import_bundle(
+                    &store,
+                    None,
+                    &bundle,
+                    &sections,
+                    strategy.into(),
+                    None,
+                    false,
+                )
+                .await
+                .unwrap();
+            }
+            let rows: Vec<(String, i64, i64)> =
+                sqlx::query_as("SELECT name, created_at, updated_at FROM saved_sql")
+                    .fetch_all(&pool)
+                    .await
+                    .unwrap();
+            let by_name = |name: &str| rows.iter().find(|r| r.0 == name).unwrap().clone();
+            let (_, created, updated) = by_name("Orders");
+            assert_eq!(created, written);
+            assert!(updated > written && updated <= now_ms());
+            let (_, created, updated) = by_name("Orders (imported)");
+            assert!(created > written && created <= now_ms());
+            assert_eq!(updated, created);
+        });
+    }
+}
@@ -1,4 +1,5 @@
-import { Avatar, Box, Group, Tabs, Text, Title, type MantineTheme } from '@mantine/core';
+import { ActionIcon, Avatar, Box, Group, Tabs, Text, Title, Tooltip, type MantineTheme } from '@mantine/core';
+import { useState } from 'react';
 import {
   IconDatabase,
   IconTable,
@@ -6,8 +7,10 @@ import {
   IconPlugConnected,
   IconHeartbeat,
   IconSparkles,
+  IconShieldLock,
 } from '@tabler/icons-react';
 import ConnectionSwitcher from '@/components/ConnectionSwitcher';
+import { LocalStoreSecurityModal } from '@/components/LocalStoreSecurityModal';
 import appIcon from '../../src-tauri/icons/icon.png';
 
 type AppFrameProps = {
@@ -26,6 +29,7 @@ const NAV_ITEMS = [
 
 export function AppFrame({ active, onNavigate }: AppFrameProps) {
   const current = active || 'connections';
+  const [securityOpen, setSecurityOpen] = useState(false);
 
   return (
     <Box
@@ -105,8 +109,19 @@ export function AppFrame({ active, onNavigate }: AppFrameProps) {
           </Tabs>
         </Group>
         <Group gap="sm" align="center">
+          <Tooltip label="Local data encryption">
+            <ActionIcon
+              variant="subtle"
+              size="lg"
+              aria-label="Local data encryption"
+              onClick={() => setSecurityOpen(true)}
+            >
+              <IconShieldLock size={18} />
+            </ActionIcon>
+          </Tooltip>
           <ConnectionSwitcher />
         </Group>
+        <LocalStoreSecurityModal opened={securityOpen} onClose={() => setSecurityOpen(false)} />
       </Group>
     </Box>
   );
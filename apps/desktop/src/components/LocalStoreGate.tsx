@@ -0,0 +1,104 @@
+import { Button, Center, Loader, Paper, PasswordInput, Stack, Text, Title } from '@mantine/core'
+import { IconLock } from '@tabler/icons-react'
+import { useCallback, useEffect, useState, type FormEvent, type ReactNode } from 'react'
+import { getLocalStoreStatus, unlockLocalStore, type LocalStoreStatus } from '@/lib/local-db'
+
+/** Dispatched after the store is locked so the gate shows the unlock screen. */
+export const LOCAL_STORE_LOCK_EVENT = 'rdv:local-store-lock-changed'
+
+export function describeLocalStoreError(e: unknown): string {
+  const msg = String((e as any)?.message ?? e)
+  if (msg.includes('local_store_passphrase_invalid')) return 'Wrong passphrase'
+  if (msg.includes('local_store_passphrase_too_short')) return 'Use at least 8 characters'
+  if (msg.includes('local_store_rolled_back')) return 'Local data was rolled back; restart the app'
+  return msg
+}
+
+// Holds the app back until rdv_local.db is open: an encrypted store needs its
+// passphrase before anything can read saved SQL, connections or settings.
+export function LocalStoreGate({ children }: { children: ReactNode }) {
+  const [status, setStatus] = useState<LocalStoreStatus | null>(null)
+  const [passphrase, setPassphrase] = useState('')
+  const [busy, setBusy] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  const refresh = useCallback(async () => {
+    try {
+      setStatus(await getLocalStoreStatus())
+    } catch (e) {
+      setError(describeLocalStoreError(e))
+    }
+  }, [])
+
+  useEffect(() => {
+    void refresh()
+    const fn = () => void refresh()
+    window.addEventListener(LOCAL_STORE_LOCK_EVENT, fn)
+    return () => window.removeEventListener(LOCAL_STORE_LOCK_EVENT, fn)
+  }, [refresh])
+
+  const onSubmit = async (e: FormEvent) => {
+    e.preventDefault()
+    if (!passphrase) return
+    setBusy(true)
+    setError(null)
+    try {
+      setStatus(await unlockLocalStore(passphrase))
+      setPassphrase('')
+    } catch (err) {
+      setError(describeLocalStoreError(err))
+    } finally {
+      setBusy(false)
+    }
+  }
+
+  if (status?.unlocked) return <>{children}</>
+
+  if (status?.rolled_back_to != null) {
+    return (
+      <Center style={{ height: '100vh' }}>
+        <Stack gap={4} align="center" w={420}>
+          <Title order={4}>Local data rolled back</Title>
+          <Text size="sm" c="dimmed" ta="center">
+            The store is at schema version {status.rolled_back_to}. Quit and install the matching
+            release; starting this version again migrates it back up.
+          </Text>
+        </Stack>
+      </Center>
+    )
+  }
+
+  return (
+    <Center style={{ height: '100vh' }}>
+      {status ? (
+        <Paper withBorder radius="md" p="xl" w={380}>
+          <form onSubmit={onSubmit}>
+            <Stack gap="md">
+              <Stack gap={4} align="center">
+                <IconLock size={28} />
+                <Title order={4}>Local data is locked</Title>
+                <Text size="sm" c="dimmed" ta="center">
+                  Enter the passphrase for this device's encrypted store.
+                </Text>
+              </Stack>
+              <PasswordInput
+                label="Passphrase"
+                value={passphrase}
+                onChange={(e) => setPassphrase(e.currentTarget.value)}
+                error={error}
+                autoFocus
+              />
+              <Button type="submit" loading={busy} disabled={!passphrase}>
+                Unlock
+              </Button>
+            </Stack>
+          </form>
+        </Paper>
+      ) : error ? (
+        <Text c="red">{error}</Text>
+      ) : (
+        <Loader />
+      )}
+    </Center>
+  )
+}
@@ -0,0 +1,120 @@
+import { Alert, Button, Group, Modal, PasswordInput, Stack, Text } from '@mantine/core'
+import { useEffect, useState } from 'react'
+import {
+  getLocalStoreStatus,
+  lockLocalStore,
+  setLocalStorePassphrase,
+  type LocalStoreStatus,
+} from '@/lib/local-db'
+import { describeLocalStoreError, LOCAL_STORE_LOCK_EVENT } from '@/components/LocalStoreGate'
+
+type Props = {
+  opened: boolean
+  onClose: () => void
+}
+
+export function LocalStoreSecurityModal({ opened, onClose }: Props) {
+  const [status, setStatus] = useState<LocalStoreStatus | null>(null)
+  const [current, setCurrent] = useState('')
+  const [next, setNext] = useState('')
+  const [confirm, setConfirm] = useState('')
+  const [busy, setBusy] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    if (!opened) return
+    setCurrent('')
+    setNext('')
+    setConfirm('')
+    setError(null)
+    getLocalStoreStatus().then(setStatus).catch((e) => setError(describeLocalStoreError(e)))
+  }, [opened])
+
+  const run = async (task: () => Promise<LocalStoreStatus>) => {
+    setBusy(true)
+    setError(null)
+    try {
+      setStatus(await task())
+      setCurrent('')
+      setNext('')
+      setConfirm('')
+    } catch (e) {
+      setError(describeLocalStoreError(e))
+    } finally {
+      setBusy(false)
+    }
+  }
+
+  const encrypted = !!status?.encrypted
+  const mismatch = confirm.length > 0 && next !== confirm
+  const canSet = next.length >= 8 && next === confirm && (!encrypted || current.length > 0)
+
+  const lockNow = async () => {
+    await run(lockLocalStore)
+    window.dispatchEvent(new CustomEvent(LOCAL_STORE_LOCK_EVENT))
+    onClose()
+  }
+
+  return (
+    <Modal opened={opened} onClose={onClose} title="Local data encryption" centered>
+      <Stack gap="sm">
+        <Text size="sm" c="dimmed">
+          {encrypted
+            ? 'Saved SQL, connections, schema caches and assistant history are encrypted on disk and unlocked with your passphrase at startup.'
+            : 'Encrypt everything stored on this device with a passphrase. The existing database is converted in place; without the passphrase it cannot be opened.'}
+        </Text>
+        {encrypted ? (
+          <PasswordInput
+            label="Current passphrase"
+            value={current}
+            onChange={(e) => setCurrent(e.currentTarget.value)}
+          />
+        ) : null}
+        <PasswordInput
+          label={encrypted ? 'New passphrase' : 'Passphrase'}
+          description="At least 8 characters"
+          value={next}
+          onChange={(e) => setNext(e.currentTarget.value)}
+        />
+        <PasswordInput
+          label="Confirm passphrase"
+          value={confirm}
+          onChange={(e) => setConfirm(e.currentTarget.value)}
+          error={mismatch ? 'Passphrases do not match' : null}
+        />
+        {error ? (
+          <Alert color="red" variant="light">
+            {error}
+          </Alert>
+        ) : null}
+        <Group justify="space-between">
+          {encrypted ? (
+            <Group gap="xs">
+              <Button variant="default" onClick={lockNow} loading={busy}>
+                Lock now
+              </Button>
+              <Button
+                variant="subtle"
+                color="red"
+                disabled={!current}
+                loading={busy}
+                onClick={() => run(() => setLocalStorePassphrase(null, current))}
+              >
+                Remove encryption
+              </Button>
+            </Group>
+          ) : (
+            <span />
+          )}
+          <Button
+            disabled={!canSet}
+            loading={busy}
+            onClick={() => run(() => setLocalStorePassphrase(next, encrypted ? current : undefined))}
+          >
+            {encrypted ? 'Change passphrase' : 'Encrypt local data'}
+          </Button>
+        </Group>
+      </Stack>
+    </Modal>
+  )
+}
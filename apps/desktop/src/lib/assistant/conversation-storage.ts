@@ -1,4 +1,4 @@
-import Database from '@tauri-apps/plugin-sql'
+import { loadLocalDb } from '@/lib/local-db'
 import type { AssistantContextChunk } from './context-chunks'
 import type { AssistantConversationMessage, AssistantMessageMetrics } from './conversation-utils'
 import { parseJsonColumn } from '@/lib/sqlite-text'
@@ -176,7 +176,7 @@ function applyLimits(payload: ConversationStoragePayload): ConversationStoragePa
 }
 
 async function openLocalDb() {
-  return await Database.load('sqlite:rdv_local.db')
+  return await loadLocalDb()
 }
 
 export async function loadConversationPayload(): Promise<ConversationStoragePayload> {
@@ -14,6 +14,7 @@ import type { SafetyEvaluation } from '@/lib/assistant/security-guard'
 import type { SimulatedToolCall } from '@/lib/assistant/tooling'
 import { getAssistantApiKey } from '@/lib/assistant/api-key-store'
 import { prepareMessagesForRequest } from '@/lib/assistant/context-divider'
+import { getCurrentConnId } from '@/lib/current-conn'
 
 const STREAM_DELAY_MS = 45
 
@@ -30,7 +31,7 @@ type DesktopChatRequest = {
   context_summary?: string | null
 }
 
-type DesktopChatPayload = DesktopChatRequest & { apiKey?: string }
+type DesktopChatPayload = DesktopChatRequest & { apiKey?: string; connId?: string }
 
 type DesktopChatResponse = {
   message: string
@@ -171,6 +172,8 @@ export class DesktopChatTransport implements ChatTransport<UIMessage> {
       const provider = this.providerSettings.provider
       const apiKey = await this.resolveApiKey(provider)
       const payload: DesktopChatPayload = apiKey ? { ...request, apiKey } : { ...request }
+      const connId = getCurrentConnId()
+      if (connId) payload.connId = connId
       const response = await invoke<DesktopChatResponse>('assistant_chat', { payload })
       this.onSuccess?.()
       this.lastMetadata = {
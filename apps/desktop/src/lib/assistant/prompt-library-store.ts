@@ -1,4 +1,4 @@
-import Database from '@tauri-apps/plugin-sql'
+import { loadLocalDb } from '@/lib/local-db'
 import { parseJsonColumn } from '@/lib/sqlite-text'
 
 const STORAGE_KEY = 'assistant.prompts.v1'
@@ -65,7 +65,7 @@ const builtinPrompts: PromptTemplate[] = [
 ]
 
 function openLocal() {
-  return Database.load('sqlite:rdv_local.db')
+  return loadLocalDb()
 }
 
 const now = () => Date.now()
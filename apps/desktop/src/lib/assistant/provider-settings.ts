@@ -1,4 +1,4 @@
-import Database from '@tauri-apps/plugin-sql'
+import { loadLocalDb } from '@/lib/local-db'
 import { parseJsonColumn } from '@/lib/sqlite-text'
 
 export type AssistantProvider = 'openai' | 'lmstudio' | 'ollama' | 'custom'
@@ -9,6 +9,8 @@ export type AssistantProviderSettings = {
   temperature: number
   maxTokens: number | null
   baseUrl: string
+  /** Optional `/embeddings` model used by the backend to re-rank retrieved context. */
+  embeddingModel?: string | null
 }
 
 export type AssistantProviderProfileModel = {
@@ -293,7 +295,7 @@ export function createAssistantProfile(
 }
 
 async function openLocalDatabase() {
-  return await Database.load('sqlite:rdv_local.db')
+  return await loadLocalDb()
 }
 
 async function loadProfilesPayload(): Promise<ProfilesPayload | null> {
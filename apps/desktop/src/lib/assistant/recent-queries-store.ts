@@ -1,4 +1,4 @@
-import Database from '@tauri-apps/plugin-sql'
+import { loadLocalDb } from '@/lib/local-db'
 import { parseJsonColumn } from '@/lib/sqlite-text'
 
 const STORAGE_KEY = 'assistant.recentQueries.v1'
@@ -36,7 +36,7 @@ export type StoredPayload = {
 }
 
 function openLocal() {
-  return Database.load('sqlite:rdv_local.db')
+  return loadLocalDb()
 }
 
 function normalizeWhitespace(text: string): string {
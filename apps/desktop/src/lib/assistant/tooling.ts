@@ -18,8 +18,8 @@ export function isReadOnlySql(text: string | null | undefined): boolean {
 export type SimulatedToolCall = {
   id: string
   name: string
-  kind: 'sql_preview'
-  input: { sql: string }
+  kind: 'sql_preview' | 'find_join_paths'
+  input: Record<string, unknown>
   status: 'success' | 'error'
   result?: {
     columns: string[]
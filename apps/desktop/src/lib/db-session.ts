@@ -1,4 +1,5 @@
 import Database from '@tauri-apps/plugin-sql'
+import { invoke } from '@tauri-apps/api/core'
 import { env } from '@/lib/env'
 
 type CacheEntry = {
@@ -167,6 +168,8 @@ const applySessionGuards = async (db: any, readOnly: boolean) => {
 type SessionOptions = {
   onConnect?: (ms: number) => void
   cacheKey?: string
+  /** Strict sessions only: recorded with each statement in the query history. */
+  savedSqlId?: string
 }
 
 function shouldInvalidateOnError(err: unknown): boolean {
@@ -317,6 +320,32 @@ export async function withWritableSession<T>(
   })
 }
 
+// Strict read-only profiles never open a webview session: statements go to
+// the Rust `execute_sql` command, which owns the read-only session, refuses
+// attempts to lift it and records violations in the ops audit log.
+function strictSessionDb(connId: string, savedSqlId?: string) {
+  return {
+    select: async (sql: string, params: unknown[] = []) => {
+      const res = await invoke<{ rows: Array<Record<string, unknown>> }>('execute_sql', {
+        connId,
+        sql,
+        params,
+        savedSqlId,
+      })
+      return res.rows
+    },
+  }
+}
+
+export async function withStrictSession<T>(
+  connId: string,
+  fn: (db: any) => Promise<T>,
+  opts?: SessionOptions
+): Promise<T> {
+  if (opts?.onConnect) opts.onConnect(0)
+  return await fn(strictSessionDb(connId, opts?.savedSqlId))
+}
+
 export const __test__ = {
   extractMessage,
   isUnsupportedDatatypeError,
@@ -2,6 +2,7 @@
 // Only allows SELECT/WITH queries; enforces MAX_ROW_LIMIT.
 
 import Database from '@tauri-apps/plugin-sql'
+import { loadLocalDb } from '@/lib/local-db'
 import { env } from '@/lib/env'
 
 export type QueryResultRow = Record<string, unknown>
@@ -22,8 +23,8 @@ function enforceLimit(sql: string): string {
 export class ReadonlyDb {
   private constructor(private readonly db: any) {}
 
-  static async openSqlite(path = 'rdv_local.db') {
-    const db = await Database.load(`sqlite:${path}`)
+  static async openSqlite() {
+    const db = await loadLocalDb()
     return new ReadonlyDb(db)
   }
 
@@ -1,4 +1,5 @@
 import Database from '@tauri-apps/plugin-sql'
+import type { SelectSource } from '@/lib/introspect'
 
 export type IndexInfo = {
   schema: string
@@ -27,8 +28,8 @@ function toPretty(n: number) {
   return `${v.toFixed(1)} ${units[i]}`
 }
 
-export async function loadIndexes(dsn: string, schema: string, table: string): Promise<IndexInfo[]> {
-  const db = await Database.load(dsn)
+export async function loadIndexes(source: string | SelectSource, schema: string, table: string): Promise<IndexInfo[]> {
+  const db = typeof source === 'string' ? await Database.load(source) : source
   // A) pg_indexes
   // @ts-ignore
   const resA = await db.select<any[]>(
@@ -44,8 +44,12 @@ function synthesizeDDL(t: TableMeta): string {
   return lines.join('\n')
 }
 
-export async function introspectPostgres(dsn: string): Promise<IntrospectResult> {
-  const db = await Database.load(dsn)
+/** Anything with the plugin's `select`, e.g. a strict session from `withStrictSession`. */
+export type SelectSource = { select: <T>(sql: string, params?: unknown[]) => Promise<T> }
+
+/** Takes a DSN, or a strict session so strict read-only profiles stay on the Rust executor. */
+export async function introspectPostgres(source: string | SelectSource): Promise<IntrospectResult> {
+  const db = typeof source === 'string' ? await Database.load(source) : source
 
   // databases
   // @ts-ignore runtime select
@@ -92,7 +96,7 @@ export async function introspectPostgres(dsn: string): Promise<IntrospectResult>
   )
 
   // foreign keys
-  type FkRow = { schema: string; table: string; column: string; ref_schema: string; ref_table: string; ref_column: string }
+  type FkRow = { schema: string; table: string; column: string; ref_schema: string; ref_table: string; ref_column: string; constraint: string }
   // @ts-ignore
   const fkRes = await db.select<FkRow[]>(
     `SELECT
@@ -101,7 +105,8 @@ export async function introspectPostgres(dsn: string): Promise<IntrospectResult>
        a.attname AS column,
        n2.nspname AS ref_schema,
        c2.relname AS ref_table,
-       a2.attname AS ref_column
+       a2.attname AS ref_column,
+       con.conname AS constraint
      FROM pg_catalog.pg_constraint con
      JOIN pg_catalog.pg_class c ON c.oid = con.conrelid
      JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
@@ -116,11 +121,11 @@ export async function introspectPostgres(dsn: string): Promise<IntrospectResult>
 
   const key = (s: string, t: string) => `${s}.${t}`
   const pkSet = new Set((pkRes || []).map((r) => `${key(r.schema, r.table)}::${r.column}`))
-  const fkMap = new Map<string, Array<{ column: string; ref_schema: string; ref_table: string; ref_column: string }>>()
+  const fkMap = new Map<string, Array<{ column: string; ref_schema: string; ref_table: string; ref_column: string; constraint: string }>>()
   for (const r of fkRes || []) {
     const k = key(r.schema, r.table)
     const arr = fkMap.get(k) || []
-    arr.push({ column: r.column, ref_schema: r.ref_schema, ref_table: r.ref_table, ref_column: r.ref_column })
+    arr.push({ column: r.column, ref_schema: r.ref_schema, ref_table: r.ref_table, ref_column: r.ref_column, constraint: r.constraint })
     fkMap.set(k, arr)
   }
 
@@ -129,15 +134,16 @@ export async function introspectPostgres(dsn: string): Promise<IntrospectResult>
     const tkey = key(r.schema, r.table)
     const arr = tableCols.get(tkey) || []
     const isPk = pkSet.has(`${tkey}::${r.column}`)
-    const fkRefs = (fkMap.get(tkey) || []).filter((f) => f.column === r.column)[0]
+    // A column can take part in several (composite) foreign keys; keep them all.
+    const foreignKeys = (fkMap.get(tkey) || [])
+      .filter((f) => f.column === r.column)
+      .map((f) => ({ schema: f.ref_schema, table: f.ref_table, column: f.ref_column, constraint: f.constraint }))
     const col: ColumnMeta = {
       name: r.column,
       dataType: r.data_type,
       nullable: r.nullable,
       isPrimaryKey: isPk,
-      ...(fkRefs
-        ? { isForeignKey: true as const, references: { schema: fkRefs.ref_schema, table: fkRefs.ref_table, column: fkRefs.ref_column } }
-        : {}),
+      ...(foreignKeys.length > 0 ? { isForeignKey: true as const, references: foreignKeys[0], foreignKeys } : {}),
     }
     arr.push(col)
     tableCols.set(tkey, arr)
@@ -0,0 +1,82 @@
+import { invoke } from '@tauri-apps/api/core'
+
+// rdv_local.db is opened only by the Rust backend (it may be encrypted), so
+// the webview reaches it through these commands instead of the SQL plugin.
+// `select`/`execute` keep the plugin's shape so call sites stay unchanged.
+
+export type LocalExecuteResult = {
+  rowsAffected: number
+  lastInsertId: number
+}
+
+export type LocalDatabase = {
+  select<T>(query: string, bindValues?: unknown[]): Promise<T>
+  execute(query: string, bindValues?: unknown[]): Promise<LocalExecuteResult>
+}
+
+const localDb: LocalDatabase = {
+  async select<T>(query: string, bindValues: unknown[] = []) {
+    return await invoke<T>('local_db_select', { query, values: bindValues })
+  },
+  async execute(query: string, bindValues: unknown[] = []) {
+    const res = await invoke<{ rows_affected: number; last_insert_id: number }>('local_db_execute', {
+      query,
+      values: bindValues,
+    })
+    return { rowsAffected: res.rows_affected, lastInsertId: res.last_insert_id }
+  },
+}
+
+/** Stand-in for `Database.load('sqlite:rdv_local.db')`. */
+export async function loadLocalDb(): Promise<LocalDatabase> {
+  return localDb
+}
+
+export type AppliedMigration = {
+  version: number
+  description: string
+  installed_on: string | null
+  success: boolean
+  /** Hex SHA-384 recorded in _sqlx_migrations. */
+  checksum: string
+  execution_ms: number
+  /** Known to this build with a matching checksum. */
+  verified: boolean
+}
+
+export type LocalStoreStatus = {
+  encrypted: boolean
+  unlocked: boolean
+  /** Highest applied migration; null while locked. */
+  schema_version: number | null
+  latest_version: number
+  migrations: AppliedMigration[]
+  /** Set after a rollback until the app restarts. */
+  rolled_back_to: number | null
+}
+
+export async function getLocalStoreStatus(): Promise<LocalStoreStatus> {
+  return await invoke<LocalStoreStatus>('local_store_status')
+}
+
+export async function unlockLocalStore(passphrase: string): Promise<LocalStoreStatus> {
+  return await invoke<LocalStoreStatus>('unlock_local_store', { passphrase })
+}
+
+export async function lockLocalStore(): Promise<LocalStoreStatus> {
+  return await invoke<LocalStoreStatus>('lock_local_store')
+}
+
+/**
+ * Encrypts the local store in place, changes its passphrase, or with a null
+ * passphrase decrypts it. `current` is required once the store is encrypted.
+ */
+export async function setLocalStorePassphrase(
+  passphrase: string | null,
+  current?: string,
+): Promise<LocalStoreStatus> {
+  return await invoke<LocalStoreStatus>('set_local_store_passphrase', {
+    passphrase,
+    current: current ?? null,
+  })
+}
@@ -1,4 +1,5 @@
 import Database from '@tauri-apps/plugin-sql'
+import { loadLocalDb } from '@/lib/local-db'
 import { validatePostgresDsn } from '@/lib/validate-dsn'
 import { getOrInitDeviceAesKey } from '@/lib/secret-store'
 import { aesEncryptString, aesDecryptToString, type AesCipher } from '@/lib/aes'
@@ -7,7 +8,7 @@ import { invalidateSessionCache } from '@/lib/db-session'
 import { decodeSqliteText } from '@/lib/sqlite-text'
 
 async function openLocal() {
-  return await Database.load('sqlite:rdv_local.db')
+  return await loadLocalDb()
 }
 
 const nowSec = () => Math.floor(Date.now() / 1000)
@@ -26,6 +27,7 @@ export type UserConn = {
   username?: string | null
   created_at?: number | null
   updated_at?: number | null
+  strict_read_only?: number | null
 }
 
 function extractConnMeta(dsn: string): {
@@ -61,7 +63,7 @@ export async function listConnections(): Promise<UserConn[]> {
   const db = await openLocal()
   // @ts-ignore select is provided by the plugin
   const rows = await db.select<UserConn[]>(
-    'SELECT id, alias, host, port, database, username, created_at, updated_at FROM user_connections ORDER BY updated_at DESC'
+    'SELECT id, alias, host, port, database, username, created_at, updated_at, strict_read_only FROM user_connections ORDER BY updated_at DESC'
   )
   const enriched = await Promise.all(
     rows.map(async (row) => {
@@ -123,6 +125,27 @@ export async function deleteConnectionById(id: string) {
   broadcastConnectionsChanged()
 }
 
+// Strict read-only profiles run every statement through the Rust executor,
+// which opens read-only sessions and refuses attempts to undo that.
+export async function isStrictReadOnly(id: string): Promise<boolean> {
+  const db = await openLocal()
+  // @ts-ignore select is provided by the plugin
+  const rows = await db.select<any[]>('SELECT strict_read_only FROM user_connections WHERE id = $1', [id])
+  return Array.isArray(rows) && rows.length > 0 && Number(rows[0].strict_read_only) === 1
+}
+
+export async function setStrictReadOnly(id: string, enabled: boolean) {
+  const db = await openLocal()
+  // @ts-ignore execute is provided by the plugin
+  await db.execute('UPDATE user_connections SET strict_read_only = $1, updated_at = $2 WHERE id = $3', [
+    enabled ? 1 : 0,
+    nowSec(),
+    id,
+  ])
+  invalidateSessionCache(id)
+  broadcastConnectionsChanged()
+}
+
 export async function testConnectionById(id: string) {
   const dsn = await getDsnForConn(id)
   return await testConnectionDsn(dsn)
@@ -1,5 +1,6 @@
-import Database from '@tauri-apps/plugin-sql'
+import { loadLocalDb } from '@/lib/local-db'
 import { decodeSqliteText } from '@/lib/sqlite-text'
+import type { ForeignRef } from '@rei-db-view/types/meta'
 
 export type SchemaCacheRecord = {
   id: string
@@ -26,13 +27,13 @@ export type IndexCacheEntry = {
 export type SchemaCachePayload = {
   databases: string[]
   schemas: string[]
-  tables: Array<{ schema: string; name: string; columns: Array<{ name: string; dataType: string; nullable?: boolean; isPrimaryKey?: boolean; isForeignKey?: true; references?: { schema: string; table: string; column: string } }> }>
+  tables: Array<{ schema: string; name: string; columns: Array<{ name: string; dataType: string; nullable?: boolean; isPrimaryKey?: boolean; isForeignKey?: true; references?: ForeignRef; foreignKeys?: ForeignRef[] }> }>
   ddls?: { schema: string; name: string; ddl: string }[]
   indexes?: Array<{ schema: string; name: string; indexes: IndexCacheEntry[] }>
 }
 
 async function openLocal() {
-  return await Database.load('sqlite:rdv_local.db')
+  return await loadLocalDb()
 }
 
 const nowSec = () => Math.floor(Date.now() / 1000)
@@ -1,7 +1,7 @@
 // Per-device secret key storage (SQLite app_prefs) for local AES fallback.
 // Avoids requiring the FS plugin; key is a random 32-byte base64 string stored in app_prefs.
 
-import Database from '@tauri-apps/plugin-sql'
+import { loadLocalDb } from '@/lib/local-db'
 import { aesDecryptToString, aesEncryptString, type AesCipher, importAesKey } from '@/lib/aes'
 import { decodeSqliteText } from '@/lib/sqlite-text'
 
@@ -33,7 +33,7 @@ function getCrypto(): Crypto {
 }
 
 async function openLocal() {
-  return await Database.load('sqlite:rdv_local.db')
+  return await loadLocalDb()
 }
 
 export async function getOrInitDeviceKeyBase64(): Promise<string> {
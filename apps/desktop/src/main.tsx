@@ -6,6 +6,7 @@ import '@mantine/core/styles.css'
 import '@mantine/notifications/styles.css'
 import './styles/overrides.css'
 import App from './App'
+import { LocalStoreGate } from '@/components/LocalStoreGate'
 
 async function installClipboardPolyfill() {
   if (typeof navigator === 'undefined') return
@@ -61,7 +62,9 @@ root.render(
   <React.StrictMode>
     <MantineProvider defaultColorScheme="light" theme={{ primaryColor: 'gray' }}>
       <Notifications />
-      <App />
+      <LocalStoreGate>
+        <App />
+      </LocalStoreGate>
     </MantineProvider>
   </React.StrictMode>
 )
@@ -19,7 +19,8 @@ import {
 } from '@tabler/icons-react';
 import SmartGrid from '@/components/SmartGrid';
 import { getCurrent } from '@/lib/localStore';
-import { getDsnForConn } from '@/lib/localStore';
+import { getDsnForConn, isStrictReadOnly } from '@/lib/localStore';
+import { withStrictSession } from '@/lib/db-session';
 import { readSchemaCache } from '@/lib/schema-cache';
 import { ReadonlyDb } from '@/lib/dbClient';
 import { buildSelectSql } from '@rei-db-view/query-engine';
@@ -180,7 +181,10 @@ export default function BrowsePage() {
     setError(null);
     setDurationMs(null);
     try {
-      const dsn = await getDsnForConn(userConnId);
+      // Strict read-only profiles go through the Rust executor (read-only
+      // check and audit record); others use the webview driver.
+      const strict = await isStrictReadOnly(userConnId);
+      const db = strict ? null : await ReadonlyDb.openPostgres(await getDsnForConn(userConnId));
       // build AST
       const alias = 't';
       const from = {
@@ -218,9 +222,10 @@ export default function BrowsePage() {
       const built = buildSelectSql(ast);
       setSqlPreview(built.text);
       setParamsPreview(built.values);
-      const db = await ReadonlyDb.openPostgres(dsn);
       const start = getNow();
-      const result = await db.select<any>(built.text, built.values);
+      const result: any[] = db
+        ? await db.select<any>(built.text, built.values)
+        : await withStrictSession(userConnId, (strictDb) => strictDb.select(built.text, built.values));
       const elapsed = Math.round(getNow() - start);
       setRows(result);
       setDurationMs(elapsed);
@@ -1,9 +1,13 @@
 import { useEffect, useMemo, useState } from 'react'
-import { Badge, Button, Code, Group, Paper, Stack, Table, Text, TextInput, Title, Modal } from '@mantine/core'
-import { listConnections, createConnection, setCurrent, getCurrent, testConnectionDsn, deleteConnectionById, updateConnectionDsn } from '@/lib/localStore'
+import { Badge, Button, Code, Group, Paper, Stack, Switch, Table, Text, TextInput, Title, Modal } from '@mantine/core'
+import { listConnections, createConnection, setCurrent, getCurrent, testConnectionDsn, deleteConnectionById, updateConnectionDsn, setStrictReadOnly } from '@/lib/localStore'
 import { validatePostgresDsn } from '@/lib/validate-dsn'
+import { getRolePrivilegesReport, setSessionReadOnly } from '@/services/privileges'
+import type { PrivilegeLevel, PrivilegeReport } from '@/services/privileges'
 
-type ConnRow = { id: string; alias: string; created_at?: number | null }
+const LEVEL_COLOR: Record<PrivilegeLevel, string> = { info: 'blue', warning: 'yellow', critical: 'red' }
+
+type ConnRow = { id: string; alias: string; created_at?: number | null; strict_read_only?: number | null }
 
 export default function ConnectionsPage() {
   const [items, setItems] = useState<ConnRow[]>([])
@@ -18,6 +22,8 @@ export default function ConnectionsPage() {
   const [editAlias, setEditAlias] = useState('')
   const [editDsn, setEditDsn] = useState('')
   const [deletingId, setDeletingId] = useState<string | null>(null)
+  const [report, setReport] = useState<PrivilegeReport | null>(null)
+  const [reportLoading, setReportLoading] = useState(false)
 
   const refresh = () => {
     setError(null)
@@ -31,6 +37,7 @@ export default function ConnectionsPage() {
   }, [])
 
   const canAdd = useMemo(() => alias.trim().length > 0 && dsn.trim().length > 0, [alias, dsn])
+  const currentStrict = Number(items.find((i) => i.id === currentId)?.strict_read_only) === 1
 
   const onAdd = async () => {
     if (!canAdd) return
@@ -52,9 +59,51 @@ export default function ConnectionsPage() {
     }
   }
 
+  const onToggleStrict = async (id: string, enabled: boolean) => {
+    setError(null)
+    setInfo(null)
+    try {
+      await setStrictReadOnly(id, enabled)
+      setInfo(enabled ? '已开启严格只读：所有语句经由只读会话执行。' : '已关闭严格只读。')
+      refresh()
+    } catch (e: any) {
+      setError(String(e?.message || e))
+    }
+  }
+
   const onUse = (id: string) => {
     setCurrentId(id)
     setCurrent(id)
+    setReport(null)
+  }
+
+  const onCheckPrivileges = async () => {
+    if (!currentId) return
+    setReportLoading(true)
+    setError(null)
+    setInfo(null)
+    try {
+      setReport(await getRolePrivilegesReport(currentId))
+    } catch (e: any) {
+      setError(String(e?.message || e))
+    } finally {
+      setReportLoading(false)
+    }
+  }
+
+  const onToggleSessionReadOnly = async (enabled: boolean) => {
+    if (!currentId) return
+    setReportLoading(true)
+    setError(null)
+    setInfo(null)
+    try {
+      setReport(await setSessionReadOnly(currentId, enabled))
+      setInfo(enabled ? '已开启会话只读：本次运行中的新会话均以只读事务开始。' : '已关闭会话只读。')
+    } catch (e: any) {
+      setError(String(e?.message || e))
+    } finally {
+      setReportLoading(false)
+    }
   }
 
   const onEdit = (id: string, alias: string) => {
@@ -168,6 +217,7 @@ export default function ConnectionsPage() {
                 <Table.Th>别名</Table.Th>
                 <Table.Th>记录ID</Table.Th>
                 <Table.Th>创建时间</Table.Th>
+                <Table.Th>严格只读</Table.Th>
                 <Table.Th w={260}>操作</Table.Th>
               </Table.Tr>
             </Table.Thead>
@@ -190,6 +240,13 @@ export default function ConnectionsPage() {
                   <Table.Td>
                     <Text size="sm" c="dimmed">{s.created_at ? new Date((s.created_at || 0) * 1000).toLocaleString() : '-'}</Text>
                   </Table.Td>
+                  <Table.Td>
+                    <Switch
+                      size="xs"
+                      checked={Number(s.strict_read_only) === 1}
+                      onChange={(e) => onToggleStrict(s.id, e.currentTarget.checked)}
+                    />
+                  </Table.Td>
                   <Table.Td>
                     <Group gap="xs">
                       <Button size="xs" onClick={() => onUse(s.id)} disabled={currentId === s.id}>设为当前</Button>
@@ -209,6 +266,49 @@ export default function ConnectionsPage() {
         <Text mt="xs">{currentId ? <Code>{currentId}</Code> : '未选择'}</Text>
         <Text c="dimmed" size="sm">提示：右上角可快速切换当前连接。</Text>
       </Paper>
+
+      <Paper withBorder p="md">
+        <Group justify="space-between">
+          <Title order={4}>角色权限</Title>
+          <Button size="xs" variant="default" disabled={!currentId} loading={reportLoading} onClick={onCheckPrivileges}>
+            检查权限
+          </Button>
+        </Group>
+        {report ? (
+          <Stack gap="xs" mt="sm">
+            <Group gap="xs">
+              <Text size="sm">
+                角色 <Code>{report.session.current_role}</Code> @ <Code>{report.session.database}</Code>
+              </Text>
+              {report.level && <Badge color={LEVEL_COLOR[report.level]}>{report.level}</Badge>}
+              {report.write_access.effectively_read_only && <Badge color="green" variant="light">无写入权限</Badge>}
+            </Group>
+            <Text size="sm" c="dimmed">
+              可见表 {report.table_summary.visible}，可写 {report.table_summary.writable}，拥有 {report.table_summary.owned}
+              {report.tables_truncated ? '（表清单已截断）' : ''}
+            </Text>
+            {report.findings.map((f, idx) => (
+              <Text key={`${f.kind}-${f.subject}-${idx}`} size="sm" c={f.level === 'critical' ? 'red' : f.level === 'warning' ? 'orange' : undefined}>
+                [{f.kind}] {f.subject}：{f.message}
+              </Text>
+            ))}
+            <Switch
+              label="会话只读（本次运行有效，角色仍可自行 SET 关闭）"
+              checked={report.write_access.pool_read_only}
+              disabled={
+                reportLoading ||
+                currentStrict ||
+                (!report.write_access.pool_read_only && !report.write_access.can_enable_session_read_only)
+              }
+              onChange={(e) => onToggleSessionReadOnly(e.currentTarget.checked)}
+            />
+          </Stack>
+        ) : (
+          <Text c="dimmed" size="sm" mt="xs">
+            检查当前连接角色的超级用户、BYPASSRLS、写入与建表权限。
+          </Text>
+        )}
+      </Paper>
     </Stack>
     <Modal opened={!!editingId} onClose={() => setEditingId(null)} title="编辑连接" centered>
       <Stack gap="sm">
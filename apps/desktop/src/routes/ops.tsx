@@ -4,8 +4,8 @@ import { IconPlayerPause, IconPlayerStop } from '@tabler/icons-react'
 import { DataGrid } from '@/components/DataGrid'
 import { listConnections, getCurrent, setCurrent, CONNS_CHANGED_EVENT } from '@/lib/localStore'
 import { subscribeCurrentConnId, getCurrentConnId } from '@/lib/current-conn'
-import { runOpsQuery, sendOpsSignal, OpsError } from '@/services/ops'
-import type { OpsSignalMode } from '@/services/ops'
+import { listOpsActions, runOpsQuery, prepareOpsSignal, sendOpsSignal, OpsError } from '@/services/ops'
+import type { OpsAction, OpsSignalMode } from '@/services/ops'
 
 export default function OpsPage() {
   const [userConnId, setUserConnIdState] = useState<string | null>(getCurrent())
@@ -18,6 +18,22 @@ export default function OpsPage() {
   const [minMinutes, setMinMinutes] = useState<number | ''>(5)
   const [limit, setLimit] = useState<number | ''>(200)
   const [loading, setLoading] = useState(false)
+  const [customActions, setCustomActions] = useState<OpsAction[]>([])
+  const [customActionId, setCustomActionId] = useState<string | null>(null)
+  const [catalogErrors, setCatalogErrors] = useState<string[]>([])
+
+  const loadCatalog = (reload = false) => {
+    listOpsActions({ reload })
+      .then((catalog) => {
+        setCustomActions(catalog.actions.filter((a) => a.source !== 'builtin'))
+        setCatalogErrors(catalog.errors.map((e) => `${e.source}${e.id ? ` [${e.id}]` : ''}: ${e.message}`))
+      })
+      .catch((err) => setCatalogErrors([String(err?.message || err)]))
+  }
+
+  useEffect(() => {
+    loadCatalog()
+  }, [])
 
   const refreshConnections = () => {
     listConnections()
@@ -58,11 +74,16 @@ export default function OpsPage() {
   const handleSignal = async (mode: OpsSignalMode, pid?: number) => {
     if (!userConnId || typeof pid !== 'number' || Number.isNaN(pid)) return
     const actionTip = mode === 'cancel' ? '取消当前查询' : '强制终止会话'
-    if (mode === 'terminate' && !confirm(`确定要${actionTip}（PID=${pid}）吗？可能导致该会话内事务回滚。`)) return
     try {
       setError(null)
       setInfo(null)
-      const res = await sendOpsSignal({ mode, pid, userConnId })
+      const confirmation = await prepareOpsSignal({ mode, pid, userConnId })
+      const { usename, application_name, query } = confirmation.target
+      const who = [usename, application_name].filter(Boolean).join(' / ')
+      const preview = query ? `\n\n${query.slice(0, 200)}` : ''
+      const warning = mode === 'terminate' ? '可能导致该会话内事务回滚。' : ''
+      if (!confirm(`确定要${actionTip}（PID=${pid}${who ? `，${who}` : ''}）吗？${warning}${preview}`)) return
+      const res = await sendOpsSignal({ confirmation, userConnId })
       setInfo(`${actionTip}请求已发送：${res.ok ? '成功' : '未生效（可能是目标状态已变化或权限不足）'}`)
     } catch (err: any) {
       setError(String(err?.message || err))
@@ -135,6 +156,19 @@ export default function OpsPage() {
   const runConnections = () =>
     runAction('connections_overview', { limit: effectiveLimit })
 
+  // Custom actions run with their defaults; the shared inputs fill any
+  // `minMinutes` / `limit` params they declare.
+  const runCustom = () => {
+    const action = customActions.find((a) => a.id === customActionId)
+    if (!action) return
+    const params: Record<string, unknown> = {}
+    for (const p of action.params) {
+      if (p.name === 'minMinutes') params.minMinutes = effectiveMin
+      if (p.name === 'limit') params.limit = effectiveLimit
+    }
+    runAction(action.id, params)
+  }
+
   return (
     <Stack gap="md" style={{ minWidth: 0 }}>
       <Title order={2}>运维快速按钮（只读）</Title>
@@ -186,6 +220,30 @@ export default function OpsPage() {
         </Button>
       </Group>
 
+      <Group wrap="wrap" gap="md" align="flex-end">
+        <Select
+          label="自定义动作"
+          placeholder={customActions.length ? '选择动作' : '无（放入 ops_actions 目录）'}
+          data={customActions.map((a) => ({ value: a.id, label: a.title }))}
+          value={customActionId}
+          onChange={setCustomActionId}
+          searchable
+          clearable
+          style={{ width: 280 }}
+        />
+        <Button variant="light" onClick={runCustom} disabled={!userConnId || loading || !customActionId}>
+          运行
+        </Button>
+        <Button variant="subtle" onClick={() => loadCatalog(true)} disabled={loading}>
+          重新加载目录
+        </Button>
+      </Group>
+      {catalogErrors.length ? (
+        <Text c="orange" size="sm">
+          部分动作未加载：{catalogErrors.join('；')}
+        </Text>
+      ) : null}
+
       {error ? <Text c="red">{error}</Text> : null}
       {info ? <Text c="green">{info}</Text> : null}
 
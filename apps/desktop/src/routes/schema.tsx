@@ -3,7 +3,8 @@ import { ActionIcon, Badge, Button, Code, Group, Loader, Modal, Pagination, Pape
 import { IconX, IconEyeOff } from '@tabler/icons-react'
 import { getCurrent } from '@/lib/localStore'
 import { subscribeCurrentConnId, getCurrentConnId } from '@/lib/current-conn'
-import { getDsnForConn } from '@/lib/localStore'
+import { getDsnForConn, isStrictReadOnly } from '@/lib/localStore'
+import { withStrictSession } from '@/lib/db-session'
 import { readSchemaCache, writeSchemaCache, type SchemaCachePayload } from '@/lib/schema-cache'
 import { applySchemaMetadataPayload } from '@/lib/schema-metadata-store'
 import { introspectPostgres } from '@/lib/introspect'
@@ -27,7 +28,9 @@ function asSchemaCachePayload(res: Awaited<ReturnType<typeof introspectPostgres>
         dataType: col.dataType,
         nullable: col.nullable,
         isPrimaryKey: col.isPrimaryKey,
-        ...(col.isForeignKey ? { isForeignKey: true as const, references: col.references } : {}),
+        ...(col.isForeignKey
+          ? { isForeignKey: true as const, references: col.references, foreignKeys: col.foreignKeys }
+          : {}),
       })),
     })),
     ddls: res.ddls,
@@ -167,8 +170,10 @@ export default function SchemaPage() {
     setLoading(true)
     setError(null)
     try {
-      const dsn = await getDsnForConn(userConnId)
-      const res = await introspectPostgres(dsn)
+      // Strict read-only profiles introspect through the Rust executor.
+      const res = (await isStrictReadOnly(userConnId))
+        ? await withStrictSession(userConnId, (db) => introspectPostgres(db))
+        : await introspectPostgres(await getDsnForConn(userConnId))
       const payload = asSchemaCachePayload(res)
       await writeSchemaCache(userConnId, payload)
       const nowSec = Math.floor(Date.now() / 1000)
@@ -233,8 +238,9 @@ export default function SchemaPage() {
     }
     setIdxLoading(true)
     try {
-      const dsn = await getDsnForConn(userConnId)
-      const rows = await loadIndexes(dsn, schema, table)
+      const rows = (await isStrictReadOnly(userConnId))
+        ? await withStrictSession(userConnId, (db) => loadIndexes(db, schema, table))
+        : await loadIndexes(await getDsnForConn(userConnId), schema, table)
       setIndexes(rows)
       indexCacheRef.current = { ...indexCacheRef.current, [fq]: rows }
       setIndexCache((prev) => ({ ...prev, [fq]: rows }))
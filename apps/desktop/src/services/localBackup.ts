@@ -0,0 +1,59 @@
+import { invoke } from '@tauri-apps/api/core'
+
+export type LocalBackupInfo = {
+  path: string
+  size_bytes: number
+  created_at: number
+  schema_version: number | null
+  /** Older backups deleted by rotation. */
+  removed: string[]
+}
+
+export type LocalRestoreInfo = {
+  source: string
+  schema_version: number
+  /** The replaced database is kept under this path. */
+  previous: string
+}
+
+export type LocalRollbackInfo = {
+  from_version: number
+  to_version: number
+  /** Safety backup taken before reverting. */
+  backup: LocalBackupInfo
+}
+
+export type LocalStoreCheck = {
+  ok: boolean
+  integrity: string[]
+  foreign_key_violations: { table: string; rowid: number | null; parent: string; fkid: number }[]
+  vacuumed: boolean
+  size_before: number
+  size_after: number
+}
+
+/** Snapshots rdv_local.db into `dir`, keeping the newest `keep` backups. */
+export async function backupLocalStore(dir: string, keep?: number): Promise<LocalBackupInfo> {
+  return await invoke<LocalBackupInfo>('backup_local_store', { dir, keep })
+}
+
+/** Validates and stages a backup; the app restarts to swap it in. */
+export async function restoreLocalStore(path: string): Promise<LocalRestoreInfo> {
+  return await invoke<LocalRestoreInfo>('restore_local_store', { path })
+}
+
+/**
+ * Reverts migrations above `targetVersion` for handing the store to an older
+ * release. `currentVersion` must match the applied version (see
+ * `getLocalStoreStatus`); the store stays closed until the app restarts.
+ */
+export async function rollbackLocalStore(
+  targetVersion: number,
+  currentVersion: number,
+): Promise<LocalRollbackInfo> {
+  return await invoke<LocalRollbackInfo>('rollback_local_store', { targetVersion, currentVersion })
+}
+
+export async function checkLocalStore(): Promise<LocalStoreCheck> {
+  return await invoke<LocalStoreCheck>('check_local_store')
+}
@@ -1,10 +1,36 @@
-import Database from '@tauri-apps/plugin-sql'
-import { buildOpsQuery, type OpsActionId } from '@rei-db-view/ops'
-import { getDsnForConn } from '@/lib/localStore'
-import { withReadonlySession, withWritableSession } from '@/lib/db-session'
+import { invoke } from '@tauri-apps/api/core'
 
 export type OpsQueryParams = Record<string, unknown>
 
+export type OpsActionParam = {
+  name: string
+  type: 'integer' | 'number' | 'boolean' | 'text'
+  title?: string | null
+  default?: unknown
+  min?: number | null
+  max?: number | null
+  max_length?: number | null
+  choices: string[]
+  optional: boolean
+}
+
+export type OpsAction = {
+  id: string
+  title: string
+  description?: string | null
+  params: OpsActionParam[]
+  sql: string
+  min_server_version?: string | null
+  required_role?: string | null
+  source: string
+}
+
+export type OpsCatalog = {
+  directory: string | null
+  actions: OpsAction[]
+  errors: Array<{ source: string; id: string | null; message: string }>
+}
+
 export type OpsQueryResult = {
   sql: string
   rows: Array<Record<string, unknown>>
@@ -14,6 +40,26 @@ export type OpsQueryResult = {
 
 export type OpsSignalMode = 'cancel' | 'terminate'
 
+export type OpsSignalTarget = {
+  pid: number
+  usename: string | null
+  datname: string | null
+  application_name: string | null
+  client_addr: string | null
+  state: string | null
+  backend_type: string | null
+  query: string | null
+  is_self: boolean
+}
+
+export type OpsSignalConfirmation = {
+  token: string
+  mode: OpsSignalMode
+  pid: number
+  target: OpsSignalTarget
+  expires_at: number
+}
+
 export class OpsError extends Error {
   code: string
   preview?: { text: string; values: any[] }
@@ -24,87 +70,59 @@ export class OpsError extends Error {
   }
 }
 
-const loadLocalDb = async () => await Database.load('sqlite:rdv_local.db')
-
-const nowSec = () => Math.floor(Date.now() / 1000)
-
-const genId = () => (typeof crypto !== 'undefined' && 'randomUUID' in crypto ? crypto.randomUUID() : `audit_${Date.now()}_${Math.random().toString(36).slice(2, 8)}`)
-
-const recordAudit = async (entry: {
-  connId: string
-  action: string
-  targetPid?: number
-  status: 'success' | 'failed'
-  message?: string
-}) => {
-  try {
-    const db = await loadLocalDb()
-    const createdAt = nowSec()
-    await db.execute(
-      `INSERT INTO ops_audit (id, conn_id, action, target_pid, status, message, created_at)
-       VALUES ($1, $2, $3, $4, $5, $6, $7)`,
-      [genId(), entry.connId, entry.action, entry.targetPid ?? null, entry.status, entry.message ?? null, createdAt],
-    )
-  } catch (err) {
-    console.warn('ops_audit_log_failed', err)
-  }
+// Actions come from the Rust catalog: built-ins plus TOML/YAML files in the
+// app data dir, each validated by the read-only checker when loaded.
+export async function listOpsActions(opts?: { reload?: boolean }): Promise<OpsCatalog> {
+  return await invoke<OpsCatalog>('list_ops_actions', { reload: opts?.reload ?? false })
 }
 
 export async function runOpsQuery(opts: {
-  actionId: OpsActionId
+  actionId: string
   params?: OpsQueryParams
   userConnId: string
 }): Promise<OpsQueryResult> {
   const { actionId, params, userConnId } = opts
-  const { text, values } = buildOpsQuery(actionId, params)
-  const dsn = await getDsnForConn(userConnId)
   try {
-    const rows = await withReadonlySession(
-      dsn,
-      async (db) => {
-        const result = await db.select(text, values)
-        return Array.isArray(result) ? (result as Array<Record<string, unknown>>) : []
-      },
-      { cacheKey: userConnId },
-    )
-    const columns = Object.keys(rows[0] ?? {})
-    return { sql: text, rows, columns, rowCount: rows.length }
+    const res = await invoke<OpsQueryResult & { row_count: number }>('run_ops_action', {
+      connId: userConnId,
+      actionId,
+      params: params ?? {},
+    })
+    return { sql: res.sql, rows: res.rows, columns: res.columns, rowCount: res.row_count }
   } catch (err: any) {
-    throw new OpsError(String(err?.message || err), 'db_query_failed', { text, values })
+    throw new OpsError(String(err?.message || err), 'db_query_failed')
   }
 }
 
-export async function sendOpsSignal(opts: {
+// Signals run in Rust: `prepare_ops_signal` looks up the backend and issues a
+// one-shot token, `run_ops_signal` redeems it and writes the HMAC-chained audit.
+export async function prepareOpsSignal(opts: {
   mode: OpsSignalMode
   pid: number
   userConnId: string
-}): Promise<{ ok: boolean }> {
+}): Promise<OpsSignalConfirmation> {
   const { mode, pid, userConnId } = opts
-  const fn = mode === 'cancel' ? 'pg_cancel_backend' : 'pg_terminate_backend'
-  const dsn = await getDsnForConn(userConnId)
-  let ok = false
-  let error: string | undefined
   try {
-    ok = await withWritableSession(
-      dsn,
-      async (db) => {
-        const rows = await db.select(`SELECT ${fn}($1) AS ok`, [pid])
-        const first = Array.isArray(rows) ? (rows as Array<{ ok?: boolean }>)[0] : undefined
-        return Boolean(first?.ok)
-      },
-      { cacheKey: userConnId },
-    )
-    return { ok }
+    return await invoke<OpsSignalConfirmation>('prepare_ops_signal', { connId: userConnId, mode, pid })
   } catch (err: any) {
-    error = String(err?.message || err)
-    throw new OpsError(error, 'signal_failed')
-  } finally {
-    await recordAudit({
+    throw new OpsError(String(err?.message || err), 'signal_prepare_failed')
+  }
+}
+
+export async function sendOpsSignal(opts: {
+  confirmation: OpsSignalConfirmation
+  userConnId: string
+}): Promise<{ ok: boolean }> {
+  const { confirmation, userConnId } = opts
+  try {
+    const res = await invoke<{ ok: boolean; audit_id: string; audit_hash: string }>('run_ops_signal', {
       connId: userConnId,
-      action: mode,
-      targetPid: pid,
-      status: ok ? 'success' : 'failed',
-      message: ok ? undefined : error,
+      mode: confirmation.mode,
+      pid: confirmation.pid,
+      confirmToken: confirmation.token,
     })
+    return { ok: res.ok }
+  } catch (err: any) {
+    throw new OpsError(String(err?.message || err), 'signal_failed')
   }
 }
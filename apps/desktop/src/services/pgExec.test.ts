@@ -0,0 +1,102 @@
+import { beforeEach, describe, expect, it, vi } from 'vitest'
+
+vi.mock('@tauri-apps/api/core', () => ({
+  invoke: vi.fn().mockResolvedValue('qh_1'),
+}))
+
+vi.mock('@/lib/localStore', () => ({
+  isStrictReadOnly: vi.fn().mockResolvedValue(false),
+  getDsnForConn: vi.fn().mockResolvedValue('postgres://localhost/app'),
+}))
+
+vi.mock('@/lib/db-session', () => {
+  const select = vi.fn()
+  const run = async (_dsn: string, fn: (db: any) => Promise<unknown>) => await fn({ select })
+  return {
+    withReadonlySession: vi.fn(run),
+    withWritableSession: vi.fn(run),
+    withStrictSession: vi.fn(),
+    __select: select,
+  }
+})
+
+vi.mock('@/services/savedSql', () => ({
+  getSavedSql: vi.fn().mockResolvedValue({ id: 'saved_1', name: 'Orders', sql: 'select 1 as n', variables: [] }),
+  listSavedSql: vi.fn(),
+}))
+
+vi.mock('@/services/privileges', () => ({
+  getSessionReadOnly: vi.fn().mockResolvedValue(false),
+}))
+
+vi.mock('@/lib/assistant/recent-queries-store', () => ({
+  recordRecentQuery: vi.fn().mockResolvedValue(undefined),
+}))
+
+const invokeMock = vi.mocked((await import('@tauri-apps/api/core')).invoke)
+const selectMock = vi.mocked(((await import('@/lib/db-session')) as any).__select)
+const sessions = vi.mocked(await import('@/lib/db-session'))
+const { getSessionReadOnly } = vi.mocked(await import('@/services/privileges'))
+const { getSavedSql } = vi.mocked(await import('@/services/savedSql'))
+const { isStrictReadOnly } = vi.mocked(await import('@/lib/localStore'))
+const { executeSavedSql } = await import('./pgExec')
+
+const historyCalls = () => invokeMock.mock.calls.filter(([cmd]) => cmd === 'record_query_history')
+
+describe('pgExec query history', () => {
+  beforeEach(() => {
+    invokeMock.mockClear()
+    selectMock.mockReset()
+  })
+
+  it('records statements run on webview sessions', async () => {
+    selectMock.mockResolvedValue([{ n: 1 }])
+    await executeSavedSql({ savedId: 'saved_1', values: {}, userConnId: 'conn_1' })
+    expect(historyCalls()).toHaveLength(1)
+    expect(historyCalls()[0][1]).toMatchObject({
+      payload: { conn_id: 'conn_1', saved_sql_id: 'saved_1', sql: 'select 1 as n', params: [], row_count: 1, error: null },
+    })
+  })
+
+  it('records failed statements with their error', async () => {
+    selectMock.mockRejectedValue(new Error('relation "t" does not exist'))
+    await expect(executeSavedSql({ savedId: 'saved_1', values: {}, userConnId: 'conn_1' })).rejects.toThrow()
+    expect(historyCalls()[0][1]).toMatchObject({
+      payload: { conn_id: 'conn_1', row_count: null, error: 'relation "t" does not exist' },
+    })
+  })
+
+  it('passes the saved query id to strict sessions', async () => {
+    isStrictReadOnly.mockResolvedValueOnce(true)
+    selectMock.mockResolvedValue([{ n: 1 }])
+    sessions.withStrictSession.mockImplementationOnce(async (_connId: string, fn: any) => await fn({ select: selectMock }))
+    await executeSavedSql({ savedId: 'saved_1', values: {}, userConnId: 'conn_1' })
+    expect(sessions.withStrictSession.mock.calls[0][2]).toMatchObject({ savedSqlId: 'saved_1' })
+    expect(historyCalls()).toHaveLength(0)
+  })
+})
+
+describe('pgExec session read-only toggle', () => {
+  beforeEach(() => {
+    selectMock.mockReset()
+    sessions.withReadonlySession.mockClear()
+    sessions.withWritableSession.mockClear()
+    getSavedSql.mockResolvedValueOnce({ id: 'saved_2', name: 'Touch', sql: 'update t set n = 1', variables: [] } as any)
+  })
+
+  it('runs confirmed writes in a writable session by default', async () => {
+    selectMock.mockResolvedValue([])
+    await executeSavedSql({ savedId: 'saved_2', values: {}, userConnId: 'conn_1', allowWrite: true })
+    expect(sessions.withWritableSession).toHaveBeenCalledTimes(1)
+    expect(sessions.withReadonlySession).not.toHaveBeenCalled()
+  })
+
+  it('keeps writes in a read-only session while the toggle is on', async () => {
+    getSessionReadOnly.mockResolvedValueOnce(true)
+    selectMock.mockResolvedValue([])
+    await executeSavedSql({ savedId: 'saved_2', values: {}, userConnId: 'conn_1', allowWrite: true })
+    expect(getSessionReadOnly).toHaveBeenCalledWith('conn_1')
+    expect(sessions.withReadonlySession).toHaveBeenCalledTimes(1)
+    expect(sessions.withWritableSession).not.toHaveBeenCalled()
+  })
+})
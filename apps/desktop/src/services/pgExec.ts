@@ -1,5 +1,5 @@
 import { env } from '@/lib/env'
-import { getDsnForConn } from '@/lib/localStore'
+import { getDsnForConn, isStrictReadOnly } from '@/lib/localStore'
 import {
   compileSql,
   extractVarNames,
@@ -7,7 +7,7 @@ import {
   renderSqlPreview,
   __test__ as sqlTestHelpers,
 } from '@/lib/sql-template'
-import { withReadonlySession, withWritableSession } from '@/lib/db-session'
+import { withReadonlySession, withStrictSession, withWritableSession } from '@/lib/db-session'
 import { recordRecentQuery } from '@/lib/assistant/recent-queries-store'
 import type {
   SavedQueryVariableDef,
@@ -20,6 +20,8 @@ import {
   type SavedSqlRecord,
   type SavedSqlSummary,
 } from '@/services/savedSql'
+import { recordQueryHistory } from '@/services/queryHistory'
+import { getSessionReadOnly } from '@/services/privileges'
 
 type QueryErrorDetail = { code: string; missing?: string[]; previewInline?: string }
 
@@ -35,6 +37,62 @@ export class QueryError extends Error {
   }
 }
 
+type SessionOptions = Parameters<typeof withReadonlySession>[2]
+
+// Records every statement of a webview session in the query history.
+function withHistory(db: any, connId: string, savedSqlId?: string) {
+  return {
+    select: async (sql: string, params: unknown[] = []) => {
+      const start = now()
+      let rowCount: number | null = null
+      let error: string | null = null
+      try {
+        const rows = await db.select(sql, params)
+        rowCount = Array.isArray(rows) ? rows.length : null
+        return rows
+      } catch (e: any) {
+        error = String(e?.message ?? e)
+        throw e
+      } finally {
+        void recordQueryHistory({
+          connId,
+          savedSqlId,
+          sql,
+          params,
+          durationMs: now() - start,
+          rowCount,
+          error,
+        }).catch((err) => {
+          console.warn('failed to record query history', err)
+        })
+      }
+    },
+  }
+}
+
+// Strict read-only profiles run through the Rust executor, which records the
+// history itself; everything else uses the webview driver with the DSN
+// decrypted here. With the session read-only toggle on, the writable session
+// opens `BEGIN READ ONLY` as well, so writes fail on the server.
+async function openSession(userConnId: string, savedSqlId?: string) {
+  if (await isStrictReadOnly(userConnId)) {
+    const strict = <T>(fn: (db: any) => Promise<T>, sessionOpts?: SessionOptions) =>
+      withStrictSession<T>(userConnId, fn, { ...sessionOpts, savedSqlId })
+    return { readonly: strict, writable: strict }
+  }
+  const dsn = await getDsnForConn(userConnId)
+  const sessionReadOnly = await getSessionReadOnly(userConnId)
+  const readonly = <T>(fn: (db: any) => Promise<T>, sessionOpts?: SessionOptions) =>
+    withReadonlySession<T>(dsn, (db) => fn(withHistory(db, userConnId, savedSqlId)), sessionOpts)
+  return {
+    readonly,
+    writable: sessionReadOnly
+      ? readonly
+      : <T>(fn: (db: any) => Promise<T>, sessionOpts?: SessionOptions) =>
+          withWritableSession<T>(dsn, (db) => fn(withHistory(db, userConnId, savedSqlId)), sessionOpts),
+  }
+}
+
 type PaginationInput = {
   enabled: boolean
   page: number
@@ -199,12 +257,11 @@ export async function executeSavedSql(opts: ExecuteOptions): Promise<ExecuteResu
     }
   }
 
-  const dsn = await getDsnForConn(opts.userConnId)
+  const session = await openSession(opts.userConnId, opts.savedId)
 
   if (!isSelect) {
     let connectMs: number | undefined
-    const execResult = await withWritableSession<ExecuteResult>(
-      dsn,
+    const execResult = await session.writable<ExecuteResult>(
       async (db) => {
         const queryStart = now()
         const res = await db.select(execText.text, execText.values)
@@ -251,8 +308,7 @@ export async function executeSavedSql(opts: ExecuteOptions): Promise<ExecuteResu
       }
     }
     let connectMs: number | undefined
-    const countResult = await withReadonlySession<ExecuteResult>(
-      dsn,
+    const countResult = await session.readonly<ExecuteResult>(
       async (db) => {
         const countStart = now()
         const rawRows = await db.select(
@@ -293,8 +349,7 @@ export async function executeSavedSql(opts: ExecuteOptions): Promise<ExecuteResu
   }
 
   let connectMs: number | undefined
-  const result = await withReadonlySession<ExecuteResult>(
-    dsn,
+  const result = await session.readonly<ExecuteResult>(
     async (db) => {
       let totalRowsValue: number | undefined
       let countMs: number | undefined
@@ -385,11 +440,10 @@ export async function explainSavedSql(opts: ExplainOptions): Promise<ExplainResu
     })
   }
   const previewInline = renderSqlPreview(compiled, saved.variables)
-  const dsn = await getDsnForConn(opts.userConnId)
+  const session = await openSession(opts.userConnId, opts.savedId)
   const format = opts.format === 'json' ? 'json' : 'text'
   const explainSql = buildExplainSQL(compiled.text, { format, analyze: opts.analyze && isReadOnlySelect(saved.sql) })
-  const rows = await withReadonlySession<Array<Record<string, unknown>>>(
-    dsn,
+  const rows = await session.readonly<Array<Record<string, unknown>>>(
     async (db) => {
       const rows = await db.select(explainSql, compiled.values)
       return Array.isArray(rows) ? (rows as Array<Record<string, unknown>>) : []
@@ -417,9 +471,8 @@ export async function fetchEnumOptions(opts: {
   } catch (e: any) {
     throw new QueryError(String(e?.message || e), { code: 'compile_failed' })
   }
-  const dsn = await getDsnForConn(opts.userConnId)
-  const rows = await withReadonlySession<Array<Record<string, unknown>>>(
-    dsn,
+  const session = await openSession(opts.userConnId)
+  const rows = await session.readonly<Array<Record<string, unknown>>>(
     async (db) => {
       const rows = await db.select(compiled.text, compiled.values)
       return Array.isArray(rows) ? (rows as Array<Record<string, unknown>>) : []
@@ -460,13 +513,12 @@ export async function computeCalcSql(opts: CalcOptions): Promise<CalcResult> {
   const calcCompiled = compileSql(calcSqlPrepared, saved.variables, opts.values)
   const finalSql = `with rdv_base as ( ${shiftParamPlaceholders(baseCompiled.text, calcCompiled.values.length)} ) ${calcCompiled.text}`
   const finalParams = [...calcCompiled.values, ...baseCompiled.values]
-  const dsn = await getDsnForConn(opts.userConnId)
+  const session = await openSession(opts.userConnId, opts.savedId)
   let connectMs: number | undefined
-  const { rows, queryMs } = await withReadonlySession<{
+  const { rows, queryMs } = await session.readonly<{
     rows: Array<Record<string, unknown>>
     queryMs: number
   }>(
-    dsn,
     async (db) => {
       const queryStart = now()
       const rawRows = await db.select(finalSql, finalParams)
@@ -0,0 +1,99 @@
+import { invoke } from '@tauri-apps/api/core'
+
+export type PrivilegeLevel = 'info' | 'warning' | 'critical'
+
+export type RoleSession = {
+  server_version: number
+  database: string
+  session_role: string
+  current_role: string
+  in_recovery: boolean
+  default_transaction_read_only: boolean
+  superuser: boolean
+  bypassrls: boolean
+  createrole: boolean
+  createdb: boolean
+  replication: boolean
+  inherit: boolean
+  can_login: boolean
+  connection_limit: number
+  valid_until: string | null
+  db_connect: boolean
+  db_create: boolean
+  db_temporary: boolean
+}
+
+export type RoleMembership = {
+  role: string
+  via: string
+  depth: number
+  admin_option: boolean
+  inherits: boolean
+  can_set_role: boolean
+  superuser: boolean
+  bypassrls: boolean
+  createrole: boolean
+  createdb: boolean
+}
+
+export type TablePrivilege = {
+  schema: string
+  name: string
+  kind: string
+  owner: string
+  is_owner: boolean
+  schema_usage: boolean
+  can_select: boolean
+  can_insert: boolean
+  can_update: boolean
+  can_delete: boolean
+  can_truncate: boolean
+  can_references: boolean
+  can_trigger: boolean
+  rls_enabled: boolean
+  rls_forced: boolean
+}
+
+export type PrivilegeFinding = {
+  kind: string
+  level: PrivilegeLevel
+  subject: string
+  message: string
+}
+
+export type WriteAccess = {
+  effectively_read_only: boolean
+  session_read_only: boolean
+  pool_read_only: boolean
+  can_enable_session_read_only: boolean
+}
+
+export type PrivilegeReport = {
+  session: RoleSession
+  memberships: RoleMembership[]
+  schemas: Array<{ schema: string; owner: string; is_owner: boolean; can_use: boolean; can_create: boolean }>
+  table_summary: { visible: number; writable: number; owned: number; rls_enabled: number }
+  tables: TablePrivilege[]
+  tables_truncated: boolean
+  columns: Array<Record<string, unknown>>
+  columns_truncated: boolean
+  policies: Array<Record<string, unknown>>
+  write_access: WriteAccess
+  level: PrivilegeLevel | null
+  findings: PrivilegeFinding[]
+  generated_at: number
+}
+
+export async function getRolePrivilegesReport(connId: string): Promise<PrivilegeReport> {
+  return await invoke<PrivilegeReport>('role_privileges_report', { connId })
+}
+
+// The flag lives in the Rust pool registry for the lifetime of the app;
+// `openSession` reads it so webview sessions honour it too.
+export async function getSessionReadOnly(connId: string): Promise<boolean> {
+  return await invoke<boolean>('session_read_only', { connId })
+}
+
+export async function setSessionReadOnly(connId: string, enabled: boolean): Promise<PrivilegeReport> {
+  return await invoke<PrivilegeReport>('set_session_read_only', { connId, enabled })
+}
@@ -0,0 +1,90 @@
+import { invoke } from '@tauri-apps/api/core'
+
+export type QueryHistoryEntry = {
+  id: string
+  conn_id: string
+  saved_sql_id: string | null
+  sql_hash: string
+  sql: string
+  /** Null when parameters were redacted. */
+  params: unknown[] | null
+  param_count: number
+  params_redacted: boolean
+  duration_ms: number
+  row_count: number | null
+  error_code: string | null
+  error_message: string | null
+  executed_at: number
+}
+
+export type QueryHistoryPage = {
+  entries: QueryHistoryEntry[]
+  total: number
+  offset: number
+  limit: number
+}
+
+export type QueryHistoryFilter = {
+  /** Substring of the SQL, or an exact SQL hash. */
+  query?: string
+  connId?: string
+  savedSqlId?: string
+  errorsOnly?: boolean
+  /** Unix seconds, inclusive. */
+  since?: number
+  until?: number
+}
+
+export async function searchQueryHistory(
+  filter: QueryHistoryFilter = {},
+  page: { offset?: number; limit?: number } = {},
+): Promise<QueryHistoryPage> {
+  return await invoke<QueryHistoryPage>('search_query_history', {
+    payload: {
+      query: filter.query,
+      conn_id: filter.connId,
+      saved_sql_id: filter.savedSqlId,
+      errors_only: filter.errorsOnly ?? false,
+      since: filter.since,
+      until: filter.until,
+      offset: page.offset,
+      limit: page.limit,
+    },
+  })
+}
+
+/**
+ * Records a statement run on a webview session. Strict read-only profiles run
+ * through the Rust executor, which records its own runs. Parameter redaction
+ * is applied by the backend.
+ */
+export async function recordQueryHistory(entry: {
+  connId: string
+  savedSqlId?: string
+  sql: string
+  params: unknown[]
+  durationMs: number
+  rowCount: number | null
+  error: string | null
+}): Promise<string> {
+  return await invoke<string>('record_query_history', {
+    payload: {
+      conn_id: entry.connId,
+      saved_sql_id: entry.savedSqlId,
+      sql: entry.sql,
+      params: entry.params,
+      duration_ms: Math.max(0, Math.round(entry.durationMs)),
+      row_count: entry.rowCount,
+      error: entry.error,
+    },
+  })
+}
+
+/** Deletes entries older than the given number of days; returns the count. */
+export async function purgeQueryHistory(olderThanDays: number): Promise<number> {
+  return await invoke<number>('purge_query_history', { olderThanDays })
+}
+
+export async function setQueryHistoryRedaction(enabled: boolean): Promise<void> {
+  await invoke('set_query_history_redaction', { enabled })
+}
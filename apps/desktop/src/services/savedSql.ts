@@ -1,4 +1,5 @@
-import Database from '@tauri-apps/plugin-sql'
+import { loadLocalDb } from '@/lib/local-db'
+import { invoke } from '@tauri-apps/api/core'
 import type {
   SavedQueryVariableDef,
   DynamicColumnDef,
@@ -41,9 +42,67 @@ export type SavedSqlRecord = SavedSqlSummary & {
   isArchived: boolean
 }
 
+export type SavedSqlExecution = {
+  saved_id: string
+  name: string
+  /** Exact SQL and parameters sent to Postgres. */
+  sql: string
+  params: unknown[]
+  placeholders: string[]
+  types: string[]
+  columns: string[]
+  rows: Record<string, unknown>[]
+  row_count: number
+  strict_read_only: boolean
+  elapsed_ms: number
+}
+
+export type SavedSqlRevision = {
+  id: string
+  saved_id: string
+  revision: number
+  name: string
+  description: string | null
+  sql: string
+  variables: SavedQueryVariableDef[] | null
+  dynamic_columns: DynamicColumnDef[] | null
+  calc_items: CalcItemDef[] | null
+  created_at: number | null
+}
+
+export type SavedSqlRevisionDiff = {
+  from: SavedSqlRevision
+  to: SavedSqlRevision
+  sql: {
+    op: 'equal' | 'insert' | 'delete'
+    text: string
+    old_line: number | null
+    new_line: number | null
+  }[]
+  changes: {
+    path: string
+    kind: 'added' | 'removed' | 'changed'
+    old: unknown
+    new: unknown
+  }[]
+}
+
+export type SnippetPart = { text: string; hit: boolean }
+
+export type SavedSqlSearchHit = {
+  id: string
+  name: string
+  description: string | null
+  is_archived: boolean
+  updated_at: number | null
+  name_highlight: SnippetPart[]
+  snippet: SnippetPart[]
+  score: number
+}
+
 export type ImportStats = { added: number; overwritten: number; skipped: number }
 
-const openLocal = () => Database.load('sqlite:rdv_local.db')
+const openLocal = () => loadLocalDb()
 
 const toIso = (ts: number | null) => {
   if (!Number.isFinite(ts)) return null
@@ -200,6 +259,46 @@ export async function updateSavedSql(
   await db.execute(`UPDATE ${TABLE} SET ${sets.join(', ')} WHERE id = $${paramIndex}`, params)
 }
 
+/** Validates, compiles and runs a saved query read-only in the Rust backend. */
+export async function executeSavedSql(
+  id: string,
+  connId: string,
+  values: Record<string, unknown>,
+): Promise<SavedSqlExecution> {
+  return await invoke<SavedSqlExecution>('execute_saved_sql', { id, connId, values })
+}
+
+/** Full-text search: words are ANDed, `"a phrase"` and `prefix*` are supported. */
+export async function searchSavedSql(
+  query: string,
+  opts?: { includeArchived?: boolean; limit?: number },
+): Promise<SavedSqlSearchHit[]> {
+  return await invoke<SavedSqlSearchHit[]>('search_saved_sql', {
+    query,
+    includeArchived: opts?.includeArchived ?? false,
+    limit: opts?.limit,
+  })
+}
+
+export async function listSavedSqlRevisions(savedId: string): Promise<SavedSqlRevision[]> {
+  return await invoke<SavedSqlRevision[]>('list_saved_sql_revisions', { savedId })
+}
+
+export async function diffSavedSqlRevisions(
+  fromId: string,
+  toId: string,
+): Promise<SavedSqlRevisionDiff> {
+  return await invoke<SavedSqlRevisionDiff>('diff_saved_sql_revisions', { fromId, toId })
+}
+
+/**
+ * Restores a revision, re-creating the query if it was deleted; the restored
+ * state is recorded as the newest revision.
+ */
+export async function restoreSavedSqlRevision(revisionId: string): Promise<SavedSqlRevision> {
+  return await invoke<SavedSqlRevision>('restore_saved_sql_revision', { revisionId })
+}
+
 export async function archiveSavedSql(id: string): Promise<void> {
   await updateSavedSql(id, { isArchived: true })
 }
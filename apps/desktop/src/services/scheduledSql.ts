@@ -0,0 +1,139 @@
+import { invoke } from '@tauri-apps/api/core'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
+
+/** Emitted by the backend after every scheduled or manual run. */
+export const SCHEDULE_RUN_EVENT = 'saved-sql-schedule://run'
+
+export type Comparison = 'gt' | 'gte' | 'lt' | 'lte' | 'eq' | 'ne'
+
+export type ScheduleCondition =
+  | { kind: 'row_count'; op: Comparison; value: number }
+  /** Compares `column` of the first row; numeric text compares as a number. */
+  | { kind: 'value'; column: string; op: Comparison; value: unknown }
+
+export type SqlSchedule = {
+  id: string
+  saved_id: string
+  saved_name: string
+  conn_id: string
+  /** Five-field cron expression in local time, or @hourly/@daily/@weekly/@monthly/@yearly. */
+  cron: string
+  values: Record<string, unknown>
+  condition: ScheduleCondition | null
+  notify: boolean
+  retention_days: number
+  max_snapshots: number
+  enabled: boolean
+  /** Last cron run; manual runs only show up as snapshots. */
+  last_run_at: number | null
+  next_run_at: number | null
+  created_at: number
+  updated_at: number
+}
+
+export type SqlScheduleInput = {
+  /** Updates this schedule; omit to create one. */
+  id?: string
+  savedId: string
+  connId: string
+  cron: string
+  values?: Record<string, unknown>
+  condition?: ScheduleCondition | null
+  notify?: boolean
+  retentionDays?: number
+  maxSnapshots?: number
+  enabled?: boolean
+}
+
+export type SqlSnapshotSummary = {
+  id: string
+  schedule_id: string
+  saved_id: string
+  conn_id: string
+  run_at: number
+  status: 'ok' | 'error'
+  duration_ms: number
+  row_count: number | null
+  /** Only the first 1000 rows were stored. */
+  truncated: boolean
+  triggered: boolean
+  /** Condition message, or the error of a failed run. */
+  message: string | null
+}
+
+export type SqlSnapshot = SqlSnapshotSummary & {
+  columns: string[]
+  rows: Record<string, unknown>[]
+}
+
+export type SqlSnapshotDiff = {
+  base: SqlSnapshotSummary
+  target: SqlSnapshotSummary
+  key_columns: string[]
+  row_count_delta: number | null
+  added: Record<string, unknown>[]
+  removed: Record<string, unknown>[]
+  changed: { key: unknown[]; before: Record<string, unknown>; after: Record<string, unknown> }[]
+  unchanged: number
+  truncated: boolean
+}
+
+/** Validates the cron expression and variable values, then creates or updates. */
+export async function saveSqlSchedule(input: SqlScheduleInput): Promise<SqlSchedule> {
+  return await invoke<SqlSchedule>('save_sql_schedule', {
+    payload: {
+      id: input.id,
+      saved_id: input.savedId,
+      conn_id: input.connId,
+      cron: input.cron,
+      values: input.values ?? {},
+      condition: input.condition ?? null,
+      notify: input.notify,
+      retention_days: input.retentionDays,
+      max_snapshots: input.maxSnapshots,
+      enabled: input.enabled,
+    },
+  })
+}
+
+export async function listSqlSchedules(savedId?: string): Promise<SqlSchedule[]> {
+  return await invoke<SqlSchedule[]>('list_sql_schedules', { savedId })
+}
+
+/** Also deletes the schedule's snapshots. */
+export async function deleteSqlSchedule(id: string): Promise<boolean> {
+  return await invoke<boolean>('delete_sql_schedule', { id })
+}
+
+export async function runSqlScheduleNow(id: string): Promise<SqlSnapshotSummary> {
+  return await invoke<SqlSnapshotSummary>('run_sql_schedule_now', { id })
+}
+
+/** Newest first, without rows. */
+export async function listSqlSnapshots(scheduleId: string, limit?: number): Promise<SqlSnapshotSummary[]> {
+  return await invoke<SqlSnapshotSummary[]>('list_sql_snapshots', { scheduleId, limit })
+}
+
+export async function getSqlSnapshot(id: string): Promise<SqlSnapshot> {
+  return await invoke<SqlSnapshot>('get_sql_snapshot', { id })
+}
+
+/**
+ * Compares a snapshot with `baseId`, or by default with the schedule's last
+ * successful run before the target's day. Rows match on `keyColumns` when
+ * given, otherwise only identical rows match.
+ */
+export async function diffSqlSnapshots(
+  targetId: string,
+  options: { baseId?: string; keyColumns?: string[] } = {},
+): Promise<SqlSnapshotDiff> {
+  return await invoke<SqlSnapshotDiff>('diff_sql_snapshots', {
+    payload: { target_id: targetId, base_id: options.baseId, key_columns: options.keyColumns ?? [] },
+  })
+}
+
+export async function onSqlScheduleRun(
+  handler: (summary: SqlSnapshotSummary) => void,
+): Promise<UnlistenFn> {
+  return await listen<SqlSnapshotSummary>(SCHEDULE_RUN_EVENT, (event) => handler(event.payload))
+}
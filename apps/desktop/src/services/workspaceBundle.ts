@@ -0,0 +1,73 @@
+import { invoke } from '@tauri-apps/api/core'
+
+export type BundleSection =
+  | 'saved_sql'
+  | 'connections'
+  | 'app_prefs'
+  | 'ops_catalog'
+  | 'assistant_profiles'
+
+export type BundleManifest = {
+  format: string
+  version: number
+  app_version: string
+  created_at: number
+  sections: Partial<Record<BundleSection, number>>
+  /** Present when connection DSNs are included under a passphrase. */
+  kdf: { alg: string; iterations: number; salt: string } | null
+  files: Record<string, string>
+}
+
+export type BundleExport = {
+  file_name: string
+  data_base64: string
+  manifest: BundleManifest
+}
+
+export type ConflictStrategy = 'skip' | 'overwrite' | 'rename'
+
+export type BundleImportItem = {
+  section: BundleSection
+  key: string
+  action: 'add' | 'skip' | 'overwrite' | 'rename'
+  target: string | null
+  note: string | null
+}
+
+export type BundleImportReport = {
+  manifest: BundleManifest
+  dry_run: boolean
+  items: BundleImportItem[]
+}
+
+/** Without a passphrase, connection DSNs are left out of the bundle. */
+export async function exportWorkspaceBundle(
+  opts: { sections?: BundleSection[]; passphrase?: string } = {},
+): Promise<BundleExport> {
+  return await invoke<BundleExport>('export_workspace_bundle', {
+    payload: { sections: opts.sections, passphrase: opts.passphrase },
+  })
+}
+
+export async function importWorkspaceBundle(
+  dataBase64: string,
+  opts: {
+    sections?: BundleSection[]
+    conflict?: ConflictStrategy
+    /** Let an overwrite turn strict read-only off; by default it stays on. */
+    allowReadOnlyDowngrade?: boolean
+    passphrase?: string
+    dryRun?: boolean
+  } = {},
+): Promise<BundleImportReport> {
+  return await invoke<BundleImportReport>('import_workspace_bundle', {
+    payload: {
+      data_base64: dataBase64,
+      sections: opts.sections,
+      conflict: opts.conflict ?? 'skip',
+      allow_read_only_downgrade: opts.allowReadOnlyDowngrade ?? false,
+      passphrase: opts.passphrase,
+      dry_run: opts.dryRun ?? false,
+    },
+  })
+}
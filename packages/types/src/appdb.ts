@@ -34,6 +34,11 @@ export interface SavedQueryVariableDef {
   options?: string[]
   // Optional SQL used to fetch enum options (first column as string)
   optionsSql?: string
+  // Element type for list variables (`{{#each}}`, `IN ({{ids}})`); Rust compiler only
+  itemType?: SavedQueryVarType
+  // Inclusive bounds, checked by the Rust backend: value for number/date/timestamp, length for text
+  min?: number | string
+  max?: number | string
 }
 
 export interface SavedQueryRecord {
@@ -4,6 +4,8 @@ export interface ForeignRef {
   schema: string
   table: string
   column: string
+  /** Foreign key constraint name; columns of a composite key share it. */
+  constraint?: string
 }
 
 export interface ColumnMeta {
@@ -13,6 +15,8 @@ export interface ColumnMeta {
   isPrimaryKey: boolean
   isForeignKey?: boolean
   references?: ForeignRef
+  /** Every foreign key the column belongs to; `references` is the first. */
+  foreignKeys?: ForeignRef[]
   sensitivity?: Sensitivity
 }
 